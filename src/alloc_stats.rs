@@ -0,0 +1,90 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Statistics of the heap allocations made by the simulation
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AllocStats {
+    /// the number of allocations, including reallocations
+    pub allocations: u64,
+    /// the number of deallocations
+    pub deallocations: u64,
+    /// the total number of bytes allocated
+    pub bytes: u64,
+    /// the largest number of bytes allocated at the same time
+    pub peak_bytes: u64,
+    /// the number of iterations finished
+    pub iterations: u64,
+    /// the largest number of allocations made in a single iteration
+    pub max_per_iteration: u64,
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_BYTES: AtomicU64 = AtomicU64::new(0);
+static ITERATIONS: AtomicU64 = AtomicU64::new(0);
+static ITERATION_START: AtomicU64 = AtomicU64::new(0);
+static MAX_PER_ITERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting the allocations passing through it
+struct Counting;
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocated(size: usize) {
+    let _ = ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let _ = BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+    let _ = PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+fn freed(size: usize) {
+    let _ = DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let _ = LIVE_BYTES.fetch_sub(size as u64, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        freed(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            freed(layout.size());
+            allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Closes the current iteration, for the per-iteration maximum
+pub fn end_iteration() {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = ITERATION_START.swap(allocations, Ordering::Relaxed);
+    let _ = MAX_PER_ITERATION.fetch_max(allocations - start, Ordering::Relaxed);
+    let _ = ITERATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the statistics of the allocations so far
+pub fn alloc_stats() -> AllocStats {
+    AllocStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+        iterations: ITERATIONS.load(Ordering::Relaxed),
+        max_per_iteration: MAX_PER_ITERATION.load(Ordering::Relaxed),
+    }
+}
@@ -0,0 +1,44 @@
+//! Checks of the invariants of the whole network, run between iterations.
+//!
+//! They cover what the network guarantees after every iteration: the sections' prefixes cover
+//! the name space without overlapping, every node is a member of the one section matching its
+//! name, the elders are the oldest adults of their section and a section with enough adults has
+//! a quorum of elders, and the relocated nodes on their way aren't members of any section yet.
+
+use network::Network;
+use params::{CheckMode, Params};
+use random;
+
+/// Checks all the invariants of the network, returning a description of the first one that
+/// doesn't hold
+pub fn check_network(network: &Network) -> Result<(), String> {
+    network.check_invariants()?;
+    network.check_elders()?;
+    network.check_relocations()
+}
+
+/// Checks the invariants after the iteration if they are due. A violation is described along
+/// with the seed and the state of the sections; in abort mode, the description is returned as
+/// the failure of the run, in warn mode it's printed and the run goes on.
+pub fn after_iteration(params: &Params, network: &Network, iteration: u64) -> Option<String> {
+    // a frequency of 0, possible when the parameters are built in code, means every iteration
+    let frequency = params.check_frequency?.max(1);
+    if (iteration + 1) % frequency != 0 {
+        return None;
+    }
+    let msg = check_network(network).err()?;
+    let diagnostic = format!(
+        "Invariant violated after iteration {}: {}\nSeed: {:?}\n{}",
+        iteration,
+        msg,
+        random::seed(),
+        network.diagnostics()
+    );
+    match params.check_mode {
+        CheckMode::Abort => Some(diagnostic),
+        CheckMode::Warn => {
+            println!("{}", diagnostic);
+            None
+        }
+    }
+}
@@ -0,0 +1,1694 @@
+//! The command line of the `ageing_sim` binary
+
+use event_log;
+use examples;
+use snapshot;
+use network::{BUFFER, GROUP_SIZE};
+use network::prefix::Prefix;
+use network::traffic::MessageSizes;
+use params::{AttackStrategy, BehaviourSpec, Coalition, Config, DropDist, FuzzedParam, Jitter, Params, Sweep,
+             MAX_MSG_DELAY_MEAN};
+use clap::{App, Arg};
+use std::env;
+use std::fs;
+use std::process;
+
+/// Parses the parameters of the simulation from the command line, exiting panicking if they are
+/// invalid
+pub fn get_params() -> Params {
+    let app = App::new("Ageing Simulation")
+        .about("Simulates ageing in SAFE network")
+        .arg(
+            Arg::with_name("initage")
+                .short("i")
+                .long("initage")
+                .value_name("AGE")
+                .help("Sets the initial age of newly joining peers; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("split")
+                .short("s")
+                .long("split")
+                .value_name("STRATEGY")
+                .help("Selects the strategy for splitting (always/complete); default: complete")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("norejectyoung")
+                .short("y")
+                .long("norejectyoung")
+                .help("Don't reject young peers when one already present in the section"),
+        )
+        .arg(
+            Arg::with_name("p_add1")
+                .long("padd1")
+                .value_name("P")
+                .help("Probability that a peer will join during a step (0-100); default: 90")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("p_drop1")
+                .long("pdrop1")
+                .value_name("P")
+                .help("Probability that a peer will be dropped during a step (0-100); default: 7")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("struct_file")
+                .long("network-struct-out")
+                .short("f")
+                .value_name("FILE")
+                .help("Output file for network structure data")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("drop_dist")
+                .long("drop-model")
+                .alias("drop-dist")
+                .value_name("MODEL")
+                .help(
+                    "How the chance of a node dropping depends on its age: \
+                     exponential(exp)[:BASE]/reverse-proportional(rev)/uniform/\
+                     table:FILE, the file with an AGE,PERCENT pair per line for the ages from \
+                     AGE on (default: exponential, which halves the chance every year)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("age_inc")
+                .long("age-inc")
+                .short("a")
+                .help("Increment node ages on merges and splits")
+        )
+        .arg(
+            Arg::with_name("debug_window")
+                .long("debug-window")
+                .value_name("START..END")
+                .help("Only print progress messages from iteration START up to (excluding) END")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("debug_prefix")
+                .long("debug-prefix")
+                .value_name("PREFIX")
+                .help("Only print progress messages about the sections and nodes under PREFIX")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pseudonyms")
+                .long("pseudonyms")
+                .help(
+                    "Show node names as short pseudonyms like N1723 in the progress messages, \
+                     results and exports, numbered in the order the names first appear",
+                ),
+        )
+        .arg(
+            Arg::with_name("stats_verbosity")
+                .long("stats-verbosity")
+                .value_name("LEVEL")
+                .help(
+                    "Statistics printed while running, regardless of the progress messages: \
+                     final (none), compact (a row of a table) or full (the distributions too); \
+                     default: final",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tui")
+                .long("tui")
+                .help("Show a dashboard of the network while running instead of the statistics and progress messages; p pauses and resumes, s steps one iteration while paused, q stops")
+                .conflicts_with_all(&["jobs", "soak", "dual_run", "experiment", "sweep"]),
+        )
+        .arg(
+            Arg::with_name("stats_interval")
+                .long("stats-interval")
+                .value_name("ITERATIONS")
+                .help("Number of iterations between the statistics printed while running (default: 10000)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("infant_quota")
+                .long("infant-quota")
+                .value_name("N")
+                .help("Turn away new nodes while the network has N infants")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("bootstrap_cache")
+                .long("bootstrap-cache")
+                .value_name("ITERATIONS")
+                .help(
+                    "Make joining nodes contact a random section from a list refreshed every \
+                     ITERATIONS iterations and count the hops to the section they join",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("misdirected_joins")
+                .long("misdirected-joins")
+                .value_name("P")
+                .help(
+                    "Make P% of the joining nodes contact a random section, which redirects \
+                     them one hop per iteration, and count the hops and the delay; default: 0",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("key_rotation")
+                .long("key-rotation")
+                .value_name("RULE")
+                .help(
+                    "Track section key rotations, needed on any change of elders (any) or when \
+                     the key holders are no longer a quorum of the elders (quorum)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("age_on_split")
+                .long("age-on-split")
+                .value_name("RULE")
+                .help("What happens to node ages on splits: keep/increment (default: keep)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("age_on_merge")
+                .long("age-on-merge")
+                .value_name("RULE")
+                .help("What happens to node ages on merges: keep/increment (default: keep)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("group_size")
+                .long("group-size")
+                .value_name("N")
+                .help("Number of elders of a complete section; default: 8")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("quorum")
+                .long("quorum")
+                .value_name("N")
+                .help("Number of elders needed for a section to reach a decision; default: half the group size plus one")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("buffer")
+                .long("buffer")
+                .value_name("N")
+                .help("Number of adults both halves of a section need on top of the group size for it to split; default: 3")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("elder_min_age")
+                .long("elder-min-age")
+                .value_name("AGE")
+                .help("Only let nodes older than AGE be elders, even among the oldest ones")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("elder_fallback")
+                .long("elder-fallback")
+                .value_name("RULE")
+                .help(
+                    "Elders kept when too few nodes are older than the elder minimum age: \
+                     shrink/quorum (default: quorum)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("elder_handover")
+                .long("elder-handover")
+                .value_name("ITERATIONS")
+                .help(
+                    "Number of iterations a section stays paused, holding back join and leave \
+                     requests, after relocating an elder; default: 0",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("merge_stabilisation")
+                .long("merge-stabilisation")
+                .value_name("ITERATIONS")
+                .help(
+                    "Number of iterations a merged section stays paused for every elder the \
+                     merge promoted or demoted; default: 0",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("scenario")
+                .long("scenario")
+                .value_name("FILE")
+                .help("Scenario file with assertions checked during the run, scripted churn and parameter changes")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("example")
+                .long("example")
+                .value_name("NAME")
+                .help("Runs a built-in example; options given explicitly override its own")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("list_examples")
+                .long("list-examples")
+                .help("Lists the built-in examples"),
+        )
+        .arg(
+            Arg::with_name("merge_negotiation")
+                .long("merge-negotiation")
+                .help("Require all the sections involved to agree to a merge before it starts"),
+        )
+        .arg(
+            Arg::with_name("batch_cascades")
+                .long("batch-cascades")
+                .help(
+                    "Process cascading merges within a single iteration; merges that are \
+                     negotiated take an iteration per round, so this can't be combined with \
+                     --merge-negotiation",
+                )
+                .conflicts_with("merge_negotiation"),
+        )
+        .arg(
+            Arg::with_name("malicious")
+                .long("malicious")
+                .value_name("P")
+                .help("Percentage of joining nodes that are malicious (0-100); default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("attack_fraction")
+                .long("attack-fraction")
+                .value_name("F")
+                .help(
+                    "Adds an attacker controlling a fraction (0-1, in whole percent) of the \
+                     joining nodes, which never leave on their own and wait for a quorum of \
+                     elders",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("attack_target")
+                .long("attack-target")
+                .value_name("PREFIX")
+                .help("Prefix the attacker's nodes keep rejoining until they land in")
+                .requires("attack_fraction")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("coalition")
+                .long("coalition")
+                .value_name("P[:STRATEGY[:PREFIX]]")
+                .help(
+                    "Adds an attacker coalition: the percentage of joining nodes in it, what its \
+                     elders do (equivocate/passive) and the prefix its nodes join; can be repeated",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("behaviour")
+                .long("behaviour")
+                .value_name("KIND:P[:RATE]")
+                .help(
+                    "Gives a percentage of joining nodes a behaviour (honest/flaky/malicious), \
+                     acting with a chance of RATE percent per iteration (default: 1); can be \
+                     repeated",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("permanent")
+                .long("permanent")
+                .value_name("P")
+                .help("Percentage of joining nodes that never drop (0-100); default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("observers")
+                .long("observer-nodes")
+                .value_name("N")
+                .help("Number of observer nodes that never leave and log what their sections see")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("equivocation")
+                .long("equivocation")
+                .value_name("P")
+                .help("Chance that a malicious elder signs a conflicting block (0-100); default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("runs")
+                .long("runs")
+                .short("r")
+                .value_name("N")
+                .help("Number of simulation runs to aggregate; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .value_name("N")
+                .help("Simulate N runs at the same time, each with a seed of its own drawn from the main one; default: 1")
+                .conflicts_with_all(&["soak", "dual_run", "experiment", "sweep", "replay", "event_socket", "max_memory_mb", "max_runtime"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("fuzz_params")
+                .long("fuzz-params")
+                .value_name("NAME=MIN..MAX,...")
+                .help("Perturb the given parameters within their ranges in every run, e.g. p_add=85..95,init_age=1..3; the parameters are init_age, p_add, p_drop, elder_handover, equivocation, permanent, storm_start, storm_end, storm_rate, diurnal_amplitude, join_targeting, elder_min_age, group_size (which sets the quorum to a majority unless it is given too), quorum and buffer; the adult age is fixed and can't be perturbed")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sweep")
+                .long("sweep")
+                .value_name("NAME=MIN..MAX[:STEP]")
+                .help("Run every combination of the values of the given parameters instead of a simulation and tabulate the results, e.g. group_size=6..12 init_age=1..4; the parameters are the ones of --fuzz-params, so the adult age can't be swept")
+                .conflicts_with_all(&["soak", "dual_run", "experiment", "replay", "record", "snapshot_every", "resume"])
+                .multiple(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("runs_per_config")
+                .long("runs-per-config")
+                .value_name("N")
+                .help("Number of runs of every combination of values in a sweep; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sweep_file")
+                .long("sweep-file")
+                .value_name("FILE")
+                .help("The CSV file the results of a sweep are written to; default: sweep.csv")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_ci_width")
+                .long("max-ci-width")
+                .value_name("PERCENT")
+                .help("Warn about aggregated metrics with wider confidence intervals; default: 10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("summary_out")
+                .long("summary-out")
+                .value_name("FILE")
+                .help("Write the headline metrics (averaged over the runs) to a JSON file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("baseline")
+                .long("baseline")
+                .value_name("FILE")
+                .help("Compare the headline metrics with a summary written by --summary-out")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("regression_threshold")
+                .long("regression-threshold")
+                .value_name("PERCENT")
+                .help("Flag metrics that got worse than the baseline by more than this; default: 5")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help(
+                    "Format of the structure output file: text/csv/json/md; default: text. The \
+                     metrics file, the relocation log and the elder list are written as JSON if \
+                     it is json and as CSV otherwise",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("relocation_log")
+                .long("relocation-log")
+                .value_name("FILE")
+                .help("Write a record of every relocation to a file: the sections, the node's names and ages, the time in transit and the retries")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("elder_list")
+                .long("elder-list")
+                .value_name("FILE")
+                .help("Write the elders of every section at the end of the run to a file, with their names, ages and the iterations they have been elders for")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("metrics_file")
+                .long("metrics-file")
+                .value_name("FILE")
+                .help("Write the statistics every --stats-interval iterations to a file, as a time series")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("history")
+                .long("history")
+                .value_name("ITERATIONS")
+                .help("Only keep the network structure of the latest ITERATIONS iterations")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .value_name("md:FILE")
+                .help("Write a markdown report of the run to FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("graph")
+                .long("graph")
+                .value_name("graphml:FILE")
+                .help("Write the final state of the network to FILE as a GraphML graph")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .value_name("FILE")
+                .help("Record the events handled by every section to FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("event_socket")
+                .long("event-socket")
+                .value_name("PATH")
+                .help("Send splits, merges, relocations and promotions as length-prefixed JSON frames to the Unix socket or named pipe at PATH")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("save_config")
+                .long("save-config")
+                .value_name("FILE")
+                .help("Save the resolved parameters and the seed to FILE, to rerun exactly with --config")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Load the parameters and the seed saved with --save-config, ignoring other options")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("snapshot_every")
+                .long("snapshot-every")
+                .value_name("N")
+                .help("Save the state of the simulation every N iterations, and when interrupted with Ctrl+C, to resume it with --resume")
+                .conflicts_with_all(&["runs", "soak", "dual_run", "replay"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("snapshot_file")
+                .long("snapshot-file")
+                .value_name("FILE")
+                .help("The file snapshots are saved to; default: ageing_sim.snapshot")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .value_name("FILE")
+                .help("Resume the simulation saved in the snapshot FILE, with its parameters, ignoring other options than the snapshot ones")
+                .conflicts_with_all(&["config", "runs", "soak", "dual_run", "replay"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_memory_mb")
+                .long("max-memory-mb")
+                .value_name("MB")
+                .help("Save a snapshot, print the results so far and exit with code 3 once the process uses more memory than this")
+                .conflicts_with_all(&["soak", "dual_run", "experiment", "sweep", "replay"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_runtime")
+                .long("max-runtime")
+                .value_name("TIME")
+                .help("Save a snapshot, print the results so far and exit with code 3 once the simulation has run this long, in seconds or with a unit, e.g. 90m or 12h")
+                .conflicts_with_all(&["soak", "dual_run", "experiment", "sweep", "replay"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("soak")
+                .long("soak")
+                .help("Run simulations indefinitely, checking invariants and restarting after failures"),
+        )
+        .arg(
+            Arg::with_name("dual_run")
+                .long("dual-run")
+                .help("Run the simulation twice from the same seed and check that the final states match"),
+        )
+        .arg(
+            Arg::with_name("experiment")
+                .long("experiment")
+                .value_name("NAME")
+                .help("Run a packaged experiment of --runs runs per setting instead of a simulation: quorum-risk (the time until an attacker first holds a quorum of elders, by its join rate)")
+                .conflicts_with_all(&["soak", "dual_run", "replay", "snapshot_every", "resume"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("attacker_rates")
+                .long("attacker-rates")
+                .value_name("P,...")
+                .help("The percentages of joining nodes belonging to the attacker in the quorum-risk experiment; default: 5,10,15,20,25,30")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("experiment_file")
+                .long("experiment-file")
+                .value_name("FILE")
+                .help("The CSV file the results of the experiment are written to; default: NAME.csv")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("soak_dir")
+                .long("soak-dir")
+                .value_name("DIR")
+                .help("Directory for the log, checkpoint and failures of the soak test (default: soak)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("soak_interval")
+                .long("soak-interval")
+                .value_name("N")
+                .help("Number of iterations between the checks of the soak test (default: 1000)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check_frequency")
+                .long("check-frequency")
+                .value_name("N")
+                .help("Check the invariants of the network every N iterations (default: every iteration if --check-mode is given, never otherwise)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check_mode")
+                .long("check-mode")
+                .value_name("MODE")
+                .help("What to do when an invariant doesn't hold: abort/warn (default: abort)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .value_name("FILE")
+                .help("Record the joins, rejoins, deaths, evictions, relocations, splits and merges with their iterations to FILE as JSON lines, to replay them with --replay")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .value_name("FILE")
+                .help("Replay the churn of the event log FILE with its parameters, ignoring other options, and check that the relocations, splits and merges match; with --section, replay the events of a single section from the trace FILE instead")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("section")
+                .long("section")
+                .value_name("PREFIX")
+                .help("The prefix of the section to replay, e.g. 0110")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sign_cost")
+                .long("sign-cost")
+                .value_name("MICROS")
+                .help("Time of creating a signature, for the CPU estimate (default: 20)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("verify_cost")
+                .long("verify-cost")
+                .value_name("MICROS")
+                .help("Time of verifying a signature, for the CPU estimate (default: 50)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("traffic")
+                .long("traffic")
+                .help("Add up the simulated bytes of the messages between the network and the sections"),
+        )
+        .arg(
+            Arg::with_name("message_size")
+                .long("message-size")
+                .value_name("KIND=BYTES")
+                .help("Size of the messages carrying events of the given kind, e.g. Live=512 or RequestSplit=256; implies --traffic")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("iteration_seconds")
+                .long("iteration-seconds")
+                .value_name("SECONDS")
+                .help("Duration of an iteration, for estimating the bandwidth of the traffic in bytes per second")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("time_events")
+                .long("time-events")
+                .help("Measure the time spent processing events of every kind"),
+        )
+        .arg(
+            Arg::with_name("relocation_fairness")
+                .long("relocation-fairness")
+                .help("Report how likely nodes of every age were to be picked for relocation"),
+        )
+        .arg(
+            Arg::with_name("elder_relocation")
+                .long("elder-relocation")
+                .help("Let the elders decide relocations: the hash of the block they signed for an event decides which node is relocated and where to"),
+        )
+        .arg(
+            Arg::with_name("ping_pong")
+                .long("ping-pong")
+                .value_name("N:M")
+                .help("Track the relocations of every node, reporting the intervals between them and the nodes relocated more than N times within M iterations")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dead_detection")
+                .long("dead-detection")
+                .value_name("D:P")
+                .help("Only process a node's death once a quorum of the elders noticed it; every elder can notice it after D iterations, with a chance of P% in every iteration")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("name_uniformity")
+                .long("name-uniformity")
+                .help("Report sections whose node names are clustered within their range"),
+        )
+        .arg(
+            Arg::with_name("whatif_merge")
+                .long("whatif-merge")
+                .help("Report which sections would merge if churn stopped at the end of the run"),
+        )
+        .arg(
+            Arg::with_name("event_budget")
+                .long("event-budget")
+                .value_name("N")
+                .help("Fail the run if the sections handle more than N events in one iteration")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_hops")
+                .long("max-hops")
+                .value_name("N")
+                .help("Report events passed between sections more than N times; default: 8")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_prefix_len")
+                .long("max-prefix-len")
+                .value_name("BITS")
+                .help("Maximum length of the sections' prefixes; longer ones don't split (default: 64)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("restrict_prefix")
+                .long("restrict-prefix")
+                .value_name("PREFIX")
+                .help("Only simulate the sub-tree of the network under PREFIX")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stub")
+                .long("stub")
+                .value_name("BEHAVIOUR")
+                .help(
+                    "What happens to relocations out of the simulated sub-tree: \
+                     closed/absorb/exchange; default: closed",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("relocation_delay")
+                .long("relocation-delay")
+                .value_name("ITERATIONS")
+                .help("Number of iterations relocated nodes spend in transit; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("reorg_relocations")
+                .long("reorg-relocations")
+                .value_name("POLICY")
+                .help(
+                    "What happens to relocations decided on by splitting or merging sections: \
+                     forward/defer; default: forward",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("transit_loss")
+                .long("transit-loss")
+                .value_name("P")
+                .help("Chance (in percent) of losing a node in transit in an iteration; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("msg_delay_mean")
+                .long("msg-delay-mean")
+                .value_name("ITERATIONS")
+                .help("Mean number of iterations the messages between sections (the requests of joining and relocated nodes and the events of merges) take to arrive, drawn from the geometric distribution; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("msg_loss_prob")
+                .long("msg-loss-prob")
+                .value_name("P")
+                .help("Chance (in percent) of losing the request of a joining or relocated node, which then leaves the network; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("node_data")
+                .long("node-data")
+                .value_name("MB")
+                .help("Amount of data transferred with every relocated node; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("transfer_bandwidth")
+                .long("transfer-bandwidth")
+                .value_name("MB")
+                .help("Data transferred to a section per iteration; default: 0 (unlimited)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("churn")
+                .long("churn")
+                .value_name("MODEL")
+                .help(
+                    "Number of churn events per iteration: fixed (one), poisson:MEAN, \
+                     burst:PERIOD:JOINS:DROPS (one, plus JOINS joins and DROPS drops every PERIOD \
+                     iterations) or trace:FILE (lines of ITERATION JOINS DROPS [REJOINS]); \
+                     default: fixed",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("storm_start")
+                .long("storm-start")
+                .value_name("P")
+                .help("Chance (in percent) that a churn storm starts in an iteration; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("storm_end")
+                .long("storm-end")
+                .value_name("P")
+                .help("Chance (in percent) that a churn storm ends in an iteration; default: 10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("storm_rate")
+                .long("storm-rate")
+                .value_name("N")
+                .help("Number of churn events per iteration during a storm; default: 10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rate_dist")
+                .long("rate-dist")
+                .value_name("DISTR")
+                .help(
+                    "Distribution of the number of events a section handles per iteration: \
+                     unlimited/const:N/uniform:MIN:MAX/exp:MEAN; default: unlimited",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("join_policy")
+                .long("join-policy")
+                .value_name("POLICY")
+                .help("Section joining nodes are directed to: random/smallest/youngest; default: random")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("join_targeting")
+                .long("join-targeting")
+                .value_name("P")
+                .help("Percentage of joining nodes following the join policy (0-100); default: 100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rejoin_policy")
+                .long("rejoin-policy")
+                .value_name("POLICY")
+                .help(
+                    "Which nodes rejoin: random, priority (most uptime first) or lenient (random, \
+                     keeping their age with enough uptime); default: random",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("uptime_credit")
+                .long("uptime-credit")
+                .value_name("ITERATIONS")
+                .help("Uptime sparing rejoining nodes from losing age if lenient; default: 1000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rejoin_prob")
+                .long("rejoin-prob")
+                .value_name("P")
+                .help("Chance (in percent) that a dropped node comes back after --rejoin-delay iterations with its age halved; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rejoin_delay")
+                .long("rejoin-delay")
+                .value_name("ITERATIONS")
+                .help("Number of iterations after which a dropped node comes back, if it does; default: 100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("diurnal_period")
+                .long("diurnal-period")
+                .value_name("ITERATIONS")
+                .help("Length of a daily cycle of the join and drop probabilities; default: none")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("diurnal_amplitude")
+                .long("diurnal-amplitude")
+                .value_name("P")
+                .help("Percentage points by which the daily cycle shifts the probabilities; default: 20")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("diurnal_table")
+                .long("diurnal-table")
+                .value_name("ADD:DROP,...")
+                .help("Join and drop probabilities for consecutive parts of the daily cycle")
+                .requires("diurnal_period")
+                .takes_value(true),
+        );
+    let mut matches = app.clone().get_matches();
+    if matches.is_present("list_examples") {
+        examples::print_list();
+        process::exit(0);
+    }
+    let save_config = matches.value_of("save_config").map(|s| s.to_owned());
+    let snapshot_every = matches.value_of("snapshot_every").map(|n| {
+        n.parse::<u64>()
+            .expect("Snapshot interval must be a number!")
+            .max(1)
+    });
+    let snapshot_file = matches
+        .value_of("snapshot_file")
+        .unwrap_or("ageing_sim.snapshot")
+        .to_owned();
+    let max_memory_mb = matches.value_of("max_memory_mb").map(|mb| {
+        mb.parse::<u64>()
+            .expect("Memory limit must be a number of MiB!")
+    });
+    let max_runtime = matches.value_of("max_runtime").map(|time| {
+        parse_duration(time)
+            .unwrap_or_else(|| panic!("Invalid running time {}, expected e.g. 3600, 90m or 12h", time))
+    });
+    if let Some(file) = matches.value_of("resume") {
+        // the saved parameters are those of the run, already jittered
+        return Params {
+            save_config,
+            snapshot_every,
+            snapshot_file,
+            max_memory_mb,
+            max_runtime,
+            resume: Some(file.to_owned()),
+            fuzz: vec![],
+            ..snapshot::read_params(file)
+        };
+    }
+    if let (Some(file), false) = (matches.value_of("replay"), matches.is_present("section")) {
+        let config = event_log::read_config(file).unwrap_or_else(|msg| panic!("{}", msg));
+        return Params {
+            save_config,
+            replay_log: Some(file.to_owned()),
+            runs: 1,
+            ..use_config(config)
+        };
+    }
+    if let Some(file) = matches.value_of("config") {
+        return Params {
+            save_config,
+            snapshot_every,
+            snapshot_file,
+            max_memory_mb,
+            max_runtime,
+            ..load_config(file)
+        };
+    }
+    let example = matches.value_of("example").map(examples::find);
+    if let Some(example) = example {
+        matches = app.get_matches_from(example.extend_args(env::args()));
+    }
+    let init_age = matches
+        .value_of("initage")
+        .unwrap_or("1")
+        .parse()
+        .expect("Initial age must be a number!");
+    let split = matches
+        .value_of("split")
+        .unwrap_or("complete")
+        .parse()
+        .ok()
+        .expect("Split strategy must be \"always\" or \"complete\".");
+    let drop_dist = DropDist::parse(matches.value_of("drop_dist").unwrap_or("exp"))
+        .unwrap_or_else(|msg| panic!("{}", msg));
+    let norejectyoung = matches.is_present("norejectyoung");
+    let debug_window = matches.value_of("debug_window").map(|s| {
+        let mut range = s.splitn(2, "..").map(|n| n.trim().parse().ok());
+        match (range.next(), range.next()) {
+            (Some(Some(start)), Some(Some(end))) if start < end => (start, end),
+            _ => panic!("Debug window must be START..END, with START lower than END!"),
+        }
+    });
+    let debug_prefix = matches.value_of("debug_prefix").map(|prefix| {
+        Prefix::from_str(prefix).expect("Debug prefix must consist of 0s and 1s!")
+    });
+    let pseudonyms = matches.is_present("pseudonyms");
+    let stats_verbosity = matches
+        .value_of("stats_verbosity")
+        .unwrap_or("final")
+        .parse()
+        .ok()
+        .expect("Stats verbosity must be final/compact/full.");
+    let tui = matches.is_present("tui");
+    let stats_interval = matches
+        .value_of("stats_interval")
+        .unwrap_or("10000")
+        .parse()
+        .ok()
+        .filter(|&interval| interval > 0)
+        .expect("Stats interval must be a positive number!");
+    let infant_quota = matches
+        .value_of("infant_quota")
+        .map(|s| s.parse().expect("Infant quota must be a number!"));
+    let bootstrap_cache = matches
+        .value_of("bootstrap_cache")
+        .map(|s| s.parse().expect("Bootstrap cache refresh interval must be a number!"));
+    let misdirected_joins = matches
+        .value_of("misdirected_joins")
+        .unwrap_or("0")
+        .parse()
+        .expect("Misdirected joins must be a number!");
+    assert!(misdirected_joins <= 100, "Misdirected joins must be between 0 and 100!");
+    let key_rotation = matches.value_of("key_rotation").map(|s| {
+        s.parse()
+            .ok()
+            .expect("Key rotation rule must be any/quorum.")
+    });
+    // `--age-inc` is a shorthand for incrementing the ages on both splits and merges
+    let default_age_rule = if matches.is_present("age_inc") {
+        "increment"
+    } else {
+        "keep"
+    };
+    let age_on_split = matches
+        .value_of("age_on_split")
+        .unwrap_or(default_age_rule)
+        .parse()
+        .ok()
+        .expect("Age rule on splits must be keep/increment.");
+    let age_on_merge = matches
+        .value_of("age_on_merge")
+        .unwrap_or(default_age_rule)
+        .parse()
+        .ok()
+        .expect("Age rule on merges must be keep/increment.");
+    let group_size: usize = matches
+        .value_of("group_size")
+        .map_or(GROUP_SIZE, |s| s.parse().expect("Group size must be a number!"));
+    assert!(group_size > 0, "Group size must be at least 1!");
+    let quorum: usize = matches
+        .value_of("quorum")
+        .map_or(group_size / 2 + 1, |s| s.parse().expect("Quorum must be a number!"));
+    assert!(
+        quorum > 0 && quorum <= group_size,
+        "Quorum must be between 1 and the group size!"
+    );
+    let buffer = matches
+        .value_of("buffer")
+        .map_or(BUFFER, |s| s.parse().expect("Buffer must be a number!"));
+    let elder_min_age = matches
+        .value_of("elder_min_age")
+        .map(|s| s.parse().expect("Elder minimum age must be a number!"));
+    let elder_fallback = matches
+        .value_of("elder_fallback")
+        .unwrap_or("quorum")
+        .parse()
+        .ok()
+        .expect("Elder fallback must be shrink/quorum.");
+    let p_add1 = matches
+        .value_of("p_add1")
+        .unwrap_or("90")
+        .parse()
+        .expect("Add probability must be a number!");
+    assert!(p_add1 < 100, "Probability must be between 0 and 100!");
+    let p_drop1 = matches
+        .value_of("p_drop1")
+        .unwrap_or("7")
+        .parse()
+        .expect("Drop probability must be a number!");
+    assert!(p_drop1 < 100, "Probability must be between 0 and 100!");
+    assert!(
+        p_add1 + p_drop1 <= 100,
+        "Add and drop probabilites must add up to at most 100!"
+    );
+    let structure_output_file = matches.value_of("struct_file").map(|s| s.to_owned());
+    let elder_handover = matches
+        .value_of("elder_handover")
+        .unwrap_or("0")
+        .parse()
+        .expect("Elder handover must be a number!");
+    let merge_stabilisation = matches
+        .value_of("merge_stabilisation")
+        .unwrap_or("0")
+        .parse()
+        .expect("Merge stabilisation must be a number!");
+    let scenario_file = matches.value_of("scenario").map(|s| s.to_owned());
+    let example = example.map(|example| example.name.to_owned());
+    let merge_negotiation = matches.is_present("merge_negotiation");
+    let batch_cascades = matches.is_present("batch_cascades");
+    let time_events = matches.is_present("time_events");
+    let name_uniformity = matches.is_present("name_uniformity");
+    let relocation_fairness = matches.is_present("relocation_fairness");
+    let elder_relocation = matches.is_present("elder_relocation");
+    let ping_pong = matches.value_of("ping_pong").map(|s| {
+        let mut parts = s.splitn(2, ':').map(|n| n.trim());
+        match (
+            parts.next().and_then(|n| n.parse().ok()),
+            parts.next().and_then(|m| m.parse().ok()),
+        ) {
+            (Some(limit), Some(window)) if window > 0 => (limit, window),
+            _ => panic!("Ping-pong threshold must be N:M, with M greater than 0!"),
+        }
+    });
+    let dead_detection = matches.value_of("dead_detection").map(|s| {
+        let mut parts = s.splitn(2, ':').map(|n| n.trim());
+        match (
+            parts.next().and_then(|d| d.parse().ok()),
+            parts.next().and_then(|p| p.parse().ok()),
+        ) {
+            (Some(delay), Some(chance)) if chance > 0.0 && chance <= 100.0 => (delay, chance),
+            _ => panic!("Dead detection must be D:P, with P between 0 (exclusive) and 100!"),
+        }
+    });
+    let whatif_merge = matches.is_present("whatif_merge");
+    let event_budget = matches
+        .value_of("event_budget")
+        .map(|s| s.parse().expect("Event budget must be a number!"));
+    let max_hops = matches
+        .value_of("max_hops")
+        .unwrap_or("8")
+        .parse()
+        .expect("Maximum number of hops must be a number!");
+    let malicious = matches
+        .value_of("malicious")
+        .unwrap_or("0")
+        .parse()
+        .expect("Malicious percentage must be a number!");
+    assert!(malicious <= 100, "Percentage must be between 0 and 100!");
+    // `--malicious` is a shorthand for a single equivocating coalition
+    let mut coalitions: Vec<Coalition> = if malicious > 0 {
+        vec![Coalition {
+            fraction: malicious,
+            strategy: AttackStrategy::Equivocate,
+            target: None,
+            rejoins: false,
+            stays: false,
+        }]
+    } else {
+        vec![]
+    };
+    if let Some(fraction) = matches.value_of("attack_fraction") {
+        let fraction: f64 = fraction
+            .parse()
+            .expect("Attack fraction must be a number!");
+        let percent = (fraction * 100.0).round();
+        assert!(
+            (0.0..=1.0).contains(&fraction) && (fraction * 100.0 - percent).abs() < 1e-6,
+            "Attack fraction must be between 0 and 1, in whole percent!"
+        );
+        let target = matches.value_of("attack_target").map(|prefix| {
+            Prefix::from_str(prefix).expect("Attack target must consist of 0s and 1s!")
+        });
+        coalitions.push(Coalition {
+            fraction: percent as u8,
+            strategy: AttackStrategy::Passive,
+            target,
+            rejoins: true,
+            stays: true,
+        });
+    }
+    coalitions.extend(matches.values_of("coalition").into_iter().flat_map(|values| {
+        values.map(|value| {
+            value
+                .parse::<Coalition>()
+                .ok()
+                .expect("Coalitions must be given as PERCENT[:STRATEGY[:PREFIX]]!")
+        })
+    }));
+    assert!(
+        coalitions.iter().map(|c| u32::from(c.fraction)).sum::<u32>() <= 100,
+        "Coalition percentages must add up to at most 100!"
+    );
+    let behaviours: Vec<BehaviourSpec> = matches
+        .values_of("behaviour")
+        .into_iter()
+        .flat_map(|values| {
+            values.map(|value| {
+                value
+                    .parse::<BehaviourSpec>()
+                    .ok()
+                    .expect("Behaviours must be given as KIND:PERCENT[:RATE]!")
+            })
+        })
+        .collect();
+    assert!(
+        behaviours.iter().map(|b| u32::from(b.fraction)).sum::<u32>() <= 100,
+        "Behaviour percentages must add up to at most 100!"
+    );
+    assert!(behaviours.len() < 32, "At most 31 behaviours can be given!");
+    let permanent = matches
+        .value_of("permanent")
+        .unwrap_or("0")
+        .parse()
+        .expect("Permanent percentage must be a number!");
+    assert!(permanent <= 100, "Percentage must be between 0 and 100!");
+    let observers = matches
+        .value_of("observers")
+        .unwrap_or("0")
+        .parse()
+        .expect("Number of observers must be a number!");
+    let equivocation = matches
+        .value_of("equivocation")
+        .unwrap_or("0")
+        .parse()
+        .expect("Equivocation chance must be a number!");
+    assert!(equivocation <= 100, "Percentage must be between 0 and 100!");
+    let runs = matches
+        .value_of("runs")
+        .unwrap_or("1")
+        .parse()
+        .expect("Number of runs must be a number!");
+    assert!(runs > 0, "There must be at least one run!");
+    let jobs = matches
+        .value_of("jobs")
+        .unwrap_or("1")
+        .parse()
+        .expect("Number of jobs must be a number!");
+    assert!(jobs > 0, "There must be at least one job!");
+    let fuzz: Vec<Jitter> = matches
+        .value_of("fuzz_params")
+        .map(|spec| {
+            spec.split(',')
+                .map(|jitter| {
+                    jitter.parse().ok().unwrap_or_else(|| {
+                        panic!("Invalid parameter range {}, expected NAME=MIN..MAX", jitter)
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let sweep: Vec<Sweep> = matches
+        .values_of("sweep")
+        .map(|specs| {
+            specs
+                .flat_map(|spec| spec.split(','))
+                .map(|sweep| {
+                    sweep.parse().ok().unwrap_or_else(|| {
+                        panic!("Invalid parameter sweep {}, expected NAME=MIN..MAX[:STEP]", sweep)
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let ranges = fuzz
+        .iter()
+        .map(|jitter| (jitter.param, jitter.max))
+        .chain(sweep.iter().map(|sweep| (sweep.param, sweep.max)));
+    for (param, max) in ranges {
+        assert!(
+            !param.is_percentage() || max <= 100.0,
+            "{} is a percentage and can't exceed 100!",
+            param.name()
+        );
+        assert!(
+            param != FuzzedParam::AddProbability || max < 100.0,
+            "Add probability must be between 0 and 100!"
+        );
+        assert!(
+            max <= param.max_value(),
+            "{} can't exceed {}!",
+            param.name(),
+            param.max_value()
+        );
+    }
+    let runs_per_config = matches
+        .value_of("runs_per_config")
+        .unwrap_or("1")
+        .parse()
+        .expect("Number of runs per configuration must be a number!");
+    assert!(runs_per_config > 0, "There must be at least one run per configuration!");
+    let sweep_file = matches.value_of("sweep_file").unwrap_or("sweep.csv").to_owned();
+    let max_ci_width = matches
+        .value_of("max_ci_width")
+        .unwrap_or("10")
+        .parse()
+        .expect("Confidence interval width must be a number!");
+    let summary_file = matches.value_of("summary_out").map(|s| s.to_owned());
+    let metrics_file = matches.value_of("metrics_file").map(|s| s.to_owned());
+    let relocation_log = matches.value_of("relocation_log").map(|s| s.to_owned());
+    let elder_list = matches.value_of("elder_list").map(|s| s.to_owned());
+    let baseline_file = matches.value_of("baseline").map(|s| s.to_owned());
+    let regression_threshold = matches
+        .value_of("regression_threshold")
+        .unwrap_or("5")
+        .parse()
+        .expect("Regression threshold must be a number!");
+    let report_format = matches
+        .value_of("format")
+        .unwrap_or("text")
+        .parse()
+        .ok()
+        .expect("Report format must be text/csv/json/md.");
+    let history = matches
+        .value_of("history")
+        .map(|s| s.parse().expect("History length must be a number!"));
+    let report_file = matches.value_of("report").map(|s| {
+        match s.split_at(s.find(':').expect("Report must be given as md:FILE!")) {
+            ("md", file) => file[1..].to_owned(),
+            _ => panic!("Only markdown reports (md:FILE) are supported!"),
+        }
+    });
+    let graph_file = matches.value_of("graph").map(|s| {
+        match s.split_at(s.find(':').expect("Graph must be given as graphml:FILE!")) {
+            ("graphml", file) => file[1..].to_owned(),
+            _ => panic!("Only GraphML graphs (graphml:FILE) are supported!"),
+        }
+    });
+    let trace_file = matches.value_of("trace").map(|s| s.to_owned());
+    let event_socket = matches.value_of("event_socket").map(|s| s.to_owned());
+    let soak = matches.is_present("soak");
+    let dual_run = matches.is_present("dual_run");
+    let experiment = matches.value_of("experiment").map(|name| {
+        name.parse()
+            .ok()
+            .expect("Experiment must be quorum-risk!")
+    });
+    let attacker_rates: Vec<u8> = matches
+        .value_of("attacker_rates")
+        .unwrap_or("5,10,15,20,25,30")
+        .split(',')
+        .map(|rate| {
+            rate.trim()
+                .parse()
+                .ok()
+                .expect("Attacker rates must be comma-separated percentages!")
+        })
+        .collect();
+    assert!(
+        attacker_rates.iter().all(|&rate| rate <= 100),
+        "Attacker rates must be between 0 and 100!"
+    );
+    let experiment_file = matches.value_of("experiment_file").map(|s| s.to_owned());
+    let soak_dir = matches.value_of("soak_dir").unwrap_or("soak").to_owned();
+    let soak_interval = matches
+        .value_of("soak_interval")
+        .unwrap_or("1000")
+        .parse::<u64>()
+        .expect("Soak interval must be a number!")
+        .max(1);
+    let check_mode = matches
+        .value_of("check_mode")
+        .unwrap_or("abort")
+        .parse()
+        .ok()
+        .expect("Check mode must be abort/warn.");
+    let check_frequency = match matches.value_of("check_frequency") {
+        Some(n) => Some(
+            n.parse::<u64>()
+                .expect("Check frequency must be a number!")
+                .max(1),
+        ),
+        None if matches.is_present("check_mode") => Some(1),
+        None => None,
+    };
+    let replay = matches.value_of("replay").map(|file| {
+        let prefix = matches.value_of("section").unwrap();
+        let prefix = Prefix::from_str(prefix).expect("Section prefix must consist of 0s and 1s!");
+        (file.to_owned(), prefix)
+    });
+    let record_file = matches.value_of("record").map(|s| s.to_owned());
+    let restrict_prefix = matches.value_of("restrict_prefix").map(|prefix| {
+        Prefix::from_str(prefix).expect("Restricted prefix must consist of 0s and 1s!")
+    });
+    let sign_cost = matches
+        .value_of("sign_cost")
+        .unwrap_or("20")
+        .parse()
+        .expect("Signing cost must be a number!");
+    let verify_cost = matches
+        .value_of("verify_cost")
+        .unwrap_or("50")
+        .parse()
+        .expect("Verification cost must be a number!");
+    let message_sizes = if matches.is_present("traffic") || matches.is_present("message_size") {
+        let mut sizes = MessageSizes::default();
+        for size in matches.values_of("message_size").into_iter().flatten() {
+            sizes
+                .set(size)
+                .unwrap_or_else(|msg| panic!("Invalid message size {}: {}", size, msg));
+        }
+        Some(sizes)
+    } else {
+        None
+    };
+    let iteration_seconds = matches.value_of("iteration_seconds").map(|seconds| {
+        let seconds: f64 = seconds
+            .parse()
+            .expect("Iteration duration must be a number of seconds!");
+        assert!(seconds > 0.0, "Iteration duration must be positive!");
+        seconds
+    });
+    let max_prefix_len = matches
+        .value_of("max_prefix_len")
+        .unwrap_or("64")
+        .parse::<u8>()
+        .expect("Maximum prefix length must be a number!");
+    assert!(
+        max_prefix_len <= 64,
+        "Maximum prefix length can't exceed the 64 bits of a name!"
+    );
+    if let Some(restricted) = restrict_prefix {
+        assert!(
+            restricted.len() <= max_prefix_len,
+            "Restricted prefix is longer than the maximum prefix length!"
+        );
+    }
+    let stub = matches
+        .value_of("stub")
+        .unwrap_or("closed")
+        .parse()
+        .ok()
+        .expect("Stub behaviour must be closed/absorb/exchange.");
+    let reorg_relocations = matches
+        .value_of("reorg_relocations")
+        .unwrap_or("forward")
+        .parse()
+        .ok()
+        .expect("Relocation policy during splits and merges must be forward/defer.");
+    let relocation_delay = matches
+        .value_of("relocation_delay")
+        .unwrap_or("0")
+        .parse()
+        .expect("Relocation delay must be a number!");
+    let transit_loss = matches
+        .value_of("transit_loss")
+        .unwrap_or("0")
+        .parse()
+        .expect("Transit loss chance must be a number!");
+    let msg_delay_mean: f64 = matches
+        .value_of("msg_delay_mean")
+        .unwrap_or("0")
+        .parse()
+        .expect("Mean message delay must be a number!");
+    assert!(
+        (0.0..=MAX_MSG_DELAY_MEAN).contains(&msg_delay_mean),
+        "Mean message delay must be between 0 and {}!",
+        MAX_MSG_DELAY_MEAN
+    );
+    let msg_loss_prob: f64 = matches
+        .value_of("msg_loss_prob")
+        .unwrap_or("0")
+        .parse()
+        .expect("Message loss chance must be a number!");
+    assert!(
+        (0.0..100.0).contains(&msg_loss_prob),
+        "Message loss chance must be at least 0 and below 100!"
+    );
+    let node_data = matches
+        .value_of("node_data")
+        .unwrap_or("0")
+        .parse()
+        .expect("Node data must be a number!");
+    let transfer_bandwidth = matches
+        .value_of("transfer_bandwidth")
+        .unwrap_or("0")
+        .parse()
+        .expect("Transfer bandwidth must be a number!");
+    let churn = matches
+        .value_of("churn")
+        .unwrap_or("fixed")
+        .parse()
+        .ok()
+        .expect("Churn model must be fixed/poisson:MEAN/burst:PERIOD:JOINS:DROPS/trace:FILE.");
+    let storm_start = matches
+        .value_of("storm_start")
+        .unwrap_or("0")
+        .parse()
+        .expect("Storm start chance must be a number!");
+    let storm_end = matches
+        .value_of("storm_end")
+        .unwrap_or("10")
+        .parse()
+        .expect("Storm end chance must be a number!");
+    let storm_rate = matches
+        .value_of("storm_rate")
+        .unwrap_or("10")
+        .parse()
+        .expect("Storm rate must be a number!");
+    let rate_dist = matches
+        .value_of("rate_dist")
+        .unwrap_or("unlimited")
+        .parse()
+        .ok()
+        .expect("Rate distribution must be unlimited/const:N/uniform:MIN:MAX/exp:MEAN.");
+    let join_policy = matches
+        .value_of("join_policy")
+        .unwrap_or("random")
+        .parse()
+        .ok()
+        .expect("Join policy must be random/smallest/youngest.");
+    let join_targeting = matches
+        .value_of("join_targeting")
+        .unwrap_or("100")
+        .parse()
+        .expect("Join targeting percentage must be a number!");
+    assert!(join_targeting <= 100, "Percentage must be between 0 and 100!");
+    let rejoin_policy = matches
+        .value_of("rejoin_policy")
+        .unwrap_or("random")
+        .parse()
+        .ok()
+        .expect("Rejoin policy must be random/priority/lenient.");
+    let uptime_credit = matches
+        .value_of("uptime_credit")
+        .unwrap_or("1000")
+        .parse()
+        .expect("Uptime credit must be a number!");
+    let rejoin_prob: f64 = matches
+        .value_of("rejoin_prob")
+        .unwrap_or("0")
+        .parse()
+        .expect("Rejoin chance must be a number!");
+    assert!(
+        (0.0..=100.0).contains(&rejoin_prob),
+        "Rejoin chance must be between 0 and 100!"
+    );
+    let rejoin_delay = matches
+        .value_of("rejoin_delay")
+        .unwrap_or("100")
+        .parse()
+        .expect("Rejoin delay must be a number!");
+    let diurnal_period = matches
+        .value_of("diurnal_period")
+        .unwrap_or("0")
+        .parse()
+        .expect("Diurnal period must be a number!");
+    let diurnal_amplitude = matches
+        .value_of("diurnal_amplitude")
+        .unwrap_or("20")
+        .parse()
+        .expect("Diurnal amplitude must be a number!");
+    let diurnal_table: Vec<(u8, u8)> = matches
+        .value_of("diurnal_table")
+        .map(|table| {
+            table
+                .split(',')
+                .map(|entry| {
+                    let mut probs = entry.split(':').map(|p| p.trim().parse().ok());
+                    match (probs.next(), probs.next(), probs.next()) {
+                        (Some(Some(p_add)), Some(Some(p_drop)), None) => (p_add, p_drop),
+                        _ => panic!("Diurnal table entries must be in the form ADD:DROP!"),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    assert!(
+        diurnal_table
+            .iter()
+            .all(|&(p_add, p_drop)| u16::from(p_add) + u16::from(p_drop) <= 100),
+        "Add and drop probabilites must add up to at most 100!"
+    );
+    if let Some(restricted) = restrict_prefix {
+        assert!(
+            coalitions
+                .iter()
+                .filter_map(|c| c.target)
+                .all(|target| target.is_compatible_with(&restricted)),
+            "Coalition targets must be compatible with the restricted prefix!"
+        );
+    }
+    Params {
+        init_age,
+        split_strategy: split,
+        norejectyoung,
+        growth: (p_add1, p_drop1),
+        structure_output_file,
+        drop_dist,
+        debug_window,
+        debug_prefix,
+        pseudonyms,
+        stats_verbosity,
+        tui,
+        stats_interval,
+        infant_quota,
+        bootstrap_cache,
+        misdirected_joins,
+        key_rotation,
+        group_size,
+        quorum,
+        buffer,
+        elder_min_age,
+        elder_fallback,
+        age_on_split,
+        age_on_merge,
+        elder_handover,
+        merge_stabilisation,
+        scenario_file,
+        example,
+        merge_negotiation,
+        batch_cascades,
+        coalitions,
+        behaviours,
+        permanent,
+        observers,
+        equivocation,
+        runs,
+        jobs,
+        fuzz,
+        sweep,
+        runs_per_config,
+        sweep_file,
+        max_ci_width,
+        summary_file,
+        baseline_file,
+        regression_threshold,
+        report_format,
+        metrics_file,
+        relocation_log,
+        elder_list,
+        history,
+        report_file,
+        graph_file,
+        trace_file,
+        record_file,
+        replay_log: None,
+        event_socket,
+        save_config,
+        snapshot_every,
+        snapshot_file,
+        resume: None,
+        max_memory_mb,
+        max_runtime,
+        soak,
+        dual_run,
+        experiment,
+        attacker_rates,
+        experiment_file,
+        soak_dir,
+        soak_interval,
+        check_frequency,
+        check_mode,
+        replay,
+        restrict_prefix,
+        max_prefix_len,
+        sign_cost,
+        time_events,
+        name_uniformity,
+        relocation_fairness,
+        elder_relocation,
+        dead_detection,
+        ping_pong,
+        whatif_merge,
+        event_budget,
+        max_hops,
+        relocation_delay,
+        reorg_relocations,
+        transit_loss,
+        msg_delay_mean,
+        msg_loss_prob,
+        node_data,
+        transfer_bandwidth,
+        verify_cost,
+        message_sizes,
+        iteration_seconds,
+        stub,
+        churn,
+        storm_start,
+        storm_end,
+        storm_rate,
+        diurnal_period,
+        diurnal_amplitude,
+        diurnal_table,
+        rate_dist,
+        join_policy,
+        rejoin_policy,
+        uptime_credit,
+        rejoin_prob,
+        rejoin_delay,
+        join_targeting,
+    }
+}
+
+/// Loads the parameters saved with `--save-config`. The saved seed is used unless `AGE_SEED`
+/// overrides it.
+fn load_config(file: &str) -> Params {
+    let text = fs::read_to_string(file).unwrap_or_else(|err| panic!("Can't read {}: {}", file, err));
+    let config: Config = serde_json::from_str(&text)
+        .unwrap_or_else(|err| panic!("Invalid configuration file {}: {}", file, err));
+    use_config(config)
+}
+
+/// Returns the parameters of a saved configuration, using its seed unless `AGE_SEED` overrides
+/// it
+fn use_config(config: Config) -> Params {
+    if env::var("AGE_SEED").is_err() {
+        env::set_var("AGE_SEED", format!("{:?}", config.seed));
+    }
+    config.params
+}
+
+/// Parses a running time given in seconds, or with a unit: `s`, `m` or `h`
+fn parse_duration(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last()? {
+        (i, 's') => (&s[..i], 1),
+        (i, 'm') => (&s[..i], 60),
+        (i, 'h') => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    number.trim().parse::<u64>().ok().map(|n| n * unit)
+}
@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use serde_json;
+use network::node::Node;
+
+/// A structural change of the network, sent to external consumers. Prefixes are sent as strings
+/// of bits, so that consumers don't need to know their internal representation.
+#[derive(Clone, Debug, Serialize)]
+pub enum BusEvent {
+    /// a new run of the simulation starts
+    RunStarted { run: usize },
+    /// a section split into two
+    Split { prefix: String, into: (String, String) },
+    /// sections merged into one
+    Merge { prefixes: Vec<String>, into: String },
+    /// a node was relocated, under its new name and age; the source or the destination is
+    /// missing if it is outside of the simulated sub-tree
+    Relocation {
+        node: Node,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// a node of the given age became an elder for the first time, in a section of the given size
+    Promotion { prefix: String, age: u8, size: usize },
+}
+
+/// A single frame sent over the bus
+#[derive(Serialize)]
+struct Frame<'a> {
+    iteration: u64,
+    event: &'a BusEvent,
+}
+
+thread_local! {
+    static BUS: RefCell<Option<BufWriter<Box<dyn Write>>>> = RefCell::new(Default::default());
+}
+
+/// Connects to the Unix socket at the given path, if there is one
+#[cfg(unix)]
+fn connect_socket(path: &str) -> Option<Box<dyn Write>> {
+    let stream = UnixStream::connect(path).ok()?;
+    Some(Box::new(stream))
+}
+
+#[cfg(not(unix))]
+fn connect_socket(_: &str) -> Option<Box<dyn Write>> {
+    None
+}
+
+/// Connects the bus to the Unix socket at the given path, or opens it for writing if it is a
+/// named pipe or a regular file
+pub fn connect(path: &str) {
+    let writer: Box<dyn Write> = match connect_socket(path) {
+        Some(socket) => socket,
+        None => Box::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+                .unwrap_or_else(|_| panic!("Couldn't connect to or open {}!", path)),
+        ),
+    };
+    BUS.with(|bus| *bus.borrow_mut() = Some(BufWriter::new(writer)));
+}
+
+/// Disconnects the bus, flushing the frames that haven't been sent yet
+pub fn disconnect() {
+    BUS.with(|bus| {
+        if let Some(mut writer) = bus.borrow_mut().take() {
+            let _ = writer.flush();
+        }
+    })
+}
+
+/// Sends an event over the bus, if it is connected, as a JSON frame prefixed with its length as
+/// a 4 byte big-endian number. Every frame is flushed, so that consumers see it straight away.
+/// If the consumer goes away, the bus disconnects and the simulation carries on.
+pub fn emit(iteration: u64, event: BusEvent) {
+    BUS.with(|bus| {
+        let mut bus = bus.borrow_mut();
+        let sent = match *bus {
+            Some(ref mut writer) => {
+                let frame = serde_json::to_vec(&Frame {
+                    iteration,
+                    event: &event,
+                }).expect("Couldn't serialise an event frame!");
+                writer
+                    .write_all(&(frame.len() as u32).to_be_bytes())
+                    .and_then(|_| writer.write_all(&frame))
+                    .and_then(|_| writer.flush())
+                    .is_ok()
+            }
+            None => return,
+        };
+        if !sent {
+            println!("The event bus consumer went away, no more events will be sent");
+            *bus = None;
+        }
+    })
+}
@@ -0,0 +1,236 @@
+//! A log of what happened to the network as a whole: the nodes joining, rejoining, coming back,
+//! dying and being evicted, which drive a run, and the relocations, splits and merges that came of them.
+//! Replaying a log feeds the recorded churn back to the network instead of generating it, and
+//! gives relocated nodes their recorded names, so the replay doesn't depend on the random
+//! generator producing the same values. Every relocation, split and merge of the replay is
+//! compared with the log, which pinpoints the first iteration in which two versions of the code
+//! disagree.
+//!
+//! The log is a JSON lines file: the configuration of the run first, then an entry per event.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use serde_json;
+use network::node::Node;
+use network::prefix::{Name, Prefix};
+use params::Config;
+
+/// Something that happened to the network
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LoggedEvent {
+    /// a new node joined, with the flags that the node's serialised form leaves out
+    Live { node: Node, flags: u16 },
+    /// a node that had left rejoined; `None` if a rejoin was due, but no node had left
+    Rejoin(Option<Name>),
+    /// a dropped node came back after an absence, with its age halved
+    Return(Name),
+    /// a node died
+    Dead(Name),
+    /// a node was evicted by its section for misbehaving
+    Evict(Name),
+    /// a node was relocated and given a new name
+    Relocate { old: Name, new: Name },
+    /// a section split
+    Split(Prefix),
+    /// sections merged into one with the given prefix
+    Merge(Prefix),
+}
+
+impl LoggedEvent {
+    /// Returns whether the event drives the run, rather than being an outcome of it
+    pub fn is_churn(&self) -> bool {
+        match *self {
+            LoggedEvent::Live { .. }
+            | LoggedEvent::Rejoin(_)
+            | LoggedEvent::Return(_)
+            | LoggedEvent::Dead(_)
+            | LoggedEvent::Evict(_) => true,
+            LoggedEvent::Relocate { .. } | LoggedEvent::Split(_) | LoggedEvent::Merge(_) => false,
+        }
+    }
+}
+
+/// A single line of the log after the configuration
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    iteration: u64,
+    event: LoggedEvent,
+}
+
+/// A log being replayed
+struct Replay {
+    /// the entries not replayed yet
+    entries: VecDeque<Entry>,
+    /// the relocations, splits and merges of the current iteration of the replay
+    outcomes: Vec<LoggedEvent>,
+}
+
+enum State {
+    Off,
+    Recording(BufWriter<File>),
+    Replaying(Replay),
+}
+
+thread_local! {
+    static STATE: RefCell<State> = const { RefCell::new(State::Off) };
+}
+
+/// Starts recording a log to the given file, headed by the configuration of the run
+pub fn start_recording(path: &str, config: &Config) {
+    let file = File::create(path).unwrap_or_else(|_| panic!("Couldn't create file {}!", path));
+    let mut file = BufWriter::new(file);
+    let line = serde_json::to_string(config).expect("Couldn't serialise the configuration!");
+    let _ = writeln!(file, "{}", line);
+    STATE.with(|state| *state.borrow_mut() = State::Recording(file));
+}
+
+/// Reads the configuration the log at the given path was recorded with
+pub fn read_config(path: &str) -> Result<Config, String> {
+    let file = File::open(path).map_err(|_| format!("Couldn't open event log {}!", path))?;
+    let line = BufReader::new(file)
+        .lines()
+        .next()
+        .unwrap_or_else(|| Ok(String::new()))
+        .map_err(|_| format!("Couldn't read event log {}!", path))?;
+    serde_json::from_str(&line).map_err(|err| format!("{}: invalid configuration: {}", path, err))
+}
+
+/// Starts replaying the log at the given path
+pub fn start_replay(path: &str) -> Result<(), String> {
+    let file = File::open(path).map_err(|_| format!("Couldn't open event log {}!", path))?;
+    let mut entries = VecDeque::new();
+    for (i, line) in BufReader::new(file).lines().enumerate().skip(1) {
+        let line = line.map_err(|_| format!("Couldn't read event log {}!", path))?;
+        let entry = serde_json::from_str(&line)
+            .map_err(|err| format!("line {}: invalid entry: {}", i + 1, err))?;
+        entries.push_back(entry);
+    }
+    let replay = Replay {
+        entries,
+        outcomes: vec![],
+    };
+    STATE.with(|state| *state.borrow_mut() = State::Replaying(replay));
+    Ok(())
+}
+
+/// Stops recording or replaying, flushing what is left of a recorded log to the file
+pub fn finish() {
+    STATE.with(|state| {
+        if let State::Recording(mut file) = state.replace(State::Off) {
+            let _ = file.flush();
+        }
+    })
+}
+
+/// Returns whether a log is being replayed
+pub fn is_replaying() -> bool {
+    STATE.with(|state| matches!(*state.borrow(), State::Replaying(_)))
+}
+
+/// Records an event of the given iteration if a log is being recorded. If one is being
+/// replayed, the relocations, splits and merges are kept to be compared with the log.
+pub fn log(iteration: u64, event: LoggedEvent) {
+    STATE.with(|state| match *state.borrow_mut() {
+        State::Off => (),
+        State::Recording(ref mut file) => {
+            let entry = Entry { iteration, event };
+            let line = serde_json::to_string(&entry).expect("Couldn't serialise a log entry!");
+            let _ = writeln!(file, "{}", line);
+        }
+        State::Replaying(ref mut replay) => {
+            if !event.is_churn() {
+                replay.outcomes.push(event);
+            }
+        }
+    })
+}
+
+/// Returns the churn recorded in the given iteration of the log being replayed
+pub fn churn(iteration: u64) -> Vec<LoggedEvent> {
+    STATE.with(|state| match *state.borrow() {
+        State::Replaying(ref replay) => replay
+            .entries
+            .iter()
+            .take_while(|entry| entry.iteration == iteration)
+            .map(|entry| entry.event)
+            .filter(LoggedEvent::is_churn)
+            .collect(),
+        _ => vec![],
+    })
+}
+
+/// Returns the name the node with the given name was relocated to in the given iteration of the
+/// log being replayed, if it was relocated then. A name can be relocated more than once in an
+/// iteration, so the relocations of the name replayed so far are skipped.
+pub fn relocated_name(iteration: u64, old_name: Name) -> Option<Name> {
+    let is_relocation = |event: &LoggedEvent| match *event {
+        LoggedEvent::Relocate { old, .. } => old == old_name,
+        _ => false,
+    };
+    STATE.with(|state| match *state.borrow() {
+        State::Replaying(ref replay) => {
+            let replayed = replay.outcomes.iter().filter(|event| is_relocation(event)).count();
+            replay
+                .entries
+                .iter()
+                .take_while(|entry| entry.iteration == iteration)
+                .map(|entry| entry.event)
+                .filter(is_relocation)
+                .nth(replayed)
+                .and_then(|event| match event {
+                    LoggedEvent::Relocate { new, .. } => Some(new),
+                    _ => None,
+                })
+        }
+        _ => None,
+    })
+}
+
+/// Finishes replaying the given iteration: compares its relocations, splits and merges with the
+/// ones recorded and returns a description of the first difference, if there is one
+pub fn check_iteration(iteration: u64) -> Result<(), String> {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let replay = match *state {
+            State::Replaying(ref mut replay) => replay,
+            _ => return Ok(()),
+        };
+        let mut recorded = vec![];
+        while replay
+            .entries
+            .front()
+            .is_some_and(|entry| entry.iteration == iteration)
+        {
+            let entry = replay.entries.pop_front().unwrap();
+            if !entry.event.is_churn() {
+                recorded.push(entry.event);
+            }
+        }
+        let outcomes = replay.outcomes.split_off(0);
+        if let Some(i) = (0..recorded.len().max(outcomes.len()))
+            .find(|&i| recorded.get(i) != outcomes.get(i))
+        {
+            return Err(format!(
+                "The replay diverged from the log in iteration {}: {} was recorded, but {} \
+                 happened",
+                iteration,
+                recorded.get(i).map_or("nothing".to_owned(), |event| format!("{:?}", event)),
+                outcomes.get(i).map_or("nothing".to_owned(), |event| format!("{:?}", event))
+            ));
+        }
+        Ok(())
+    })
+}
+
+/// Returns whether the log being replayed has no entries after the given iteration
+pub fn replay_finished(iteration: u64) -> bool {
+    STATE.with(|state| match *state.borrow() {
+        State::Replaying(ref replay) => replay
+            .entries
+            .back()
+            .map_or(true, |entry| entry.iteration <= iteration),
+        _ => false,
+    })
+}
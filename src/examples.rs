@@ -0,0 +1,94 @@
+use std::process;
+
+/// A ready-made configuration of the simulation: command line arguments and a scenario checked
+/// during the run
+#[derive(Debug)]
+pub struct Example {
+    /// the name the example is selected by
+    pub name: &'static str,
+    /// what the example shows
+    pub description: &'static str,
+    /// the command line arguments of the example
+    pub args: &'static [&'static str],
+    /// the scenario checked during the run
+    pub scenario: &'static str,
+}
+
+/// The examples built into the binary
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "growth",
+        description: "nodes join much more often than they leave, and the network keeps splitting",
+        args: &["--padd1", "90", "--pdrop1", "7"],
+        scenario: include_str!("../scenarios/examples/growth.txt"),
+    },
+    Example {
+        name: "steady-state",
+        description: "complete sections reject young nodes, holding the network at a small size",
+        args: &["--padd1", "60", "--pdrop1", "40"],
+        scenario: include_str!("../scenarios/examples/steady_state.txt"),
+    },
+    Example {
+        name: "catastrophe",
+        description: "joins stop suddenly and churn storms tear the network down",
+        args: &[
+            "--storm-start",
+            "0.05",
+            "--storm-end",
+            "20",
+            "--storm-rate",
+            "100",
+        ],
+        scenario: include_str!("../scenarios/examples/catastrophe.txt"),
+    },
+    Example {
+        name: "attack",
+        description: "an attacker controlling a tenth of the joining nodes tries to take over sections",
+        args: &["--coalition", "10:equivocate", "--equivocation", "50"],
+        scenario: include_str!("../scenarios/examples/attack.txt"),
+    },
+];
+
+/// Returns the example with the given name, exiting with the list of examples if there is none
+pub fn find(name: &str) -> &'static Example {
+    match EXAMPLES.iter().find(|example| example.name == name) {
+        Some(example) => example,
+        None => {
+            println!("Unknown example {}!", name);
+            print_list();
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the names and descriptions of the examples
+pub fn print_list() {
+    println!("Examples (run with --example NAME):");
+    for example in EXAMPLES {
+        println!("\t{}: {}", example.name, example.description);
+        println!("\t\targuments: {}", example.args.join(" "));
+    }
+}
+
+impl Example {
+    /// Returns the command line with the arguments of the example added, apart from the options
+    /// that are given on the command line already
+    pub fn extend_args<I: IntoIterator<Item = String>>(&self, args: I) -> Vec<String> {
+        let mut args: Vec<_> = args.into_iter().collect();
+        let given: Vec<_> = args
+            .iter()
+            .filter(|arg| arg.starts_with("--"))
+            .map(|arg| arg.split('=').next().unwrap().to_owned())
+            .collect();
+        let mut skipping = false;
+        for arg in self.args {
+            if arg.starts_with("--") {
+                skipping = given.iter().any(|option| option == arg);
+            }
+            if !skipping {
+                args.push(arg.to_string());
+            }
+        }
+        args
+    }
+}
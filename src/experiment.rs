@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::Write;
+use std::process;
+use log;
+use network::Network;
+use params::{AttackStrategy, Coalition, Experiment, Params};
+use random::{random_seed, with_seed};
+use workload::Workload;
+
+/// The number of iterations after which a run is given up on, as in a normal simulation
+const MAX_ITERATIONS: u64 = 100000;
+
+/// The width of the longest bar in the plots
+const PLOT_WIDTH: usize = 50;
+
+/// Runs the experiment and exits
+pub fn run(experiment: Experiment, params: &Params) -> ! {
+    // the experiment makes many runs, so their progress messages are off unless asked for
+    let params = Params {
+        debug_window: params.debug_window.or(Some((0, 0))),
+        ..params.clone()
+    };
+    let file = params
+        .experiment_file
+        .clone()
+        .unwrap_or_else(|| format!("{}.csv", experiment.name()));
+    match experiment {
+        Experiment::QuorumRisk => quorum_risk(&params, &file),
+    }
+    process::exit(0)
+}
+
+/// Runs the simulation with a single attacker coalition until the coalition first holds a
+/// quorum of elders in any section, and returns that iteration, or `None` if it never did
+fn time_to_compromise(params: &Params) -> Option<u64> {
+    let mut network = Network::new(params.clone());
+    let mut workload = Workload::new();
+    for i in 0..MAX_ITERATIONS {
+        log::enter_iteration(params, i);
+        workload.generate(&mut network, params);
+        network.process_events();
+        if let Some(msg) = network.failure() {
+            println!("{}", msg);
+            process::exit(1);
+        }
+        if let Some(iteration) = network.output().coalitions[0].first_compromised {
+            return Some(iteration);
+        }
+    }
+    None
+}
+
+/// Returns the median of the times, where `None` stands for a time longer than any other, or
+/// `None` if more than half of them are
+fn median(times: &[Option<u64>]) -> Option<f64> {
+    let mut sorted: Vec<_> = times.iter().map(|t| t.unwrap_or(u64::MAX)).collect();
+    sorted.sort();
+    let mid = sorted.len() / 2;
+    let median = if sorted.len() % 2 == 1 {
+        sorted[mid] as f64
+    } else {
+        (sorted[mid - 1] as f64 + sorted[mid] as f64) / 2.0
+    };
+    if sorted[mid] == u64::MAX {
+        None
+    } else {
+        Some(median)
+    }
+}
+
+/// Measures the time until an attacker coalition first holds a quorum of elders, for every
+/// rate in `attacker_rates`, over `runs` runs per rate. The coalition follows the strategy and
+/// the target of the first `--coalition`, if one is given. Every run has a seed of its own,
+/// drawn from the main one, so that it can be reproduced alone by setting `AGE_SEED` to it and
+/// adding the coalition with `--coalition`.
+fn quorum_risk(params: &Params, file: &str) {
+    let template = params.coalitions.first().cloned().unwrap_or(Coalition {
+        fraction: 0,
+        strategy: AttackStrategy::Equivocate,
+        target: None,
+        rejoins: false,
+        stays: false,
+    });
+    let mut results = vec![];
+    for &rate in &params.attacker_rates {
+        let run_params = Params {
+            coalitions: vec![Coalition {
+                fraction: rate,
+                ..template
+            }],
+            ..params.clone()
+        };
+        let times: Vec<_> = (0..params.runs)
+            .map(|run| {
+                let seed = random_seed();
+                let time = with_seed(seed, || time_to_compromise(&run_params));
+                match time {
+                    Some(iteration) => println!(
+                        "Attacker rate {}%, run {} (seed {:?}): compromised in iteration {}",
+                        rate,
+                        run + 1,
+                        seed,
+                        iteration
+                    ),
+                    None => println!(
+                        "Attacker rate {}%, run {} (seed {:?}): never compromised",
+                        rate,
+                        run + 1,
+                        seed
+                    ),
+                }
+                time
+            })
+            .collect();
+        results.push((rate, times));
+    }
+
+    let medians: Vec<_> = results.iter().map(|(_, times)| median(times)).collect();
+    let longest = medians.iter().filter_map(|&m| m).fold(1.0, f64::max);
+    println!("\nMedian time to quorum compromise (iterations):");
+    for ((rate, times), median) in results.iter().zip(&medians) {
+        let compromised = times.iter().filter(|t| t.is_some()).count();
+        match *median {
+            Some(median) => {
+                let bar = (median / longest * PLOT_WIDTH as f64).round() as usize;
+                println!(
+                    "{:>3}% |{} {:.0} ({}/{} runs compromised)",
+                    rate,
+                    "#".repeat(bar.max(1)),
+                    median,
+                    compromised,
+                    times.len()
+                );
+            }
+            None => println!(
+                "{:>3}% | never within {} iterations ({}/{} runs compromised)",
+                rate,
+                MAX_ITERATIONS,
+                compromised,
+                times.len()
+            ),
+        }
+    }
+
+    let mut csv = File::create(file).unwrap_or_else(|_| panic!("Couldn't create file {}!", file));
+    let _ = writeln!(csv, "attacker_rate,runs,compromised_runs,median_time_to_compromise");
+    for ((rate, times), median) in results.iter().zip(&medians) {
+        let compromised = times.iter().filter(|t| t.is_some()).count();
+        let median = median.map_or_else(String::new, |m| m.to_string());
+        let _ = writeln!(csv, "{},{},{},{}", rate, times.len(), compromised, median);
+    }
+    println!("\nResults written to {}", file);
+}
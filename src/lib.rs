@@ -0,0 +1,50 @@
+//! A simulation of node ageing in a network of sections that split, merge and relocate their
+//! nodes as they churn.
+//!
+//! The `ageing_sim` binary drives it from the command line; other tools can embed it through
+//! `Network::builder()`, which configures the parameters, runs the random churn and returns the
+//! network for inspection:
+//!
+//! ```
+//! use ageing_sim::Network;
+//!
+//! let network = Network::builder().growth(90, 7).seed([1, 2, 3, 4]).run(2000).unwrap();
+//! println!("Age distribution: {:?}", network.age_distribution());
+//! println!("Summary: {:?}", ageing_sim::stats::summary(&network));
+//! ```
+
+extern crate bincode;
+#[cfg(feature = "crypto")]
+extern crate ed25519_dalek;
+#[cfg(unix)]
+extern crate libc;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tiny_keccak;
+
+#[macro_use]
+pub mod log;
+pub mod network;
+pub mod checks;
+pub mod event_bus;
+pub mod event_log;
+pub mod examples;
+pub mod random;
+pub mod results;
+pub mod params;
+pub mod pseudonyms;
+pub mod scenario;
+pub mod signal;
+pub mod snapshot;
+pub mod stats;
+pub mod trace;
+pub mod workload;
+
+pub use network::{Network, NetworkBuilder};
+pub use network::network::Output;
+pub use network::section::Section;
+pub use params::Params;
+pub use workload::Workload;
@@ -0,0 +1,66 @@
+use std::cell::Cell;
+use std::time::Instant;
+use params::Params;
+
+/// The exit code of a simulation stopped by a resource limit, as opposed to 1 for a failure
+pub const EXIT_CODE: i32 = 3;
+
+/// The number of iterations between checks of the memory use, which needs a system call
+const MEMORY_CHECK_INTERVAL: u64 = 100;
+
+thread_local! {
+    /// When the simulation started
+    static START: Cell<Option<Instant>> = const { Cell::new(None) };
+    /// Whether a limit has been reached
+    static REACHED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Starts the clock of the running time limit
+pub fn start() {
+    START.with(|start| start.set(Some(Instant::now())));
+}
+
+/// Returns whether a limit has been reached
+pub fn reached() -> bool {
+    REACHED.with(Cell::get)
+}
+
+/// Returns the resident memory of the process in MiB, if the system tells it
+#[cfg(target_os = "linux")]
+fn resident_memory_mb() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size as u64 / (1024 * 1024))
+}
+
+/// The resident memory can't be read on this system, so the memory limit isn't enforced
+#[cfg(not(target_os = "linux"))]
+fn resident_memory_mb() -> Option<u64> {
+    None
+}
+
+/// Checks the limits after the iteration, returning a description of the one reached, if any
+pub fn exceeded(params: &Params, iteration: u64) -> Option<String> {
+    let mut limit = None;
+    if let Some(max_runtime) = params.max_runtime {
+        let elapsed = START.with(|start| start.get().map_or(0, |start| start.elapsed().as_secs()));
+        if elapsed >= max_runtime {
+            limit = Some(format!("Reached the running time limit of {} s", max_runtime));
+        }
+    }
+    if let Some(max_memory) = params.max_memory_mb {
+        if (iteration + 1) % MEMORY_CHECK_INTERVAL == 0 {
+            if let Some(memory) = resident_memory_mb().filter(|&memory| memory >= max_memory) {
+                limit = Some(format!(
+                    "Reached the memory limit of {} MiB ({} MiB in use)",
+                    max_memory, memory
+                ));
+            }
+        }
+    }
+    if limit.is_some() {
+        REACHED.with(|reached| reached.set(true));
+    }
+    limit
+}
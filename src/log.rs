@@ -0,0 +1,123 @@
+//! The progress messages printed while the simulation runs, one or more for every event. They
+//! can be compiled out with the `max_level_info` feature for long runs where only the results
+//! matter, which saves formatting and printing them, or switched off at runtime outside of a
+//! window of iterations and for sections outside of a prefix. Instead of being printed, the
+//! last ones can be captured to be shown by a dashboard.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use network::prefix::{Name, Prefix};
+use params::Params;
+
+thread_local! {
+    /// Whether progress messages are currently printed; every thread runs a simulation of its own
+    static ENABLED: Cell<bool> = const { Cell::new(true) };
+    /// The prefix the messages about sections and nodes are restricted to, if any
+    static PREFIX: Cell<Option<Prefix>> = const { Cell::new(None) };
+    /// The number of lines of the last messages kept instead of printed, and the lines, if they
+    /// are captured
+    static CAPTURED: RefCell<Option<(usize, VecDeque<String>)>> = const { RefCell::new(None) };
+}
+
+/// Something a progress message can be about
+pub trait Scope {
+    /// Returns whether messages about it are printed when they are restricted to the prefix
+    fn is_within(&self, prefix: &Prefix) -> bool;
+}
+
+/// Messages about a section are printed if it is under the prefix or covers a part of it
+impl Scope for Prefix {
+    fn is_within(&self, prefix: &Prefix) -> bool {
+        self.is_compatible_with(prefix)
+    }
+}
+
+/// Messages about a node are printed if its name matches the prefix
+impl Scope for Name {
+    fn is_within(&self, prefix: &Prefix) -> bool {
+        prefix.matches(*self)
+    }
+}
+
+/// Switches the progress messages on or off for the given iteration, depending on whether it is
+/// within the debug window (start inclusive, end exclusive), and restricts them to the debug
+/// prefix. Without a window, they are always on.
+pub fn enter_iteration(params: &Params, iteration: u64) {
+    let enabled = params
+        .debug_window
+        .map_or(true, |(start, end)| start <= iteration && iteration < end);
+    ENABLED.with(|cell| cell.set(enabled));
+    PREFIX.with(|prefix| prefix.set(params.debug_prefix));
+}
+
+/// Returns whether progress messages are currently printed
+pub fn enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Returns whether progress messages about the given section or node are currently printed
+pub fn in_scope<S: Scope>(scope: &S) -> bool {
+    enabled()
+        && PREFIX.with(|prefix| {
+            prefix
+                .get()
+                .map_or(true, |prefix| scope.is_within(&prefix))
+        })
+}
+
+/// Keeps the last `lines` lines of the progress messages instead of printing them
+pub fn capture(lines: usize) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some((lines, VecDeque::new())));
+}
+
+/// Prints the progress messages again, dropping the captured ones
+pub fn release() {
+    CAPTURED.with(|captured| *captured.borrow_mut() = None);
+}
+
+/// Returns the last `count` lines of the captured messages, oldest first
+pub fn tail(count: usize) -> Vec<String> {
+    CAPTURED.with(|captured| match *captured.borrow() {
+        Some((_, ref lines)) => lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect(),
+        None => vec![],
+    })
+}
+
+/// Prints a progress message, or keeps it if the messages are captured
+pub fn write(message: fmt::Arguments) {
+    CAPTURED.with(|captured| match *captured.borrow_mut() {
+        Some((capacity, ref mut lines)) => {
+            for line in message.to_string().lines() {
+                if lines.len() == capacity {
+                    let _ = lines.pop_front();
+                }
+                lines.push_back(line.to_owned());
+            }
+        }
+        None => println!("{}", message),
+    })
+}
+
+/// Prints a progress message, unless the `max_level_info` feature is enabled or the current
+/// iteration is outside of the debug window. Messages about a section or a node, given as
+/// `debug!(in prefix_or_name; ...)`, are also skipped if it is outside of the debug prefix;
+/// other messages are printed regardless of it. The arguments are only evaluated if the message
+/// is printed.
+#[macro_export]
+macro_rules! debug {
+    (in $scope:expr; $($arg:tt)*) => {
+        if cfg!(not(feature = "max_level_info")) && $crate::log::in_scope(&$scope) {
+            $crate::log::write(format_args!($($arg)*));
+        }
+    };
+    ($($arg:tt)*) => {
+        if cfg!(not(feature = "max_level_info")) && $crate::log::enabled() {
+            $crate::log::write(format_args!($($arg)*));
+        }
+    };
+}
@@ -1,199 +1,669 @@
+#[macro_use]
+extern crate ageing_sim;
 extern crate clap;
-extern crate rand;
-extern crate serde;
+#[cfg(unix)]
+extern crate libc;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
-extern crate tiny_keccak;
 
-mod network;
-mod random;
-mod params;
+#[cfg(feature = "alloc-stats")]
+mod alloc_stats;
+mod cli;
+mod experiment;
+mod limits;
+mod soak;
+mod sweep;
+mod tui;
 
-use random::random_range;
-use network::{Network, NetworkStructure};
-use params::Params;
+use ageing_sim::{checks, event_bus, event_log, examples, log, network, params, pseudonyms, random, results,
+                 scenario, signal, snapshot, stats, trace, workload};
+use cli::get_params;
+use results::{print_dist, print_dist_summary, print_results, print_sparse_dist};
+use event_bus::BusEvent;
+use network::Network;
+use pseudonyms::Pseudonyms;
+use network::network::RelocationRecord;
+use stats::{Recorder, ReportFormat};
+use stats::metrics::{Metrics, MetricsWriter};
+use params::{ChurnModel, Config, Params, StatsVerbosity};
+use scenario::Scenario;
+use tui::Dashboard;
+use workload::Workload;
 use std::collections::BTreeMap;
-use clap::{App, Arg};
-
-/// Generates a random churn event in the network. There are three possible kinds:
-/// node joining, node leaving and node rejoining.
-fn random_event(network: &mut Network, probs: (u8, u8)) {
-    let x = random_range(0, 100);
-    if x < probs.0 {
-        network.add_random_node();
-    } else if x >= probs.0 && x < probs.0 + probs.1 {
-        network.drop_random_node();
-    } else {
-        network.rejoin_random_node();
-    }
-}
-
-fn print_dist(mut dist: BTreeMap<u8, usize>) {
-    let mut age = 1;
-    while !dist.is_empty() {
-        let num = dist.remove(&age).unwrap_or(0);
-        println!("{}\t{}", age, num);
-        age += 1;
-    }
-}
-
-fn get_params() -> Params {
-    let matches = App::new("Ageing Simulation")
-        .about("Simulates ageing in SAFE network")
-        .arg(
-            Arg::with_name("initage")
-                .short("i")
-                .long("initage")
-                .value_name("AGE")
-                .help("Sets the initial age of newly joining peers; default: 1")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("split")
-                .short("s")
-                .long("split")
-                .value_name("STRATEGY")
-                .help("Selects the strategy for splitting (always/complete); default: complete")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("norejectyoung")
-                .short("y")
-                .long("norejectyoung")
-                .help("Don't reject young peers when one already present in the section"),
-        )
-        .arg(
-            Arg::with_name("p_add1")
-                .long("padd1")
-                .value_name("P")
-                .help("Probability that a peer will join during a step (0-100); default: 90")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("p_drop1")
-                .long("pdrop1")
-                .value_name("P")
-                .help("Probability that a peer will be dropped during a step (0-100); default: 7")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("struct_file")
-                .long("network-struct-out")
-                .short("f")
-                .value_name("FILE")
-                .help("Output file for network structure data")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("drop_dist")
-                .long("drop-dist")
-                .value_name("DISTR")
-                .help("Drop probability distribution based on the age: exponential(exp)/reverse-proportional(rev) (default: exponential)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("age_inc")
-                .long("age-inc")
-                .short("a")
-                .help("Increment node ages on merges and splits")
-        )
-        .get_matches();
-    let init_age = matches
-        .value_of("initage")
-        .unwrap_or("1")
-        .parse()
-        .expect("Initial age must be a number!");
-    let split = matches
-        .value_of("split")
-        .unwrap_or("complete")
-        .parse()
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// The columns of the statistics printed while the simulation runs, with their widths
+const STATS_COLUMNS: [(&str, usize); 14] = [
+    ("iteration", 9),
+    ("nodes", 8),
+    ("sections", 8),
+    ("age_min", 7),
+    ("age_avg", 7),
+    ("age_max", 7),
+    ("size_min", 8),
+    ("size_avg", 8),
+    ("size_max", 8),
+    ("prefix_min", 10),
+    ("prefix_max", 10),
+    ("merges", 7),
+    ("splits", 7),
+    ("relocations", 11),
+];
+
+/// The number of rows of statistics after which the header is repeated
+const STATS_HEADER_EVERY: u64 = 20;
+
+/// Prints the values right-aligned in the columns of the statistics
+fn print_stats_row<T: Display>(values: &[T]) {
+    let row: Vec<_> = values
+        .iter()
+        .zip(&STATS_COLUMNS)
+        .map(|(value, &(_, width))| format!("{:>1$}", value, width))
+        .collect();
+    println!("{}", row.join(" "));
+}
+
+/// Prints the statistics of the run so far, as much of them as `params.stats_verbosity` asks for:
+/// a row of a table, with the header repeated every `STATS_HEADER_EVERY` rows, or before every
+/// row if the distributions follow it
+fn print_window_stats(params: &Params, network: &Network, iteration: u64) {
+    let row = (iteration + 1) / params.stats_interval - 1;
+    if params.stats_verbosity == StatsVerbosity::Full || row % STATS_HEADER_EVERY == 0 {
+        let header: Vec<_> = STATS_COLUMNS.iter().map(|&(name, _)| name).collect();
+        print_stats_row(&header);
+    }
+    let metrics = Metrics::collect(network, iteration);
+    print_stats_row(&[
+        metrics.iteration.to_string(),
+        metrics.nodes.to_string(),
+        metrics.sections.to_string(),
+        metrics.ages.min.to_string(),
+        format!("{:.2}", metrics.ages.mean),
+        metrics.ages.max.to_string(),
+        metrics.section_sizes.min.to_string(),
+        format!("{:.2}", metrics.section_sizes.mean),
+        metrics.section_sizes.max.to_string(),
+        metrics.prefix_lens.min.to_string(),
+        metrics.prefix_lens.max.to_string(),
+        metrics.merges.to_string(),
+        metrics.splits.to_string(),
+        metrics.relocations.to_string(),
+    ]);
+    if params.stats_verbosity != StatsVerbosity::Full {
+        return;
+    }
+    let output = network.output();
+    let age_dist = network.age_distribution();
+    let prefix_lens = network.prefix_len_distribution();
+    print_dist_summary("Ages", &age_dist);
+    print_dist_summary("Section sizes", &network.size_distribution());
+    print_dist_summary("Prefix lengths", &prefix_lens);
+    println!("Age distribution:");
+    print_dist(age_dist);
+    println!("Sections by prefix length:");
+    print_sparse_dist(&prefix_lens);
+    println!("Rejections by age:");
+    print_dist(output.rejected_ages.clone());
+}
+fn output_structure_file(file: &str, format: ReportFormat, data: &Recorder) {
+    use std::fs::File;
+    let mut file = File::create(file)
         .ok()
-        .expect("Split strategy must be \"always\" or \"complete\".");
-    let drop_dist = matches
-        .value_of("drop_dist")
-        .unwrap_or("exp")
-        .parse()
+        .expect(&format!("Couldn't create file {}!", file));
+    let _ = stats::report::write_report(format, data, &mut file);
+}
+
+fn output_relocation_log(file: &str, format: ReportFormat, records: &[RelocationRecord]) {
+    let mut file =
+        File::create(file).unwrap_or_else(|err| panic!("Couldn't create file {}: {}", file, err));
+    stats::relocations::write_relocation_log(format, records, &mut file)
+        .expect("Couldn't write the relocation log!");
+}
+
+fn output_elder_list(file: &str, format: ReportFormat, network: &Network) {
+    let mut file =
+        File::create(file).unwrap_or_else(|err| panic!("Couldn't create file {}: {}", file, err));
+    stats::elders::write_elder_list(format, network, &mut file)
+        .expect("Couldn't write the elder list!");
+}
+
+/// Saves the parameters, apart from the file they are saved to, along with the seed
+fn save_config(file: &str, params: &Params) {
+    let config = Config {
+        seed: random::seed(),
+        params: Params {
+            save_config: None,
+            ..params.clone()
+        },
+    };
+    let json = serde_json::to_string_pretty(&config).expect("Couldn't serialise the configuration!");
+    fs::write(file, json).unwrap_or_else(|_| panic!("Couldn't write file {}!", file));
+}
+
+fn output_report_file(file: &str, params: &Params, network: &Network, failure: Option<&str>) {
+    use std::fs::File;
+    let mut file = File::create(file)
         .ok()
-        .expect("Drop distribution must be exp/exponential/rev/reverse-proportional.");
-    let norejectyoung = matches.is_present("norejectyoung");
-    let inc_age = matches.is_present("age_inc");
-    let p_add1 = matches
-        .value_of("p_add1")
-        .unwrap_or("90")
-        .parse()
-        .expect("Add probability must be a number!");
-    assert!(p_add1 < 100, "Probability must be between 0 and 100!");
-    let p_drop1 = matches
-        .value_of("p_drop1")
-        .unwrap_or("7")
-        .parse()
-        .expect("Drop probability must be a number!");
-    assert!(p_drop1 < 100, "Probability must be between 0 and 100!");
-    assert!(
-        p_add1 + p_drop1 <= 100,
-        "Add and drop probabilites must add up to at most 100!"
-    );
-    let structure_output_file = matches.value_of("struct_file").map(|s| s.to_owned());
-    Params {
-        init_age,
-        split_strategy: split,
-        norejectyoung,
-        growth: (p_add1, p_drop1),
-        structure_output_file,
-        drop_dist,
-        inc_age,
-    }
+        .expect(&format!("Couldn't create file {}!", file));
+    let _ = stats::markdown::write_report(&mut file, params, network, failure);
 }
 
-fn output_structure_file(file: &str, data: &[NetworkStructure]) {
+fn output_graph_file(file: &str, network: &Network) {
     use std::fs::File;
-    use std::io::Write;
     let mut file = File::create(file)
         .ok()
         .expect(&format!("Couldn't create file {}!", file));
-    for (i, data) in data.into_iter().enumerate() {
-        let _ = write!(
-            file,
-            "{} {} {} {}\n",
-            i, data.size, data.sections, data.complete
+    let _ = stats::graphml::write_graph(&mut file, network);
+}
+
+/// Returns the name of the output file for the given run, distinguishing the runs by a suffix
+/// if there are more than one
+fn run_file_name(file: &str, params: &Params, run_index: usize) -> String {
+    if params.runs > 1 {
+        format!("{}.{}", file, run_index + 1)
+    } else {
+        file.to_owned()
+    }
+}
+
+/// Checks that the input files given in the parameters can be read and that the output files
+/// can be created, so that mistakes show up before a long run rather than after it
+fn check_files(params: &Params) -> Result<(), String> {
+    if let Some(ref file) = params.scenario_file {
+        let text = fs::read_to_string(file).map_err(|err| format!("Can't read {}: {}", file, err))?;
+        Scenario::parse(&text).map_err(|msg| format!("Invalid scenario file {}: {}", file, msg))?;
+    }
+    if let Some((ref file, _)) = params.replay {
+        let _ = File::open(file).map_err(|err| format!("Can't read {}: {}", file, err))?;
+    }
+    if let Some(ref file) = params.replay_log {
+        let _ = File::open(file).map_err(|err| format!("Can't read {}: {}", file, err))?;
+    }
+    if let Some(ref file) = params.baseline_file {
+        let _ = stats::baseline::read_baseline(file)?;
+    }
+    if let ChurnModel::Trace(ref file) = params.churn {
+        let _ = workload::read_churn_trace(file)?;
+    }
+    let outputs = params
+        .structure_output_file
+        .iter()
+        .chain(params.report_file.iter())
+        .chain(params.graph_file.iter())
+        .chain(params.trace_file.iter())
+        .chain(params.record_file.iter())
+        .chain(params.summary_file.iter())
+        .chain(params.metrics_file.iter())
+        .chain(params.relocation_log.iter())
+        .chain(params.elder_list.iter())
+        .chain(params.save_config.iter())
+        .chain(params.experiment_file.iter())
+        .chain(Some(&params.sweep_file).filter(|_| !params.sweep.is_empty()))
+        .chain(
+            params
+                .snapshot_every
+                .or(params.max_memory_mb)
+                .or(params.max_runtime)
+                .map(|_| &params.snapshot_file),
         );
+    for file in outputs {
+        let path = Path::new(file);
+        if path.is_dir() {
+            return Err(format!("Can't write to {}: it is a directory", file));
+        }
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if !dir.is_dir() {
+            return Err(format!(
+                "Can't write to {}: directory {} doesn't exist",
+                file,
+                dir.display()
+            ));
+        }
     }
+    Ok(())
+}
+#[cfg(feature = "alloc-stats")]
+fn print_alloc_stats() {
+    let stats = alloc_stats::alloc_stats();
+    println!(
+        "\nAllocations: {} ({:.1} per iteration, at most {} in one), {} deallocations",
+        stats.allocations,
+        stats.allocations as f64 / stats.iterations.max(1) as f64,
+        stats.max_per_iteration,
+        stats.deallocations
+    );
+    println!(
+        "Bytes allocated: {:.1} MB in total, {:.1} MB at peak",
+        stats.bytes as f64 / 1e6,
+        stats.peak_bytes as f64 / 1e6
+    );
 }
 
-fn main() {
-    let params = get_params();
-    let mut network = Network::new(params.clone());
+/// Runs a single simulation, writing its statistics to the metrics time series if there is one.
+/// If an assertion of the scenario fails, the run is stopped and the failure is returned along
+/// with the network.
+fn run(params: &Params, mut metrics: Option<MetricsWriter>) -> (Network, Option<String>) {
+    let (mut network, mut workload, start) = match params.resume {
+        Some(ref file) => {
+            let snapshot = snapshot::load(file);
+            println!("Resuming after {} iterations", snapshot.iterations);
+            (snapshot.network, snapshot.workload, snapshot.iterations)
+        }
+        None => (Network::new(params.clone()), Workload::new(), 0),
+    };
+    let mut scenario = Scenario::from_params(params);
+    let mut dashboard = if params.tui {
+        Some(Dashboard::start())
+    } else {
+        None
+    };
 
-    for i in 0..100000 {
-        println!("Iteration {}...", i);
-        // Generate a random event...
-        random_event(&mut network, params.growth);
+    for i in start..100000 {
+        log::enter_iteration(params, i);
+        debug!("Iteration {}...", i);
+        // Generate random and scripted events...
+        workload.stop_joins(scenario.joins_stopped(i));
+        if !event_log::is_replaying() {
+            // the scripted churn is part of the churn recorded in the event log
+            scenario.apply(&mut network, i);
+        }
+        scenario.change_params(&mut network, i);
+        workload.generate(&mut network, params);
         // ... and process the churn cascade that may happen
         // (every churn event may trigger other churn events, that
         // may trigger others etc.)
         network.process_events();
+        #[cfg(feature = "alloc-stats")]
+        alloc_stats::end_iteration();
+        if signal::dump_requested() {
+            eprintln!("{}", network.diagnostics());
+        }
+        if let Some(ref mut dashboard) = dashboard {
+            if !dashboard.after_iteration(&network, i) {
+                break;
+            }
+        } else if params.stats_verbosity != StatsVerbosity::Final
+            && (i + 1) % params.stats_interval == 0
+        {
+            print_window_stats(params, &network, i);
+        }
+        if let Some(ref mut metrics) = metrics {
+            if (i + 1) % params.stats_interval == 0 {
+                metrics
+                    .write(&Metrics::collect(&network, i))
+                    .expect("Couldn't write the metrics!");
+            }
+        }
+        if let Some(msg) = network.failure().map(str::to_owned) {
+            return (network, Some(msg));
+        }
+        if let Some(msg) = checks::after_iteration(params, &network, i) {
+            return (network, Some(msg));
+        }
+        if let Err(msg) = event_log::check_iteration(i) {
+            return (network, Some(msg));
+        }
+        if let Err(msg) = scenario.check(&network, i) {
+            return (network, Some(msg));
+        }
+        if scenario.drains_after(i) {
+            workload.drain();
+            network.start_drain();
+        }
+        if network.output().drain.is_some() {
+            network.record_drain();
+            let moving = network.num_in_transit() + network.num_in_flight();
+            if network.num_nodes() == 0 && moving == 0 {
+                println!("The network is empty");
+                break;
+            }
+        }
+        if event_log::replay_finished(i) {
+            println!("Replayed the event log to its end, all matching");
+            break;
+        }
+        if let Some(every) = params.snapshot_every {
+            let interrupted = signal::interrupted();
+            if interrupted || (i + 1) % every == 0 {
+                snapshot::save(&params.snapshot_file, params, i + 1, &network, &workload);
+            }
+            if interrupted {
+                let msg = format!(
+                    "Interrupted after iteration {}, resume with --resume {}",
+                    i + 1,
+                    params.snapshot_file
+                );
+                return (network, Some(msg));
+            }
+        }
+        if params.tui && signal::interrupted() {
+            break;
+        }
+        if let Some(limit) = limits::exceeded(params, i) {
+            snapshot::save(&params.snapshot_file, params, i + 1, &network, &workload);
+            println!(
+                "{} after iteration {}, resume with --resume {}",
+                limit,
+                i + 1,
+                params.snapshot_file
+            );
+            break;
+        }
     }
+    (network, None)
+}
 
-    println!("Network state:\n{:?}", network);
-    println!("");
+/// Runs the simulation twice from the same seed and exits, successfully if both runs ended in
+/// the same state: a check that a run depends on nothing but its seed and parameters.
+fn dual_run(params: &Params) -> ! {
+    let seed = random::seed();
+    let hashes: Vec<_> = (0..2)
+        .map(|i| {
+            let (network, failure) = random::with_seed(seed, || run(params, None));
+            let hash = network.state_hash();
+            println!(
+                "Run {}: {}, final state {}",
+                i + 1,
+                failure.as_ref().map_or("finished", |msg| &msg[..]),
+                hash
+            );
+            hash
+        })
+        .collect();
+    if hashes[0] == hashes[1] {
+        println!("The final states match.");
+        process::exit(0);
+    } else {
+        println!("The final states differ!");
+        process::exit(1);
+    }
+}
 
-    println!("{:?}\n", params.clone());
-    println!(
-        "Number of sections: {} (complete: {})",
-        network.num_sections(),
-        network.complete_sections()
-    );
+/// A run of a batch that has been simulated
+struct FinishedRun {
+    /// the index of the run in the batch
+    index: usize,
+    /// the parameters of the run, after jittering
+    params: Params,
+    /// the values drawn for the jittered parameters
+    jitter: Vec<(&'static str, f64)>,
+    network: Network,
+    failure: Option<String>,
+    /// the pseudonyms the node names were shown as during the run, if they are
+    pseudonyms: Option<Pseudonyms>,
+}
 
-    let age_dist = network.age_distribution();
-    println!("\nAge distribution:");
-    print_dist(age_dist);
+/// Draws the jittered parameters of a run, exiting if they don't go together
+fn jittered_params(params: &Params) -> (Params, Vec<(&'static str, f64)>) {
+    params.jittered().unwrap_or_else(|msg| {
+        println!("Invalid jittered parameters: {}", msg);
+        event_bus::disconnect();
+        process::exit(1)
+    })
+}
 
-    let drop_dist = &network.output().drops_dist;
-    println!("\nDrops distribution by age:");
-    print_dist(drop_dist.clone());
+/// Simulates a run of the batch with the given parameters, writing its trace, metrics and event
+/// log if they are asked for
+fn simulate_run(params: &Params, run_params: &Params, run_index: usize) -> (Network, Option<String>) {
+    if let Some(ref file) = params.trace_file {
+        trace::start(&run_file_name(file, params, run_index));
+    }
+    event_bus::emit(0, BusEvent::RunStarted { run: run_index + 1 });
+    if params.pseudonyms {
+        pseudonyms::start();
+    }
+    let metrics = params.metrics_file.as_ref().map(|file| {
+        let file = run_file_name(file, params, run_index);
+        MetricsWriter::create(&file, params.report_format)
+            .unwrap_or_else(|err| panic!("Couldn't create file {}: {}", file, err))
+    });
+    if let Some(ref file) = params.record_file {
+        // the log replays the run alone, with the state the random generator started it in
+        let config = Config {
+            seed: random::state(),
+            params: Params {
+                runs: 1,
+                jobs: 1,
+                record_file: None,
+                save_config: None,
+                ..run_params.clone()
+            },
+        };
+        event_log::start_recording(&run_file_name(file, params, run_index), &config);
+    }
+    if let Some(ref file) = params.replay_log {
+        event_log::start_replay(file).unwrap_or_else(|msg| panic!("{}", msg));
+    }
+    let result = run(run_params, metrics);
+    trace::finish();
+    event_log::finish();
+    result
+}
+
+/// Writes the output files of a simulated run and prints its results, or its failure, after
+/// which the process exits
+fn finish_run(
+    params: &Params,
+    run: FinishedRun,
+    summaries: &mut Vec<stats::aggregate::Summary>,
+    jitters: &mut Vec<stats::aggregate::Summary>,
+) {
+    let FinishedRun {
+        index,
+        params: run_params,
+        jitter,
+        network,
+        failure,
+        pseudonyms,
+    } = run;
+    // the exports show the names the way the run's progress messages did
+    pseudonyms::install(pseudonyms);
+    if let Some(ref file) = params.report_file {
+        let file = run_file_name(file, params, index);
+        output_report_file(&file, &run_params, &network, failure.as_ref().map(|s| &s[..]));
+    }
+    if let Some(ref file) = params.graph_file {
+        output_graph_file(&run_file_name(file, params, index), &network);
+    }
+    if let Some(msg) = failure {
+        println!("{}", msg);
+        event_bus::disconnect();
+        process::exit(1);
+    }
+    print_results(&run_params, &network);
+    #[cfg(feature = "alloc-stats")]
+    print_alloc_stats();
+    summaries.push(stats::summary(&network));
+    jitters.push(jitter);
 
     if let Some(ref file) = params.structure_output_file {
-        output_structure_file(file, &network.output().network_structure);
+        let file = run_file_name(file, params, index);
+        output_structure_file(&file, params.report_format, &network.output().history);
+    }
+    if let Some(ref file) = params.relocation_log {
+        let file = run_file_name(file, params, index);
+        output_relocation_log(&file, params.report_format, &network.output().relocation_log);
+    }
+    if let Some(ref file) = params.elder_list {
+        let file = run_file_name(file, params, index);
+        output_elder_list(&file, params.report_format, &network);
+    }
+}
+
+/// Returns the seeds of the runs of the batch. A single run starts from the main seed; in a batch
+/// of more, every run has a seed of its own, drawn from the main one in the order of the runs, so
+/// that a run can be reproduced alone by setting `AGE_SEED` to it. The seeds are the same whether
+/// the runs are simulated one after another or in parallel.
+fn run_seeds(params: &Params) -> Vec<[u32; 4]> {
+    if params.runs == 1 {
+        vec![random::seed()]
+    } else {
+        (0..params.runs).map(|_| random::random_seed()).collect()
+    }
+}
+
+/// Simulates the runs of the batch on `jobs` threads, each from its seed. The finished runs are
+/// passed to `finish` in their order, whichever order they finish in, so the results don't depend
+/// on the scheduling of the threads.
+fn run_in_parallel<F: FnMut(FinishedRun)>(params: &Params, seeds: &[[u32; 4]], mut finish: F) {
+    // the progress messages of the runs would be interleaved, so they are off unless asked for
+    let params = &Params {
+        debug_window: params.debug_window.or(Some((0, 0))),
+        ..params.clone()
+    };
+    let next_run = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..params.jobs.min(params.runs) {
+            let sender = sender.clone();
+            let next_run = &next_run;
+            let _ = scope.spawn(move || loop {
+                let index = next_run.fetch_add(1, Ordering::SeqCst);
+                if index >= seeds.len() {
+                    break;
+                }
+                random::seed_thread(seeds[index]);
+                let (run_params, jitter) = jittered_params(params);
+                let (network, failure) = simulate_run(params, &run_params, index);
+                let run = FinishedRun {
+                    index,
+                    params: run_params,
+                    jitter,
+                    network,
+                    failure,
+                    pseudonyms: pseudonyms::take(),
+                };
+                if sender.send(run).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        let mut finished = BTreeMap::new();
+        let mut next_to_finish = 0;
+        for run in receiver {
+            let _ = finished.insert(run.index, run);
+            while let Some(run) = finished.remove(&next_to_finish) {
+                println!("Run {} (seed {:?})...", run.index + 1, seeds[run.index]);
+                for &(name, value) in &run.jitter {
+                    println!("Jittered {}: {}", name, value);
+                }
+                finish(run);
+                next_to_finish += 1;
+            }
+        }
+    });
+}
+
+fn main() {
+    if let Err(msg) = random::check_env_seed() {
+        println!("{}", msg);
+        process::exit(1);
+    }
+    let params = get_params();
+    if let Err(msg) = check_files(&params) {
+        println!("{}", msg);
+        process::exit(1);
+    }
+    println!("Seed: {:?}", random::seed());
+    if let Some(ref file) = params.save_config {
+        save_config(file, &params);
+    }
+    let mut summaries = vec![];
+    let mut jitters = vec![];
+    signal::install();
+    limits::start();
+    if params.snapshot_every.is_some() || params.tui {
+        signal::install_interrupt();
+    }
+
+    if let Some((ref file, prefix)) = params.replay {
+        match trace::replay_section(file, prefix, &params) {
+            Ok(count) => println!("Replayed {} events, all matching the trace.", count),
+            Err(msg) => {
+                println!("{}", msg);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if params.soak {
+        soak::soak(&params);
+    }
+
+    if params.dual_run {
+        dual_run(&params);
+    }
+
+    if let Some(experiment) = params.experiment {
+        experiment::run(experiment, &params);
+    }
+
+    if !params.sweep.is_empty() {
+        sweep::run(&params, |params| run(params, None));
+    }
+
+    if let Some(ref path) = params.event_socket {
+        event_bus::connect(path);
+    }
+
+    let seeds = run_seeds(&params);
+    if params.jobs > 1 {
+        run_in_parallel(&params, &seeds, |run| finish_run(&params, run, &mut summaries, &mut jitters));
+    } else {
+        for (run_index, &seed) in seeds.iter().enumerate() {
+            if params.runs > 1 {
+                println!("Run {} (seed {:?})...", run_index + 1, seed);
+            }
+            random::seed_thread(seed);
+            let (run_params, jitter) = jittered_params(&params);
+            for &(name, value) in &jitter {
+                println!("Jittered {}: {}", name, value);
+            }
+            let (network, failure) = simulate_run(&params, &run_params, run_index);
+            let run = FinishedRun {
+                index: run_index,
+                params: run_params,
+                jitter,
+                network,
+                failure,
+                pseudonyms: pseudonyms::take(),
+            };
+            finish_run(&params, run, &mut summaries, &mut jitters);
+            if limits::reached() {
+                break;
+            }
+        }
+    }
+    event_bus::disconnect();
+
+    if params.runs > 1 {
+        stats::print_aggregate(&summaries, params.max_ci_width);
+        if !params.fuzz.is_empty() {
+            stats::print_sensitivity(&jitters, &summaries);
+        }
+    }
+
+    let summary = stats::baseline::mean_summary(&summaries);
+    if let Some(ref file) = params.summary_file {
+        stats::baseline::write_summary(file, &summary);
+    }
+    if let Some(ref file) = params.baseline_file {
+        let baseline = stats::baseline::read_baseline(file).unwrap_or_else(|msg| panic!("{}", msg));
+        stats::baseline::print_comparison(&summary, &baseline, params.regression_threshold);
+    }
+    if limits::reached() {
+        process::exit(limits::EXIT_CODE);
     }
 }
@@ -0,0 +1,58 @@
+use random::random;
+use network::prefix::{Name, Prefix};
+use network::section::Section;
+
+/// The number of names a joining attacker's node tries at most before giving up on landing in
+/// its target and staying wherever the last attempt put it
+pub const MAX_JOIN_ATTEMPTS: u64 = 1 << 20;
+
+/// The attacker elders of a section
+#[derive(Clone, Copy, Debug)]
+pub struct ElderShare {
+    pub prefix: Prefix,
+    /// the number of elders of the section
+    pub elders: usize,
+    /// the number of them belonging to any attacker coalition
+    pub attackers: usize,
+}
+
+impl ElderShare {
+    /// Returns the fraction of the section's elders controlled by attackers
+    pub fn share(&self) -> f64 {
+        self.attackers as f64 / self.elders.max(1) as f64
+    }
+}
+
+/// Simulates an attacker's node that can't choose its name rejoining with new random names
+/// until it lands in the target, starting from the given name. Returns the name it ends up with
+/// and the number of attempts it took.
+pub fn join_into(target: Prefix, mut name: Name) -> (Name, u64) {
+    let mut attempts = 1;
+    while !target.matches(name) && attempts < MAX_JOIN_ATTEMPTS {
+        name = Name(random());
+        attempts += 1;
+    }
+    (name, attempts)
+}
+
+/// Returns the shares of attacker elders in the sections that have any, from the highest
+pub fn elder_shares<'a, I: Iterator<Item = &'a Section>>(sections: I) -> Vec<ElderShare> {
+    let mut shares: Vec<_> = sections
+        .map(|section| {
+            let elders = section.elders();
+            ElderShare {
+                prefix: section.prefix(),
+                elders: elders.len(),
+                attackers: elders.iter().filter(|n| n.is_malicious()).count(),
+            }
+        })
+        .filter(|share| share.attackers > 0)
+        .collect();
+    shares.sort_by(|a, b| {
+        b.share()
+            .partial_cmp(&a.share())
+            .unwrap()
+            .then(a.prefix.cmp(&b.prefix))
+    });
+    shares
+}
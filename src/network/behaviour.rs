@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use random::{from_percent, gen_bool_with_probability, Probability};
+use network::node::Node;
+use params::{BehaviourKind, BehaviourSpec};
+
+/// What a node does in an iteration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// the node carries on as usual
+    Stay,
+    /// the node drops out of the network
+    Drop,
+    /// the node misbehaves; its section notices and evicts it
+    Misbehave,
+}
+
+/// Decides how the nodes following it act, independently of the random churn generated by the
+/// workload. Nodes are assigned a behaviour when they join, according to the population
+/// fractions in the parameters; new behaviours only need an implementation of this trait and a
+/// `BehaviourKind` to be built from.
+pub trait Behaviour: Send + Sync {
+    /// Decides what a node that is a member of a section does in the given iteration
+    fn act(&self, node: &Node, iteration: u64) -> Action;
+
+    /// Decides whether a node that has left the network attempts to rejoin it in the given
+    /// iteration
+    fn rejoins(&self, node: &Node, iteration: u64) -> bool;
+}
+
+/// Nodes that only leave and rejoin as part of the random churn
+pub struct Honest;
+
+impl Behaviour for Honest {
+    fn act(&self, _: &Node, _: u64) -> Action {
+        Action::Stay
+    }
+
+    fn rejoins(&self, _: &Node, _: u64) -> bool {
+        false
+    }
+}
+
+/// Nodes with an unreliable connection: they drop out often, but come back ten times as fast
+pub struct Flaky {
+    drop: Probability,
+    rejoin: Probability,
+}
+
+impl Behaviour for Flaky {
+    fn act(&self, _: &Node, _: u64) -> Action {
+        if gen_bool_with_probability(self.drop) {
+            Action::Drop
+        } else {
+            Action::Stay
+        }
+    }
+
+    fn rejoins(&self, _: &Node, _: u64) -> bool {
+        gen_bool_with_probability(self.rejoin)
+    }
+}
+
+/// Nodes that misbehave once they are trusted enough to be adults, and keep rejoining after
+/// being evicted
+pub struct Malicious {
+    misbehave: Probability,
+}
+
+impl Behaviour for Malicious {
+    fn act(&self, node: &Node, _: u64) -> Action {
+        if node.is_adult() && gen_bool_with_probability(self.misbehave) {
+            Action::Misbehave
+        } else {
+            Action::Stay
+        }
+    }
+
+    fn rejoins(&self, _: &Node, _: u64) -> bool {
+        gen_bool_with_probability(self.misbehave)
+    }
+}
+
+/// Builds the built-in behaviour described by the specification
+pub fn build(spec: &BehaviourSpec) -> Arc<dyn Behaviour> {
+    match spec.kind {
+        BehaviourKind::Honest => Arc::new(Honest),
+        BehaviourKind::Flaky => Arc::new(Flaky {
+            drop: from_percent(spec.rate),
+            rejoin: from_percent((10.0 * spec.rate).min(100.0)),
+        }),
+        BehaviourKind::Malicious => Arc::new(Malicious {
+            misbehave: from_percent(spec.rate),
+        }),
+    }
+}
@@ -0,0 +1,188 @@
+use log;
+use network::Network;
+use params::{DropDist, Params, Strategy};
+use random::with_seed;
+use scenario::Scenario;
+use workload::Workload;
+
+/// Configures and runs a simulation without going through the command line. The parameters
+/// start out as the command line defaults, except that the progress messages are off unless a
+/// debug window is set.
+///
+/// ```
+/// use ageing_sim::Network;
+///
+/// let network = Network::builder()
+///     .growth(90, 7)
+///     .group_size(6)
+///     .seed([1, 2, 3, 4])
+///     .run(2000)
+///     .unwrap();
+/// assert!(network.failure().is_none());
+/// assert!(network.sections().all(|section| section.elder_count() <= 6));
+/// println!("{} nodes in {} sections", network.num_nodes(), network.num_sections());
+/// ```
+///
+/// Invalid parameters are reported instead of starting the simulation:
+///
+/// ```
+/// use ageing_sim::Network;
+///
+/// let result = Network::builder().group_size(6).quorum(7).build();
+/// assert!(result.is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct NetworkBuilder {
+    params: Params,
+    seed: Option<[u32; 4]>,
+}
+
+impl NetworkBuilder {
+    /// Creates a builder with the default parameters
+    pub fn new() -> NetworkBuilder {
+        NetworkBuilder {
+            params: Params {
+                debug_window: Some((0, 0)),
+                ..Default::default()
+            },
+            seed: None,
+        }
+    }
+
+    /// Replaces all the parameters, for the settings without a method of their own
+    pub fn params(self, params: Params) -> NetworkBuilder {
+        NetworkBuilder { params, ..self }
+    }
+
+    /// Sets the age of the nodes joining the network for the first time
+    pub fn init_age(mut self, age: u8) -> NetworkBuilder {
+        self.params.init_age = age;
+        self
+    }
+
+    /// Sets when the sections split
+    pub fn split_strategy(mut self, strategy: Strategy) -> NetworkBuilder {
+        self.params.split_strategy = strategy;
+        self
+    }
+
+    /// Sets the chances (in percent) of a node joining and of a node dropping in an iteration
+    pub fn growth(mut self, add: u8, drop: u8) -> NetworkBuilder {
+        self.params.growth = (add, drop);
+        self
+    }
+
+    /// Sets the number of elders of a complete section. The quorum becomes more than half of
+    /// them, unless `quorum` is set afterwards.
+    pub fn group_size(mut self, size: usize) -> NetworkBuilder {
+        self.params.group_size = size;
+        self.params.quorum = size / 2 + 1;
+        self
+    }
+
+    /// Sets the number of elders needed for a section to reach a decision
+    pub fn quorum(mut self, quorum: usize) -> NetworkBuilder {
+        self.params.quorum = quorum;
+        self
+    }
+
+    /// Sets the number of adults both halves of a section need on top of the group size for it
+    /// to split
+    pub fn buffer(mut self, buffer: usize) -> NetworkBuilder {
+        self.params.buffer = buffer;
+        self
+    }
+
+    /// Sets how the dropping nodes are chosen by age
+    pub fn drop_dist(mut self, dist: DropDist) -> NetworkBuilder {
+        self.params.drop_dist = dist;
+        self
+    }
+
+    /// Sets the maximum length of the sections' prefixes
+    pub fn max_prefix_len(mut self, len: u8) -> NetworkBuilder {
+        self.params.max_prefix_len = len;
+        self
+    }
+
+    /// Prints the progress messages from iteration `start` up to (excluding) `end`
+    pub fn debug_window(mut self, start: u64, end: u64) -> NetworkBuilder {
+        self.params.debug_window = Some((start, end));
+        self
+    }
+
+    /// Makes the run reproducible by seeding the random number generator. Without a seed, the
+    /// run continues the random stream of the thread.
+    pub fn seed(mut self, seed: [u32; 4]) -> NetworkBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Creates the network, before any iteration has run, or returns what is wrong with the
+    /// parameters
+    pub fn build(self) -> Result<Network, String> {
+        self.params.validate()?;
+        Ok(Network::new(self.params))
+    }
+
+    /// Runs the given number of iterations of random churn and returns the network, or what is
+    /// wrong with the parameters. The run stops early if the network fails; `Network::failure`
+    /// then tells why.
+    pub fn run(self, iterations: u64) -> Result<Network, String> {
+        self.params.validate()?;
+        Ok(self.simulate(&mut Scenario::default(), iterations).0)
+    }
+
+    /// Runs the given number of iterations of random churn along with the scripted changes of
+    /// the scenario, and checks its assertions after every iteration. Returns the network, or
+    /// what is wrong with the parameters, why the network failed or which assertion failed
+    /// first.
+    pub fn run_scenario(self, scenario: &mut Scenario, iterations: u64) -> Result<Network, String> {
+        self.params.validate()?;
+        match self.simulate(scenario, iterations) {
+            (network, None) => Ok(network),
+            (_, Some(msg)) => Err(msg),
+        }
+    }
+
+    /// Runs the simulation like the `ageing_sim` binary does, stopping early at the first failure
+    fn simulate(self, scenario: &mut Scenario, iterations: u64) -> (Network, Option<String>) {
+        let params = self.params;
+        let mut run = || {
+            let mut network = Network::new(params.clone());
+            let mut workload = Workload::new();
+            for i in 0..iterations {
+                log::enter_iteration(&params, i);
+                workload.stop_joins(scenario.joins_stopped(i));
+                scenario.apply(&mut network, i);
+                scenario.change_params(&mut network, i);
+                workload.generate(&mut network, &params);
+                network.process_events();
+                if let Some(msg) = network.failure().map(str::to_owned) {
+                    return (network, Some(msg));
+                }
+                if let Err(msg) = scenario.check(&network, i) {
+                    return (network, Some(msg));
+                }
+                if scenario.drains_after(i) {
+                    workload.drain();
+                    network.start_drain();
+                }
+                if network.output().drain.is_some() {
+                    network.record_drain();
+                }
+            }
+            (network, None)
+        };
+        match self.seed {
+            Some(seed) => with_seed(seed, run),
+            None => run(),
+        }
+    }
+}
+
+impl Default for NetworkBuilder {
+    fn default() -> NetworkBuilder {
+        NetworkBuilder::new()
+    }
+}
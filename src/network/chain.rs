@@ -0,0 +1,217 @@
+#[cfg(feature = "crypto")]
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+#[cfg(feature = "crypto")]
+use std::collections::BTreeMap;
+#[cfg(feature = "crypto")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "crypto")]
+use ed25519_dalek::{ExpandedSecretKey, PublicKey, Signature, Verifier};
+use random::{self, gen_bool_with_probability, ratio, ONE};
+use network::hash::Hash;
+use network::node::Node;
+#[cfg(feature = "crypto")]
+use network::prefix::Name;
+
+/// Statistics of the cryptographic operations performed when appending blocks to the chains
+#[cfg(feature = "crypto")]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CryptoStats {
+    /// the number of signatures created
+    pub signatures: u64,
+    /// the total time spent signing
+    pub sign_time: Duration,
+    /// the number of signatures verified
+    pub verifications: u64,
+    /// the total time spent verifying
+    pub verify_time: Duration,
+}
+
+#[cfg(feature = "crypto")]
+thread_local! {
+    static STATS: RefCell<CryptoStats> = RefCell::new(Default::default());
+
+    /// Deriving a public key costs about as much as signing, so they are cached
+    static PUBLIC_KEYS: RefCell<BTreeMap<Name, PublicKey>> = RefCell::new(Default::default());
+}
+
+/// Returns the statistics of the cryptographic operations so far.
+#[cfg(feature = "crypto")]
+pub fn crypto_stats() -> CryptoStats {
+    STATS.with(|stats| *stats.borrow())
+}
+
+/// Returns the public key of the node, computing it if it isn't cached yet.
+#[cfg(feature = "crypto")]
+fn public_key(node: &Node) -> PublicKey {
+    PUBLIC_KEYS.with(|keys| {
+        *keys
+            .borrow_mut()
+            .entry(node.name())
+            .or_insert_with(|| PublicKey::from(&node.secret_key()))
+    })
+}
+
+/// A block of a section's data chain: the hash of a churn event, linked to the previous block.
+/// With the `crypto` feature, the block is also signed by the section's elders.
+struct Block {
+    prev: Hash,
+    event: Hash,
+    #[cfg(feature = "crypto")]
+    signatures: Vec<(Node, Signature)>,
+}
+
+impl Block {
+    fn new(prev: Hash, event: Hash) -> Block {
+        Block {
+            prev,
+            event,
+            #[cfg(feature = "crypto")]
+            signatures: vec![],
+        }
+    }
+
+    /// Returns the bytes that get hashed and signed
+    fn payload(&self) -> Vec<u8> {
+        let mut payload = self.prev.as_bytes().to_vec();
+        payload.extend_from_slice(self.event.as_bytes());
+        payload
+    }
+
+    fn hash(&self) -> Hash {
+        Hash::of(&self.payload())
+    }
+
+    /// Adds the signatures of all the given nodes to the block
+    #[cfg(feature = "crypto")]
+    fn sign(&mut self, signers: &BTreeSet<Node>) {
+        let payload = self.payload();
+        for signer in signers {
+            let public = public_key(signer);
+            let start = Instant::now();
+            let signature = ExpandedSecretKey::from(&signer.secret_key()).sign(&payload, &public);
+            let elapsed = start.elapsed();
+            STATS.with(|stats| {
+                let mut stats = stats.borrow_mut();
+                stats.signatures += 1;
+                stats.sign_time += elapsed;
+            });
+            self.signatures.push((*signer, signature));
+        }
+    }
+
+    /// Verifies all the signatures of the block
+    #[cfg(feature = "crypto")]
+    fn verify(&self) -> bool {
+        let payload = self.payload();
+        self.signatures.iter().all(|(signer, signature)| {
+            let public = public_key(signer);
+            let start = Instant::now();
+            let valid = public.verify(&payload, signature).is_ok();
+            let elapsed = start.elapsed();
+            STATS.with(|stats| {
+                let mut stats = stats.borrow_mut();
+                stats.verifications += 1;
+                stats.verify_time += elapsed;
+            });
+            valid
+        })
+    }
+}
+
+/// A conflicting block inserted into the chain by a malicious elder, that the honest nodes
+/// haven't noticed yet
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Fork {
+    /// the height of the chain at which the conflicting block was inserted
+    height: u64,
+    /// the number of nodes that received the conflicting block
+    informed: usize,
+}
+
+/// A section's data chain.
+/// Only the head of the chain is kept in memory, as the simulation never needs to look at the
+/// older blocks.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Chain {
+    len: u64,
+    head: Hash,
+    /// undetected equivocations
+    forks: Vec<Fork>,
+}
+
+impl Chain {
+    /// Creates the chain of a section resulting from a merge of sections with the given chains
+    pub fn merged(chain0: &Chain, chain1: &Chain) -> Chain {
+        Chain {
+            len: chain0.len.max(chain1.len) + 1,
+            head: chain0.head.combine(&chain1.head),
+            forks: chain0.forks.iter().chain(&chain1.forks).cloned().collect(),
+        }
+    }
+
+    /// Appends a block for the event with the given hash, signed by the elders
+    #[cfg_attr(not(feature = "crypto"), allow(unused_variables))]
+    pub fn append(&mut self, event: Hash, elders: &BTreeSet<Node>) {
+        #[cfg_attr(not(feature = "crypto"), allow(unused_mut))]
+        let mut block = Block::new(self.head, event);
+        #[cfg(feature = "crypto")]
+        {
+            block.sign(elders);
+            assert!(
+                block.verify(),
+                "Invalid signature in block {:?}",
+                block.hash()
+            );
+        }
+        self.head = block.hash();
+        self.len += 1;
+    }
+
+    /// Records a conflicting block at the current height, sent to `informed` nodes of the section
+    pub fn fork(&mut self, informed: usize) {
+        self.forks.push(Fork {
+            height: self.len,
+            informed,
+        });
+    }
+
+    /// Simulates a round of gossip between the `nodes` members of the section, in which every
+    /// node compares its chain with a random peer. An equivocation is detected as soon as a node
+    /// holding the conflicting block talks to one that doesn't. Returns the number of blocks it
+    /// took to detect each of the equivocations detected in this round.
+    pub fn gossip(&mut self, nodes: usize) -> Vec<u64> {
+        let mut detected = vec![];
+        let len = self.len;
+        self.forks.retain(|fork| {
+            // the chance that a single node and its peer hold different blocks
+            let total = nodes.max(1) as u64;
+            let informed = fork.informed.min(nodes) as u64;
+            let cross = ratio(2 * informed * (total - informed), total * total);
+            let p_undetected = random::pow(ONE - cross, nodes as u32);
+            if gen_bool_with_probability(ONE - p_undetected) {
+                detected.push(len - fork.height);
+                false
+            } else {
+                true
+            }
+        });
+        detected
+    }
+
+    /// Stops tracking the undetected equivocations. Used after a split, so that only one of the
+    /// resulting sections keeps them and they aren't counted twice.
+    pub fn forget_forks(&mut self) {
+        self.forks.clear();
+    }
+
+    /// Returns the number of blocks in the chain
+    pub fn blocks(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the hash of the latest block
+    pub fn head(&self) -> Hash {
+        self.head
+    }
+}
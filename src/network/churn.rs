@@ -1,13 +1,13 @@
 use network::prefix::{Name, Prefix};
-use network::node::{Digest, Node};
+use network::hash::Hash;
+use network::node::Node;
 use serde_json;
-use tiny_keccak::sha3_256;
 
 /// Events that can happen in the network.
 /// The sections handle them and generate new ones
 /// in the process. Some events can also be generated from
 /// the outside.
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum NetworkEvent {
     Live(Node),
     Lost(Name),
@@ -18,10 +18,14 @@ pub enum NetworkEvent {
 }
 
 impl NetworkEvent {
+    /// The names of all the kinds of events, as returned by `kind`
+    pub const KINDS: &'static [&'static str] =
+        &["Live", "Lost", "Gone", "Relocated", "PrefixChange", "StartMerge"];
+
     /// Returns the digest of some representation of the network event:
     /// used in ageing (to determine if a peer should be relocated).
-    pub fn hash(&self) -> Digest {
-        sha3_256(&serde_json::to_vec(self).unwrap())
+    pub fn hash(&self) -> Hash {
+        Hash::of(&serde_json::to_vec(self).unwrap())
     }
 
     /// Returns the peer passed in the event (if any).
@@ -32,6 +36,38 @@ impl NetworkEvent {
         }
     }
 
+    /// Returns the name of the kind of the event
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            NetworkEvent::Live(_) => "Live",
+            NetworkEvent::Lost(_) => "Lost",
+            NetworkEvent::Gone(_) => "Gone",
+            NetworkEvent::Relocated(_) => "Relocated",
+            NetworkEvent::PrefixChange(_) => "PrefixChange",
+            NetworkEvent::StartMerge(_) => "StartMerge",
+        }
+    }
+
+    /// Returns whether the event is a request of a node to join or leave the section, as
+    /// opposed to bookkeeping after splits and merges. Only requests are subject to the
+    /// sections' processing rates.
+    pub fn is_request(&self) -> bool {
+        match *self {
+            NetworkEvent::Live(_) | NetworkEvent::Lost(_) | NetworkEvent::Relocated(_) => true,
+            NetworkEvent::Gone(_) | NetworkEvent::PrefixChange(_) | NetworkEvent::StartMerge(_) => {
+                false
+            }
+        }
+    }
+
+    /// Returns the name of the node the event is about, if any
+    pub fn name(&self) -> Option<Name> {
+        match *self {
+            NetworkEvent::Lost(name) => Some(name),
+            _ => self.get_node().map(|node| node.name()),
+        }
+    }
+
     /// This function determines whether an event should count towards
     /// churn in ageing peers in the section. Currently true for all events.
     pub fn should_count(&self) -> bool {
@@ -46,11 +82,57 @@ impl NetworkEvent {
 /// The network processes them and responds with churn
 /// events that the nodes would add to their data chains
 /// in the real network.
-#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum SectionEvent {
     NodeDropped(Node),
     NodeRejected(Node),
-    NeedRelocate(Node),
+    /// a node has to be relocated, as decided based on the given hash
+    NeedRelocate(Node, Hash),
+    ElderRelocated(Node),
+    /// a node became an elder for the first time, at the given age, in a section of the given
+    /// size
+    ElderPromoted(u8, usize),
+    /// a malicious elder from the given coalition signed a conflicting block
+    Equivocated(u8),
+    EquivocationDetected(u64),
     RequestMerge,
     RequestSplit,
+    /// the section should split, but its prefix is already at the maximum length
+    SplitRefused,
+    /// the section's elders changed enough to need a new section key
+    KeyRotated,
+}
+
+impl SectionEvent {
+    /// The names of all the kinds of events, as returned by `kind`
+    pub const KINDS: &'static [&'static str] = &[
+        "NodeDropped",
+        "NodeRejected",
+        "NeedRelocate",
+        "ElderRelocated",
+        "ElderPromoted",
+        "Equivocated",
+        "EquivocationDetected",
+        "RequestMerge",
+        "RequestSplit",
+        "SplitRefused",
+        "KeyRotated",
+    ];
+
+    /// Returns the name of the kind of the event
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            SectionEvent::NodeDropped(_) => "NodeDropped",
+            SectionEvent::NodeRejected(_) => "NodeRejected",
+            SectionEvent::NeedRelocate(..) => "NeedRelocate",
+            SectionEvent::ElderRelocated(_) => "ElderRelocated",
+            SectionEvent::ElderPromoted(..) => "ElderPromoted",
+            SectionEvent::Equivocated(_) => "Equivocated",
+            SectionEvent::EquivocationDetected(_) => "EquivocationDetected",
+            SectionEvent::RequestMerge => "RequestMerge",
+            SectionEvent::RequestSplit => "RequestSplit",
+            SectionEvent::SplitRefused => "SplitRefused",
+            SectionEvent::KeyRotated => "KeyRotated",
+        }
+    }
 }
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+/// The kinds of messages a section signs with its authority
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SignedMessage {
+    /// a section announcing its split to the resulting sections
+    Split,
+    /// a section agreeing to merge, sent to the merged section
+    Merge,
+    /// a section sending a node to its new section
+    Relocation,
+    /// a section announcing its new elders to its neighbours
+    Promotion,
+}
+
+/// Counts the messages signed by sections and estimates the signing and verification work
+/// they cause. A message is signed by every elder of the sending section, and every elder of
+/// the receiving sections verifies a quorum of the signatures.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SigningCost {
+    /// the number of messages of every kind
+    pub messages: BTreeMap<SignedMessage, u64>,
+    /// the number of signatures created
+    pub signatures: u64,
+    /// the number of signatures verified
+    pub verifications: u64,
+    /// the distribution of the number of messages signed in a single iteration
+    pub per_iteration: BTreeMap<u64, usize>,
+    /// the number of messages signed in the current iteration
+    current: u64,
+}
+
+impl SigningCost {
+    /// Records a message signed by `signers` elders and received by `verifiers` elders, who
+    /// each verify `quorum` of the signatures
+    pub fn record(&mut self, kind: SignedMessage, signers: usize, verifiers: usize, quorum: usize) {
+        *self.messages.entry(kind).or_insert(0) += 1;
+        self.signatures += signers as u64;
+        self.verifications += (verifiers * quorum.min(signers)) as u64;
+        self.current += 1;
+    }
+
+    /// Closes the current iteration
+    pub fn end_iteration(&mut self) {
+        *self.per_iteration.entry(self.current).or_insert(0) += 1;
+        self.current = 0;
+    }
+
+    /// Returns the total number of messages
+    pub fn total_messages(&self) -> u64 {
+        self.messages.values().sum()
+    }
+
+    /// Returns the estimated CPU time of signing and verifying, in seconds, given the time of
+    /// a single signature and verification in microseconds
+    pub fn cpu_time(&self, sign_micros: f64, verify_micros: f64) -> (f64, f64) {
+        (
+            self.signatures as f64 * sign_micros / 1e6,
+            self.verifications as f64 * verify_micros / 1e6,
+        )
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+use std::str::FromStr;
+use tiny_keccak::sha3_256;
+
+/// The length of a hash in bytes
+pub const HASH_LEN: usize = 32;
+
+/// A SHA3-256 hash, used for the blocks of the data chains and for deciding which nodes to
+/// relocate
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Hash(pub [u8; HASH_LEN]);
+
+/// The byte order in which bytes of a hash are interpreted as a number
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+impl Hash {
+    /// Hashes the given data
+    pub fn of(data: &[u8]) -> Hash {
+        Hash(sha3_256(data))
+    }
+
+    /// Creates a hash from exactly `HASH_LEN` bytes, or returns `None` if the length is wrong
+    pub fn from_bytes(bytes: &[u8]) -> Option<Hash> {
+        if bytes.len() != HASH_LEN {
+            return None;
+        }
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(bytes);
+        Some(Hash(hash))
+    }
+
+    /// Returns the bytes of the hash
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the first 8 bytes of the hash as a number in the given byte order
+    pub fn to_u64(self, endianness: Endianness) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.0[..8]);
+        match endianness {
+            Endianness::Big => u64::from_be_bytes(bytes),
+            Endianness::Little => u64::from_le_bytes(bytes),
+        }
+    }
+
+    /// Returns the number of trailing zero bits, counting from the end of the last byte
+    pub fn trailing_zeros(&self) -> u8 {
+        let mut result = 0;
+        for byte in self.0.iter().rev() {
+            result += byte.trailing_zeros();
+            if *byte != 0 {
+                break;
+            }
+        }
+        result as u8
+    }
+
+    /// Returns the hash of this hash
+    pub fn rehash(&self) -> Hash {
+        Hash::of(&self.0)
+    }
+
+    /// Returns an endless iterator over the chain of rehashes: the hash of this hash, the hash
+    /// of that, and so on
+    pub fn rehashes(&self) -> Rehashes {
+        Rehashes(*self)
+    }
+
+    /// Returns the hash of this hash followed by the other one
+    pub fn combine(&self, other: &Hash) -> Hash {
+        let mut data = self.0.to_vec();
+        data.extend_from_slice(&other.0);
+        Hash::of(&data)
+    }
+}
+
+/// An iterator over repeated hashes of a hash
+pub struct Rehashes(Hash);
+
+impl Iterator for Rehashes {
+    type Item = Hash;
+
+    fn next(&mut self) -> Option<Hash> {
+        self.0 = self.0.rehash();
+        Some(self.0)
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(fmt, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{:02x}{:02x}{:02x}...",
+            self.0[0], self.0[1], self.0[2]
+        )
+    }
+}
+
+impl FromStr for Hash {
+    type Err = String;
+
+    /// Parses a hash from its hex representation, as displayed
+    fn from_str(s: &str) -> Result<Hash, String> {
+        if s.len() != 2 * HASH_LEN || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("{} isn't {} hex digits", s, 2 * HASH_LEN));
+        }
+        let bytes: Vec<_> = (0..HASH_LEN)
+            .map(|i| u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap())
+            .collect();
+        Ok(Hash::from_bytes(&bytes).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA3-256 hash of no data
+    const EMPTY: &str = "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a";
+
+    #[test]
+    fn hex_round_trip() {
+        let hash = Hash::of(&[]);
+        assert_eq!(hash.to_string(), EMPTY);
+        assert_eq!(EMPTY.parse(), Ok(hash));
+        let hash = Hash::of(b"ageing");
+        assert_eq!(hash.to_string().parse(), Ok(hash));
+        assert_eq!(EMPTY.to_uppercase().parse(), Ok(Hash::of(&[])));
+    }
+
+    #[test]
+    fn parsing_rejects_wrong_lengths() {
+        assert!("".parse::<Hash>().is_err());
+        assert!(EMPTY[1..].parse::<Hash>().is_err());
+        assert!(format!("{}0", EMPTY).parse::<Hash>().is_err());
+    }
+
+    #[test]
+    fn parsing_rejects_non_hex_characters() {
+        let replaced = |i: usize, c: &str| format!("{}{}{}", &EMPTY[..i], c, &EMPTY[i + 1..]);
+        assert!(replaced(5, "g").parse::<Hash>().is_err());
+        assert!(replaced(0, "+").parse::<Hash>().is_err());
+        assert!(replaced(2, "+").parse::<Hash>().is_err());
+        assert!(replaced(7, " ").parse::<Hash>().is_err());
+        // a multi-byte character in place of two digits keeps the length in bytes
+        assert!(format!("{}\u{e9}", &EMPTY[2..]).parse::<Hash>().is_err());
+    }
+
+    #[test]
+    fn from_bytes_checks_the_length() {
+        assert_eq!(Hash::from_bytes(&[7; HASH_LEN]), Some(Hash([7; HASH_LEN])));
+        assert_eq!(Hash::from_bytes(&[7; HASH_LEN - 1]), None);
+        assert_eq!(Hash::from_bytes(&[7; HASH_LEN + 1]), None);
+    }
+
+    #[test]
+    fn to_u64_reads_the_first_bytes_in_the_given_order() {
+        let mut bytes = [0xff; HASH_LEN];
+        for (i, byte) in bytes.iter_mut().take(8).enumerate() {
+            *byte = i as u8 + 1;
+        }
+        let hash = Hash(bytes);
+        assert_eq!(hash.to_u64(Endianness::Big), 0x0102_0304_0506_0708);
+        assert_eq!(hash.to_u64(Endianness::Little), 0x0807_0605_0403_0201);
+    }
+
+    #[test]
+    fn rehashes_chain_the_hashes() {
+        let hash = Hash::of(b"ageing");
+        let chain: Vec<_> = hash.rehashes().take(3).collect();
+        assert_eq!(chain[0], hash.rehash());
+        assert_eq!(chain[1], hash.rehash().rehash());
+        assert_eq!(chain[2], Hash::of(&chain[1].0));
+        assert_ne!(chain[0], hash);
+    }
+}
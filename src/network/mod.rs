@@ -1,15 +1,27 @@
+pub mod attack;
+pub mod behaviour;
+pub mod builder;
+pub mod chain;
 pub mod churn;
+pub mod cost;
+pub mod hash;
 pub mod prefix;
 pub mod node;
 pub mod network;
 pub mod section;
+pub mod timing;
+pub mod traffic;
 
-/// Determines the numbers of the elders in every section
+/// Determines the numbers of the elders in every section, unless `Params::group_size` says
+/// otherwise
 pub const GROUP_SIZE: usize = 8;
+/// The number of elders needed for the section to reach a decision, by default
+pub const QUORUM: usize = GROUP_SIZE / 2 + 1;
 /// A number of spare nodes when splitting - we don't want to
 /// merge again right after we split if a node leaves, so we
 /// only split if the child sections will have at least
-/// GROUP_SIZE + BUFFER nodes
+/// GROUP_SIZE + BUFFER nodes. `Params::buffer` can change it.
 pub const BUFFER: usize = 3;
 
+pub use self::builder::NetworkBuilder;
 pub use self::network::{Network, NetworkStructure};
@@ -1,13 +1,30 @@
-use std::collections::BTreeMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt;
 use std::mem;
-use std::iter::{Iterator, Sum};
-use random::{random, shuffle};
-use network::prefix::Prefix;
+use std::time::Instant;
+use std::iter::{self, Iterator, Sum};
+use std::sync::Arc;
+use random::{from_percent, gen_bool_with_probability, geometric, random, random_range, shuffle};
+use network::hash::{Endianness, Hash};
+use network::prefix::{Name, Prefix};
 use network::node::Node;
-use network::section::Section;
+use network::section::{RelocationTally, Section};
+use network::attack;
+use network::behaviour::{self, Action, Behaviour};
+use network::builder::NetworkBuilder;
 use network::churn::{NetworkEvent, SectionEvent};
-use params::Params;
+use network::cost::{SignedMessage, SigningCost};
+use network::timing::{ProcessingTime, FINALISE_MERGE};
+use network::traffic::Traffic;
+use params::{AgeRule, Coalition, ElderFallback, FuzzedParam, JoinPolicy, Params, RateDist,
+             RejoinPolicy, ReorgRelocations, Stub};
+use event_bus::{self, BusEvent};
+use event_log::{self, LoggedEvent};
+use signal;
+use stats::Recorder;
+use trace;
+use serde_json;
 
 /// A wrapper struct that handles merges in progress
 /// When two sections merge, they need to handle a bunch
@@ -15,17 +32,27 @@ use params::Params;
 /// section. This remembers which sections are in the
 /// process of merging and reports whether all of them are
 /// ready to be combined.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 struct PendingMerge {
     complete: BTreeMap<Prefix, bool>,
+    /// the length of the chain of merges this one continues (1 if it doesn't continue any)
+    depth: usize,
+    /// the names of the elders of the merging sections when the merge was initiated
+    elders: BTreeSet<Name>,
 }
 
 impl PendingMerge {
     /// Creates a new "pending merge" from a set of prefixes - the prefixes passed
     /// are the ones that are supposed to merge
-    fn from_prefixes<I: IntoIterator<Item = Prefix>>(pfxs: I) -> Self {
+    fn from_prefixes<I: IntoIterator<Item = Prefix>>(
+        pfxs: I,
+        depth: usize,
+        elders: BTreeSet<Name>,
+    ) -> Self {
         PendingMerge {
             complete: pfxs.into_iter().map(|pfx| (pfx, false)).collect(),
+            depth,
+            elders,
         }
     }
 
@@ -47,14 +74,355 @@ impl PendingMerge {
     }
 }
 
-#[derive(Clone, Default)]
+/// A merge proposed by one of the sections that the others haven't agreed to yet.
+/// Used only when merges are negotiated: every section under the merged prefix must
+/// accept the proposal before the merge is initiated.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct MergeProposal {
+    /// the sections that accepted the proposal
+    accepted: BTreeSet<Prefix>,
+    /// the number of rounds of exchanging proposals so far
+    rounds: u64,
+}
+
+impl MergeProposal {
+    fn new(initiator: Prefix) -> Self {
+        MergeProposal {
+            accepted: Some(initiator).into_iter().collect(),
+            rounds: 0,
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct NetworkStructure {
     pub size: usize,
     pub sections: usize,
     pub complete: usize,
 }
 
+/// The churn a single section was subjected to
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct SectionChurn {
+    /// the number of nodes that tried to join (or rejoin) the section
+    pub joins: u64,
+    /// the number of nodes that dropped from the section
+    pub drops: u64,
+    /// the number of nodes relocated to the section
+    pub relocations_in: u64,
+    /// the number of nodes relocated away from the section
+    pub relocations_out: u64,
+}
+
+/// Something an observer node saw in its section
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Observation {
+    /// the observer is in a section with a new prefix, after joining, a split or a merge
+    Section(Prefix),
+    /// the section handled an event and responded with the given section events
+    Event(NetworkEvent, Vec<SectionEvent>),
+    /// the section's elders changed
+    Elders(Vec<Node>),
+}
+
+/// Everything an observer node saw, with the iterations in which it happened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObserverLog {
+    pub name: Name,
+    /// the prefix of the observer's section when it last saw something
+    prefix: Option<Prefix>,
+    pub entries: Vec<(u64, Observation)>,
+}
+
+/// The relocations of a node, followed across its changes of name
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct RelocationLog {
+    /// the number of times the node was relocated
+    count: u64,
+    /// the iterations of the node's relocations within the ping-pong window, the latest last
+    recent: VecDeque<u64>,
+    /// whether the node has been flagged for ping-pong
+    flagged: bool,
+}
+
+/// A node relocated more times within the ping-pong window than the limit allows
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PingPong {
+    /// the iteration in which the node went over the limit
+    pub iteration: u64,
+    /// the node, as it was after the relocation that went over the limit
+    pub node: Node,
+    /// the number of the node's relocations within the window
+    pub relocations: usize,
+}
+
+/// A single relocation, logged if the relocation log is exported
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RelocationRecord {
+    /// the iteration in which the relocation was decided
+    pub iteration: u64,
+    pub src: Prefix,
+    /// the section the node joined, or `None` if it never arrived in the simulated network: it
+    /// was lost in transit or relocated out of the simulated sub-tree
+    pub dst: Option<Prefix>,
+    /// the node before the relocation
+    pub old: Node,
+    /// the node after the relocation, with its new name and age
+    pub new: Node,
+    /// the number of iterations the node spent in transit
+    pub transit: u64,
+    /// the number of times the node's name matched no section on arrival, so it was delivered
+    /// again in the next iteration
+    pub retries: u64,
+}
+
+/// The end of life of a network that nodes only leave
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DrainStats {
+    /// the iteration in which the joins stopped
+    pub start: u64,
+    /// the number of sections when the joins stopped
+    pub sections: usize,
+    /// the number of complete sections when the joins stopped
+    pub complete_sections: usize,
+    /// the first iteration without complete sections
+    pub no_complete_sections: Option<u64>,
+    /// the first iteration in which the network was back to a single section
+    pub single_section: Option<u64>,
+    /// the prefixes of the sections created by merges, with the iterations of the merges
+    pub merges: Vec<(u64, Prefix)>,
+}
+
+/// The splits that happened within a window of iterations
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct SplitDepths {
+    /// the number of splits
+    pub splits: usize,
+    /// the sum of the prefix lengths of the sections created by the splits
+    pub total_depth: u64,
+    /// the longest prefix created by the splits
+    pub max_depth: u8,
+}
+
+impl SplitDepths {
+    /// Returns the mean prefix length of the sections created by the splits
+    pub fn mean_depth(&self) -> f64 {
+        self.total_depth as f64 / self.splits.max(1) as f64
+    }
+}
+
+/// The counts of the changes to the network that a parameter change can stir up
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Turbulence {
+    /// the number of relocations
+    pub relocations: u64,
+    /// the number of nodes rejected
+    pub rejections: u64,
+    /// the number of splits
+    pub splits: u64,
+    /// the number of merges
+    pub merges: u64,
+}
+
+impl Turbulence {
+    /// Returns the changes counted since the `earlier` counts
+    fn since(&self, earlier: &Turbulence) -> Turbulence {
+        Turbulence {
+            relocations: self.relocations - earlier.relocations,
+            rejections: self.rejections - earlier.rejections,
+            splits: self.splits - earlier.splits,
+            merges: self.merges - earlier.merges,
+        }
+    }
+
+    /// Adds the changes of another window
+    fn add(&mut self, other: &Turbulence) {
+        self.relocations += other.relocations;
+        self.rejections += other.rejections;
+        self.splits += other.splits;
+        self.merges += other.merges;
+    }
+}
+
+/// A parameter change made by a scenario, with the turbulence in the `TURBULENCE_WINDOW`
+/// iterations before and after it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Transition {
+    /// the iteration in which the parameter changed
+    pub iteration: u64,
+    /// the change, as `NAME=VALUE`, followed by the prefix of the sections it was rolled out to
+    pub change: String,
+    /// the turbulence in the iterations before the change
+    pub before: Turbulence,
+    /// the number of iterations `before` covers, fewer than the window early in a run
+    pub before_iterations: u64,
+    /// the turbulence in the iterations from the change on
+    pub after: Turbulence,
+    /// the number of iterations `after` covers, fewer than the window late in a run
+    pub after_iterations: u64,
+}
+
+/// The merges that would happen if churn stopped, as predicted by `Network::preview_merges`
+#[derive(Clone, Debug, Default)]
+pub struct MergePreview {
+    /// the merges in every round, as the merged prefix and the prefixes merging into it; the
+    /// merges of later rounds are cascades of the earlier ones
+    pub rounds: Vec<Vec<(Prefix, Vec<Prefix>)>>,
+    /// the prefixes and sizes of the sections left after all the merges
+    pub sections: BTreeMap<Prefix, usize>,
+}
+
+/// The prefixes of the sections, ordered by their bits before their lengths, so that the
+/// descendants of a prefix follow it. `Prefix` itself orders by length first, which is the order
+/// in which the network processes the sections, so the index is kept next to the map of sections
+/// instead of replacing it.
 #[derive(Clone, Default)]
+struct PrefixIndex {
+    prefixes: BTreeMap<(u64, u8), Prefix>,
+}
+
+impl PrefixIndex {
+    fn key(prefix: Prefix) -> (u64, u8) {
+        (prefix.bits(), prefix.len())
+    }
+
+    fn insert(&mut self, prefix: Prefix) {
+        let _ = self.prefixes.insert(PrefixIndex::key(prefix), prefix);
+    }
+
+    fn remove(&mut self, prefix: Prefix) {
+        let _ = self.prefixes.remove(&PrefixIndex::key(prefix));
+    }
+
+    fn contains(&self, prefix: Prefix) -> bool {
+        self.prefixes.contains_key(&PrefixIndex::key(prefix))
+    }
+
+    /// Returns the longest prefix in the index matching the name
+    fn longest_match(&self, name: Name) -> Option<Prefix> {
+        let (_, &candidate) = self.prefixes.range(..=(name.0, 64)).next_back()?;
+        if candidate.matches(name) {
+            return Some(candidate);
+        }
+        // any other matching prefix sorts before the candidate only as its ancestor
+        self.ancestors(candidate).find(|pfx| pfx.matches(name))
+    }
+
+    /// Returns the prefixes in the index that are ancestors of the given one, including the
+    /// prefix itself, from the longest
+    fn ancestors<'a>(&'a self, prefix: Prefix) -> impl Iterator<Item = Prefix> + 'a {
+        iter::successors(Some(prefix), |pfx| {
+            if pfx.len() > 0 {
+                Some(pfx.shorten())
+            } else {
+                None
+            }
+        }).filter(move |&pfx| self.contains(pfx))
+    }
+
+    /// Returns the prefixes in the index that are descendants of the given one, including the
+    /// prefix itself, ordered by their bits
+    fn descendants<'a>(&'a self, prefix: Prefix) -> impl Iterator<Item = Prefix> + 'a {
+        let last = prefix.bits() | u64::MAX.checked_shr(u32::from(prefix.len())).unwrap_or(0);
+        self.prefixes
+            .range(PrefixIndex::key(prefix)..=(last, 64))
+            .map(|(_, &pfx)| pfx)
+    }
+
+    /// Returns the prefixes in the index that are ancestors or descendants of the given one
+    fn compatible<'a>(&'a self, prefix: Prefix) -> impl Iterator<Item = Prefix> + 'a {
+        self.ancestors(prefix)
+            .filter(move |&pfx| pfx != prefix)
+            .chain(self.descendants(prefix))
+    }
+
+    /// Returns the prefixes in the index that are neighbours of the given one, in the order of
+    /// the map of sections. A neighbour differs from the prefix in a single bit, so it's
+    /// compatible with the prefix with that bit flipped.
+    fn neighbours(&self, prefix: Prefix) -> Vec<Prefix> {
+        let mut neighbours: Vec<_> = (0..prefix.len())
+            .flat_map(|bit| self.compatible(prefix.with_flipped_bit(bit)))
+            .filter(|pfx| pfx.is_neighbour(&prefix))
+            .collect();
+        neighbours.sort();
+        neighbours.dedup();
+        neighbours
+    }
+}
+
+/// Returns the parameters the section with the given prefix follows: the ones staged for the
+/// longest prefix it matches, or the network's if there are none
+fn section_params<'a>(
+    staged: &'a BTreeMap<Prefix, Params>,
+    params: &'a Params,
+    prefix: &Prefix,
+) -> &'a Params {
+    staged
+        .iter()
+        .filter(|&(pfx, _)| pfx.is_ancestor(prefix))
+        .max_by_key(|&(pfx, _)| pfx.len())
+        .map_or(params, |(_, params)| params)
+}
+
+/// Returns the prefix of the section the sections, which must cover a single prefix, merge into
+fn merged_prefix(sections: &[Section]) -> Option<Prefix> {
+    let shortest = sections.iter().map(Section::prefix).min_by_key(Prefix::len)?;
+    if sections.len() > 1 {
+        Some(shortest.shorten())
+    } else {
+        Some(shortest)
+    }
+}
+
+/// Merges the sections, which must cover a single prefix, pairwise from the longest prefixes
+fn merge_sections(mut sections: Vec<Section>, params: &Params) -> Section {
+    while sections.len() > 1 {
+        sections.sort_by_key(|s| s.prefix());
+        let section1 = sections.pop().unwrap();
+        let section2 = sections.pop().unwrap();
+        let section = section1.merge(section2, params);
+        sections.push(section);
+    }
+
+    sections.pop().unwrap()
+}
+
+/// What a single attacker coalition achieved
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct CoalitionStats {
+    /// the number of the coalition's nodes that tried to join
+    pub joins: u64,
+    /// the number of conflicting blocks signed by the coalition's elders
+    pub equivocations: u64,
+    /// the highest fraction of a section's elders the coalition held
+    pub peak_elder_share: f64,
+    /// the section in which the peak was reached
+    pub peak_section: Prefix,
+    /// the iteration in which the peak was reached
+    pub peak_iteration: u64,
+    /// the number of iterations in which the coalition held a quorum of elders in any section
+    pub compromised_iterations: u64,
+    /// the first iteration in which the coalition held a quorum of elders in any section
+    pub first_compromised: Option<u64>,
+    /// the number of names the coalition's nodes tried before landing in its target, if they
+    /// have to rejoin until they do
+    pub join_attempts: u64,
+}
+
+/// What the nodes following a behaviour did
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct BehaviourStats {
+    /// the number of the nodes that joined the network
+    pub joins: u64,
+    /// the number of times the nodes dropped out on their own
+    pub drops: u64,
+    /// the number of times the nodes misbehaved and were evicted
+    pub evictions: u64,
+    /// the number of times the nodes rejoined on their own
+    pub rejoins: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Output {
     /// the number of "add" random events
     pub adds: u64,
@@ -66,76 +434,1035 @@ pub struct Output {
     pub rejoins: u64,
     /// the number of relocations
     pub relocations: u64,
+    /// every relocation, if the relocation log is exported
+    pub relocation_log: Vec<RelocationRecord>,
+    /// the number of splits
+    pub splits: u64,
+    /// the number of merges
+    pub merges: u64,
     /// the number of rejected nodes
     pub rejections: u64,
+    /// the distribution of the ages of rejected nodes
+    pub rejected_ages: BTreeMap<u8, usize>,
+    /// the number of nodes rejected by the sections with each prefix; the rejected names are
+    /// distributed accordingly
+    pub rejections_by_section: BTreeMap<Prefix, u64>,
     /// the total number of churn events
     pub churn: u64,
-    /// the structure of the network
-    pub network_structure: Vec<NetworkStructure>,
+    /// the number of relocated elders
+    pub elder_relocations: u64,
+    /// the total number of iterations sections spent paused due to elder handovers
+    pub paused_iterations: u64,
+    /// the distribution of the number of nodes a merge made elders: nodes that weren't elders
+    /// of any of the merging sections
+    pub merge_promotions: BTreeMap<usize, usize>,
+    /// the distribution of the number of elders of the merging sections that a merge demoted
+    pub merge_demotions: BTreeMap<usize, usize>,
+    /// the total number of iterations merged sections spent paused to stabilise their elders
+    pub stabilisation_iterations: u64,
+    /// the sum over all iterations of the events held back in paused sections' queues
+    pub delayed_events: u64,
+    /// the distribution of negotiation rounds needed before a merge was agreed
+    pub merge_rounds: BTreeMap<u64, usize>,
+    /// the number of merge proposals superseded by a proposal with a conflicting parent
+    pub merge_conflicts: u64,
+    /// the splits in every window of `SPLIT_DEPTH_WINDOW` iterations that had any, indexed by
+    /// the first iteration of the window
+    pub split_depths: BTreeMap<u64, SplitDepths>,
+    /// the iteration in which a split first created sections with the given prefix length
+    pub first_split_at_depth: BTreeMap<u8, u64>,
+    /// the length and the first iteration of the longest stretch without splits or merges that
+    /// has ended
+    pub longest_stable_stretch: (u64, u64),
+    /// the longest lifetime of a section with every prefix, among the sections that split or
+    /// merged
+    pub section_stability: BTreeMap<Prefix, u64>,
+    /// the messages signed by sections and the signing work they caused
+    pub signing: SigningCost,
+    /// the simulated traffic of the messages, if it is added up
+    pub traffic: Traffic,
+    /// the time spent processing events of every kind, if it is measured
+    pub processing_time: ProcessingTime,
+    /// the number of times a section should have split, but couldn't because its prefix was at
+    /// the maximum length
+    pub refused_splits: u64,
+    /// the number of merge requests absorbed by a pending merge into an ancestor
+    pub absorbed_merges: u64,
+    /// the number of pending merges superseded by a merge into an ancestor
+    pub superseded_merges: u64,
+    /// the number of conflicting blocks inserted by malicious elders
+    pub equivocations: u64,
+    /// the statistics of every attacker coalition
+    pub coalitions: Vec<CoalitionStats>,
+    /// the elders of the sections with each prefix, recorded whenever they changed, if there are
+    /// any attackers
+    pub elder_timelines: BTreeMap<Prefix, Vec<(u64, Vec<Node>)>>,
+    /// the highest fraction of malicious elders (from all the coalitions together) any section
+    /// had, with the section and the iteration
+    pub worst_malicious_elders: Option<(f64, Prefix, u64)>,
+    /// the distribution of the number of blocks it took to detect an equivocation
+    pub equivocation_detection: BTreeMap<u64, usize>,
+    /// the distribution of the lengths of merge cascades (chains of merges where the merged
+    /// section immediately needs to merge again)
+    pub merge_cascades: BTreeMap<usize, usize>,
+    /// the structure of the network after every iteration
+    pub history: Recorder,
+    /// the percentage of elder slots occupied by permanent nodes, sampled every
+    /// `PERMANENT_SAMPLING` iterations
+    pub permanent_elders: Vec<(u64, f64)>,
+    /// the distribution of node ages at the first promotion to elder
+    pub promotion_ages: BTreeMap<u8, usize>,
+    /// the distributions of node ages at the first promotion to elder, by the size of the
+    /// section (rounded down to a multiple of `SIZE_BRACKET`)
+    pub promotion_ages_by_size: BTreeMap<usize, BTreeMap<u8, usize>>,
+    /// the churn in the sections with each prefix
+    pub section_churn: BTreeMap<Prefix, SectionChurn>,
+    /// the sum over all iterations of the events held back in slow sections' queues
+    pub throttled_events: u64,
+    /// the logs of the observer nodes
+    pub observer_logs: Vec<ObserverLog>,
+    /// how the network shrank after the joins stopped, if they did
+    pub drain: Option<DrainStats>,
+    /// the number of iterations with bursts of churn
+    pub storm_iterations: u64,
+    /// the number of nodes relocated out of the simulated sub-tree
+    pub stub_relocations: u64,
+    /// the number of relocated nodes lost while in transit
+    pub transit_losses: u64,
+    /// the number of iterations every section ended with fewer than a quorum of elders present,
+    /// i.e. elders that haven't died
+    pub below_quorum: BTreeMap<Prefix, u64>,
+    /// the parameter changes made by the scenario, with the turbulence around them
+    pub transitions: Vec<Transition>,
+    /// the number of distinct nodes that ever became members of a section
+    pub joined_identities: u64,
+    /// the number of distinct nodes that ever were adults
+    pub adult_identities: u64,
+    /// the distribution of the number of iterations the messages between sections took to reach
+    /// them
+    pub message_delays: BTreeMap<u64, usize>,
+    /// the number of requests of joining and relocated nodes lost on their way
+    pub lost_messages: u64,
+    /// the number of requests that arrived after the section they were sent to split or merged
+    pub stale_messages: u64,
+    /// the number of times a joining node's name matched no section, so it was delivered again
+    /// in the next iteration
+    pub redeliveries: u64,
+    /// the number of events that arrived for sections that had split or merged, and were passed
+    /// on to the sections that took over from them
+    pub rerouted_events: u64,
+    /// the number of relocations decided on by sections while they were splitting or merging
+    pub reorg_relocations: u64,
+    /// the number of relocated nodes sent to sections that were splitting or merging
+    pub reorg_destinations: u64,
+    /// the distribution of the number of iterations relocations were deferred for, until the
+    /// sections that decided on them had finished splitting or merging
+    pub deferral_delays: BTreeMap<u64, usize>,
+    /// the distribution of the number of iterations between a node's death and the `Lost`
+    /// event for it, if deaths have to be noticed by the elders
+    pub detection_delays: BTreeMap<u64, usize>,
+    /// the largest number of dead nodes still counted as members at once
+    pub max_unnoticed_deaths: usize,
+    /// the number of dead nodes whose section tried to relocate them before a quorum of its
+    /// elders noticed that they died
+    pub revealed_deaths: u64,
+    /// the distribution of the uptime credit of rejoining nodes, if it matters to the rejoin
+    /// policy
+    pub rejoin_uptimes: BTreeMap<u64, usize>,
+    /// the number of rejoining nodes that kept their age thanks to their uptime credit
+    pub lenient_rejoins: u64,
+    /// the number of dropped nodes that came back after `params.rejoin_delay` iterations, with
+    /// their age halved
+    pub returns: u64,
+    /// the distribution of the number of times events were passed between sections before
+    /// being handled
+    pub forwarding_hops: BTreeMap<usize, usize>,
+    /// events forwarded more than `max_hops` times, with the iteration and the path they took
+    pub forwarding_loops: Vec<(u64, NetworkEvent, Vec<Prefix>)>,
+    /// the largest number of relocated nodes in transit at the same time
+    pub max_in_transit: usize,
+    /// the total amount of data transferred with relocated nodes, in MB
+    pub data_transferred: f64,
+    /// the distribution of the number of iterations relocated nodes spent waiting for their
+    /// data to be transferred, including the transfers queued before theirs
+    pub transfer_waits: BTreeMap<u64, usize>,
+    /// the iteration in which the network first had more than one section
+    pub first_split: Option<u64>,
+    /// the size of the largest section seen, and the iteration in which it was seen
+    pub largest_section: (usize, u64),
+    /// the iteration in which a section first had fewer elders than a quorum
+    pub first_below_quorum: Option<u64>,
+    /// the number of section key rotations by the sections with every prefix
+    pub key_rotations: BTreeMap<Prefix, u64>,
+    /// the number of iterations sections with every prefix existed for, if key rotations are
+    /// tracked
+    pub section_lifetimes: BTreeMap<Prefix, u64>,
+    /// the distribution of the numbers of iterations the sections kept the same set of elders
+    /// for, ended by a change of elders or of the prefix
+    pub elder_set_lifetimes: BTreeMap<u64, usize>,
+    /// the distribution of the number of times a joining node's request was forwarded from
+    /// the section it contacted to the one it joins
+    pub bootstrap_hops: BTreeMap<usize, usize>,
+    /// the number of joining nodes whose cached contact section no longer existed
+    pub stale_contacts: u64,
+    /// the distribution of the number of times the request of a joining node that contacted
+    /// the wrong section was redirected before reaching the section it joined
+    pub redirect_hops: BTreeMap<usize, usize>,
+    /// the distribution of the number of iterations the redirects added to those joins
+    pub redirect_delays: BTreeMap<u64, usize>,
+    /// the number of new nodes turned away because the network had as many infants as the
+    /// global quota allows
+    pub quota_rejections: u64,
+    /// the distribution of the share of the infant quota used at the end of every iteration, in
+    /// tens of percent
+    pub quota_usage: BTreeMap<usize, usize>,
+    /// the largest number of infants in the network at the end of an iteration
+    pub max_infants: usize,
+    /// the relocation checks of all the sections, if relocation fairness is reported
+    pub relocation_tally: RelocationTally,
+    /// the distribution of the numbers of elders of the sections at the end of every iteration,
+    /// tracked when elders have a minimum age
+    pub elder_counts: BTreeMap<usize, usize>,
+    /// the distribution of the difference between the adult counts of the two sections created
+    /// by a split, in brackets of `SPLIT_GAP_BRACKET`
+    pub split_adult_gaps: BTreeMap<usize, usize>,
+    /// the same distribution, for the splits whose sections later merged back
+    pub remerged_split_gaps: BTreeMap<usize, usize>,
+    /// the iteration of the first merge
+    pub first_merge: Option<u64>,
+    /// the iteration in which a node was first relocated
+    pub first_relocation: Option<u64>,
+    /// the iteration in which a node was first rejected
+    pub first_rejection: Option<u64>,
+    /// the iteration in which a section was first complete
+    pub first_complete: Option<u64>,
+    /// the statistics of every node behaviour given in the parameters
+    pub behaviours: Vec<BehaviourStats>,
+    /// the distribution of the numbers of iterations between consecutive relocations of the
+    /// same node, in brackets from every power of two, if relocation ping-pong is tracked
+    pub relocation_intervals: BTreeMap<u64, usize>,
+    /// the nodes relocated too often, in the order in which they went over the limit
+    pub ping_pongs: Vec<PingPong>,
+}
+
+/// Splits the events into the ones a section that can handle `capacity` more requests handles
+/// now, and the ones it holds back. Events other than requests are always handled.
+fn throttle(events: Vec<NetworkEvent>, capacity: usize) -> (Vec<NetworkEvent>, Vec<NetworkEvent>) {
+    let mut requests = 0;
+    events.into_iter().partition(|event| {
+        if !event.is_request() {
+            return true;
+        }
+        requests += 1;
+        requests <= capacity
+    })
+}
+
+/// How often the share of elder slots occupied by permanent nodes is sampled, in iterations
+const PERMANENT_SAMPLING: u64 = 1000;
+
+/// The number of iterations over which split depths are aggregated
+pub const SPLIT_DEPTH_WINDOW: u64 = 1000;
+
+/// The width of the section size brackets in which promotion ages are reported
+pub const SIZE_BRACKET: usize = 10;
+
+/// The width of the brackets in which the differences between split sections' adult counts
+/// are reported
+pub const SPLIT_GAP_BRACKET: usize = 5;
+
+/// The number of iterations before and after a parameter change whose turbulence is compared
+pub const TURBULENCE_WINDOW: u64 = 100;
+
+/// The numbers of iterations after which client staleness is reported
+pub const STALENESS_TICKS: &[u64] = &[1, 10, 100, 1000, 10000];
+
+/// The number of iterations the prefix of a split or merged section is remembered for, to
+/// reroute events that arrive for it late
+const TOMBSTONE_ITERATIONS: u64 = 10;
+
+/// A section removed by a split or merge: the sections that took over from it, and the
+/// iteration after which it is forgotten
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Tombstone {
+    successors: Vec<Prefix>,
+    expires: u64,
+}
+
+/// The request of a joining node that contacted the wrong section, on its way to the right one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Redirect {
+    node: Node,
+    /// the section the request is at
+    at: Prefix,
+    /// the number of times the request has been redirected so far
+    hops: usize,
+    /// the iteration in which the node sent the request
+    since: u64,
+}
+
+/// A message from one section to another, which takes `--msg-delay-mean` iterations on average
+/// to arrive
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Message {
+    /// the request of a joining or relocated node to join the section
+    Join(Node),
+    /// the events of a merge into the given prefix, which the section handles before anything
+    /// else queued
+    Merge(Prefix, Vec<NetworkEvent>),
+}
+
+/// A node that died, but that its section hasn't noticed yet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Death {
+    /// the iteration in which the node died
+    since: u64,
+    /// the elders that noticed the death so far
+    noticed_by: BTreeSet<Name>,
 }
 
 /// The structure representing the whole network
 /// It's a container for sections that simulates all the
 /// churn and communication between them.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Network {
     /// all the sections in the network indexed by prefixes
+    #[serde(with = "sections")]
     nodes: BTreeMap<Prefix, Section>,
+    /// the prefixes of the sections, for lookups by name and by ancestry. Not serialised,
+    /// `restore` rebuilds it
+    #[serde(skip)]
+    index: PrefixIndex,
     /// the nodes that left the network and could rejoin in the future
     left_nodes: Vec<Node>,
+    /// the dropped nodes that come back, by the iteration in which they do
+    departed: BTreeMap<u64, Vec<Node>>,
     /// queues of events to be processed by each section
     event_queue: BTreeMap<Prefix, Vec<NetworkEvent>>,
+    /// relocated nodes that aren't members of any section yet, with the iteration in which
+    /// they arrive
+    in_transit: Vec<(u64, Node)>,
+    /// relocations decided on while their section was splitting or merging and deferred until
+    /// it has finished, with the iteration they were decided on and the section deciding them
+    deferred_relocations: Vec<(u64, Node, Hash, Prefix)>,
+    /// joining nodes whose names matched no section, to be delivered in the next iteration
+    undelivered: Vec<Node>,
+    /// the messages on their way to the sections they were sent to, by the iteration in which
+    /// they arrive
+    in_flight: BTreeMap<u64, Vec<(Prefix, Message)>>,
+    /// requests of joining nodes that contacted the wrong section, being redirected
+    redirected: Vec<Redirect>,
+    /// the relocations of the nodes that haven't arrived yet, by the nodes' new names, if the
+    /// relocation log is exported
+    unfinished_relocations: BTreeMap<Name, RelocationRecord>,
+    /// the nodes that died, but are still members of their sections until a quorum of the
+    /// elders notices, if deaths have to be noticed
+    deaths: BTreeMap<Name, Death>,
+    /// every node that ever became a member of a section, by its current name, with whether it
+    /// ever was an adult
+    identities: BTreeMap<Name, bool>,
+    /// the time (in fractional iterations) until which the transfers of the data of relocated
+    /// nodes to every section are scheduled
+    transfers: BTreeMap<Prefix, f64>,
     /// prefixes that are in the process of merging
     pending_merges: BTreeMap<Prefix, PendingMerge>,
+    /// merges that are still being negotiated, indexed by the merged prefix
+    merge_proposals: BTreeMap<Prefix, MergeProposal>,
+    /// sections created by merges in this or the previous iteration, with the iteration of
+    /// the merge and the depth of the cascade it was part of
+    recent_merges: BTreeMap<Prefix, (u64, usize)>,
+    /// the iteration of the last split or merge
+    last_structural_change: u64,
+    /// the iteration in which every section was created
+    section_since: BTreeMap<Prefix, u64>,
+    /// the recently split or merged sections, for rerouting events that arrive for them late
+    tombstones: BTreeMap<Prefix, Tombstone>,
+    /// sections handing over after an elder relocation, which hold back requests, with the
+    /// iteration at which they resume
+    paused: BTreeMap<Prefix, u64>,
+    /// the number of events each section can handle per iteration, if limited
+    rates: BTreeMap<Prefix, usize>,
+    /// the number of events each section handled in the current iteration
+    handled: BTreeMap<Prefix, usize>,
+    /// the number of events all the sections handled in the current iteration
+    events_this_iteration: u64,
+    /// the prefixes that events passed between sections went through, by the name of the node
+    /// and the kind of the event
+    #[serde(with = "forwarded_paths")]
+    forwarded: ForwardedPaths,
+    /// the number of elder changes of every section seen at the end of the last iteration, and
+    /// the iteration in which its current elders were first seen
+    elder_sets: BTreeMap<Prefix, (u64, u64)>,
+    /// the difference between the adult counts of the children of every prefix that split
+    /// and hasn't merged back since
+    split_gaps: BTreeMap<Prefix, usize>,
+    /// the sections joining nodes know of, as of the last refresh of the contact list
+    contacts: Vec<Prefix>,
+    /// the relocations of every node relocated at least once, by its current name, if
+    /// relocation ping-pong is tracked
+    relocation_logs: BTreeMap<Name, RelocationLog>,
+    /// the iteration since which every current elder has been one, if the elder list is
+    /// exported
+    elder_since: BTreeMap<Name, u64>,
+    /// the reason the simulation can't go on, if it can't
+    failure: Option<String>,
+    /// the behaviours the nodes can follow; nodes with behaviour 1 follow the first one etc.
+    /// Not serialised, `restore_behaviours` rebuilds them from the parameters
+    #[serde(skip)]
+    behaviours: Vec<Arc<dyn Behaviour>>,
+    /// the number of the current iteration
+    iteration: u64,
     /// Simulation parameters
     params: Params,
+    /// the parameters of the sections a change was rolled out to, by the prefix it was rolled
+    /// out under; a section follows the ones of the longest such prefix it matches, or `params`
+    staged: BTreeMap<Prefix, Params>,
+    /// the turbulence counted up to the end of each of the last `TURBULENCE_WINDOW` iterations,
+    /// and before them
+    recent_turbulence: VecDeque<Turbulence>,
     /// Simulation outputs
     output: Output,
 }
 
+/// Serialises the sections along with the parts of their state they don't serialise themselves
+mod sections {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+    use network::prefix::Prefix;
+    use network::section::{LocalState, Section};
+
+    pub fn serialize<S: Serializer>(
+        sections: &BTreeMap<Prefix, Section>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            sections
+                .values()
+                .map(|section| (section, section.local_state())),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Prefix, Section>, D::Error> {
+        let sections: Vec<(Section, LocalState)> = Vec::deserialize(deserializer)?;
+        Ok(sections
+            .into_iter()
+            .map(|(mut section, state)| {
+                section.restore_local_state(state);
+                (section.prefix(), section)
+            })
+            .collect())
+    }
+}
+
+/// The prefixes that forwarded events went through, by the name of the node and the kind of the
+/// event
+type ForwardedPaths = BTreeMap<(Name, &'static str), Vec<Prefix>>;
+
+/// Serialises the paths of forwarded events, whose kinds are restored as their static names
+mod forwarded_paths {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+    use network::churn::NetworkEvent;
+    use network::prefix::{Name, Prefix};
+    use super::ForwardedPaths;
+
+    pub fn serialize<S: Serializer>(
+        forwarded: &ForwardedPaths,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        forwarded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ForwardedPaths, D::Error> {
+        let forwarded: BTreeMap<(Name, String), Vec<Prefix>> = BTreeMap::deserialize(deserializer)?;
+        forwarded
+            .into_iter()
+            .map(|((name, kind), path)| {
+                NetworkEvent::KINDS
+                    .iter()
+                    .find(|&&known| known == kind)
+                    .map(|&kind| ((name, kind), path))
+                    .ok_or_else(|| D::Error::custom(format!("unknown event kind {}", kind)))
+            })
+            .collect()
+    }
+}
+
 impl Network {
+    /// Returns a builder for configuring and running a simulation programmatically
+    pub fn builder() -> NetworkBuilder {
+        NetworkBuilder::new()
+    }
+
     /// Starts a new network
     pub fn new(params: Params) -> Network {
         let mut nodes = BTreeMap::new();
-        nodes.insert(Prefix::empty(), Section::new(Prefix::empty()));
-        Network {
+        let root = params.restrict_prefix.unwrap_or_else(Prefix::empty);
+        let section = Section::new(root);
+        trace::snapshot(0, &section);
+        nodes.insert(root, section);
+        let output = Output {
+            history: Recorder::new(params.history),
+            coalitions: vec![Default::default(); params.coalitions.len()],
+            behaviours: vec![Default::default(); params.behaviours.len()],
+            ..Default::default()
+        };
+        let behaviours = params.behaviours.iter().map(behaviour::build).collect();
+        let mut index = PrefixIndex::default();
+        index.insert(root);
+        let mut network = Network {
             nodes,
+            index,
             left_nodes: Vec::new(),
+            departed: BTreeMap::new(),
             event_queue: BTreeMap::new(),
+            in_transit: Vec::new(),
+            deferred_relocations: Vec::new(),
+            undelivered: Vec::new(),
+            redirected: Vec::new(),
+            in_flight: BTreeMap::new(),
+            unfinished_relocations: BTreeMap::new(),
+            deaths: BTreeMap::new(),
+            identities: BTreeMap::new(),
+            transfers: BTreeMap::new(),
             pending_merges: BTreeMap::new(),
+            merge_proposals: BTreeMap::new(),
+            recent_merges: BTreeMap::new(),
+            last_structural_change: 0,
+            section_since: Some((root, 0)).into_iter().collect(),
+            tombstones: BTreeMap::new(),
+            paused: BTreeMap::new(),
+            rates: BTreeMap::new(),
+            handled: BTreeMap::new(),
+            events_this_iteration: 0,
+            forwarded: BTreeMap::new(),
+            elder_sets: BTreeMap::new(),
+            split_gaps: BTreeMap::new(),
+            contacts: vec![],
+            relocation_logs: BTreeMap::new(),
+            elder_since: BTreeMap::new(),
+            failure: None,
+            behaviours,
+            iteration: 0,
             params,
-            output: Default::default(),
+            staged: BTreeMap::new(),
+            recent_turbulence: Some(Turbulence::default()).into_iter().collect(),
+            output,
+        };
+        network.add_observers();
+        network
+    }
+
+    /// Rebuilds the parts of the state that aren't serialised, after the network has been
+    /// deserialised: the behaviours of the nodes, from the parameters, and the prefix index
+    pub fn restore(&mut self) {
+        self.behaviours = self.params.behaviours.iter().map(behaviour::build).collect();
+        self.index = PrefixIndex::default();
+        for &prefix in self.nodes.keys() {
+            self.index.insert(prefix);
+        }
+    }
+
+    /// Sends the observer nodes to join the network
+    fn add_observers(&mut self) {
+        for _ in 0..self.params.observers {
+            let node = Node::new(self.random_name(), self.params.init_age).into_observer();
+            debug!(in node.name(); "Adding observer {:?}", node);
+            self.output.observer_logs.push(ObserverLog {
+                name: node.name(),
+                prefix: None,
+                entries: vec![],
+            });
+            let _ = self.send_live(node);
         }
     }
 
-    /// Checks whether there are any events in the queues
+    /// Returns the indices of the observers in the section with the given prefix
+    fn observers_in(&self, prefix: &Prefix) -> Vec<usize> {
+        let section = match self.nodes.get(prefix) {
+            Some(section) if !self.output.observer_logs.is_empty() => section,
+            _ => return vec![],
+        };
+        self.output
+            .observer_logs
+            .iter()
+            .enumerate()
+            .filter(|&(_, log)| section.has_node(log.name))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Records an event handled by the section in the logs of the given observers, along with
+    /// the change of elders it caused, if any
+    fn observe(
+        &mut self,
+        observers: &[usize],
+        prefix: Prefix,
+        event: NetworkEvent,
+        response: &[SectionEvent],
+        elders_before: &BTreeSet<Node>,
+    ) {
+        let elders = match self.nodes.get(&prefix) {
+            Some(section) => section.elders(),
+            None => BTreeSet::new(),
+        };
+        let iteration = self.iteration;
+        for &i in observers {
+            let log = &mut self.output.observer_logs[i];
+            if log.prefix != Some(prefix) {
+                log.prefix = Some(prefix);
+                log.entries.push((iteration, Observation::Section(prefix)));
+            }
+            log.entries
+                .push((iteration, Observation::Event(event, response.to_vec())));
+            if elders != *elders_before {
+                log.entries
+                    .push((iteration, Observation::Elders(elders.iter().cloned().collect())));
+            }
+        }
+    }
+
+    /// Checks whether there are any events in the queues of sections that can process them
     fn has_events(&self) -> bool {
-        self.event_queue.values().any(|x| !x.is_empty())
+        self.event_queue
+            .iter()
+            .any(|(pfx, x)| !x.is_empty() && !self.is_held(pfx, x))
+    }
+
+    /// Returns whether the section can't process any of the given events in this iteration.
+    /// Paused and slow sections only hold back requests, and go on with the bookkeeping after
+    /// splits and merges.
+    fn is_held(&self, prefix: &Prefix, events: &[NetworkEvent]) -> bool {
+        (self.is_paused(prefix) || self.is_throttled(prefix))
+            && events.iter().all(|event| event.is_request())
+    }
+
+    /// Returns whether the section has handled as many events in this iteration as it can
+    fn is_throttled(&self, prefix: &Prefix) -> bool {
+        match (self.rates.get(prefix), self.handled.get(prefix)) {
+            (Some(rate), Some(handled)) => handled >= rate,
+            _ => false,
+        }
+    }
+
+    /// Returns the number of events the section can still handle in this iteration, drawing
+    /// its processing rate if it doesn't have one yet
+    fn remaining_capacity(&mut self, prefix: Prefix) -> usize {
+        let rate_dist = self.params.rate_dist;
+        let rate = *self.rates.entry(prefix).or_insert_with(|| {
+            let rate = match rate_dist {
+                RateDist::Unlimited => return usize::MAX,
+                RateDist::Constant(rate) => rate,
+                RateDist::Uniform(min, max) => random_range(min, max + 1),
+                RateDist::Exponential(mean) => {
+                    // the geometric distribution: the discrete counterpart of the exponential one
+                    let p_stop = from_percent(100.0 / mean.max(1.0));
+                    let mut rate = 1;
+                    while !gen_bool_with_probability(p_stop) {
+                        rate += 1;
+                    }
+                    rate
+                }
+            };
+            // every section handles at least one event per iteration, so that it doesn't stall
+            rate.max(1)
+        });
+        rate.saturating_sub(self.handled.get(&prefix).cloned().unwrap_or(0))
+    }
+
+    /// Removes the queues of the given sections and returns the events in them, if they could
+    /// have been held back by slow or paused sections. Otherwise the queues are left to be
+    /// dropped.
+    fn take_held_events<'a, I: IntoIterator<Item = &'a Prefix>>(
+        &mut self,
+        prefixes: I,
+    ) -> Vec<(Prefix, NetworkEvent)> {
+        let limited = self.params.rate_dist.is_limited();
+        let mut held = vec![];
+        for pfx in prefixes {
+            if !limited && !self.is_paused(pfx) {
+                continue;
+            }
+            if let Some(events) = self.event_queue.remove(pfx) {
+                held.extend(events.into_iter().map(|event| (*pfx, event)));
+            }
+        }
+        held
+    }
+
+    /// Passes the events held back by or sent to sections that no longer exist to the sections
+    /// now responsible for the nodes in them. Events about the old sections' prefixes are
+    /// obsolete and get dropped.
+    fn requeue(&mut self, events: Vec<(Prefix, NetworkEvent)>) {
+        for (src, event) in events {
+            let name = match event.name() {
+                Some(name) => name,
+                None => continue,
+            };
+            let successor = self.successor(src, name);
+            if let Some(prefix) = successor.or_else(|| self.index.longest_match(name)) {
+                self.record_hop(src, prefix, event);
+                self.event_queue
+                    .entry(prefix)
+                    .or_insert_with(Vec::new)
+                    .push(event);
+            }
+        }
+    }
+
+    /// Follows the tombstones of the removed section with the given prefix to the section that
+    /// is now responsible for the name
+    fn successor(&self, mut prefix: Prefix, name: Name) -> Option<Prefix> {
+        while !self.nodes.contains_key(&prefix) {
+            prefix = *self.tombstones
+                .get(&prefix)?
+                .successors
+                .iter()
+                .find(|pfx| pfx.matches(name))?;
+        }
+        Some(prefix)
+    }
+
+    /// Adds a hop of a forwarded event to its path. If the event has been forwarded more than
+    /// `max_hops` times without being handled, it is most likely going round in circles, and
+    /// the path it took is recorded in the output.
+    fn record_hop(&mut self, src: Prefix, dst: Prefix, event: NetworkEvent) {
+        let key = match event.name() {
+            Some(name) => (name, event.kind()),
+            None => return,
+        };
+        let path = self.forwarded.entry(key).or_insert_with(|| vec![src]);
+        path.push(dst);
+        if path.len() - 1 == self.params.max_hops + 1 {
+            let path_text: Vec<_> = path.iter().map(|pfx| format!("{:?}", pfx)).collect();
+            debug!(
+                in dst;
+                "Forwarding loop: {:?} forwarded {} times: {}",
+                event,
+                path.len() - 1,
+                path_text.join(" -> ")
+            );
+            self.output
+                .forwarding_loops
+                .push((self.iteration, event, path.clone()));
+        }
+    }
+
+    /// Forgets the path of a forwarded event once it is handled
+    fn forget_hops(&mut self, event: NetworkEvent) {
+        if self.forwarded.is_empty() {
+            return;
+        }
+        if let Some(name) = event.name() {
+            if let Some(path) = self.forwarded.remove(&(name, event.kind())) {
+                *self.output.forwarding_hops.entry(path.len() - 1).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Returns whether the section is still handing over after relocating an elder
+    fn is_paused(&self, prefix: &Prefix) -> bool {
+        match self.paused.get(prefix) {
+            Some(&until) => until > self.iteration,
+            None => false,
+        }
+    }
+
+    /// Stops the section from processing requests for `params.elder_handover` iterations
+    fn pause(&mut self, prefix: Prefix) {
+        self.output.elder_relocations += 1;
+        if self.params.elder_handover == 0 || self.is_paused(&prefix) {
+            return;
+        }
+        debug!(in prefix;
+            "Pausing {:?} for {} iterations",
+            prefix, self.params.elder_handover
+        );
+        self.output.paused_iterations += self.params.elder_handover;
+        let _ = self.paused
+            .insert(prefix, self.iteration + self.params.elder_handover);
     }
 
     fn capture_network_structure(&mut self) {
         let structure = NetworkStructure {
             size: self.nodes.values().map(|x| x.len()).sum(),
             sections: self.nodes.len(),
-            complete: self.nodes.values().filter(|x| x.is_complete()).count(),
+            complete: self.nodes.values().filter(|x| x.is_complete(&self.params)).count(),
         };
-        self.output.network_structure.push(structure);
+        if structure.sections > 1 && self.output.first_split.is_none() {
+            self.output.first_split = Some(self.iteration);
+        }
+        if structure.complete > 0 && self.output.first_complete.is_none() {
+            self.output.first_complete = Some(self.iteration);
+        }
+        if self.output.relocations > 0 && self.output.first_relocation.is_none() {
+            self.output.first_relocation = Some(self.iteration);
+        }
+        if self.output.rejections > 0 && self.output.first_rejection.is_none() {
+            self.output.first_rejection = Some(self.iteration);
+        }
+        let largest = self.nodes.values().map(|x| x.len()).max().unwrap_or(0);
+        if largest > self.output.largest_section.0 {
+            self.output.largest_section = (largest, self.iteration);
+        }
+        // a young network is naturally below quorum, so only count it once it has split
+        if self.output.first_split.is_some()
+            && self.output.first_below_quorum.is_none()
+            && self.sections_below_quorum() > 0
+        {
+            self.output.first_below_quorum = Some(self.iteration);
+        }
+        self.output.history.record(structure);
+        self.record_elder_sets();
+        if self.params.elder_list.is_some() {
+            self.record_elder_tenures();
+        }
+        if let Some(quota) = self.params.infant_quota {
+            let infants = self.num_infants();
+            self.output.max_infants = self.output.max_infants.max(infants);
+            let usage = (10 * infants / quota.max(1)).min(10);
+            *self.output.quota_usage.entry(usage).or_insert(0) += 1;
+        }
+        if self.params.elder_min_age.is_some() {
+            for section in self.nodes.values() {
+                *self.output
+                    .elder_counts
+                    .entry(section.elder_count())
+                    .or_insert(0) += 1;
+            }
+        }
+        if self.params.key_rotation.is_some() {
+            for prefix in self.nodes.keys() {
+                *self.output.section_lifetimes.entry(*prefix).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Records how long the sections kept their sets of elders. A set ends when the elders
+    /// change or when the section splits or merges.
+    fn record_elder_sets(&mut self) {
+        let iteration = self.iteration;
+        let mut ended = vec![];
+        let nodes = &self.nodes;
+        self.elder_sets.retain(|prefix, &mut (_, since)| {
+            if nodes.contains_key(prefix) {
+                return true;
+            }
+            ended.push(iteration - since);
+            false
+        });
+        for (prefix, section) in &self.nodes {
+            let changes = section.elder_changes();
+            let entry = self.elder_sets.entry(*prefix).or_insert((changes, iteration));
+            if entry.0 != changes {
+                ended.push(iteration - entry.1);
+                *entry = (changes, iteration);
+            }
+        }
+        for lifetime in ended {
+            *self.output.elder_set_lifetimes.entry(lifetime).or_insert(0) += 1;
+        }
+    }
+
+    /// Notes the nodes that became elders in this iteration and forgets the ones that stopped
+    /// being elders. An elder that stays one through a split or merge keeps its tenure.
+    fn record_elder_tenures(&mut self) {
+        let elders: BTreeSet<_> = self.nodes
+            .values()
+            .flat_map(|section| section.elders())
+            .map(|node| node.name())
+            .collect();
+        self.elder_since.retain(|name, _| elders.contains(name));
+        for name in elders {
+            let _ = self.elder_since.entry(name).or_insert(self.iteration);
+        }
+    }
+
+    /// Returns the number of iterations the node has been an elder for, if it is one and the
+    /// elder list is exported
+    pub fn elder_tenure(&self, name: Name) -> Option<u64> {
+        self.elder_since
+            .get(&name)
+            .map(|&since| self.iteration - since)
+    }
+
+    /// Returns the chance that a client caching the elders of a random section at a random
+    /// time finds them stale within the given number of iterations, for each of them. The
+    /// current sets of elders, which haven't changed yet, count as not stale.
+    pub fn client_staleness(&self, ticks: &[u64]) -> Vec<(u64, f64)> {
+        let ongoing: u64 = self.elder_sets
+            .values()
+            .map(|&(_, since)| self.iteration - since)
+            .sum();
+        let lifetimes = &self.output.elder_set_lifetimes;
+        let total = ongoing + lifetimes.iter().map(|(&l, &n)| l * n as u64).sum::<u64>();
+        ticks
+            .iter()
+            .map(|&t| {
+                let stale: u64 = lifetimes.iter().map(|(&l, &n)| l.min(t) * n as u64).sum();
+                (t, stale as f64 / total.max(1) as f64)
+            })
+            .collect()
     }
 
     /// Sends all events to the corresponding sections and processes the events passed
     /// back. The responses generate new events and the cycle continues until the queues are empty.
     /// Then. if any pending merges are ready, they are processed, too.
     pub fn process_events(&mut self) {
+        self.redeliver();
+        self.redirect_joins();
+        self.deliver_relocations();
+        self.resume_relocations();
+        if !event_log::is_replaying() {
+            self.return_departed();
+        }
+        self.deliver_messages();
+        self.notice_deaths();
+        self.negotiate_merges();
+        self.events_this_iteration = 0;
+        loop {
+            self.process_queues();
+            if self.failure.is_some() {
+                return;
+            }
+            let merged = self.finalise_merges();
+            // with batched cascades, merged sections that need to merge again do so
+            // within the same iteration
+            if !self.params.batch_cascades || !self.cascade_merges(merged) {
+                break;
+            }
+        }
+        self.output.delayed_events += self.event_queue
+            .iter()
+            .filter(|&(pfx, _)| self.is_paused(pfx))
+            .map(|(_, events)| events.len() as u64)
+            .sum::<u64>();
+        self.output.throttled_events += self.event_queue
+            .iter()
+            .filter(|&(pfx, events)| !self.is_paused(pfx) && self.is_held(pfx, events))
+            .map(|(_, events)| events.len() as u64)
+            .sum::<u64>();
+        self.handled.clear();
+        self.output.signing.end_iteration();
+        if self.params.message_sizes.is_some() {
+            self.output.traffic.end_iteration();
+        }
+        self.capture_network_structure();
+        self.record_quorum_exposure();
+        self.record_turbulence();
+        if !self.params.coalitions.is_empty() {
+            self.record_coalitions();
+        }
+        if self.params.permanent > 0 && self.iteration % PERMANENT_SAMPLING == 0 {
+            let share = self.permanent_elder_share();
+            self.output.permanent_elders.push((self.iteration, share));
+        }
+        self.iteration += 1;
+        let iteration = self.iteration;
+        self.paused.retain(|_, &mut until| until > iteration);
+        self.tombstones
+            .retain(|_, tombstone| tombstone.expires > iteration);
+        self.recent_merges
+            .retain(|_, &mut (merged_at, _)| merged_at + 1 >= iteration);
+    }
+
+    /// Processes the event queues until they are empty (apart from the requests held back by
+    /// paused and slow sections)
+    fn process_queues(&mut self) {
         while self.has_events() {
-            let queue = mem::replace(&mut self.event_queue, BTreeMap::new());
-            for (prefix, events) in queue {
+            // a cascade that doesn't end is where a run looks hung, so dumps are taken within it
+            if signal::dump_requested() {
+                eprintln!(
+                    "Iteration {} still has events queued, {} handled so far",
+                    self.iteration, self.events_this_iteration
+                );
+                eprintln!("{}", self.diagnostics());
+            }
+            if let Some(budget) = self.params.event_budget {
+                if self.events_this_iteration > budget {
+                    self.exceed_budget(budget);
+                    return;
+                }
+            }
+            let mut queue = mem::take(&mut self.event_queue);
+            // paused and slow sections keep their requests until they can process them
+            let paused: Vec<_> = queue
+                .iter()
+                .filter(|&(pfx, events)| self.is_held(pfx, events))
+                .map(|(pfx, _)| *pfx)
+                .collect();
+            for pfx in paused {
+                let events = queue.remove(&pfx).unwrap();
+                let _ = self.event_queue.insert(pfx, events);
+            }
+            for (prefix, mut events) in queue {
+                if !self.nodes.contains_key(&prefix) {
+                    // events that arrive after the section split or merged go to its successors
+                    let (late, rest): (Vec<_>, Vec<_>) = events
+                        .into_iter()
+                        .partition(|event| event.name().is_some());
+                    self.output.rerouted_events += late.len() as u64;
+                    self.requeue(late.into_iter().map(|event| (prefix, event)).collect());
+                    events = rest;
+                }
+                let capacity = if self.is_paused(&prefix) {
+                    Some(0)
+                } else if self.params.rate_dist.is_limited() {
+                    Some(self.remaining_capacity(prefix))
+                } else {
+                    None
+                };
+                if let Some(capacity) = capacity {
+                    let (handled, mut held) = throttle(events, capacity);
+                    if !held.is_empty() {
+                        // the held events go before any that other sections have sent already
+                        let queue = self.event_queue.entry(prefix).or_insert_with(Vec::new);
+                        held.append(queue);
+                        *queue = held;
+                    }
+                    let requests = handled.iter().filter(|event| event.is_request()).count();
+                    *self.handled.entry(prefix).or_insert(0) += requests;
+                    events = handled;
+                }
                 let mut section_events = vec![];
                 for event in events {
-                    let params = &self.params;
+                    self.events_this_iteration += 1;
+                    self.forget_hops(event);
+                    let observers = self.observers_in(&prefix);
+                    let elders_before = match self.nodes.get(&prefix) {
+                        Some(section) if !observers.is_empty() => section.elders(),
+                        _ => BTreeSet::new(),
+                    };
+                    let params = section_params(&self.staged, &self.params, &prefix);
+                    let iteration = self.iteration;
+                    let started = self.start_timer();
                     let result = self.nodes
                         .get_mut(&prefix)
-                        .map(|section| section.handle_event(event, params))
+                        .map(|section| trace::handle_event(iteration, section, event, params))
                         .unwrap_or_else(Vec::new);
+                    if let Some(started) = started {
+                        self.output
+                            .processing_time
+                            .record_section(event.kind(), started.elapsed());
+                    }
+                    if let Some(ref sizes) = self.params.message_sizes {
+                        let traffic = &mut self.output.traffic;
+                        traffic.record(prefix, event.kind(), sizes.of(event.kind()));
+                        for reported in &result {
+                            traffic.record(prefix, reported.kind(), sizes.of(reported.kind()));
+                        }
+                    }
+                    if !observers.is_empty() {
+                        self.observe(&observers, prefix, event, &result, &elders_before);
+                    }
+                    if let NetworkEvent::Live(node) = event {
+                        self.record_identity(prefix, node);
+                    }
                     section_events.extend(result);
                     if let NetworkEvent::PrefixChange(pfx) = event {
                         if let Some(pending_merge) = self.pending_merges.get_mut(&pfx) {
@@ -143,45 +1470,310 @@ impl Network {
                         }
                     }
                 }
+                if self.params.relocation_fairness {
+                    if let Some(section) = self.nodes.get_mut(&prefix) {
+                        let tally = section.take_relocation_tally();
+                        self.output.relocation_tally.add(&tally);
+                    }
+                }
                 for section_event in section_events {
                     self.process_single_event(prefix, section_event);
                 }
             }
         }
-        let merges_to_finalise: Vec<_> = self.pending_merges
+    }
+
+    /// Stops the simulation after the sections handled more than `budget` events in a single
+    /// iteration, which means that they are most likely sending events to each other in a loop,
+    /// and describes what the backlog of events consists of in the failure message
+    fn exceed_budget(&mut self, budget: u64) {
+        let mut by_kind = BTreeMap::new();
+        let mut by_section: Vec<_> = self.event_queue
             .iter()
-            .filter(|&(_, pm)| pm.is_done())
-            .map(|(pfx, _)| *pfx)
+            .map(|(pfx, events)| (events.len(), *pfx))
             .collect();
-        for pfx in merges_to_finalise {
-            println!("Finalising a merge into {:?}", pfx);
-            self.output.churn += 1; // counting merge as a single churn event
-            let pending_merge = self.pending_merges.remove(&pfx).unwrap().into_map();
-            let merged_section = self.merged_section(pending_merge.keys(), true);
-            self.nodes.insert(merged_section.prefix(), merged_section);
+        for event in self.event_queue.values().flatten() {
+            *by_kind.entry(event.kind()).or_insert(0) += 1;
         }
-        self.capture_network_structure();
+        by_section.sort_by_key(|&(len, pfx)| (Reverse(len), pfx));
+        let mut msg = format!(
+            "Event budget exceeded: the sections handled {} events in iteration {}, more than {}; \
+             backlog of {} events:",
+            self.events_this_iteration,
+            self.iteration,
+            budget,
+            by_section.iter().map(|&(len, _)| len).sum::<usize>()
+        );
+        for (kind, count) in &by_kind {
+            msg.push_str(&format!("\n\t{}: {}", kind, count));
+        }
+        msg.push_str("\nSections with the most events queued:");
+        for &(len, pfx) in by_section.iter().take(10) {
+            let state = match self.nodes.get(&pfx) {
+                Some(section) if section.is_splitting() => " (splitting)",
+                Some(section) if section.is_merging() => " (merging)",
+                Some(_) => "",
+                None => " (no such section)",
+            };
+            msg.push_str(&format!("\n\t{:?}{}: {}", pfx, state, len));
+        }
+        self.failure = Some(msg);
     }
 
-    /// Processes a single response from a section and potentially inserts some events into its
-    /// queue
+    /// Returns the reason the simulation can't go on, if it can't
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_ref().map(|s| &s[..])
+    }
+
+    /// Combines the sections of all the pending merges that are ready and returns the prefixes
+    /// of the merged sections
+    fn finalise_merges(&mut self) -> Vec<Prefix> {
+        let merges_to_finalise: Vec<_> = self.pending_merges
+            .iter()
+            .filter(|&(_, pm)| pm.is_done())
+            .map(|(pfx, _)| *pfx)
+            .collect();
+        for &pfx in &merges_to_finalise {
+            let started = self.start_timer();
+            debug!(in pfx; "Finalising a merge into {:?}", pfx);
+            self.output.churn += 1; // counting merge as a single churn event
+            let pending_merge = self.pending_merges.remove(&pfx).unwrap();
+            let depth = pending_merge.depth;
+            // a cascade that grows by one merge is no longer counted at its previous depth
+            if depth > 1 {
+                if let Some(count) = self.output.merge_cascades.get_mut(&(depth - 1)) {
+                    *count = count.saturating_sub(1);
+                }
+            }
+            *self.output.merge_cascades.entry(depth).or_insert(0) += 1;
+            let _ = self.recent_merges.insert(pfx, (self.iteration, depth));
+            let _ = self.output.first_merge.get_or_insert(self.iteration);
+            if let Some(gap) = self.split_gaps.remove(&pfx) {
+                *self.output.remerged_split_gaps.entry(gap).or_insert(0) += 1;
+            }
+            let old_elders = pending_merge.elders.clone();
+            let prefixes = pending_merge.into_map();
+            let iteration = self.iteration;
+            if let Some(ref mut drain) = self.output.drain {
+                drain.merges.push((iteration, pfx));
+            }
+            event_log::log(self.iteration, LoggedEvent::Merge(pfx));
+            event_bus::emit(
+                self.iteration,
+                BusEvent::Merge {
+                    prefixes: prefixes.keys().map(Prefix::to_string).collect(),
+                    into: pfx.to_string(),
+                },
+            );
+            let held = self.take_held_events(prefixes.keys());
+            let signers: Vec<_> = prefixes.keys().map(|pfx| self.elder_count(pfx)).collect();
+            let merged_section = self.merged_section(prefixes.keys(), true);
+            let new_elders: BTreeSet<_> = merged_section.elders().iter().map(Node::name).collect();
+            let promotions = new_elders.difference(&old_elders).count();
+            let demotions = old_elders.difference(&new_elders).count();
+            for signers in signers {
+                self.output.signing.record(
+                    SignedMessage::Merge,
+                    signers,
+                    merged_section.elder_count(),
+                    section_params(&self.staged, &self.params, &merged_section.prefix()).quorum,
+                );
+            }
+            trace::snapshot(self.iteration, &merged_section);
+            let removed: Vec<_> = prefixes.keys().cloned().collect();
+            self.structural_change(&removed, &[merged_section.prefix()]);
+            self.nodes.insert(merged_section.prefix(), merged_section);
+            if self.params.age_on_merge != AgeRule::Keep {
+                self.record_adult_identities(pfx);
+            }
+            self.record_reshuffle(pfx, promotions, demotions);
+            self.requeue(held);
+            if let Some(started) = started {
+                self.output
+                    .processing_time
+                    .record_network(FINALISE_MERGE, started.elapsed());
+            }
+        }
+        merges_to_finalise
+    }
+
+    /// Records how many elders a merge into `prefix` changed, and pauses the merged section for
+    /// `params.merge_stabilisation` iterations per changed elder
+    fn record_reshuffle(&mut self, prefix: Prefix, promotions: usize, demotions: usize) {
+        *self.output.merge_promotions.entry(promotions).or_insert(0) += 1;
+        *self.output.merge_demotions.entry(demotions).or_insert(0) += 1;
+        let pause = self.params.merge_stabilisation * (promotions + demotions) as u64;
+        if pause == 0 {
+            return;
+        }
+        debug!(in prefix;
+            "Pausing {:?} for {} iterations to stabilise its elders",
+            prefix, pause
+        );
+        self.output.stabilisation_iterations += pause;
+        let until = self.iteration + pause;
+        let paused = self.paused.entry(prefix).or_insert(until);
+        *paused = (*paused).max(until);
+    }
+
+    /// Makes the freshly merged sections request another merge if they need one. Returns whether
+    /// any new events were generated this way.
+    fn cascade_merges(&mut self, merged: Vec<Prefix>) -> bool {
+        for pfx in merged {
+            let should_merge = match self.nodes.get_mut(&pfx) {
+                Some(section) => {
+                    section.request_merge(section_params(&self.staged, &self.params, &pfx))
+                }
+                None => false,
+            };
+            if should_merge {
+                trace::snapshot(self.iteration, &self.nodes[&pfx]);
+                self.process_single_event(pfx, SectionEvent::RequestMerge);
+            }
+        }
+        self.has_events()
+    }
+
+    /// Returns the current time if the processing time of events is measured
+    fn start_timer(&self) -> Option<Instant> {
+        if self.params.time_events {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    /// Processes a single response from a section, measuring the time it takes if needed
     fn process_single_event(&mut self, prefix: Prefix, event: SectionEvent) {
+        let started = self.start_timer();
+        self.dispatch_section_event(prefix, event);
+        if let Some(started) = started {
+            self.output
+                .processing_time
+                .record_network(event.kind(), started.elapsed());
+        }
+    }
+
+    /// Handles a single response from a section and potentially inserts some events into its
+    /// queue
+    fn dispatch_section_event(&mut self, prefix: Prefix, event: SectionEvent) {
         match event {
-            SectionEvent::NodeDropped(node) => {
-                self.left_nodes.push(node);
+            SectionEvent::NodeDropped(mut node) => {
+                let _ = self.deaths.remove(&node.name());
+                node.left(self.iteration);
+                if self.params.rejoin_prob > 0.0
+                    && gen_bool_with_probability(from_percent(self.params.rejoin_prob))
+                {
+                    let back = self.iteration + self.params.rejoin_delay.max(1);
+                    debug!(in node.name(); "{:?} will come back in iteration {}", node, back);
+                    self.departed.entry(back).or_insert_with(Vec::new).push(node);
+                } else {
+                    self.left_nodes.push(node);
+                }
+            }
+            SectionEvent::NeedRelocate(node, hash) => {
+                if let Some(death) = self.deaths.remove(&node.name()) {
+                    // a dead node can't take part in its relocation, which gives its death away
+                    debug!(in prefix; "Relocating {:?} reveals that it died", node);
+                    self.output.revealed_deaths += 1;
+                    self.lose_dead_node(prefix, node.name(), death.since);
+                } else if self.is_reorganising(&prefix) {
+                    self.output.reorg_relocations += 1;
+                    match self.params.reorg_relocations {
+                        ReorgRelocations::Forward => self.relocate(node, hash, prefix),
+                        ReorgRelocations::Defer => {
+                            debug!(in prefix;
+                                "Deferring the relocation of {:?} until {:?} has split or merged",
+                                node, prefix
+                            );
+                            self.deferred_relocations
+                                .push((self.iteration, node, hash, prefix));
+                        }
+                    }
+                } else {
+                    self.relocate(node, hash, prefix);
+                }
+            }
+            SectionEvent::ElderRelocated(_) => {
+                self.pause(prefix);
+            }
+            SectionEvent::ElderPromoted(age, size) => {
+                let neighbour_elders = self.index
+                    .neighbours(prefix)
+                    .iter()
+                    .map(|pfx| self.elder_count(pfx))
+                    .sum();
+                let signers = self.elder_count(&prefix);
+                let quorum = section_params(&self.staged, &self.params, &prefix).quorum;
+                self.output.signing.record(
+                    SignedMessage::Promotion,
+                    signers,
+                    neighbour_elders,
+                    quorum,
+                );
+                let event = BusEvent::Promotion {
+                    prefix: prefix.to_string(),
+                    age,
+                    size,
+                };
+                event_bus::emit(self.iteration, event);
+                *self.output.promotion_ages.entry(age).or_insert(0) += 1;
+                *self.output
+                    .promotion_ages_by_size
+                    .entry(size / SIZE_BRACKET * SIZE_BRACKET)
+                    .or_insert_with(BTreeMap::new)
+                    .entry(age)
+                    .or_insert(0) += 1;
+            }
+            SectionEvent::Equivocated(coalition) => {
+                self.output.equivocations += 1;
+                self.output.coalitions[coalition as usize - 1].equivocations += 1;
             }
-            SectionEvent::NeedRelocate(node) => {
-                self.relocate(node);
+            SectionEvent::EquivocationDetected(blocks) => {
+                *self.output
+                    .equivocation_detection
+                    .entry(blocks)
+                    .or_insert(0) += 1;
             }
-            SectionEvent::NodeRejected(_) => {
+            SectionEvent::NodeRejected(node) => {
                 self.output.rejections += 1;
+                *self.output.rejected_ages.entry(node.age()).or_insert(0) += 1;
+                *self.output
+                    .rejections_by_section
+                    .entry(prefix)
+                    .or_insert(0) += 1;
             }
             SectionEvent::RequestMerge => {
-                self.merge(prefix);
+                if self.params.merge_negotiation {
+                    self.propose_merge(prefix);
+                } else {
+                    self.merge(prefix);
+                }
+            }
+            SectionEvent::SplitRefused => {
+                self.output.refused_splits += 1;
+            }
+            SectionEvent::KeyRotated => {
+                *self.output.key_rotations.entry(prefix).or_insert(0) += 1;
             }
             SectionEvent::RequestSplit => {
                 if let Some(section) = self.nodes.remove(&prefix) {
-                    let ((sec0, ev0), (sec1, ev1)) = section.split(&self.params);
+                    let signers = section.elder_count();
+                    let (adults0, adults1) = section.adults_by_half();
+                    let params = section_params(&self.staged, &self.params, &prefix);
+                    let quorum = params.quorum;
+                    let ((sec0, ev0), (sec1, ev1)) = section.split(params);
+                    let verifiers = sec0.elder_count() + sec1.elder_count();
+                    self.output
+                        .signing
+                        .record(SignedMessage::Split, signers, verifiers, quorum);
+                    self.record_split_depth(sec0.prefix().len());
+                    let gap = adults0.abs_diff(adults1) / SPLIT_GAP_BRACKET * SPLIT_GAP_BRACKET;
+                    *self.output.split_adult_gaps.entry(gap).or_insert(0) += 1;
+                    let _ = self.split_gaps.insert(prefix, gap);
+                    let held = self.take_held_events(Some(&prefix));
+                    self.structural_change(&[prefix], &[sec0.prefix(), sec1.prefix()]);
+                    event_log::log(self.iteration, LoggedEvent::Split(prefix));
                     let _ = self.event_queue.remove(&prefix);
                     self.event_queue
                         .entry(sec0.prefix())
@@ -191,14 +1783,107 @@ impl Network {
                         .entry(sec1.prefix())
                         .or_insert_with(Vec::new)
                         .extend(ev1);
+                    trace::snapshot(self.iteration, &sec0);
+                    trace::snapshot(self.iteration, &sec1);
+                    event_bus::emit(
+                        self.iteration,
+                        BusEvent::Split {
+                            prefix: prefix.to_string(),
+                            into: (sec0.prefix().to_string(), sec1.prefix().to_string()),
+                        },
+                    );
+                    let prefixes = [sec0.prefix(), sec1.prefix()];
                     self.nodes.insert(sec0.prefix(), sec0);
                     self.nodes.insert(sec1.prefix(), sec1);
+                    if self.params.age_on_split != AgeRule::Keep {
+                        for prefix in prefixes {
+                            self.record_adult_identities(prefix);
+                        }
+                    }
+                    self.requeue(held);
+                    self.retarget_merge(prefix, &prefixes);
                     self.output.churn += 1; // counting the split as one churn event
                 }
             }
         }
     }
 
+    /// Starts the log record of a relocation of a node from the section `src`, if the relocation
+    /// log is exported
+    fn start_relocation(&mut self, src: Prefix, old: Node, new: Node) {
+        if self.params.relocation_log.is_none() {
+            return;
+        }
+        let record = RelocationRecord {
+            iteration: self.iteration,
+            src,
+            dst: None,
+            old,
+            new,
+            transit: 0,
+            retries: 0,
+        };
+        let _ = self.unfinished_relocations.insert(new.name(), record);
+    }
+
+    /// Completes the log record of the relocation of the node, if it was relocated, once it
+    /// arrives in the section `dst` or never arrives
+    fn finish_relocation(&mut self, node: Node, dst: Option<Prefix>) {
+        if let Some(mut record) = self.unfinished_relocations.remove(&node.name()) {
+            record.dst = dst;
+            record.transit = self.iteration - record.iteration;
+            self.output.relocation_log.push(record);
+        }
+    }
+
+    /// Follows a node relocated from the name `old_name` to its new name, recording the interval
+    /// since its previous relocation and flagging it if it went over the ping-pong limit
+    fn record_ping_pong(&mut self, old_name: Name, node: Node) {
+        let (limit, window) = match self.params.ping_pong {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let now = self.iteration;
+        let mut log = self.relocation_logs.remove(&old_name).unwrap_or_default();
+        if let Some(&last) = log.recent.back() {
+            let interval = now - last;
+            let bracket = if interval == 0 {
+                0
+            } else {
+                1 << (63 - interval.leading_zeros())
+            };
+            *self.output.relocation_intervals.entry(bracket).or_insert(0) += 1;
+        }
+        log.count += 1;
+        log.recent.push_back(now);
+        while log.recent[0] + window <= now {
+            let _ = log.recent.pop_front();
+        }
+        if log.recent.len() > limit && !log.flagged {
+            log.flagged = true;
+            debug!(in node.name();
+                "{:?} relocated {} times within {} iterations",
+                node, log.recent.len(), window
+            );
+            self.output.ping_pongs.push(PingPong {
+                iteration: now,
+                node,
+                relocations: log.recent.len(),
+            });
+        }
+        let _ = self.relocation_logs.insert(node.name(), log);
+    }
+
+    /// Returns the distribution of the numbers of times the nodes were relocated, among the
+    /// nodes relocated at least once, if relocation ping-pong is tracked
+    pub fn relocation_counts(&self) -> BTreeMap<u64, usize> {
+        let mut counts = BTreeMap::new();
+        for log in self.relocation_logs.values() {
+            *counts.entry(log.count).or_insert(0) += 1;
+        }
+        counts
+    }
+
     /// Returns the section that would be the result of merging sections with the given prefixes.
     /// If `destructive` is true, the sections are actually removed from `self.nodes` to be
     /// combined.
@@ -207,7 +1892,7 @@ impl Network {
         prefixes: I,
         destructive: bool,
     ) -> Section {
-        let mut sections: Vec<_> = prefixes
+        let sections: Vec<_> = prefixes
             .clone()
             .into_iter()
             .filter_map(|pfx| {
@@ -220,44 +1905,265 @@ impl Network {
             })
             .collect();
 
-        while sections.len() > 1 {
-            sections.sort_by_key(|s| s.prefix());
-            let section1 = sections.pop().unwrap();
-            let section2 = sections.pop().unwrap();
-            let section = section1.merge(section2, &self.params);
-            sections.push(section);
-        }
+        let merged = merged_prefix(&sections).unwrap_or_else(Prefix::empty);
+        merge_sections(sections, section_params(&self.staged, &self.params, &merged))
+    }
 
-        sections.pop().unwrap()
+    /// Predicts which sections would merge if churn stopped now, without changing the network.
+    /// Sections already merging or wanting to merge are merged into their parents, shorter
+    /// merged prefixes absorbing the longer ones, and the resulting sections are checked again
+    /// until no more merges happen.
+    pub fn preview_merges(&self) -> MergePreview {
+        let mut sections = self.nodes.clone();
+        let mut preview = MergePreview::default();
+        loop {
+            let mut merged_pfxs: Vec<_> = sections
+                .values()
+                .filter(|s| {
+                    s.is_merging()
+                        || s.should_merge(section_params(&self.staged, &self.params, &s.prefix()))
+                })
+                .map(|s| s.prefix().shorten())
+                .collect();
+            merged_pfxs.sort();
+            merged_pfxs.dedup();
+            let mut round = vec![];
+            for merged_pfx in merged_pfxs {
+                let prefixes: Vec<_> = sections
+                    .keys()
+                    .filter(|&pfx| merged_pfx.is_ancestor(pfx) && *pfx != merged_pfx)
+                    .cloned()
+                    .collect();
+                // absorbed by a merge into an ancestor in this round
+                if prefixes.is_empty() {
+                    continue;
+                }
+                let merging = prefixes
+                    .iter()
+                    .filter_map(|pfx| sections.remove(pfx))
+                    .collect();
+                let params = section_params(&self.staged, &self.params, &merged_pfx);
+                let merged = merge_sections(merging, params);
+                let _ = sections.insert(merged_pfx, merged);
+                round.push((merged_pfx, prefixes));
+            }
+            if round.is_empty() {
+                break;
+            }
+            preview.rounds.push(round);
+        }
+        preview.sections = sections
+            .iter()
+            .map(|(pfx, section)| (*pfx, section.len()))
+            .collect();
+        preview
     }
 
-    /// Calculates which sections will merge into a given prefix, creates a pending merge for them
-    /// and prepares queues for churn events to be processed before the merge itself.
-    fn merge(&mut self, prefix: Prefix) {
+    /// Records a merge proposal from the section `prefix`. If a proposal for a compatible merge
+    /// already exists, the one with the shorter merged prefix wins.
+    fn propose_merge(&mut self, prefix: Prefix) {
         let merged_pfx = prefix.shorten();
-        if let Some(&compatible_merge) = self.pending_merges
+        let conflicting: Vec<_> = self.merge_proposals
             .keys()
-            .find(|pfx| pfx.is_compatible_with(&merged_pfx))
-        {
-            if compatible_merge.is_ancestor(&merged_pfx) {
+            .filter(|pfx| pfx.is_compatible_with(&merged_pfx))
+            .cloned()
+            .collect();
+        for pfx in conflicting {
+            if pfx.is_ancestor(&merged_pfx) {
+                let _ = self.merge_proposals
+                    .get_mut(&pfx)
+                    .map(|proposal| proposal.accepted.insert(prefix));
                 return;
             }
-            let _ = self.pending_merges.remove(&compatible_merge);
+            debug!(in merged_pfx;
+                "Merge proposal into {:?} superseded by one into {:?}",
+                pfx, merged_pfx
+            );
+            self.output.merge_conflicts += 1;
+            let _ = self.merge_proposals.remove(&pfx);
+        }
+        debug!(in prefix; "{:?} proposing a merge into {:?}", prefix, merged_pfx);
+        let _ = self.merge_proposals
+            .insert(merged_pfx, MergeProposal::new(prefix));
+    }
+
+    /// Runs a round of merge negotiation: every section under a proposed merged prefix accepts
+    /// the proposal, unless it is splitting or already taking part in another merge. Merges
+    /// accepted by all the sections involved are initiated.
+    fn negotiate_merges(&mut self) {
+        let mut agreed = vec![];
+        for (merged_pfx, proposal) in &mut self.merge_proposals {
+            proposal.rounds += 1;
+            let mut all_accepted = true;
+            for pfx in self.index.descendants(*merged_pfx) {
+                if proposal.accepted.contains(&pfx) {
+                    continue;
+                }
+                let busy = self.nodes[&pfx].is_splitting()
+                    || self.pending_merges
+                        .keys()
+                        .any(|merging| merging.is_compatible_with(&pfx));
+                if busy {
+                    all_accepted = false;
+                } else {
+                    let _ = proposal.accepted.insert(pfx);
+                }
+            }
+            if all_accepted {
+                agreed.push(*merged_pfx);
+            }
         }
-        println!("Initiating a merge into {:?}", merged_pfx);
-        let prefixes: Vec<_> = self.nodes
+        for merged_pfx in agreed {
+            let proposal = self.merge_proposals.remove(&merged_pfx).unwrap();
+            *self.output
+                .merge_rounds
+                .entry(proposal.rounds)
+                .or_insert(0) += 1;
+            // the initiator could have split or merged in the meantime, so we use a prefix
+            // that is certain to merge into the proposed one
+            self.merge(merged_pfx.extend(0));
+        }
+    }
+
+    /// Calculates which sections will merge into a given prefix, creates a pending merge for them
+    /// and puts the churn events to be processed before the merge itself in front of their queues.
+    ///
+    /// Sections can request merges into different parents: after nested merges, a section can
+    /// want to merge into its parent while a sibling's descendants are already merging into the
+    /// sibling. A request covered by a pending merge into the same prefix or an ancestor is
+    /// absorbed by it. Otherwise the request wins: all the pending merges into its descendants
+    /// are superseded, and their sections go on to the bigger merge instead.
+    fn merge(&mut self, prefix: Prefix) {
+        let merged_pfx = prefix.shorten();
+        let compatible: Vec<_> = self.pending_merges
             .keys()
-            .filter(|&pfx| merged_pfx.is_ancestor(pfx))
+            .filter(|pfx| pfx.is_compatible_with(&merged_pfx))
             .cloned()
             .collect();
+        if let Some(ancestor) = compatible.iter().find(|pfx| pfx.is_ancestor(&merged_pfx)) {
+            if *ancestor != merged_pfx {
+                debug!(in merged_pfx;
+                    "Merge into {:?} absorbed by the pending merge into {:?}",
+                    merged_pfx, ancestor
+                );
+                self.output.absorbed_merges += 1;
+            }
+            return;
+        }
+        for pfx in compatible {
+            debug!(in merged_pfx;
+                "Pending merge into {:?} superseded by one into {:?}",
+                pfx, merged_pfx
+            );
+            self.output.superseded_merges += 1;
+            if let Some(pending_merge) = self.pending_merges.remove(&pfx) {
+                for section in pending_merge.into_map().keys() {
+                    self.unqueue_merge_events(pfx, *section);
+                }
+            }
+        }
+        debug!(in merged_pfx; "Initiating a merge into {:?}", merged_pfx);
+        let mut prefixes: Vec<_> = self.index.descendants(merged_pfx).collect();
+        prefixes.sort();
+        // if any of the sections has just been created by a merge, this one continues a cascade
+        let depth = 1 + prefixes
+            .iter()
+            .filter_map(|pfx| self.recent_merges.get(pfx))
+            .map(|&(_, depth)| depth)
+            .max()
+            .unwrap_or(0);
 
-        let pending_merge = PendingMerge::from_prefixes(prefixes.iter().cloned());
+        let elders = prefixes
+            .iter()
+            .filter_map(|pfx| self.nodes.get(pfx))
+            .flat_map(Section::elders)
+            .map(|node| node.name())
+            .collect();
+        let pending_merge = PendingMerge::from_prefixes(prefixes.iter().cloned(), depth, elders);
         self.pending_merges.insert(merged_pfx, pending_merge);
 
         let merged_section = self.merged_section(prefixes.iter(), false);
         for pfx in prefixes {
-            let events = self.calculate_merge_events(&merged_section, pfx);
-            let _ = self.event_queue.insert(pfx, events);
+            self.queue_merge_events(&merged_section, pfx);
+        }
+    }
+
+    /// Sends the events of a merge into `merged` to the section `pfx`. With delayed messages,
+    /// they arrive later, and the section can split in the meantime.
+    fn queue_merge_events(&mut self, merged: &Section, pfx: Prefix) {
+        let events = self.calculate_merge_events(merged, pfx);
+        if self.params.delays_messages() {
+            self.send_message(pfx, Message::Merge(merged.prefix(), events));
+        } else {
+            self.queue_in_front(pfx, events);
+        }
+    }
+
+    /// Puts the events of a merge in front of the events already queued for the section `pfx`,
+    /// which it might be holding back while slow or paused. The section starts merging before it
+    /// handles them, so that none of them can make it split while merging.
+    fn queue_in_front(&mut self, pfx: Prefix, mut events: Vec<NetworkEvent>) {
+        let queue = self.event_queue.entry(pfx).or_insert_with(Vec::new);
+        events.append(queue);
+        *queue = events;
+    }
+
+    /// Removes the events of a superseded merge into `merged_pfx` from the queue of the section
+    /// `pfx`, or from the way there, if it hasn't handled them yet
+    fn unqueue_merge_events(&mut self, merged_pfx: Prefix, pfx: Prefix) {
+        for messages in self.in_flight.values_mut() {
+            messages.retain(|&(dst, ref message)| match *message {
+                Message::Merge(prefix, _) => dst != pfx || prefix != merged_pfx,
+                Message::Join(_) => true,
+            });
+        }
+        self.in_flight.retain(|_, messages| !messages.is_empty());
+        let queue = match self.event_queue.get_mut(&pfx) {
+            Some(queue) => queue,
+            None => return,
+        };
+        let start = queue.iter().position(|event| match *event {
+            NetworkEvent::StartMerge(prefix) => prefix == merged_pfx,
+            _ => false,
+        });
+        let end = queue.iter().position(|event| match *event {
+            NetworkEvent::PrefixChange(prefix) => prefix == merged_pfx,
+            _ => false,
+        });
+        if let (Some(start), Some(end)) = (start, end) {
+            let _ = queue.drain(start..end + 1);
+        }
+    }
+
+    /// Puts the halves of a section that split while a merge including it was pending into the
+    /// merge in its place, so that the merge doesn't wait for a section that no longer exists.
+    /// The section can still split after the merge is initiated, while it handles the events it
+    /// took before.
+    fn retarget_merge(&mut self, prefix: Prefix, halves: &[Prefix]) {
+        let merged_pfx = match self.pending_merges
+            .iter()
+            .find(|&(_, pending_merge)| pending_merge.complete.contains_key(&prefix))
+        {
+            Some((merged_pfx, _)) => *merged_pfx,
+            None => return,
+        };
+        debug!(in prefix;
+            "{:?} split while merging, its halves merge into {:?} instead",
+            prefix, merged_pfx
+        );
+        // the events sent to the section before it split are no use to it any more
+        self.unqueue_merge_events(merged_pfx, prefix);
+        let prefixes: Vec<_> = {
+            let pending_merge = self.pending_merges.get_mut(&merged_pfx).unwrap();
+            let _ = pending_merge.complete.remove(&prefix);
+            for half in halves {
+                let _ = pending_merge.complete.insert(*half, false);
+            }
+            pending_merge.complete.keys().cloned().collect()
+        };
+        let merged_section = self.merged_section(prefixes.iter(), false);
+        for &half in halves {
+            self.queue_merge_events(&merged_section, half);
         }
     }
 
@@ -281,139 +2187,1670 @@ impl Network {
     pub fn add_random_node(&mut self) {
         self.output.adds += 1;
         self.output.churn += 1;
-        let node = Node::new(random(), self.params.init_age);
-        println!("Adding node {:?}", node);
-        let prefix = self.prefix_for_node(node).unwrap();
-        self.event_queue
-            .entry(prefix)
-            .or_insert_with(Vec::new)
-            .push(NetworkEvent::Live(node));
+        if let Some(quota) = self.params.infant_quota {
+            if self.num_infants() >= quota {
+                debug!("Joining node turned away, the network is at its infant quota");
+                self.output.quota_rejections += 1;
+                return;
+            }
+        }
+        let name = match self.join_target() {
+            Some(prefix) => prefix.substituted_in(Name(random())).0,
+            None => self.random_name(),
+        };
+        let mut node = match self.random_coalition() {
+            0 => Node::new(name, self.params.init_age),
+            coalition => {
+                self.output.coalitions[coalition as usize - 1].joins += 1;
+                // coalitions concentrating on a part of the network choose names inside it, or
+                // rejoin until they get one
+                let name = match self.params.coalition(coalition) {
+                    Some(&Coalition {
+                        target: Some(target),
+                        rejoins: true,
+                        ..
+                    }) => {
+                        let (name, attempts) = attack::join_into(target, Name(name));
+                        self.output.coalitions[coalition as usize - 1].join_attempts += attempts;
+                        name.0
+                    }
+                    Some(&Coalition {
+                        target: Some(target),
+                        ..
+                    }) => target.substituted_in(Name(name)).0,
+                    _ => name,
+                };
+                Node::new_malicious(name, self.params.init_age, coalition)
+            }
+        };
+        if self.params.permanent > 0 && random_range(0, 100) < self.params.permanent {
+            node = node.into_permanent();
+        }
+        let behaviour = self.random_behaviour();
+        if behaviour > 0 {
+            self.output.behaviours[behaviour as usize - 1].joins += 1;
+            node = node.with_behaviour(behaviour);
+        }
+        node.joined(self.iteration);
+        debug!(in node.name(); "Adding node {:?}", node);
+        let flags = node.flags();
+        event_log::log(self.iteration, LoggedEvent::Live { node, flags });
+        if self.params.bootstrap_cache.is_some() {
+            match self.bootstrap(node.name()) {
+                Some(hops) => *self.output.bootstrap_hops.entry(hops).or_insert(0) += 1,
+                None => debug!(in node.name(); "No section for {:?} to bootstrap off", node),
+            }
+        }
+        self.send_join(node);
     }
 
-    /// Calculates the sum of weights for the dropping probability.
-    /// When choosing the node to be dropped, every node is assigned a weight, so that older nodes
-    /// have less chance of dropping. This helps in calculating which node should be dropped.
-    fn total_drop_weight(&self) -> f64 {
-        self.nodes
-            .iter()
-            .flat_map(|(_, s)| s.nodes().into_iter())
-            .map(|n| n.drop_probability(self.params.drop_dist))
-            .sum()
+    /// Chooses the attacker coalition of a joining node according to the coalitions' fractions;
+    /// returns 0 for an honest node
+    fn random_coalition(&self) -> u8 {
+        if self.params.coalitions.is_empty() {
+            return 0;
+        }
+        let mut x = random_range(0, 100);
+        for (i, coalition) in self.params.coalitions.iter().enumerate() {
+            if x < coalition.fraction {
+                return i as u8 + 1;
+            }
+            x -= coalition.fraction;
+        }
+        0
     }
 
-    /// Returns the prefix a node should belong to.
-    fn prefix_for_node(&self, node: Node) -> Option<Prefix> {
-        self.nodes
-            .keys()
-            .find(|pfx| pfx.matches(node.name()))
-            .cloned()
+    /// Simulates a joining node contacting a random section from the cached contact list and
+    /// its request being forwarded to the section it joins, and returns the number of hops, or
+    /// `None` if there are no sections to contact
+    fn bootstrap(&mut self, name: Name) -> Option<usize> {
+        let interval = self.params.bootstrap_cache.unwrap_or(1).max(1);
+        if self.contacts.is_empty() || self.iteration % interval == 0 {
+            self.contacts = self.nodes.keys().cloned().collect();
+        }
+        if self.contacts.is_empty() {
+            return None;
+        }
+        let contact = self.contacts[random_range(0, self.contacts.len())];
+        let start = if self.nodes.contains_key(&contact) {
+            contact
+        } else {
+            // the section split or merged; whichever section took over its part of the name
+            // space answers
+            self.output.stale_contacts += 1;
+            self.index.compatible(contact).min()?
+        };
+        let path = self.route(start, name)?;
+        debug!(in name; "Bootstrapping {:?} via {:?}", name, path);
+        Some(path.len() - 1)
     }
 
-    /// Chooses a new section for the given node, generates a new name for it,
-    /// increases its age,  and sends a `Live` event to the section.
-    fn relocate(&mut self, mut node: Node) {
-        self.output.relocations += 1;
-        self.output.churn += 2; // leaving one section and joining another one
-        let (node, neighbour) = {
-            let src_section = self.nodes
-                .keys()
-                .find(|&pfx| pfx.matches(node.name()))
-                .unwrap();
-            let mut neighbours: Vec<_> = self.nodes
-                .keys()
-                .filter(|&pfx| pfx.is_neighbour(src_section))
-                .collect();
-            // relocate to the neighbour with the least peers as per the document
-            neighbours.sort_by_key(|pfx| self.nodes.get(pfx).unwrap().len());
-            let neighbour = if let Some(n) = neighbours.first() {
-                n
-            } else {
-                src_section
+    /// Returns the sections a message for the given name passes through, starting from `src`:
+    /// every section passes it on to the neighbour sharing the longest prefix with the name.
+    /// Returns `None` if no section is responsible for the name.
+    fn route(&self, src: Prefix, name: Name) -> Option<Vec<Prefix>> {
+        let common = |pfx: &Prefix| ((pfx.bits() ^ name.0).leading_zeros() as u8).min(pfx.len());
+        let mut path = vec![src];
+        let mut current = src;
+        while !current.matches(name) {
+            let next = self.index
+                .neighbours(current)
+                .into_iter()
+                .max_by_key(|pfx| common(pfx));
+            current = match next {
+                Some(next) if common(&next) > common(&current) => next,
+                // no neighbour gets closer; the section has to look the target up
+                _ => self.index.longest_match(name)?,
             };
-            let old_node = node.clone();
-            node.relocate(neighbour);
-            println!(
-                "Relocating {:?} from {:?} to {:?} as {:?}",
-                old_node, src_section, neighbour, node
-            );
-            (node, neighbour)
-        };
-        self.event_queue
-            .entry(*neighbour)
-            .or_insert_with(Vec::new)
-            .push(NetworkEvent::Live(node));
+            path.push(current);
+        }
+        Some(path)
     }
 
-    /// Drops a random node from the network by sending a `Lost` event to the section.
-    /// The probability of a given node dropping is weighted based on its age.
-    pub fn drop_random_node(&mut self) {
-        self.output.drops += 1;
-        self.output.churn += 1;
-        let total_weight = self.total_drop_weight();
-        let mut drop = random::<f64>() * total_weight;
-        let node_and_prefix = {
-            let mut res = None;
-            let nodes_iter = self.nodes
-                .iter()
-                .flat_map(|(p, s)| s.nodes().into_iter().map(move |n| (*p, n)));
-            for (p, n) in nodes_iter {
-                if n.drop_probability(self.params.drop_dist) > drop {
-                    res = Some((p, n));
-                    break;
+    /// Chooses the behaviour of a joining node according to the behaviours' fractions; returns 0
+    /// for a node only subject to the random churn
+    fn random_behaviour(&self) -> u8 {
+        if self.params.behaviours.is_empty() {
+            return 0;
+        }
+        let mut x = random_range(0, 100);
+        for (i, behaviour) in self.params.behaviours.iter().enumerate() {
+            if x < behaviour.fraction {
+                return i as u8 + 1;
+            }
+            x -= behaviour.fraction;
+        }
+        0
+    }
+
+    /// Lets the nodes following a behaviour act: the members of the sections drop out or get
+    /// evicted for misbehaving, and the nodes that left rejoin, unless the network is draining
+    pub fn apply_behaviours(&mut self) {
+        let iteration = self.iteration;
+        let mut actions = vec![];
+        for (prefix, section) in &self.nodes {
+            for node in section.iter_nodes() {
+                if node.behaviour() == 0
+                    || node.is_permanent()
+                    || node.is_observer()
+                    || self.deaths.contains_key(&node.name())
+                {
+                    continue;
+                }
+                match self.behaviours[node.behaviour() as usize - 1].act(&node, iteration) {
+                    Action::Stay => (),
+                    action => actions.push((*prefix, node, action)),
                 }
-                drop -= n.drop_probability(self.params.drop_dist);
             }
-            res
-        };
-        node_and_prefix.map(|(prefix, node)| {
-            *self.output.drops_dist.entry(node.age()).or_insert(0) += 1;
-            let name = node.name();
-            println!("Dropping node {:?} from section {:?}", name, prefix);
-            self.event_queue
-                .entry(prefix)
-                .or_insert_with(Vec::new)
-                .push(NetworkEvent::Lost(name));
+        }
+        for (prefix, node, action) in actions {
+            self.output.churn += 1;
+            let stats = &mut self.output.behaviours[node.behaviour() as usize - 1];
+            if action == Action::Drop {
+                stats.drops += 1;
+                self.drop_node(prefix, node);
+            } else {
+                stats.evictions += 1;
+                self.evict_node(prefix, node);
+            }
+        }
+
+        if self.output.drain.is_some() {
+            return;
+        }
+        let behaviours = &self.behaviours;
+        let (rejoining, staying) = self.left_nodes.drain(..).partition(|node| {
+            node.behaviour() > 0
+                && behaviours[node.behaviour() as usize - 1].rejoins(node, iteration)
         });
+        self.left_nodes = staying;
+        for node in rejoining {
+            self.output.churn += 1;
+            self.output.behaviours[node.behaviour() as usize - 1].rejoins += 1;
+            self.rejoin(node);
+        }
     }
 
-    /// Chooses a random node from among the ones that left the network and gets it to rejoin.
-    /// The age of the rejoining node is reduced.
-    pub fn rejoin_random_node(&mut self) {
-        self.output.rejoins += 1;
-        self.output.churn += 1;
-        shuffle(&mut self.left_nodes);
-        if let Some(mut node) = self.left_nodes.pop() {
-            println!("Rejoining node {:?}", node);
-            node.rejoined(self.params.init_age);
-            let prefix = self.prefix_for_node(node).unwrap();
-            self.event_queue
-                .entry(prefix)
-                .or_insert_with(Vec::new)
-                .push(NetworkEvent::Live(node));
-        }
+    /// Evicts a misbehaving node from the section by sending a `Lost` event to it
+    fn evict_node(&mut self, prefix: Prefix, node: Node) {
+        debug!(in prefix; "Evicting misbehaving node {:?} from {:?}", node.name(), prefix);
+        event_log::log(self.iteration, LoggedEvent::Evict(node.name()));
+        self.event_queue
+            .entry(prefix)
+            .or_insert_with(Vec::new)
+            .push(NetworkEvent::Lost(node.name()));
     }
 
-    pub fn num_sections(&self) -> usize {
-        self.nodes.len()
+    /// Applies the churn recorded for this iteration in the event log being replayed, instead of
+    /// generating it. If a node the log refers to isn't where it should be, the replay has
+    /// diverged and the simulation fails.
+    pub fn replay_churn(&mut self) {
+        for event in event_log::churn(self.iteration) {
+            self.output.churn += 1;
+            let found = match event {
+                LoggedEvent::Live { node, flags } => {
+                    self.output.adds += 1;
+                    let mut node = node.with_flags(flags);
+                    node.joined(self.iteration);
+                    self.send_join(node);
+                    true
+                }
+                LoggedEvent::Rejoin(None) => {
+                    self.output.rejoins += 1;
+                    true
+                }
+                LoggedEvent::Rejoin(Some(name)) => {
+                    self.output.rejoins += 1;
+                    match self.take_left_node(name) {
+                        Some(node) => {
+                            self.rejoin(node);
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                LoggedEvent::Return(name) => match self.take_left_node(name) {
+                    Some(node) => {
+                        self.return_node(node);
+                        true
+                    }
+                    None => false,
+                },
+                LoggedEvent::Dead(name) | LoggedEvent::Evict(name) => {
+                    let member = self.index.longest_match(name).and_then(|prefix| {
+                        self.nodes[&prefix]
+                            .iter_nodes()
+                            .find(|node| node.name() == name)
+                            .map(|node| (prefix, node))
+                    });
+                    match member {
+                        Some((prefix, node)) if event == LoggedEvent::Dead(name) => {
+                            self.output.drops += 1;
+                            self.drop_node(prefix, node);
+                            true
+                        }
+                        Some((prefix, node)) => {
+                            self.evict_node(prefix, node);
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                _ => true,
+            };
+            if !found {
+                self.failure = Some(format!(
+                    "The replay diverged from the log in iteration {}: {:?} was recorded, but \
+                     there is no such node",
+                    self.iteration, event
+                ));
+                return;
+            }
+        }
     }
 
-    pub fn age_distribution(&self) -> BTreeMap<u8, usize> {
-        let mut result = BTreeMap::new();
-        for (_, section) in &self.nodes {
-            for node in section.nodes() {
-                *result.entry(node.age()).or_insert(0) += 1;
+    /// Changes a parameter from the current iteration on, for the sections matching the prefix,
+    /// or for the whole network if it's empty. The affected sections reconsider their elders
+    /// under the new parameters right away. A change that would make the parameters invalid
+    /// fails the run instead.
+    pub fn change_param(&mut self, param: FuzzedParam, value: f64, prefix: Prefix) {
+        let mut change = format!("{}={}", param.name(), value);
+        if prefix != Prefix::empty() {
+            change = format!("{} in {:?}", change, prefix);
+        }
+        // the sections under the prefix that follow parameters of their own, and the ones that
+        // follow the parameters of the prefix itself
+        let affected = self.staged
+            .iter()
+            .filter(|&(pfx, _)| prefix.is_ancestor(pfx))
+            .map(|(_, params)| params)
+            .chain(iter::once(section_params(&self.staged, &self.params, &prefix)));
+        for params in affected {
+            let mut params = params.clone();
+            param.set(&mut params, value);
+            if let Err(msg) = params.validate() {
+                self.failure = Some(format!(
+                    "Can't change {} in iteration {}: {}",
+                    change, self.iteration, msg
+                ));
+                return;
             }
         }
-        result
+        debug!("Changing {}", change);
+        if prefix == Prefix::empty() {
+            param.set(&mut self.params, value);
+        } else if !self.staged.contains_key(&prefix) {
+            let params = section_params(&self.staged, &self.params, &prefix).clone();
+            let _ = self.staged.insert(prefix, params);
+        }
+        for (_, params) in self.staged
+            .iter_mut()
+            .filter(|&(pfx, _)| prefix.is_ancestor(pfx))
+        {
+            param.set(params, value);
+        }
+        for (pfx, section) in self.nodes
+            .iter_mut()
+            .filter(|&(pfx, _)| prefix.is_ancestor(pfx))
+        {
+            section.reconsider_elders(section_params(&self.staged, &self.params, pfx));
+        }
+        let latest = *self.recent_turbulence.back().unwrap();
+        let earliest = *self.recent_turbulence.front().unwrap();
+        self.output.transitions.push(Transition {
+            iteration: self.iteration,
+            change,
+            before: latest.since(&earliest),
+            before_iterations: self.recent_turbulence.len() as u64 - 1,
+            ..Default::default()
+        });
+    }
+
+    /// Returns the turbulence counted since the start of the run
+    fn turbulence(&self) -> Turbulence {
+        Turbulence {
+            relocations: self.output.relocations,
+            rejections: self.output.rejections,
+            splits: self.output.splits,
+            merges: self.output.merges,
+        }
+    }
+
+    /// Adds the turbulence of the iteration to the parameter changes made in the last
+    /// `TURBULENCE_WINDOW` iterations, and remembers it for the changes to come
+    fn record_turbulence(&mut self) {
+        let total = self.turbulence();
+        let iteration = total.since(self.recent_turbulence.back().unwrap());
+        for transition in &mut self.output.transitions {
+            if transition.after_iterations < TURBULENCE_WINDOW {
+                transition.after.add(&iteration);
+                transition.after_iterations += 1;
+            }
+        }
+        self.recent_turbulence.push_back(total);
+        if self.recent_turbulence.len() as u64 > TURBULENCE_WINDOW + 1 {
+            let _ = self.recent_turbulence.pop_front();
+        }
+    }
+
+    /// Counts the iteration for the sections with fewer than a quorum of elders present, which
+    /// can't reach consensus
+    fn record_quorum_exposure(&mut self) {
+        for (prefix, section) in &self.nodes {
+            let present = if self.deaths.is_empty() {
+                section.elder_count()
+            } else {
+                section
+                    .elders()
+                    .iter()
+                    .filter(|elder| !self.deaths.contains_key(&elder.name()))
+                    .count()
+            };
+            if present < section_params(&self.staged, &self.params, prefix).quorum {
+                *self.output.below_quorum.entry(*prefix).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Updates the statistics of the attacker coalitions with the current elders
+    fn record_coalitions(&mut self) {
+        let mut compromised = BTreeSet::new();
+        for (prefix, section) in &self.nodes {
+            let elders = section.elders();
+            let quorum = section_params(&self.staged, &self.params, prefix).quorum;
+            let timeline = self.output
+                .elder_timelines
+                .entry(*prefix)
+                .or_insert_with(Vec::new);
+            let changed = match timeline.last() {
+                Some((_, last)) => !last.iter().eq(elders.iter()),
+                None => true,
+            };
+            if changed {
+                timeline.push((self.iteration, elders.iter().cloned().collect()));
+            }
+            // with so few elders the section can't make decisions anyway
+            if elders.len() < quorum {
+                continue;
+            }
+            let malicious = elders.iter().filter(|n| n.is_malicious()).count();
+            let share = malicious as f64 / elders.len() as f64;
+            let worst = match self.output.worst_malicious_elders {
+                Some((worst, _, _)) => share > worst,
+                None => malicious > 0,
+            };
+            if worst {
+                self.output.worst_malicious_elders = Some((share, *prefix, self.iteration));
+            }
+            let mut counts = BTreeMap::new();
+            for elder in elders.iter().filter(|n| n.is_malicious()) {
+                *counts.entry(elder.coalition()).or_insert(0) += 1;
+            }
+            for (coalition, count) in counts {
+                let stats = &mut self.output.coalitions[coalition as usize - 1];
+                let share = count as f64 / elders.len() as f64;
+                if share > stats.peak_elder_share {
+                    stats.peak_elder_share = share;
+                    stats.peak_section = *prefix;
+                    stats.peak_iteration = self.iteration;
+                }
+                if count >= quorum {
+                    let _ = compromised.insert(coalition);
+                }
+            }
+        }
+        for coalition in compromised {
+            let stats = &mut self.output.coalitions[coalition as usize - 1];
+            stats.compromised_iterations += 1;
+            stats.first_compromised = stats.first_compromised.or(Some(self.iteration));
+        }
+    }
+
+    /// Returns the churn counters of the section with the given prefix
+    fn section_churn(&mut self, prefix: Prefix) -> &mut SectionChurn {
+        self.output
+            .section_churn
+            .entry(prefix)
+            .or_insert_with(Default::default)
+    }
+
+    /// Notes that the current iteration is part of a churn storm
+    pub fn record_storm_iteration(&mut self) {
+        self.output.storm_iterations += 1;
+    }
+
+    /// Returns the section a joining node should be directed to according to the join policy, or
+    /// `None` if it should get a random name
+    fn join_target(&self) -> Option<Prefix> {
+        if self.params.join_policy == JoinPolicy::Random
+            || self.params.join_targeting < 100
+                && random_range(0, 100) >= self.params.join_targeting
+        {
+            return None;
+        }
+        let mean_age = |section: &Section| {
+            let nodes = section.nodes();
+            let total: f64 = nodes.iter().map(|n| f64::from(n.age())).sum();
+            total / nodes.len().max(1) as f64
+        };
+        match self.params.join_policy {
+            JoinPolicy::Random => None,
+            JoinPolicy::Smallest => self.nodes
+                .iter()
+                .min_by_key(|&(_, section)| section.len())
+                .map(|(pfx, _)| *pfx),
+            JoinPolicy::Youngest => self.nodes
+                .iter()
+                .min_by(|&(_, s0), &(_, s1)| mean_age(s0).partial_cmp(&mean_age(s1)).unwrap())
+                .map(|(pfx, _)| *pfx),
+        }
+    }
+
+    /// Generates a random name within the simulated part of the network
+    fn random_name(&self) -> u64 {
+        match self.params.restrict_prefix {
+            Some(prefix) => prefix.substituted_in(Name(random())).0,
+            None => random(),
+        }
+    }
+
+    /// Returns whether a relocation from the section `src` should leave the simulated sub-tree.
+    /// Relocations go to neighbouring sections, and the neighbours differing from `src` within
+    /// the restricting prefix are the ones outside the sub-tree.
+    fn relocates_out(&self, src: &Prefix) -> bool {
+        let restricted = self.params.min_prefix_len();
+        self.params.stub != Stub::Closed && restricted > 0
+            && random_range(0, src.len()) < restricted
+    }
+
+    /// Calculates the sum of weights for the dropping probability.
+    /// When choosing the node to be dropped, every node is assigned a weight, so that older nodes
+    /// have less chance of dropping. This helps in calculating which node should be dropped.
+    fn total_drop_weight(&self) -> u64 {
+        self.nodes
+            .iter()
+            .flat_map(|(_, s)| s.nodes().into_iter())
+            .map(|n| self.drop_weight(n))
+            .sum()
+    }
+
+    /// Returns the weight of the node in randomly choosing a node to be dropped; attackers that
+    /// never leave on their own have none
+    fn drop_weight(&self, node: Node) -> u64 {
+        if node.is_malicious() && self.params.stays(node.coalition())
+            || self.deaths.contains_key(&node.name())
+        {
+            0
+        } else {
+            node.drop_weight(&self.params.drop_dist)
+        }
+    }
+
+    /// Returns the prefix a node should belong to, or an error if its name matches no section,
+    /// which can happen transiently while sections split or merge
+    fn prefix_for_node(&self, node: Node) -> Result<Prefix, String> {
+        self.index
+            .longest_match(node.name())
+            .ok_or_else(|| format!("No section matches {:?}", node.name()))
+    }
+
+    /// Queues a `Live` event for the node in the section it belongs to and returns its prefix.
+    /// If the name matches no section, the node is kept to be delivered again in the next
+    /// iteration instead. With delayed messages, the event is queued when it arrives.
+    fn send_live(&mut self, node: Node) -> Result<Prefix, String> {
+        let result = self.prefix_for_node(node);
+        match result {
+            Ok(prefix) if self.params.delays_messages() => {
+                self.send_message(prefix, Message::Join(node))
+            }
+            Ok(prefix) => {
+                self.finish_relocation(node, Some(prefix));
+                self.event_queue
+                    .entry(prefix)
+                    .or_insert_with(Vec::new)
+                    .push(NetworkEvent::Live(node));
+            }
+            Err(ref msg) => {
+                debug!("{}, delivering {:?} in the next iteration", msg, node);
+                self.output.redeliveries += 1;
+                if let Some(record) = self.unfinished_relocations.get_mut(&node.name()) {
+                    record.retries += 1;
+                }
+                self.undelivered.push(node);
+            }
+        }
+        result
+    }
+
+    /// Sends the request of a joining node to the section it belongs to or, for the share of
+    /// the nodes that contact the wrong section, to a random section that redirects it
+    fn send_join(&mut self, node: Node) {
+        if self.params.misdirected_joins > 0
+            && random_range(0, 100) < self.params.misdirected_joins
+        {
+            let at = *self.nodes.keys().nth(random_range(0, self.nodes.len())).unwrap();
+            if !at.matches(node.name()) {
+                debug!(in at; "{:?} contacts the wrong section {:?}", node, at);
+                self.redirected.push(Redirect {
+                    node,
+                    at,
+                    hops: 0,
+                    since: self.iteration,
+                });
+                return;
+            }
+        }
+        if let Ok(prefix) = self.send_live(node) {
+            self.section_churn(prefix).joins += 1;
+        }
+    }
+
+    /// Passes the requests of the joining nodes that contacted the wrong section on by a hop
+    /// towards the sections they belong to, and delivers the ones that arrive. A section
+    /// handing over after an elder relocation doesn't pass requests on.
+    fn redirect_joins(&mut self) {
+        for mut redirect in mem::take(&mut self.redirected) {
+            let name = redirect.node.name();
+            // the section the request is at could have split or merged in the meantime
+            let at = self.successor(redirect.at, name)
+                .or_else(|| self.index.compatible(redirect.at).min())
+                .unwrap_or(redirect.at);
+            redirect.at = at;
+            // the request reaches the contacted section in the iteration it is sent
+            if redirect.since == self.iteration || self.is_paused(&at) {
+                self.redirected.push(redirect);
+                continue;
+            }
+            if !at.matches(name) {
+                // with no section responsible for the name, the request waits for one
+                redirect.at = match self.route(at, name) {
+                    Some(path) => path[1],
+                    None => {
+                        self.redirected.push(redirect);
+                        continue;
+                    }
+                };
+                redirect.hops += 1;
+                debug!(in at; "Redirecting {:?} from {:?} to {:?}", name, at, redirect.at);
+            }
+            if !redirect.at.matches(name) {
+                self.redirected.push(redirect);
+                continue;
+            }
+            *self.output.redirect_hops.entry(redirect.hops).or_insert(0) += 1;
+            *self.output
+                .redirect_delays
+                .entry(self.iteration - redirect.since)
+                .or_insert(0) += 1;
+            if let Ok(prefix) = self.send_live(redirect.node) {
+                self.section_churn(prefix).joins += 1;
+            }
+        }
+    }
+
+    /// Sends a message to the section with the given prefix, which it reaches after a delay
+    /// drawn from the geometric distribution. The request of a joining or relocated node can be
+    /// lost on the way, in which case the node leaves the network; the sections resend the
+    /// events of merges until they arrive, which the delay accounts for.
+    fn send_message(&mut self, dst: Prefix, message: Message) {
+        if let Message::Join(mut node) = message {
+            if gen_bool_with_probability(from_percent(self.params.msg_loss_prob)) {
+                debug!(in dst; "The request of {:?} to {:?} is lost", node, dst);
+                self.output.lost_messages += 1;
+                self.finish_relocation(node, None);
+                node.left(self.iteration);
+                self.left_nodes.push(node);
+                return;
+            }
+        }
+        let mean = self.params.msg_delay_mean;
+        let delay = geometric(from_percent(100.0 * mean / (1.0 + mean)));
+        *self.output.message_delays.entry(delay).or_insert(0) += 1;
+        if delay == 0 {
+            self.deliver_message(dst, message);
+        } else {
+            self.in_flight
+                .entry(self.iteration + delay)
+                .or_insert_with(Vec::new)
+                .push((dst, message));
+        }
+    }
+
+    /// Delivers the messages that arrive in this iteration
+    fn deliver_messages(&mut self) {
+        let later = self.in_flight.split_off(&(self.iteration + 1));
+        let arrived = mem::replace(&mut self.in_flight, later);
+        for (dst, message) in arrived.into_values().flatten() {
+            self.deliver_message(dst, message);
+        }
+    }
+
+    /// Queues the events of a message in the section it was sent to. The request of a node
+    /// goes to the section that took over the node's name if that section split or merged while
+    /// the request was on its way.
+    fn deliver_message(&mut self, dst: Prefix, message: Message) {
+        let node = match message {
+            Message::Join(node) => node,
+            Message::Merge(merged_pfx, events) => {
+                // the events of superseded merges and of sections that split were taken off
+                // the way already
+                debug!(in dst; "The events of the merge into {:?} reach {:?}", merged_pfx, dst);
+                self.queue_in_front(dst, events);
+                return;
+            }
+        };
+        let name = node.name();
+        let prefix = match self.successor(dst, name).or_else(|| self.index.longest_match(name)) {
+            Some(prefix) => prefix,
+            None => {
+                debug!("No section matches {:?}, delivering it in the next iteration", node);
+                self.output.redeliveries += 1;
+                self.undelivered.push(node);
+                return;
+            }
+        };
+        if prefix != dst {
+            debug!(in prefix; "The request of {:?} to {:?} arrives at {:?}", node, dst, prefix);
+            self.output.stale_messages += 1;
+        }
+        self.finish_relocation(node, Some(prefix));
+        self.event_queue
+            .entry(prefix)
+            .or_insert_with(Vec::new)
+            .push(NetworkEvent::Live(node));
+    }
+
+    /// Returns the number of messages on their way between sections
+    pub fn num_in_flight(&self) -> usize {
+        self.in_flight.values().map(Vec::len).sum()
+    }
+
+    /// Returns the number of joining nodes whose requests are being redirected
+    pub fn num_redirected(&self) -> usize {
+        self.redirected.len()
+    }
+
+    /// Delivers again the nodes whose names matched no section in the previous iteration
+    fn redeliver(&mut self) {
+        for node in mem::take(&mut self.undelivered) {
+            let _ = self.send_live(node);
+        }
+    }
+
+    /// Chooses a new section for the given node, generates a new name for it,
+    /// increases its age,  and sends a `Live` event to the section.
+    /// The section is the neighbour with the fewest nodes, or if the elders decide relocations,
+    /// the one matching the hash of the node's name and the hash the relocation was decided on.
+    fn relocate(&mut self, mut node: Node, hash: Hash, from: Prefix) {
+        let old = node;
+        self.output.relocations += 1;
+        self.output.churn += 2; // leaving one section and joining another one
+        // if the node's name matches no section while they split or merge, it leaves the one
+        // that decided to relocate it
+        let src = self.prefix_for_node(node).unwrap_or(from);
+        let signers = self.elder_count(&src);
+        let (group_size, quorum) = {
+            let params = section_params(&self.staged, &self.params, &src);
+            (params.group_size, params.quorum)
+        };
+        self.section_churn(src).relocations_out += 1;
+        if self.relocates_out(&src) {
+            self.output.stub_relocations += 1;
+            debug!(in src; "Relocating {:?} from {:?} out of the sub-tree", node, src);
+            // the rest of the network is assumed to have complete sections
+            self.output
+                .signing
+                .record(SignedMessage::Relocation, signers, group_size, quorum);
+            if self.params.stub == Stub::Absorb {
+                self.start_relocation(src, old, node);
+                self.finish_relocation(node, None);
+                let event = BusEvent::Relocation {
+                    node,
+                    from: Some(src.to_string()),
+                    to: None,
+                };
+                event_bus::emit(self.iteration, event);
+                return;
+            }
+            // the rest of the network sends a node of the same age back in its place; it joins
+            // the smallest section, as if it was relocated from a neighbour
+            let dst = *self.nodes
+                .iter()
+                .min_by_key(|&(_, section)| section.len())
+                .unwrap()
+                .0;
+            let old_name = node.name();
+            self.relocate_node(&mut node, &dst);
+            self.record_ping_pong(old_name, node);
+            self.start_relocation(src, old, node);
+            debug!(in dst; "Relocating {:?} into the sub-tree at {:?}", node, dst);
+            let verifiers = self.elder_count(&dst);
+            self.output
+                .signing
+                .record(SignedMessage::Relocation, group_size, verifiers, quorum);
+            let event = BusEvent::Relocation {
+                node,
+                from: None,
+                to: Some(dst.to_string()),
+            };
+            event_bus::emit(self.iteration, event);
+            self.section_churn(dst).relocations_in += 1;
+            self.send_relocated(dst, node);
+            return;
+        }
+        let old_name = node.name();
+        let (node, neighbour) = {
+            let src_section = &src;
+            let neighbour = if self.params.elder_relocation {
+                // the elders only know the block they signed, not the sizes of the neighbours
+                let name_hash = Hash::of(&old_name.0.to_be_bytes());
+                let name = Name(hash.combine(&name_hash).to_u64(Endianness::Big));
+                self.index.longest_match(name).unwrap_or(src)
+            } else {
+                let mut neighbours = self.index.neighbours(*src_section);
+                // relocate to the neighbour with the least peers as per the document
+                neighbours.sort_by_key(|pfx| self.nodes.get(pfx).unwrap().len());
+                neighbours.first().map_or(src, |&n| n)
+            };
+            let old_node = node.clone();
+            self.relocate_node(&mut node, &neighbour);
+            debug!(in *src_section;
+                "Relocating {:?} from {:?} to {:?} as {:?}",
+                old_node, src_section, neighbour, node
+            );
+            (node, neighbour)
+        };
+        self.record_ping_pong(old_name, node);
+        self.start_relocation(src, old, node);
+        if self.is_reorganising(&neighbour) {
+            self.output.reorg_destinations += 1;
+        }
+        let verifiers = self.elder_count(&neighbour);
+        self.output
+            .signing
+            .record(SignedMessage::Relocation, signers, verifiers, quorum);
+        let event = BusEvent::Relocation {
+            node,
+            from: Some(src.to_string()),
+            to: Some(neighbour.to_string()),
+        };
+        event_bus::emit(self.iteration, event);
+        self.section_churn(neighbour).relocations_in += 1;
+        self.send_relocated(neighbour, node);
+    }
+
+    /// Gives a node being relocated to the section `dst` its new name: a random one, or the one
+    /// it got in the recorded run if an event log is being replayed
+    fn relocate_node(&mut self, node: &mut Node, dst: &Prefix) {
+        let old = node.name();
+        match event_log::relocated_name(self.iteration, old) {
+            Some(name) => node.relocate_as(name),
+            None => node.relocate(dst),
+        }
+        event_log::log(self.iteration, LoggedEvent::Relocate { old, new: node.name() });
+        if let Some(adult) = self.identities.remove(&old) {
+            let _ = self.identities.insert(node.name(), adult);
+        }
+    }
+
+    /// Counts the node as a distinct member if it has just joined the section for the first
+    /// time, and as a distinct adult if it is one for the first time
+    fn record_identity(&mut self, prefix: Prefix, node: Node) {
+        if !self.nodes.get(&prefix).is_some_and(|section| section.has_node(node.name())) {
+            return;
+        }
+        let adult = match self.identities.get(&node.name()) {
+            Some(&adult) => adult,
+            None => {
+                self.output.joined_identities += 1;
+                false
+            }
+        };
+        if node.is_adult() && !adult {
+            self.output.adult_identities += 1;
+        }
+        let _ = self.identities.insert(node.name(), adult || node.is_adult());
+    }
+
+    /// Counts the members of the section that became adults because of an age rule
+    fn record_adult_identities(&mut self, prefix: Prefix) {
+        let nodes = self.nodes.get(&prefix).map(Section::nodes).unwrap_or_default();
+        for node in nodes {
+            self.record_identity(prefix, node);
+        }
+    }
+
+    /// Sends a relocated node to its new section, either immediately or after it spends
+    /// `relocation_delay` iterations in transit. If nodes hold data and the bandwidth is
+    /// limited, the node only sets off once its data has been transferred to the section,
+    /// after the data of the nodes relocated there before it.
+    fn send_relocated(&mut self, dst: Prefix, node: Node) {
+        let transfer = self.transfer_time();
+        self.output.data_transferred += self.params.node_data;
+        if self.params.relocation_delay == 0 && transfer == 0.0 {
+            if self.params.delays_messages() {
+                self.send_message(dst, Message::Join(node));
+                return;
+            }
+            self.finish_relocation(node, Some(dst));
+            self.event_queue
+                .entry(dst)
+                .or_insert_with(Vec::new)
+                .push(NetworkEvent::Live(node));
+            return;
+        }
+        let mut arrival = self.iteration;
+        if transfer > 0.0 {
+            let now = self.iteration as f64;
+            let scheduled = self.transfers.entry(dst).or_insert(now);
+            *scheduled = scheduled.max(now) + transfer;
+            arrival = scheduled.ceil() as u64;
+            *self
+                .output
+                .transfer_waits
+                .entry(arrival - self.iteration)
+                .or_insert(0) += 1;
+        }
+        arrival += self.params.relocation_delay;
+        self.in_transit.push((arrival, node));
+        self.output.max_in_transit = self.output.max_in_transit.max(self.in_transit.len());
+    }
+
+    /// Returns the number of iterations it takes to transfer the data of a single node, or 0 if
+    /// the transfers aren't limited
+    fn transfer_time(&self) -> f64 {
+        if self.params.transfer_bandwidth > 0.0 {
+            self.params.node_data / self.params.transfer_bandwidth
+        } else {
+            0.0
+        }
+    }
+
+    /// Delivers the relocated nodes that have arrived to the sections they belong to now, which
+    /// might have split or merged in the meantime. Every node still in transit can be lost, in
+    /// which case it leaves the network.
+    fn deliver_relocations(&mut self) {
+        if self.in_transit.is_empty() {
+            return;
+        }
+        let iteration = self.iteration;
+        self.transfers
+            .retain(|_, &mut scheduled| scheduled > iteration as f64);
+        let loss = from_percent(self.params.transit_loss);
+        let in_transit = mem::take(&mut self.in_transit);
+        for (arrival, mut node) in in_transit {
+            if loss > 0 && gen_bool_with_probability(loss) {
+                debug!(in node.name(); "Relocated {:?} lost in transit", node);
+                self.output.transit_losses += 1;
+                self.finish_relocation(node, None);
+                node.left(iteration);
+                self.left_nodes.push(node);
+            } else if arrival > iteration {
+                self.in_transit.push((arrival, node));
+            } else if let Ok(dst) = self.send_live(node) {
+                debug!(in dst; "Relocated {:?} arrives at {:?}", node, dst);
+            }
+        }
+    }
+
+    /// Returns whether the section with the given prefix is splitting, merging or about to
+    /// merge
+    fn is_reorganising(&self, prefix: &Prefix) -> bool {
+        self.nodes
+            .get(prefix)
+            .is_some_and(|section| section.is_splitting() || section.is_merging())
+            || self.pending_merges.keys().any(|merged| merged.is_ancestor(prefix))
+    }
+
+    /// Carries out the deferred relocations whose sections have finished splitting or merging.
+    /// A relocation goes from the section that has taken over the relocated node's name.
+    fn resume_relocations(&mut self) {
+        if self.deferred_relocations.is_empty() {
+            return;
+        }
+        for (since, node, hash, from) in mem::take(&mut self.deferred_relocations) {
+            let src = self.prefix_for_node(node).unwrap_or(from);
+            if self.is_reorganising(&src) {
+                self.deferred_relocations.push((since, node, hash, from));
+                continue;
+            }
+            *self.output
+                .deferral_delays
+                .entry(self.iteration - since)
+                .or_insert(0) += 1;
+            debug!(in src; "Resuming the relocation of {:?}", node);
+            self.relocate(node, hash, src);
+        }
+    }
+
+    /// Returns the number of relocations deferred until their sections have split or merged
+    pub fn num_deferred(&self) -> usize {
+        self.deferred_relocations.len()
+    }
+
+    /// Returns the number of relocated nodes in transit
+    pub fn num_in_transit(&self) -> usize {
+        self.in_transit.len()
+    }
+
+    /// Describes everything that is in progress: the sections that are splitting, merging,
+    /// paused or have events queued, the merges that haven't completed yet and the relocated
+    /// nodes still in transit. Meant for looking into runs that seem to be stuck.
+    pub fn diagnostics(&self) -> String {
+        let mut lines = vec![format!(
+            "Diagnostics after iteration {}: {} nodes in {} sections",
+            self.iteration,
+            self.num_nodes(),
+            self.nodes.len()
+        )];
+        for (prefix, section) in &self.nodes {
+            let mut states = vec![];
+            if section.is_splitting() {
+                states.push("splitting".to_owned());
+            }
+            if section.is_merging() {
+                states.push("merging".to_owned());
+            }
+            if let Some(resume) = self.paused.get(prefix) {
+                states.push(format!("paused until {}", resume));
+            }
+            let mut queued: BTreeMap<&'static str, usize> = BTreeMap::new();
+            for event in self.event_queue.get(prefix).into_iter().flatten() {
+                *queued.entry(event.kind()).or_insert(0) += 1;
+            }
+            if !queued.is_empty() {
+                let kinds: Vec<_> = queued
+                    .iter()
+                    .map(|(kind, count)| format!("{} {}", count, kind))
+                    .collect();
+                states.push(format!("queued: {}", kinds.join(", ")));
+            }
+            if !states.is_empty() {
+                lines.push(format!(
+                    "  section {:?} ({} nodes): {}",
+                    prefix,
+                    section.len(),
+                    states.join("; ")
+                ));
+            }
+        }
+        for (prefix, queue) in &self.event_queue {
+            if !queue.is_empty() && !self.nodes.contains_key(prefix) {
+                lines.push(format!(
+                    "  {} events queued for {:?}, which has no section",
+                    queue.len(),
+                    prefix
+                ));
+            }
+        }
+        for (prefix, merge) in &self.pending_merges {
+            let waiting: Vec<_> = merge
+                .complete
+                .iter()
+                .filter(|&(_, &complete)| !complete)
+                .map(|(pfx, _)| format!("{:?}", pfx))
+                .collect();
+            lines.push(format!(
+                "  merge into {:?} (depth {}) waiting for {}",
+                prefix,
+                merge.depth,
+                waiting.join(", ")
+            ));
+        }
+        for (prefix, proposal) in &self.merge_proposals {
+            let accepted: Vec<_> = proposal
+                .accepted
+                .iter()
+                .map(|pfx| format!("{:?}", pfx))
+                .collect();
+            lines.push(format!(
+                "  merge into {:?} proposed, round {}, accepted by {}",
+                prefix,
+                proposal.rounds,
+                accepted.join(", ")
+            ));
+        }
+        if let Some(&(arrival, _)) = self.in_transit.iter().max_by_key(|&&(arrival, _)| arrival) {
+            lines.push(format!(
+                "  {} relocated nodes in transit, the last arriving in iteration {}",
+                self.in_transit.len(),
+                arrival
+            ));
+        }
+        if let Some(&arrival) = self.in_flight.keys().next_back() {
+            lines.push(format!(
+                "  {} messages between sections in flight, the last arriving in iteration {}",
+                self.num_in_flight(),
+                arrival
+            ));
+        }
+        if !self.deferred_relocations.is_empty() {
+            lines.push(format!(
+                "  {} relocations deferred until their sections have split or merged",
+                self.num_deferred()
+            ));
+        }
+        if !self.undelivered.is_empty() {
+            lines.push(format!(
+                "  {} joining nodes matching no section, to be delivered again",
+                self.undelivered.len()
+            ));
+        }
+        lines.push(format!(
+            "  {} nodes out of the network that could rejoin",
+            self.left_nodes.len()
+        ));
+        if !self.departed.is_empty() {
+            lines.push(format!(
+                "  {} dropped nodes away, but coming back",
+                self.num_departed()
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Records a split or merge removing and creating sections with the given prefixes, updates
+    /// the prefix index and passes the pauses of the removed sections on to the created ones
+    fn structural_change(&mut self, removed: &[Prefix], created: &[Prefix]) {
+        let now = self.iteration;
+        let stretch = now - self.last_structural_change;
+        if stretch > self.output.longest_stable_stretch.0 {
+            self.output.longest_stable_stretch = (stretch, self.last_structural_change);
+        }
+        self.last_structural_change = now;
+        if created.len() > removed.len() {
+            self.output.splits += 1;
+        } else {
+            self.output.merges += 1;
+        }
+        for &pfx in removed {
+            self.index.remove(pfx);
+            let tombstone = Tombstone {
+                successors: created
+                    .iter()
+                    .filter(|created| created.is_compatible_with(&pfx))
+                    .cloned()
+                    .collect(),
+                expires: now + TOMBSTONE_ITERATIONS,
+            };
+            let _ = self.tombstones.insert(pfx, tombstone);
+        }
+        for &pfx in created {
+            self.index.insert(pfx);
+        }
+        for pfx in removed {
+            if let Some(since) = self.section_since.remove(pfx) {
+                let longest = self.output.section_stability.entry(*pfx).or_insert(0);
+                *longest = (*longest).max(now - since);
+            }
+        }
+        for pfx in created {
+            let _ = self.section_since.insert(*pfx, now);
+        }
+        let pause = removed
+            .iter()
+            .filter_map(|pfx| self.paused.remove(pfx))
+            .max();
+        if let Some(until) = pause {
+            for pfx in created {
+                let paused = self.paused.entry(*pfx).or_insert(until);
+                *paused = (*paused).max(until);
+            }
+        }
+    }
+
+    /// Returns the length and the first iteration of the longest stretch without splits or
+    /// merges, and the longest stretch every section prefix went without them, including the
+    /// stretches still going on
+    pub fn stable_stretches(&self) -> ((u64, u64), BTreeMap<Prefix, u64>) {
+        let current = (
+            self.iteration - self.last_structural_change,
+            self.last_structural_change,
+        );
+        let mut sections = self.output.section_stability.clone();
+        for (pfx, since) in &self.section_since {
+            let longest = sections.entry(*pfx).or_insert(0);
+            *longest = (*longest).max(self.iteration - since);
+        }
+        (current.max(self.output.longest_stable_stretch), sections)
+    }
+
+    /// Records a split creating sections with prefixes of the given length
+    fn record_split_depth(&mut self, depth: u8) {
+        let window = self.iteration / SPLIT_DEPTH_WINDOW * SPLIT_DEPTH_WINDOW;
+        let depths = self.output.split_depths.entry(window).or_default();
+        depths.splits += 1;
+        depths.total_depth += u64::from(depth);
+        depths.max_depth = depths.max_depth.max(depth);
+        let _ = self.output
+            .first_split_at_depth
+            .entry(depth)
+            .or_insert(self.iteration);
+    }
+
+    /// Returns the number of elders of the section with the given prefix
+    fn elder_count(&self, prefix: &Prefix) -> usize {
+        self.nodes.get(prefix).map_or(0, Section::elder_count)
+    }
+
+    /// Drops a random node from the network by sending a `Lost` event to the section.
+    /// The probability of a given node dropping is weighted based on its age.
+    pub fn drop_random_node(&mut self) {
+        self.output.drops += 1;
+        self.output.churn += 1;
+        let total_weight = self.total_drop_weight();
+        let node_and_prefix = {
+            let mut res = None;
+            let mut drop = match total_weight {
+                0 => u64::MAX,
+                _ => random_range(0, total_weight),
+            };
+            let nodes_iter = self.nodes
+                .iter()
+                .flat_map(|(p, s)| s.nodes().into_iter().map(move |n| (*p, n)));
+            for (p, n) in nodes_iter {
+                let weight = self.drop_weight(n);
+                if weight > drop {
+                    res = Some((p, n));
+                    break;
+                }
+                drop -= weight;
+            }
+            res
+        };
+        if let Some((prefix, node)) = node_and_prefix {
+            self.drop_node(prefix, node);
+        }
+    }
+
+    /// Drops the given percentage of the nodes whose names match the prefix, chosen at random.
+    /// Permanent nodes and observers never leave, so they are neither dropped nor counted.
+    pub fn drop_share(&mut self, percent: u8, prefix: Prefix) {
+        let mut nodes: Vec<_> = self.nodes
+            .iter()
+            .filter(|&(pfx, _)| pfx.is_compatible_with(&prefix))
+            .flat_map(|(pfx, section)| section.iter_nodes().map(move |node| (*pfx, node)))
+            .filter(|&(_, node)| {
+                prefix.matches(node.name())
+                    && !node.is_permanent()
+                    && !node.is_observer()
+                    && !self.deaths.contains_key(&node.name())
+            })
+            .collect();
+        let count = (nodes.len() * usize::from(percent) + 50) / 100;
+        debug!(in prefix; "Dropping {} of {} nodes in {:?}", count, nodes.len(), prefix);
+        shuffle(&mut nodes);
+        for (pfx, node) in nodes.into_iter().take(count) {
+            self.output.drops += 1;
+            self.output.churn += 1;
+            self.drop_node(pfx, node);
+        }
+    }
+
+    /// Drops the node from the section by sending a `Lost` event to it
+    fn drop_node(&mut self, prefix: Prefix, node: Node) {
+        *self.output.drops_dist.entry(node.age()).or_insert(0) += 1;
+        self.section_churn(prefix).drops += 1;
+        let name = node.name();
+        debug!(in prefix; "Dropping node {:?} from section {:?}", name, prefix);
+        event_log::log(self.iteration, LoggedEvent::Dead(name));
+        if self.params.dead_detection.is_some() {
+            let death = Death {
+                since: self.iteration,
+                noticed_by: BTreeSet::new(),
+            };
+            let _ = self.deaths.insert(name, death);
+            let unnoticed = self.deaths.len();
+            self.output.max_unnoticed_deaths = self.output.max_unnoticed_deaths.max(unnoticed);
+            return;
+        }
+        self.event_queue
+            .entry(prefix)
+            .or_insert_with(Vec::new)
+            .push(NetworkEvent::Lost(name));
+    }
+
+    /// Lets the live elders of the sections of the dead nodes notice the deaths, and sends a
+    /// `Lost` event for every node whose death a quorum of the live elders (or all of them, if
+    /// there are fewer) has noticed. A section without any live elders notices the death as soon
+    /// as the detection delay is over.
+    fn notice_deaths(&mut self) {
+        let (delay, chance) = match self.params.dead_detection {
+            Some(detection) if !self.deaths.is_empty() => detection,
+            _ => return,
+        };
+        let chance = from_percent(chance);
+        let dead: BTreeSet<_> = self.deaths.keys().cloned().collect();
+        let mut noticed = vec![];
+        let mut gone = vec![];
+        for (name, death) in &mut self.deaths {
+            let section = match self.index.longest_match(*name) {
+                Some(prefix) if self.nodes[&prefix].has_node(*name) => &self.nodes[&prefix],
+                _ => {
+                    gone.push(*name);
+                    continue;
+                }
+            };
+            let live_elders: Vec<_> = section
+                .elders()
+                .into_iter()
+                .filter(|elder| !dead.contains(&elder.name()))
+                .collect();
+            if self.iteration < death.since + delay {
+                continue;
+            }
+            for elder in &live_elders {
+                if !death.noticed_by.contains(&elder.name()) && gen_bool_with_probability(chance) {
+                    let _ = death.noticed_by.insert(elder.name());
+                }
+            }
+            let count = live_elders
+                .iter()
+                .filter(|elder| death.noticed_by.contains(&elder.name()))
+                .count();
+            // with too many dead elders for a quorum, the remaining ones have to agree on it
+            let quorum = section_params(&self.staged, &self.params, &section.prefix()).quorum;
+            if count >= quorum.min(live_elders.len()) {
+                noticed.push((section.prefix(), *name, death.since));
+            }
+        }
+        for name in gone {
+            let _ = self.deaths.remove(&name);
+        }
+        for (prefix, name, since) in noticed {
+            let _ = self.deaths.remove(&name);
+            self.lose_dead_node(prefix, name, since);
+        }
+    }
+
+    /// Sends the `Lost` event for a node whose death its section has noticed
+    fn lose_dead_node(&mut self, prefix: Prefix, name: Name, since: u64) {
+        debug!(in prefix; "Section {:?} noticed that {:?} died", prefix, name);
+        *self.output
+            .detection_delays
+            .entry(self.iteration - since)
+            .or_insert(0) += 1;
+        self.event_queue
+            .entry(prefix)
+            .or_insert_with(Vec::new)
+            .push(NetworkEvent::Lost(name));
+    }
+
+    /// Chooses a node from among the ones that left the network according to the rejoin
+    /// policy and gets it to rejoin. The age of the rejoining node is reduced, unless the
+    /// policy spares nodes with enough uptime.
+    pub fn rejoin_random_node(&mut self) {
+        self.output.rejoins += 1;
+        self.output.churn += 1;
+        shuffle(&mut self.left_nodes);
+        if self.params.rejoin_policy == RejoinPolicy::Priority {
+            // the most credited node goes last, so that it's popped first
+            self.left_nodes.sort_by_key(|node| node.uptime());
+        }
+        match self.left_nodes.pop() {
+            Some(node) => self.rejoin(node),
+            None => event_log::log(self.iteration, LoggedEvent::Rejoin(None)),
+        }
+    }
+
+    /// Brings back the dropped nodes due to come back in this iteration, with their age halved
+    fn return_departed(&mut self) {
+        if self.output.drain.is_some() {
+            return;
+        }
+        let iteration = self.iteration;
+        let returning = match self.departed.remove(&iteration) {
+            Some(nodes) => nodes,
+            None => return,
+        };
+        for node in returning {
+            self.output.churn += 1;
+            self.return_node(node);
+        }
+    }
+
+    /// Takes the node with the given name out of the nodes that left, whether or not it was due
+    /// to come back. The replay of an event log draws its own chances of dropped nodes coming
+    /// back, so a node the log brings back may be in either pool.
+    fn take_left_node(&mut self, name: Name) -> Option<Node> {
+        if let Some(i) = self.left_nodes.iter().position(|node| node.name() == name) {
+            return Some(self.left_nodes.remove(i));
+        }
+        let (back, i) = self.departed.iter().find_map(|(&back, nodes)| {
+            nodes
+                .iter()
+                .position(|node| node.name() == name)
+                .map(|i| (back, i))
+        })?;
+        let nodes = self.departed.get_mut(&back).unwrap();
+        let node = nodes.remove(i);
+        if nodes.is_empty() {
+            let _ = self.departed.remove(&back);
+        }
+        Some(node)
+    }
+
+    /// Gets a dropped node to come back with its age halved
+    fn return_node(&mut self, mut node: Node) {
+        debug!(in node.name(); "{:?} comes back", node);
+        event_log::log(self.iteration, LoggedEvent::Return(node.name()));
+        self.output.returns += 1;
+        node.returned(self.params.init_age);
+        node.joined(self.iteration);
+        self.send_join(node);
+    }
+
+    /// Returns the number of dropped nodes that are away, but will come back
+    pub fn num_departed(&self) -> usize {
+        self.departed.values().map(Vec::len).sum()
+    }
+
+    /// Gets a node that left the network to rejoin it
+    fn rejoin(&mut self, mut node: Node) {
+        debug!(in node.name(); "Rejoining node {:?}", node);
+        event_log::log(self.iteration, LoggedEvent::Rejoin(Some(node.name())));
+        if self.params.rejoin_policy != RejoinPolicy::Random {
+            *self.output.rejoin_uptimes.entry(node.uptime()).or_insert(0) += 1;
+        }
+        let lenient = self.params.rejoin_policy == RejoinPolicy::Lenient
+            && node.uptime() >= self.params.uptime_credit;
+        if lenient {
+            self.output.lenient_rejoins += 1;
+        } else {
+            node.rejoined(self.params.init_age);
+        }
+        node.joined(self.iteration);
+        self.send_join(node);
+    }
+
+    /// Returns a hash of the state of all the sections: their prefixes, members, elders and
+    /// chains. Two runs ending with the same hash ended in the same state.
+    pub fn state_hash(&self) -> Hash {
+        self.nodes
+            .values()
+            .map(|section| {
+                Hash::of(&serde_json::to_vec(section).expect("Couldn't serialise a section!"))
+            })
+            .fold(Hash::default(), |hash, section| hash.combine(&section))
+    }
+
+    /// Checks the invariants the network should satisfy between iterations: the sections cover
+    /// the simulated name space without overlapping, every node is in the section matching its
+    /// name and in no other one, and the elders of every section are its members. The sections
+    /// of a pending merge can already share the elders of the merged section, until it forms.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let root = self.params.restrict_prefix.unwrap_or_else(Prefix::empty);
+        let indexed = self.index.prefixes.len() == self.nodes.len()
+            && self.nodes.keys().all(|&prefix| self.index.contains(prefix));
+        if !indexed {
+            return Err("The prefix index doesn't match the sections".to_owned());
+        }
+        let mut covered = 0u128;
+        for (prefix, section) in &self.nodes {
+            if !root.is_ancestor(prefix) {
+                return Err(format!("Section {:?} is outside of {:?}", prefix, root));
+            }
+            if let Some(other) = self.index.compatible(*prefix).find(|other| other != prefix) {
+                return Err(format!("Sections {:?} and {:?} overlap", prefix, other));
+            }
+            if !section.elders_are_members() {
+                return Err(format!("Section {:?} has elders that aren't members", prefix));
+            }
+            covered += 1 << (64 - prefix.len());
+        }
+        if covered != 1 << (64 - root.len()) {
+            return Err(format!("The sections don't cover the whole of {:?}", root));
+        }
+        if self.overlapping_merges() > 0 {
+            return Err("Pending merges overlap".to_owned());
+        }
+        for (merged_pfx, pending_merge) in &self.pending_merges {
+            if let Some(pfx) = pending_merge
+                .complete
+                .keys()
+                .find(|pfx| !self.nodes.contains_key(pfx))
+            {
+                return Err(format!(
+                    "Section {:?} of the pending merge into {:?} doesn't exist",
+                    pfx, merged_pfx
+                ));
+            }
+        }
+        let mut names = BTreeMap::new();
+        for (prefix, section) in &self.nodes {
+            let scope = self.pending_merges
+                .iter()
+                .find(|&(_, pending_merge)| pending_merge.complete.contains_key(prefix))
+                .map_or(*prefix, |(merged_pfx, _)| *merged_pfx);
+            for node in section.nodes() {
+                if !scope.matches(node.name()) {
+                    return Err(format!("{:?} doesn't belong in section {:?}", node, prefix));
+                }
+                match names.insert(node.name(), scope) {
+                    Some(other) if other != scope || scope == *prefix => {
+                        return Err(format!("{:?} is a member of more than one section", node));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every section's elders are its oldest adults and, unless the elder rules let
+    /// it shrink, that a section with a quorum of adults has a quorum of elders
+    pub fn check_elders(&self) -> Result<(), String> {
+        for (prefix, section) in &self.nodes {
+            let params = section_params(&self.staged, &self.params, prefix);
+            let may_shrink =
+                params.elder_min_age.is_some() && params.elder_fallback == ElderFallback::Shrink;
+            section.check_caches()?;
+            if !section.elders_are_oldest(params) {
+                return Err(format!("The elders of section {:?} aren't its oldest adults", prefix));
+            }
+            let (adults0, adults1) = section.adults_by_half();
+            if !may_shrink
+                && adults0 + adults1 >= params.quorum
+                && section.elder_count() < params.quorum
+            {
+                return Err(format!(
+                    "Section {:?} has {} adults, but only {} elders",
+                    prefix,
+                    adults0 + adults1,
+                    section.elder_count()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the relocated nodes on their way to their new sections aren't members of any
+    /// section yet, and that every relocation still being logged is for such a node
+    pub fn check_relocations(&self) -> Result<(), String> {
+        let mut moving = BTreeSet::new();
+        let nodes = self.in_transit.iter().map(|&(_, node)| node);
+        let in_flight = self.in_flight
+            .values()
+            .flatten()
+            .filter_map(|&(_, ref message)| match *message {
+                Message::Join(node) => Some(node),
+                Message::Merge(..) => None,
+            });
+        for node in nodes.chain(in_flight).chain(self.undelivered.iter().cloned()) {
+            if !moving.insert(node.name()) {
+                return Err(format!("{:?} is being relocated more than once", node));
+            }
+            if let Some(prefix) = self.index.longest_match(node.name()) {
+                if self.nodes.get(&prefix).is_some_and(|section| section.has_node(node.name())) {
+                    return Err(format!(
+                        "{:?} is a member of section {:?} while being relocated",
+                        node, prefix
+                    ));
+                }
+            }
+        }
+        if let Some(name) = self.unfinished_relocations.keys().find(|name| !moving.contains(name)) {
+            return Err(format!(
+                "The relocation of {:?} hasn't finished, but the node isn't on its way",
+                name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts recording how the network shrinks, as no more nodes join it
+    pub fn start_drain(&mut self) {
+        debug!("Draining the network");
+        self.output.drain = Some(DrainStats {
+            start: self.iteration,
+            sections: self.num_sections(),
+            complete_sections: self.complete_sections(),
+            ..Default::default()
+        });
+    }
+
+    /// Records the milestones of the draining network reached in the last iteration
+    pub fn record_drain(&mut self) {
+        let (complete, sections) = (self.complete_sections(), self.num_sections());
+        let iteration = self.iteration - 1;
+        if let Some(ref mut drain) = self.output.drain {
+            if complete == 0 && drain.no_complete_sections.is_none() {
+                drain.no_complete_sections = Some(iteration);
+            }
+            if sections == 1 && drain.single_section.is_none() {
+                drain.single_section = Some(iteration);
+            }
+        }
+    }
+
+    /// Returns the number of pairs of pending merges into compatible prefixes, which should
+    /// never happen
+    pub fn overlapping_merges(&self) -> usize {
+        let merges: Vec<_> = self.pending_merges.keys().collect();
+        merges
+            .iter()
+            .enumerate()
+            .map(|(i, pfx)| {
+                merges[i + 1..]
+                    .iter()
+                    .filter(|other| other.is_compatible_with(pfx))
+                    .count()
+            })
+            .sum()
+    }
+
+    pub fn num_sections(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.values().map(|s| s.len()).sum()
+    }
+
+    /// Returns an iterator over the sections, in the order of their prefixes
+    pub fn sections<'a>(&'a self) -> impl Iterator<Item = &'a Section> + 'a {
+        self.nodes.values()
+    }
+
+    /// Returns the number of nodes in the network that aren't adults yet
+    pub fn num_infants(&self) -> usize {
+        self.nodes.values().map(Section::infant_count).sum()
+    }
+
+    /// Returns the number of elders in the network
+    pub fn num_elders(&self) -> usize {
+        self.nodes.values().map(Section::elder_count).sum()
+    }
+
+    pub fn min_prefix_len(&self) -> u8 {
+        self.nodes.keys().map(|pfx| pfx.len()).min().unwrap_or(0)
+    }
+
+    pub fn max_prefix_len(&self) -> u8 {
+        self.nodes.keys().map(|pfx| pfx.len()).max().unwrap_or(0)
+    }
+
+    /// Returns the length of the longest chain of any section
+    pub fn max_chain_len(&self) -> u64 {
+        self.nodes
+            .values()
+            .map(|s| s.chain().blocks())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the percentage of elder slots occupied by permanent nodes
+    pub fn permanent_elder_share(&self) -> f64 {
+        let elders: Vec<_> = self.nodes.values().flat_map(|s| s.elders()).collect();
+        let permanent = elders.iter().filter(|n| n.is_permanent()).count();
+        100.0 * permanent as f64 / elders.len().max(1) as f64
+    }
+
+    /// Returns the number of sections with fewer than a quorum of elders
+    pub fn sections_below_quorum(&self) -> usize {
+        self.nodes
+            .values()
+            .filter(|s| {
+                s.elders().len() < section_params(&self.staged, &self.params, &s.prefix()).quorum
+            })
+            .count()
+    }
+
+    pub fn age_distribution(&self) -> BTreeMap<u8, usize> {
+        let mut result = BTreeMap::new();
+        for (_, section) in &self.nodes {
+            for node in section.nodes() {
+                *result.entry(node.age()).or_insert(0) += 1;
+            }
+        }
+        result
+    }
+
+    /// Returns the number of sections of every size
+    pub fn size_distribution(&self) -> BTreeMap<u32, usize> {
+        let mut result = BTreeMap::new();
+        for section in self.nodes.values() {
+            *result.entry(section.len() as u32).or_insert(0) += 1;
+        }
+        result
+    }
+
+    /// Returns the number of sections with every prefix length
+    pub fn prefix_len_distribution(&self) -> BTreeMap<u8, usize> {
+        let mut result = BTreeMap::new();
+        for prefix in self.nodes.keys() {
+            *result.entry(prefix.len()).or_insert(0) += 1;
+        }
+        result
+    }
+
+    /// Returns the prefix, size and name uniformity (see `Section::name_uniformity`) of every
+    /// section
+    pub fn name_uniformity(&self) -> Vec<(Prefix, usize, f64)> {
+        self.nodes
+            .iter()
+            .map(|(prefix, section)| (*prefix, section.len(), section.name_uniformity()))
+            .collect()
     }
 
     pub fn complete_sections(&self) -> usize {
-        self.nodes.iter().filter(|&(_, s)| s.is_complete()).count()
+        self.nodes.values().filter(|s| s.is_complete(&self.params)).count()
     }
 
     pub fn output(&self) -> &Output {
         &self.output
     }
+
+    /// Returns the parameters of the simulation
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// Returns the parameters the section with the given prefix follows, which differ from the
+    /// network's if a scenario changed them for it
+    pub fn params_of(&self, prefix: &Prefix) -> &Params {
+        section_params(&self.staged, &self.params, prefix)
+    }
 }
 
 impl fmt::Debug for Network {
@@ -433,3 +3870,151 @@ impl fmt::Debug for Network {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use random::with_seed;
+
+    /// Grows a network with dead node detection until its first section has a full set of
+    /// elders, and returns it with the prefix of that section
+    fn network_with_full_section() -> (Network, Prefix) {
+        let params = Params {
+            dead_detection: Some((0, 100.0)),
+            ..Default::default()
+        };
+        let network = Network::builder()
+            .params(params)
+            .growth(90, 0)
+            .seed([1, 2, 3, 4])
+            .run(200)
+            .unwrap();
+        let prefix = network
+            .sections()
+            .find(|section| section.elder_count() == network.params.group_size)
+            .map(Section::prefix)
+            .expect("no section with a full set of elders");
+        (network, prefix)
+    }
+
+    /// Marks the given nodes as dead without their section noticing yet
+    fn kill(network: &mut Network, names: &[Name]) {
+        for &name in names {
+            let death = Death {
+                since: network.iteration,
+                noticed_by: BTreeSet::new(),
+            };
+            let _ = network.deaths.insert(name, death);
+        }
+    }
+
+    #[test]
+    fn remaining_elders_notice_deaths_without_a_quorum() {
+        let (mut network, prefix) = network_with_full_section();
+        let section = &network.nodes[&prefix];
+        let elders: Vec<_> = section.elders().iter().map(Node::name).collect();
+        let member = section
+            .nodes()
+            .iter()
+            .map(Node::name)
+            .find(|name| !elders.contains(name))
+            .expect("no member other than the elders");
+        // one more dead elder than a quorum can spare
+        let mut dead = elders[..network.params.group_size - network.params.quorum + 1].to_vec();
+        dead.push(member);
+        kill(&mut network, &dead);
+        with_seed([5, 6, 7, 8], || network.notice_deaths());
+        assert!(network.deaths.is_empty());
+        let mut lost: Vec<_> = network.event_queue[&prefix]
+            .iter()
+            .filter_map(|event| match *event {
+                NetworkEvent::Lost(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        lost.sort();
+        dead.sort();
+        assert_eq!(lost, dead);
+    }
+
+    #[test]
+    fn section_without_live_elders_notices_deaths_after_the_delay() {
+        let (mut network, prefix) = network_with_full_section();
+        network.params.dead_detection = Some((2, 100.0));
+        let elders: Vec<_> = network.nodes[&prefix]
+            .elders()
+            .iter()
+            .map(Node::name)
+            .collect();
+        kill(&mut network, &elders);
+        with_seed([5, 6, 7, 8], || network.notice_deaths());
+        assert_eq!(network.deaths.len(), elders.len());
+        network.iteration += 2;
+        with_seed([5, 6, 7, 8], || network.notice_deaths());
+        assert!(network.deaths.is_empty());
+    }
+
+    #[test]
+    fn merge_events_arrive_after_the_delay() {
+        let mut network = Network::builder()
+            .growth(90, 0)
+            .seed([1, 2, 3, 4])
+            .run(2000)
+            .unwrap();
+        network.params.msg_delay_mean = 1000.0;
+        let prefix = *network
+            .nodes
+            .keys()
+            .find(|pfx| network.nodes.keys().any(|other| other.is_sibling(pfx)))
+            .expect("no pair of sibling sections");
+        let merged_pfx = prefix.shorten();
+        with_seed([5, 6, 7, 8], || network.merge(prefix));
+        let starts_merge = |network: &Network, pfx: &Prefix| {
+            match network.event_queue.get(pfx).and_then(|events| events.first()) {
+                Some(&NetworkEvent::StartMerge(pfx)) => pfx == merged_pfx,
+                _ => false,
+            }
+        };
+        assert!(network.pending_merges.contains_key(&merged_pfx));
+        assert!(!starts_merge(&network, &prefix));
+        let arrival = network
+            .in_flight
+            .iter()
+            .find(|&(_, messages)| {
+                messages.iter().any(|&(dst, ref message)| match *message {
+                    Message::Merge(pfx, _) => dst == prefix && pfx == merged_pfx,
+                    Message::Join(_) => false,
+                })
+            })
+            .map(|(&arrival, _)| arrival)
+            .expect("the events of the merge aren't on their way");
+        assert!(arrival > network.iteration);
+        network.iteration = arrival - 1;
+        network.deliver_messages();
+        assert!(!starts_merge(&network, &prefix));
+        network.iteration = arrival;
+        network.deliver_messages();
+        assert!(starts_merge(&network, &prefix));
+    }
+
+    #[test]
+    fn prefix_index_finds_the_neighbours() {
+        let prefixes: Vec<_> = ["00", "010", "0110", "0111", "10", "110", "1110", "1111"]
+            .iter()
+            .map(|bits| Prefix::from_str(bits).unwrap())
+            .collect();
+        let mut index = PrefixIndex::default();
+        for &prefix in &prefixes {
+            index.insert(prefix);
+        }
+        for &prefix in &prefixes {
+            let mut expected: Vec<_> = prefixes
+                .iter()
+                .cloned()
+                .filter(|pfx| pfx.is_neighbour(&prefix))
+                .collect();
+            expected.sort();
+            assert_eq!(index.neighbours(prefix), expected, "neighbours of {:?}", prefix);
+        }
+    }
+}
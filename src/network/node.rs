@@ -1,17 +1,74 @@
 use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json;
-use random::random;
-use tiny_keccak::sha3_256;
+use random::{from_percent, pow, random, ONE};
+use network::hash::Hash;
 use network::prefix::{Name, Prefix};
-use params::DropDist;
+use params::{AgeRule, DropDist};
+#[cfg(feature = "crypto")]
+use ed25519_dalek::SecretKey;
 
-pub type Digest = [u8; 32];
-
-/// A node has a name and an age
-#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
+/// A node has a name and an age. Only these are part of the node's representation in
+/// human-readable formats, which is hashed in ageing; binary formats, used for snapshots, keep
+/// everything.
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
 pub struct Node {
     name: Name,
     age: u8,
+    /// the attacker coalition the node belongs to, numbered from 1; 0 for honest nodes. Not
+    /// part of the hashed representation, so that it doesn't influence ageing
+    coalition: u8,
+    /// the behaviour the node follows, numbered from 1; 0 for nodes only subject to the random
+    /// churn
+    behaviour: u8,
+    /// permanent nodes never drop out of the network
+    permanent: bool,
+    /// whether the node has ever been an elder
+    promoted: bool,
+    /// observers never drop or get relocated, and record what happens in their section
+    observer: bool,
+    /// the number of iterations the node spent in the network before it last joined
+    uptime: u64,
+    /// the iteration in which the node last joined or rejoined the network
+    joined_at: u64,
+}
+
+/// The representation of a node in human-readable formats
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Node")]
+struct HashedNode {
+    name: Name,
+    age: u8,
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            HashedNode {
+                name: self.name,
+                age: self.age,
+            }.serialize(serializer)
+        } else {
+            (self.name, self.age, self.flags(), self.uptime, self.joined_at).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Node, D::Error> {
+        if deserializer.is_human_readable() {
+            let HashedNode { name, age } = HashedNode::deserialize(deserializer)?;
+            Ok(Node::new(name.0, age))
+        } else {
+            let (name, age, flags, uptime, joined_at): (Name, u8, u16, u64, u64) =
+                Deserialize::deserialize(deserializer)?;
+            Ok(Node {
+                uptime,
+                joined_at,
+                ..Node::new(name.0, age).with_flags(flags)
+            })
+        }
+    }
 }
 
 impl fmt::Debug for Node {
@@ -26,12 +83,53 @@ impl Node {
         Node {
             name: Name(name),
             age,
+            coalition: 0,
+            behaviour: 0,
+            permanent: false,
+            promoted: false,
+            observer: false,
+            uptime: 0,
+            joined_at: 0,
+        }
+    }
+
+    /// Creates a new malicious node belonging to the given attacker coalition
+    pub fn new_malicious(name: u64, age: u8, coalition: u8) -> Node {
+        Node {
+            coalition,
+            ..Node::new(name, age)
+        }
+    }
+
+    /// Returns the node flagged as permanent
+    pub fn into_permanent(self) -> Node {
+        Node {
+            permanent: true,
+            ..self
+        }
+    }
+
+    /// Returns the node following the given behaviour
+    pub fn with_behaviour(self, behaviour: u8) -> Node {
+        Node { behaviour, ..self }
+    }
+
+    /// Returns the node flagged as an observer
+    pub fn into_observer(self) -> Node {
+        Node {
+            observer: true,
+            ..self
         }
     }
 
     /// Generates a relocated name and increases the age by 1
     pub fn relocate(&mut self, prefix: &Prefix) {
-        self.name = prefix.substituted_in(Name(random()));
+        self.relocate_as(prefix.substituted_in(Name(random())));
+    }
+
+    /// Relocates the node, giving it the given name
+    pub fn relocate_as(&mut self, name: Name) {
+        self.name = name;
         self.age += 1;
     }
 
@@ -42,6 +140,28 @@ impl Node {
         }
     }
 
+    /// Halves the age of a node coming back after an absence, down to at least `min_age`
+    pub fn returned(&mut self, min_age: u8) {
+        self.age = (self.age / 2).max(min_age);
+    }
+
+    /// Records that the node joins the network in the given iteration
+    pub fn joined(&mut self, iteration: u64) {
+        self.joined_at = iteration;
+    }
+
+    /// Records that the node leaves the network in the given iteration, crediting it with the
+    /// time it has spent in it
+    pub fn left(&mut self, iteration: u64) {
+        self.uptime += iteration.saturating_sub(self.joined_at);
+    }
+
+    /// Returns the number of iterations the node had spent in the network by the time it last
+    /// left
+    pub fn uptime(&self) -> u64 {
+        self.uptime
+    }
+
     /// Returns the name
     pub fn name(&self) -> Name {
         self.name
@@ -57,22 +177,108 @@ impl Node {
         self.age = self.age + 1
     }
 
+    /// Changes the age as the rule for splits or merges requires
+    pub fn apply_age_rule(&mut self, rule: AgeRule) {
+        match rule {
+            AgeRule::Keep => (),
+            AgeRule::Increment => self.increment_age(),
+        }
+    }
+
+    /// Returns whether the node is malicious
+    pub fn is_malicious(&self) -> bool {
+        self.coalition != 0
+    }
+
+    /// Returns the attacker coalition of the node, or 0 if it is honest
+    pub fn coalition(&self) -> u8 {
+        self.coalition
+    }
+
+    /// Returns the behaviour the node follows, or 0 if it is only subject to the random churn
+    pub fn behaviour(&self) -> u8 {
+        self.behaviour
+    }
+
+    /// Returns whether the node is permanent
+    pub fn is_permanent(&self) -> bool {
+        self.permanent
+    }
+
+    /// Returns whether the node is an observer
+    pub fn is_observer(&self) -> bool {
+        self.observer
+    }
+
+    /// Returns whether the node has ever been an elder
+    pub fn is_promoted(&self) -> bool {
+        self.promoted
+    }
+
+    /// Marks the node as having been an elder
+    pub fn promote(&mut self) {
+        self.promoted = true;
+    }
+
+    /// Returns the flags that aren't part of the serialised node, packed into a number: the
+    /// coalition in the low byte, the boolean flags above it and the behaviour in the top bits
+    pub fn flags(&self) -> u16 {
+        u16::from(self.coalition) | u16::from(self.permanent) << 8 | u16::from(self.promoted) << 9
+            | u16::from(self.observer) << 10 | u16::from(self.behaviour) << 11
+    }
+
+    /// Returns the node with the flags restored from a number returned by `flags()`
+    pub fn with_flags(self, flags: u16) -> Node {
+        Node {
+            coalition: flags as u8,
+            permanent: flags & 1 << 8 != 0,
+            promoted: flags & 1 << 9 != 0,
+            observer: flags & 1 << 10 != 0,
+            behaviour: (flags >> 11) as u8,
+            ..self
+        }
+    }
+
     /// Returns whether the node is an Adult
     pub fn is_adult(&self) -> bool {
         self.age > 4
     }
 
-    /// Returns the weight used in randomly choosing a node to be dropped
-    pub fn drop_probability(&self, dist: DropDist) -> f64 {
-        match dist {
-            DropDist::RevProp => 10.0 / self.age as f64,
-            DropDist::Exponential => 2.0f64.powf(-(self.age as f64)),
+    /// Returns the weight used in randomly choosing a node to be dropped, in fixed point.
+    /// Exponential weights stop halving at age 32, where they reach the smallest unit, and
+    /// weights with other bases don't go below it either.
+    pub fn drop_weight(&self, dist: &DropDist) -> u64 {
+        if self.permanent || self.observer {
+            return 0;
         }
+        match *dist {
+            DropDist::RevProp => 10 * ONE / u64::from(self.age.max(1)),
+            DropDist::Exponential => ONE >> self.age.min(32),
+            DropDist::ExponentialBase(factor) => pow(factor, u32::from(self.age)).max(1),
+            DropDist::Uniform => ONE,
+            DropDist::Table(ref table) => {
+                let percent = table
+                    .iter()
+                    .rev()
+                    .find(|&&(age, _)| age <= self.age)
+                    .unwrap_or(&table[0])
+                    .1;
+                from_percent(percent)
+            }
+        }
+    }
+
+    /// Returns the node's secret signing key. The key is derived from the name, so a relocated
+    /// node gets a new identity.
+    #[cfg(feature = "crypto")]
+    pub fn secret_key(&self) -> SecretKey {
+        let seed = Hash::of(&serde_json::to_vec(&self.name).unwrap());
+        SecretKey::from_bytes(seed.as_bytes()).unwrap()
     }
 
     /// Returns the hash of the node struct
     #[allow(unused)]
-    pub fn hash(&self) -> Digest {
-        sha3_256(serde_json::to_string(self).unwrap().as_bytes())
+    pub fn hash(&self) -> Hash {
+        Hash::of(serde_json::to_string(self).unwrap().as_bytes())
     }
 }
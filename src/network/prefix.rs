@@ -1,4 +1,5 @@
 use std::fmt;
+use pseudonyms;
 
 /// A helper struct that only has the purpose of pretty-printing debug information
 #[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
@@ -6,6 +7,9 @@ pub struct Name(pub u64);
 
 impl fmt::Debug for Name {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(number) = pseudonyms::of(*self) {
+            return write!(fmt, "N{}", number);
+        }
         let (b0, b1, b2) = (
             (self.0 >> 56) as u8,
             (self.0 >> 48) as u8,
@@ -15,9 +19,19 @@ impl fmt::Debug for Name {
     }
 }
 
+/// The name in full, as written to the exports: 16 hexadecimal digits, or its pseudonym
+impl fmt::Display for Name {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match pseudonyms::of(*self) {
+            Some(number) => write!(fmt, "N{}", number),
+            None => write!(fmt, "{:016x}", self.0),
+        }
+    }
+}
+
 /// A structure representing a network prefix - a simplified version of the Prefix struct from
 /// `routing`
-#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Prefix {
     len: u8,
     bits: u64,
@@ -43,6 +57,10 @@ impl Prefix {
         self.len
     }
 
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
     fn len_mask(&self) -> u64 {
         if self.len == 0 {
             0
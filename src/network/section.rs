@@ -1,10 +1,13 @@
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
-use network::{BUFFER, GROUP_SIZE};
+use std::mem;
 use network::prefix::{Name, Prefix};
-use network::node::{Digest, Node};
+use network::node::Node;
+use network::chain::Chain;
 use network::churn::{NetworkEvent, SectionEvent};
-use params::Params;
+use params::{ElderFallback, KeyRotation, Params};
+use random::random_range;
 
 /// An enum for return values of some methods.
 /// The methods can say that the event was ignored, in which case its processing ends as if nothing
@@ -17,19 +20,50 @@ enum EventResult {
     Ignored,
 }
 
-/// Returns the number of trailing zeros in a hash
-fn trailing_zeros(hash: Digest) -> u8 {
-    let mut result = 0;
-    let mut byte_index = 31;
-    loop {
-        let zeros = hash[byte_index].trailing_zeros();
-        result += zeros;
-        if zeros < 8 || byte_index == 0 {
-            break;
+/// The numbers of adults and of all nodes in both halves of a section's name space, i.e. among
+/// the names that would go to each of the sections resulting from a split. The split decision
+/// needs them for every event, so they are kept up to date as nodes come and go instead of
+/// being recounted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct HalfCounts {
+    adults: [usize; 2],
+    nodes: [usize; 2],
+}
+
+/// The relocation checks sections made, by the ages of the nodes involved. Tracked when
+/// relocation fairness is reported.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RelocationTally {
+    /// for every age, the number of nodes of that age that could have been relocated, summed
+    /// over all the checks
+    pub exposure: BTreeMap<u8, u64>,
+    /// for every age, the number of checks that picked a node of that age
+    pub picks: BTreeMap<u8, u64>,
+    /// for every age, the number of nodes of that age the tie-breaking rule chose from, summed
+    /// over the checks that picked one of them
+    pub tied: BTreeMap<u8, u64>,
+}
+
+impl RelocationTally {
+    /// Adds the checks of another tally to this one
+    pub fn add(&mut self, other: &RelocationTally) {
+        for (total, part) in &mut [
+            (&mut self.exposure, &other.exposure),
+            (&mut self.picks, &other.picks),
+            (&mut self.tied, &other.tied),
+        ] {
+            for (age, count) in part.iter() {
+                *total.entry(*age).or_insert(0) += count;
+            }
         }
-        byte_index -= 1;
     }
-    result as u8
+}
+
+/// The parts of a section's state that aren't serialised with it, saved separately in snapshots
+#[derive(Serialize, Deserialize)]
+pub struct LocalState {
+    key: Option<(Prefix, BTreeSet<Name>)>,
+    elder_changes: u64,
 }
 
 /// A section after a split together with events it needs to process afterwards.
@@ -38,7 +72,7 @@ pub type SplitData = (Section, Vec<NetworkEvent>);
 /// The structure representing a section.
 /// It has a prefix and some nodes. The nodes are sorted into categories: Elders, Adults and
 /// Infants, according to their age an function in the section.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Section {
     /// the section's prefix
     prefix: Prefix,
@@ -58,6 +92,30 @@ pub struct Section {
     merging: bool,
     /// are we currently splitting?
     splitting: bool,
+    /// has a split been refused because the prefix can't get any longer?
+    split_refused: bool,
+    /// the numbers of nodes in the halves of the name space
+    counts: HalfCounts,
+    /// the names of the nodes ordered from the oldest to the youngest, and by name among nodes
+    /// of the same age; not serialised, `restore_flags` rebuilds it
+    #[serde(skip)]
+    by_age: BTreeSet<(Reverse<u8>, Name)>,
+    /// the section's data chain
+    chain: Chain,
+    /// the ages of nodes promoted to elders for the first time and the section sizes at the
+    /// time, not reported to the network yet
+    promotions: Vec<(u8, usize)>,
+    /// the prefix and the elders the current section key was created for, if key rotations
+    /// are tracked
+    #[serde(skip)]
+    key: Option<(Prefix, BTreeSet<Name>)>,
+    /// the number of times the set of elders changed
+    #[serde(skip)]
+    elder_changes: u64,
+    /// the relocation checks made since the network last took the tally, if relocation
+    /// fairness is reported
+    #[serde(skip)]
+    relocation_tally: RelocationTally,
 }
 
 impl Section {
@@ -72,6 +130,14 @@ impl Section {
             infants: BTreeSet::new(),
             merging: false,
             splitting: false,
+            split_refused: false,
+            counts: Default::default(),
+            by_age: BTreeSet::new(),
+            chain: Default::default(),
+            promotions: vec![],
+            key: None,
+            elder_changes: 0,
+            relocation_tally: Default::default(),
         }
     }
 
@@ -80,32 +146,81 @@ impl Section {
         self.nodes.len()
     }
 
-    /// Returns the list of nodes in the section sorted by age.
-    fn nodes_by_age(&self) -> Vec<Node> {
-        let mut by_age: Vec<_> = self.nodes.iter().map(|(_, n)| *n).collect();
-        by_age.sort_by_key(|x| -(x.age() as i8));
-        by_age
+    /// Returns an iterator over the nodes in the section, in the order of their names
+    pub fn iter_nodes<'a>(&'a self) -> impl Iterator<Item = Node> + 'a {
+        self.nodes.values().cloned()
+    }
+
+    /// Returns an iterator over the nodes in the section, from the oldest to the youngest
+    fn nodes_by_age<'a>(&'a self) -> impl Iterator<Item = Node> + 'a {
+        self.by_age.iter().map(move |&(_, name)| self.nodes[&name])
+    }
+
+    /// Builds the age index from scratch
+    fn index_by_age(&self) -> BTreeSet<(Reverse<u8>, Name)> {
+        self.nodes
+            .values()
+            .map(|node| (Reverse(node.age()), node.name()))
+            .collect()
+    }
+
+    /// Returns whether the section has a node of the given age that isn't an observer
+    fn has_member_aged(&self, age: u8) -> bool {
+        self.by_age
+            .range((Reverse(age), Name(0))..=(Reverse(age), Name(u64::MAX)))
+            .any(|&(_, name)| !self.nodes[&name].is_observer())
     }
 
     /// Returns whether the section has a complete group.
-    /// A complete group is GROUP_SIZE nodes that are Adults (have age > 4)
-    pub fn is_complete(&self) -> bool {
-        self.elders.len() == 8
+    /// A complete group is `group_size` nodes that are Adults (have age > 4)
+    pub fn is_complete(&self, params: &Params) -> bool {
+        self.elders.len() == params.group_size
             && self.elders
                 .iter()
                 .filter_map(|x| self.nodes.get(x))
                 .all(|n| n.is_adult())
     }
 
-    /// Updates the names of the Elders in the section
-    fn update_elders(&mut self) {
-        let by_age = self.nodes_by_age();
-        self.elders = by_age
-            .into_iter()
-            .take(GROUP_SIZE)
+    /// Returns the names of the nodes that should be the Elders of the section
+    fn oldest_elders(&self, params: &Params) -> BTreeSet<Name> {
+        let oldest: Vec<_> = self.nodes_by_age()
+            .take(params.group_size)
             .filter(|n| n.is_adult())
-            .map(|n| n.name())
             .collect();
+        let count = match params.elder_min_age {
+            None => oldest.len(),
+            Some(min_age) => {
+                // the nodes are ordered by age, so the ones old enough come first
+                let eligible = oldest.iter().take_while(|n| n.age() > min_age).count();
+                match params.elder_fallback {
+                    ElderFallback::Shrink => eligible,
+                    ElderFallback::Quorum => eligible.max(params.quorum.min(oldest.len())),
+                }
+            }
+        };
+        oldest.iter().take(count).map(|n| n.name()).collect()
+    }
+
+    /// Updates the names of the Elders in the section
+    fn update_elders(&mut self, params: &Params) {
+        let elders = self.oldest_elders(params);
+        if elders != self.elders {
+            self.elder_changes += 1;
+            self.elders = elders;
+        }
+        let size = self.nodes.len();
+        for name in &self.elders {
+            let node = self.nodes.get_mut(name).unwrap();
+            if !node.is_promoted() {
+                node.promote();
+                self.promotions.push((node.age(), size));
+            }
+        }
+    }
+
+    /// Updates the elders after the parameters they are chosen by changed
+    pub fn reconsider_elders(&mut self, params: &Params) {
+        self.update_elders(params);
     }
 
     /// Processes a network event passed to the section and responds with appropriate section
@@ -114,10 +229,12 @@ impl Section {
         let mut events = vec![];
         let other_event = match event {
             NetworkEvent::Live(node) => self.add(node, params),
-            NetworkEvent::Relocated(node) | NetworkEvent::Gone(node) => self.relocate(node.name()),
-            NetworkEvent::Lost(name) => self.remove(name),
+            NetworkEvent::Relocated(node) | NetworkEvent::Gone(node) => {
+                self.relocate(node.name(), params)
+            }
+            NetworkEvent::Lost(name) => self.remove(name, params),
             NetworkEvent::PrefixChange(p) => {
-                println!("{:?} PrefixChange to {:?}", self.prefix, p);
+                debug!(in self.prefix; "{:?} PrefixChange to {:?}", self.prefix, p);
                 EventResult::Handled
             }
             NetworkEvent::StartMerge(prefix) => {
@@ -125,7 +242,7 @@ impl Section {
                     // in order to accept new nodes, we must know that we are merging
                     self.verifying_prefix = prefix;
                     self.merging = true;
-                    println!(
+                    debug!(in self.prefix;
                         "MERGE: {:?} (verifying: {:?}) StartMerge({:?})",
                         self.prefix, self.verifying_prefix, prefix
                     );
@@ -135,54 +252,149 @@ impl Section {
                 }
             }
         };
-        if self.should_merge(params) {
-            self.merging = true;
+        if self.request_merge(params) {
             events.push(SectionEvent::RequestMerge);
         }
-        if self.should_split(params) {
+        if !self.wants_split(params) {
+            self.split_refused = false;
+        } else if self.prefix.len() < params.max_prefix_len {
             self.splitting = true;
-            println!("{:?} Requesting a split", self.prefix);
+            debug!(in self.prefix; "{:?} Requesting a split", self.prefix);
             events.push(SectionEvent::RequestSplit);
+        } else if !self.split_refused {
+            self.split_refused = true;
+            debug!(in self.prefix; "{:?} Can't split, the prefix is at its maximum length", self.prefix);
+            events.push(SectionEvent::SplitRefused);
         }
         match other_event {
             EventResult::Handled => {
-                events.extend(self.check_ageing(event));
+                events.extend(self.append_block(event, params));
+                events.extend(self.check_ageing(event, params));
             }
             EventResult::HandledWithEvent(ev) => {
-                events.extend(self.check_ageing(event));
+                events.extend(self.append_block(event, params));
+                events.extend(self.check_ageing(event, params));
                 events.push(ev);
             }
             EventResult::Ignored => (),
         }
+        events.extend(
+            self.promotions
+                .drain(..)
+                .map(|(age, size)| SectionEvent::ElderPromoted(age, size)),
+        );
+        if let Some(rule) = params.key_rotation {
+            if self.rotate_key(rule, params.quorum) {
+                events.push(SectionEvent::KeyRotated);
+            }
+        }
         events
     }
 
-    /// Return the node that should be relocated, with age no greater than `age`
-    fn choose_for_relocation(&self, age: u8) -> Option<Node> {
-        let by_age: Vec<_> = self.nodes_by_age()
+    /// Creates a new section key if the elders changed enough since the current one was
+    /// created, as the rule requires, and returns whether it did. A section with a new prefix
+    /// gets a key for it without it counting as a rotation, as splits and merges are counted
+    /// separately. With the quorum rule, the key is kept as long as `quorum` of its elders
+    /// remain.
+    fn rotate_key(&mut self, rule: KeyRotation, quorum: usize) -> bool {
+        let rotate = match self.key {
+            Some((prefix, ref elders)) if prefix == self.prefix => match rule {
+                KeyRotation::Any => *elders != self.elders,
+                KeyRotation::Quorum => {
+                    elders.intersection(&self.elders).count() < quorum.min(self.elders.len())
+                }
+            },
+            _ => {
+                self.key = Some((self.prefix, self.elders.clone()));
+                return false;
+            }
+        };
+        if rotate {
+            self.key = Some((self.prefix, self.elders.clone()));
+        }
+        rotate
+    }
+
+    /// Records a handled event in the section's chain. If any of the elders belongs to an
+    /// equivocating coalition, it may also sign a conflicting block and show it to some of the
+    /// section's nodes.
+    fn append_block(&mut self, event: NetworkEvent, params: &Params) -> Vec<SectionEvent> {
+        let elders = self.elders();
+        self.chain.append(event.hash(), &elders);
+        let mut events: Vec<_> = self.chain
+            .gossip(self.len())
             .into_iter()
-            .filter(|n| n.age() <= age)
+            .map(SectionEvent::EquivocationDetected)
             .collect();
-        let candidates = by_age.first().cloned().map(|n| {
-            by_age
-                .into_iter()
-                .filter(|m| m.age() == n.age())
-                .collect::<Vec<_>>()
-        });
-        candidates.and_then(|mut cand| {
-            if cand.len() <= 1 {
-                cand.first().cloned()
-            } else {
-                let total_xor = cand.iter().fold(0, |total, node| total ^ node.name().0);
-                cand.sort_by_key(|node| node.name().0 ^ total_xor);
-                cand.first().cloned()
+        if params.equivocation == 0 {
+            return events;
+        }
+        // the coalition with the most elders is the one to equivocate
+        let mut counts = BTreeMap::new();
+        for elder in elders.iter().filter(|n| params.equivocates(n.coalition())) {
+            *counts.entry(elder.coalition()).or_insert(0) += 1;
+        }
+        let coalition = counts
+            .into_iter()
+            .max_by_key(|&(coalition, count)| (count, Reverse(coalition)))
+            .map(|(coalition, _)| coalition);
+        if let Some(coalition) = coalition {
+            if random_range(0, 100) < params.equivocation {
+                let informed = random_range(1, self.len().max(2));
+                debug!(in self.prefix;
+                    "{:?} Malicious elder equivocating to {} nodes",
+                    self.prefix, informed
+                );
+                self.chain.fork(informed);
+                events.push(SectionEvent::Equivocated(coalition));
             }
-        })
+        }
+        events
+    }
+
+    /// Return the node that should be relocated, with age no greater than `age`
+    fn choose_for_relocation(&self, age: u8) -> Option<Node> {
+        // the index is ordered from the oldest, so the range starts at the oldest allowed age
+        let mut by_age = self.by_age
+            .range((Reverse(age), Name(0))..)
+            .map(|&(_, name)| self.nodes[&name])
+            .filter(|n| !n.is_observer())
+            .peekable();
+        let oldest = by_age.peek()?.age();
+        let mut cand: Vec<_> = by_age.take_while(|n| n.age() == oldest).collect();
+        if cand.len() <= 1 {
+            cand.first().cloned()
+        } else {
+            let total_xor = cand.iter().fold(0, |total, node| total ^ node.name().0);
+            cand.sort_by_key(|node| node.name().0 ^ total_xor);
+            cand.first().cloned()
+        }
+    }
+
+    /// Records which nodes a relocation check could have picked, and the one it did, if any
+    fn tally_relocation_check(&mut self, picked: Option<Node>) {
+        let tally = &mut self.relocation_tally;
+        for node in self.nodes.values().filter(|n| !n.is_observer()) {
+            *tally.exposure.entry(node.age()).or_insert(0) += 1;
+        }
+        if let Some(node) = picked {
+            let tied = self.nodes
+                .values()
+                .filter(|n| n.age() == node.age() && !n.is_observer())
+                .count();
+            *tally.picks.entry(node.age()).or_insert(0) += 1;
+            *tally.tied.entry(node.age()).or_insert(0) += tied as u64;
+        }
+    }
+
+    /// Returns the relocation checks made since the last call
+    pub fn take_relocation_tally(&mut self) -> RelocationTally {
+        mem::take(&mut self.relocation_tally)
     }
 
     /// Checks the hash of the NetworkEvent and returns any SectionEvents triggered by it due to
     /// node ageing - in particular, relocations
-    fn check_ageing(&mut self, event: NetworkEvent) -> Vec<SectionEvent> {
+    fn check_ageing(&mut self, event: NetworkEvent, params: &Params) -> Vec<SectionEvent> {
         if let Some(node) = event.get_node() {
             if !node.is_adult() && self.prefix.len() > 4 {
                 return vec![];
@@ -191,12 +403,25 @@ impl Section {
         if !event.should_count() {
             return vec![];
         }
-        let event_hash = event.hash();
-        let trailing_zeros = trailing_zeros(event_hash);
-        let node_to_age = self.choose_for_relocation(trailing_zeros);
+        // the elders decide based on the block they signed rather than the event alone
+        let hash = if params.elder_relocation {
+            self.chain.head()
+        } else {
+            event.hash()
+        };
+        let node_to_age = self.choose_for_relocation(hash.trailing_zeros());
+        if params.relocation_fairness {
+            self.tally_relocation_check(node_to_age);
+        }
         if let Some(node) = node_to_age {
-            let _ = self.relocate(node.name());
-            vec![SectionEvent::NeedRelocate(node)]
+            let was_elder = self.elders.contains(&node.name());
+            let _ = self.relocate(node.name(), params);
+            let mut events = vec![SectionEvent::NeedRelocate(node, hash)];
+            if was_elder {
+                // the section has to re-elect and hand over the elder's state
+                events.push(SectionEvent::ElderRelocated(node));
+            }
+            events
         } else {
             vec![]
         }
@@ -204,13 +429,13 @@ impl Section {
 
     /// Adds a node to the section and returns whether the event was handled
     fn add(&mut self, node: Node, params: &Params) -> EventResult {
-        if !params.norejectyoung && node.age() == params.init_age
-            && self.nodes.values().any(|n| n.age() == params.init_age)
-            && self.is_complete()
+        // observers don't take part in the limit on young nodes
+        if !params.norejectyoung && node.age() == params.init_age && !node.is_observer()
+            && self.has_member_aged(params.init_age) && self.is_complete(params)
         {
             // disallow more than one node aged 1 per section if the section is complete
             // (all elders are adults)
-            println!("Node {:?} refused in section {:?}", node, self.prefix);
+            debug!(in self.prefix; "Node {:?} refused in section {:?}", node, self.prefix);
             return EventResult::HandledWithEvent(SectionEvent::NodeRejected(node));
         }
         assert!(
@@ -220,28 +445,83 @@ impl Section {
             node.name(),
             self.verifying_prefix
         );
+        self.insert_node(node);
+        self.update_elders(params);
+        if !node.is_adult() && self.is_complete(params) {
+            EventResult::Ignored
+        } else {
+            EventResult::Handled
+        }
+    }
+
+    /// Returns the half of the section's name space the name belongs to, or `None` if the name
+    /// doesn't match the prefix, as with nodes about to leave after a split. A section with the
+    /// longest possible prefix has everything in the first half.
+    fn half(&self, name: Name) -> Option<usize> {
+        match self.prefix.len() {
+            _ if !self.prefix.matches(name) => None,
+            len if len >= 64 => Some(0),
+            len => Some((name.0 >> (63 - len)) as usize & 1),
+        }
+    }
+
+    /// Counts the nodes in the halves of the name space from scratch
+    fn count_halves(&self) -> HalfCounts {
+        let mut counts = HalfCounts::default();
+        for name in self.nodes.keys() {
+            if let Some(half) = self.half(*name) {
+                counts.nodes[half] += 1;
+                if self.adults.contains(name) {
+                    counts.adults[half] += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Adds a node to the section's sets, without updating the elders
+    fn insert_node(&mut self, node: Node) {
+        let half = self.half(node.name());
         if node.is_adult() {
-            self.adults.insert(node.name());
+            if let (true, Some(half)) = (self.adults.insert(node.name()), half) {
+                self.counts.adults[half] += 1;
+            }
         } else {
             self.infants.insert(node.name());
         }
-        self.nodes.insert(node.name(), node);
-        self.update_elders();
-        if !node.is_adult() && self.is_complete() {
-            EventResult::Ignored
-        } else {
-            EventResult::Handled
+        let old = self.nodes.insert(node.name(), node);
+        if let Some(old) = old {
+            let _ = self.by_age.remove(&(Reverse(old.age()), old.name()));
+        }
+        let _ = self.by_age.insert((Reverse(node.age()), node.name()));
+        if let (None, Some(half)) = (old, half) {
+            self.counts.nodes[half] += 1;
         }
     }
 
-    /// Removes a node from the section and returns whether the event was handled
-    fn remove(&mut self, name: Name) -> EventResult {
-        let node = self.nodes.remove(&name);
-        let _ = self.adults.remove(&name);
+    /// Removes a node from the section's sets, without updating the elders
+    fn remove_node(&mut self, name: Name) -> Option<Node> {
+        let half = self.half(name);
+        if let (true, Some(half)) = (self.adults.remove(&name), half) {
+            self.counts.adults[half] -= 1;
+        }
         let _ = self.infants.remove(&name);
-        self.update_elders();
+        let node = self.nodes.remove(&name);
         if let Some(node) = node {
-            if !node.is_adult() && self.is_complete() {
+            let _ = self.by_age.remove(&(Reverse(node.age()), name));
+        }
+        if let (Some(_), Some(half)) = (node, half) {
+            self.counts.nodes[half] -= 1;
+        }
+        node
+    }
+
+    /// Removes a node from the section and returns whether the event was handled
+    fn remove(&mut self, name: Name, params: &Params) -> EventResult {
+        let node = self.remove_node(name);
+        self.update_elders(params);
+        if let Some(node) = node {
+            if !node.is_adult() && self.is_complete(params) {
                 EventResult::Ignored
             } else {
                 EventResult::HandledWithEvent(SectionEvent::NodeDropped(node))
@@ -253,13 +533,11 @@ impl Section {
 
     /// Relocates a node from the section - that is, removes it, but doesn't generate a `Dropped`
     /// section event, which would cause the network to think that the node has actually left
-    fn relocate(&mut self, name: Name) -> EventResult {
-        let node = self.nodes.remove(&name);
-        let _ = self.adults.remove(&name);
-        let _ = self.infants.remove(&name);
-        self.update_elders();
+    fn relocate(&mut self, name: Name, params: &Params) -> EventResult {
+        let node = self.remove_node(name);
+        self.update_elders(params);
         if let Some(node) = node {
-            if !node.is_adult() && self.is_complete() {
+            if !node.is_adult() && self.is_complete(params) {
                 EventResult::Ignored
             } else {
                 EventResult::Handled
@@ -274,13 +552,23 @@ impl Section {
         self.prefix
     }
 
+    /// Returns whether the section is in the process of splitting
+    pub fn is_splitting(&self) -> bool {
+        self.splitting
+    }
+
+    /// Returns whether the section is in the process of merging
+    pub fn is_merging(&self) -> bool {
+        self.merging
+    }
+
     /// Splits the section into two and generates the corresponding churn events
     pub fn split(mut self, params: &Params) -> (SplitData, SplitData) {
         self.splitting = false;
         let mut churn0 = vec![];
         let mut churn1 = vec![];
         let (prefix0, prefix1) = (self.prefix.extend(0), self.prefix.extend(1));
-        println!(
+        debug!(in self.prefix;
             "Splitting {:?} into {:?} and {:?}",
             self.prefix, prefix0, prefix1
         );
@@ -289,10 +577,11 @@ impl Section {
         section0.verifying_prefix = prefix0;
         section1.prefix = prefix1;
         section1.verifying_prefix = prefix1;
-        for (name, mut node) in &mut section0.nodes {
-            if params.inc_age {
-                node.increment_age();
-            }
+        section0.chain.forget_forks();
+        section0.counts = section0.count_halves();
+        section1.counts = section1.count_halves();
+        for (name, node) in &mut section0.nodes {
+            node.apply_age_rule(params.age_on_split);
             if prefix0.matches(*name) {
                 churn1.push(NetworkEvent::Gone(*node));
             } else if prefix1.matches(*name) {
@@ -305,6 +594,7 @@ impl Section {
                 );
             }
         }
+        section0.by_age = section0.index_by_age();
         ((section0, churn0), (section1, churn1))
     }
 
@@ -320,6 +610,7 @@ impl Section {
         );
         let merged_prefix = self.prefix.shorten();
         let mut result = Section::new(merged_prefix);
+        result.chain = Chain::merged(&self.chain, &other.chain);
         // for multi-level merges - the next level must remember to verify against
         // the fully-merged prefix
         result.verifying_prefix = if self.verifying_prefix.len() < other.verifying_prefix.len() {
@@ -331,67 +622,172 @@ impl Section {
             result.verifying_prefix = merged_prefix;
         }
         for (_, mut node) in self.nodes.into_iter().chain(other.nodes.into_iter()) {
-            if params.inc_age {
-                node.increment_age();
-            }
+            node.apply_age_rule(params.age_on_merge);
             result.add(node, params);
         }
         result
     }
 
-    fn count_adults(&self, prefix: &Prefix) -> usize {
-        self.adults.iter().filter(|&n| prefix.matches(*n)).count()
+    /// Returns whether the section is big enough to split, regardless of the length of its
+    /// prefix. If we are already splitting, returns false
+    fn wants_split(&self, params: &Params) -> bool {
+        use params::Strategy::*;
+        debug_assert_eq!(
+            self.counts,
+            self.count_halves(),
+            "Cached node counts of section {:?} are out of date",
+            self.prefix
+        );
+        debug_assert_eq!(
+            self.by_age,
+            self.index_by_age(),
+            "Age index of section {:?} is out of date",
+            self.prefix
+        );
+        let counts = match params.split_strategy {
+            Always if !self.is_complete(params) => self.counts.nodes,
+            _ => self.counts.adults,
+        };
+        let (count0, count1) = (counts[0], counts[1]);
+        let min_count = params.group_size + params.buffer;
+        !self.merging && !self.splitting && count0 >= min_count && count1 >= min_count
     }
 
-    fn count_peers(&self, prefix: &Prefix) -> usize {
-        if self.is_complete() {
-            self.count_adults(prefix)
+    /// Returns whether the section should merge and if so, marks it as merging
+    pub fn request_merge(&mut self, params: &Params) -> bool {
+        if self.should_merge(params) {
+            self.merging = true;
+            true
         } else {
-            self.nodes
-                .iter()
-                .filter(|&(n, _)| prefix.matches(*n))
-                .count()
+            false
         }
     }
 
-    /// Returns whether the section should split. If we are already splitting, returns false
-    pub fn should_split(&self, params: &Params) -> bool {
-        use params::Strategy::*;
-        let prefix0 = self.prefix.extend(0);
-        let prefix1 = self.prefix.extend(1);
-        let count_prefix = match params.split_strategy {
-            Complete => Self::count_adults,
-            Always => Self::count_peers,
-        };
-        let count0 = count_prefix(self, &prefix0);
-        let count1 = count_prefix(self, &prefix1);
-        !self.merging && !self.splitting && count0 >= GROUP_SIZE + BUFFER
-            && count1 >= GROUP_SIZE + BUFFER
-    }
-
     /// Returns whether the section should merge. If we are already merging, returns false
     pub fn should_merge(&self, params: &Params) -> bool {
         use params::Strategy::*;
         match params.split_strategy {
             Complete => {
-                !self.merging && !self.splitting && self.prefix.len() > 0
-                    && self.adults.len() <= GROUP_SIZE
+                !self.merging && !self.splitting && self.prefix.len() > params.min_prefix_len()
+                    && self.adults.len() <= params.group_size
             }
             Always => {
-                !self.merging && !self.splitting && self.prefix.len() > 0 && if self.is_complete() {
-                    self.adults.len() <= GROUP_SIZE
-                } else {
-                    self.nodes.len() <= GROUP_SIZE
-                }
+                !self.merging
+                    && !self.splitting
+                    && self.prefix.len() > params.min_prefix_len()
+                    && if self.is_complete(params) {
+                        self.adults.len() <= params.group_size
+                    } else {
+                        self.nodes.len() <= params.group_size
+                    }
             }
         }
     }
 
+    /// Restores the flags of the nodes with the given names, as returned by `Node::flags()`,
+    /// along with the age index, after the section has been deserialised
+    pub fn restore_flags(&mut self, flags: &[(Name, u16)]) {
+        for &(name, flags) in flags {
+            if let Some(node) = self.nodes.get_mut(&name) {
+                *node = node.with_flags(flags);
+            }
+        }
+        self.by_age = self.index_by_age();
+    }
+
+    /// Returns the parts of the state that aren't serialised with the section
+    pub fn local_state(&self) -> LocalState {
+        LocalState {
+            key: self.key.clone(),
+            elder_changes: self.elder_changes,
+        }
+    }
+
+    /// Restores the parts of the state returned by `local_state`, along with the age index,
+    /// after the section has been deserialised
+    pub fn restore_local_state(&mut self, state: LocalState) {
+        self.key = state.key;
+        self.elder_changes = state.elder_changes;
+        self.by_age = self.index_by_age();
+    }
+
+    /// Returns whether the node with the given name belongs to the section
+    pub fn has_node(&self, name: Name) -> bool {
+        self.nodes.contains_key(&name)
+    }
+
+    /// Returns the number of elders
+    pub fn elder_count(&self) -> usize {
+        self.elders.len()
+    }
+
+    /// Returns the number of times the set of elders changed, to tell whether it changed since
+    /// an earlier call
+    pub fn elder_changes(&self) -> u64 {
+        self.elder_changes
+    }
+
+    /// Returns the numbers of adults, including the elders, in the halves of the section's name
+    /// space, i.e. in the sections a split would create
+    pub fn adults_by_half(&self) -> (usize, usize) {
+        (self.counts.adults[0], self.counts.adults[1])
+    }
+
+    /// Returns the number of members that aren't adults yet
+    pub fn infant_count(&self) -> usize {
+        self.infants.len()
+    }
+
+    /// Returns whether all the elders are members of the section
+    pub fn elders_are_members(&self) -> bool {
+        self.elders.iter().all(|name| self.nodes.contains_key(name))
+    }
+
+    /// Returns whether the elders are the oldest adults of the section, as many as the elder
+    /// rules allow
+    pub fn elders_are_oldest(&self, params: &Params) -> bool {
+        self.elders == self.oldest_elders(params)
+    }
+
+    /// Checks that the cached node counts and the age index match the members
+    pub fn check_caches(&self) -> Result<(), String> {
+        if self.counts != self.count_halves() {
+            return Err(format!("Cached node counts of section {:?} are out of date", self.prefix));
+        }
+        if self.by_age != self.index_by_age() {
+            return Err(format!("Age index of section {:?} is out of date", self.prefix));
+        }
+        Ok(())
+    }
+
     /// Returns a set of all the nodes in the section
     pub fn nodes(&self) -> BTreeSet<Node> {
         self.nodes.iter().map(|(_, n)| *n).collect()
     }
 
+    /// Returns the Kolmogorov-Smirnov distance between the distribution of the node names and
+    /// the uniform distribution over the section's address range: 0 for perfectly spread names,
+    /// close to 1 if they are all clustered together
+    pub fn name_uniformity(&self) -> f64 {
+        let n = self.nodes.len() as f64;
+        if self.nodes.is_empty() {
+            return 0.0;
+        }
+        let shift = u32::from(self.prefix.len());
+        // the names are sorted, and so are their positions within the prefix
+        self.nodes
+            .keys()
+            .map(|name| name.0.checked_shl(shift).unwrap_or(0) as f64 / 2f64.powi(64))
+            .enumerate()
+            .map(|(i, pos)| (pos - i as f64 / n).max((i + 1) as f64 / n - pos))
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns the section's data chain
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
     /// Returns the section's Elders as `Node`s
     pub fn elders(&self) -> BTreeSet<Node> {
         self.elders
@@ -411,7 +807,7 @@ impl fmt::Debug for Section {
             self.elders.len(),
             self.adults.len() - self.elders.len(),
             self.infants.len(),
-            self.nodes_by_age(),
+            self.nodes_by_age().collect::<Vec<_>>(),
         )
     }
 }
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use network::churn::{NetworkEvent, SectionEvent};
+
+/// The kind under which the network records finalising merges
+pub const FINALISE_MERGE: &str = "FinaliseMerge";
+
+/// The wall-clock time spent processing events, by the kind of the event
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProcessingTime {
+    /// the number and the total processing time of the network events handled by sections
+    #[serde(with = "kind_keys")]
+    pub by_sections: BTreeMap<&'static str, (u64, Duration)>,
+    /// the number and the total processing time of the section events handled by the network,
+    /// including the finalisation of merges
+    #[serde(with = "kind_keys")]
+    pub by_network: BTreeMap<&'static str, (u64, Duration)>,
+}
+
+impl ProcessingTime {
+    /// Records a network event of the given kind handled by a section
+    pub fn record_section(&mut self, kind: &'static str, elapsed: Duration) {
+        record(&mut self.by_sections, kind, elapsed);
+    }
+
+    /// Records a section event of the given kind handled by the network
+    pub fn record_network(&mut self, kind: &'static str, elapsed: Duration) {
+        record(&mut self.by_network, kind, elapsed);
+    }
+
+    /// Returns the total time spent processing events
+    pub fn total(&self) -> Duration {
+        self.by_sections
+            .values()
+            .chain(self.by_network.values())
+            .map(|&(_, time)| time)
+            .sum()
+    }
+}
+
+fn record(times: &mut BTreeMap<&'static str, (u64, Duration)>, kind: &'static str, elapsed: Duration) {
+    let entry = times.entry(kind).or_insert((0, Duration::from_secs(0)));
+    entry.0 += 1;
+    entry.1 += elapsed;
+}
+
+/// Returns the static name of the kind of an event recorded by the network, given its name
+pub fn static_kind(kind: &str) -> Option<&'static str> {
+    NetworkEvent::KINDS
+        .iter()
+        .chain(SectionEvent::KINDS)
+        .chain(&[FINALISE_MERGE])
+        .find(|&&known| known == kind)
+        .cloned()
+}
+
+/// Serialises maps keyed by the kinds of events, which are restored as their static names
+pub mod kind_keys {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<V: Serialize, S: Serializer>(
+        map: &BTreeMap<&'static str, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        map.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<&'static str, V>, D::Error> {
+        let map: BTreeMap<String, V> = BTreeMap::deserialize(deserializer)?;
+        map.into_iter()
+            .map(|(kind, value)| {
+                super::static_kind(&kind)
+                    .map(|kind| (kind, value))
+                    .ok_or_else(|| D::Error::custom(format!("unknown event kind {}", kind)))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use network::churn::{NetworkEvent, SectionEvent};
+use network::prefix::Prefix;
+
+/// The size of the messages of every kind of event that no size is given for, in bytes: a
+/// node's name, key and age, a section's signature and some framing. Each default is a rough
+/// estimate.
+const DEFAULT_SIZES: &[(&str, u64)] = &[
+    ("Live", 512),
+    ("Lost", 256),
+    ("Gone", 384),
+    ("Relocated", 512),
+    ("PrefixChange", 256),
+    ("StartMerge", 256),
+    ("NodeDropped", 384),
+    ("NodeRejected", 384),
+    ("NeedRelocate", 416),
+    ("ElderRelocated", 384),
+    ("ElderPromoted", 256),
+    ("Equivocated", 1024),
+    ("EquivocationDetected", 1024),
+    ("RequestMerge", 256),
+    ("RequestSplit", 256),
+    ("SplitRefused", 128),
+    ("KeyRotated", 384),
+];
+
+/// The simulated sizes of the messages carrying the events, in bytes, by the kind of the event
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MessageSizes(BTreeMap<String, u64>);
+
+impl Default for MessageSizes {
+    fn default() -> MessageSizes {
+        MessageSizes(
+            DEFAULT_SIZES
+                .iter()
+                .map(|&(kind, size)| (kind.to_owned(), size))
+                .collect(),
+        )
+    }
+}
+
+impl MessageSizes {
+    /// Sets the size of the messages of the given kind of event, given as `KIND=BYTES`
+    pub fn set(&mut self, s: &str) -> Result<(), String> {
+        let mut parts = s.splitn(2, '=');
+        let kind = parts.next().unwrap_or("").trim();
+        if !NetworkEvent::KINDS.contains(&kind) && !SectionEvent::KINDS.contains(&kind) {
+            return Err(format!("unknown kind of event \"{}\"", kind));
+        }
+        let size = parts
+            .next()
+            .and_then(|size| size.trim().parse().ok())
+            .ok_or_else(|| format!("expected {}=BYTES", kind))?;
+        let _ = self.0.insert(kind.to_owned(), size);
+        Ok(())
+    }
+
+    /// Returns the size of a message carrying an event of the given kind
+    pub fn of(&self, kind: &str) -> u64 {
+        self.0.get(kind).cloned().unwrap_or(0)
+    }
+}
+
+/// Adds up the simulated traffic of the messages between the network and the sections: an
+/// event handled by a section is a message the section receives, and an event a section
+/// reports back is a message it sends.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Traffic {
+    /// the total number of bytes
+    pub total: u64,
+    /// the bytes of the messages carrying every kind of event
+    pub by_kind: BTreeMap<String, u64>,
+    /// the bytes every section sent and received
+    pub by_section: BTreeMap<Prefix, u64>,
+    /// the number of iterations closed
+    pub iterations: u64,
+    /// the most bytes sent in a single iteration
+    pub peak: u64,
+    /// the bytes sent in the current iteration
+    current: u64,
+}
+
+impl Traffic {
+    /// Records a message of the given size, carrying an event of the given kind, sent or
+    /// received by the section with the given prefix
+    pub fn record(&mut self, prefix: Prefix, kind: &str, bytes: u64) {
+        self.total += bytes;
+        self.current += bytes;
+        match self.by_kind.get_mut(kind) {
+            Some(total) => *total += bytes,
+            None => {
+                let _ = self.by_kind.insert(kind.to_owned(), bytes);
+            }
+        }
+        *self.by_section.entry(prefix).or_insert(0) += bytes;
+    }
+
+    /// Closes the current iteration
+    pub fn end_iteration(&mut self) {
+        self.peak = self.peak.max(self.current);
+        self.iterations += 1;
+        self.current = 0;
+    }
+
+    /// Returns the mean number of bytes sent per iteration
+    pub fn mean_per_iteration(&self) -> f64 {
+        self.total as f64 / self.iterations.max(1) as f64
+    }
+}
@@ -1,6 +1,13 @@
+use std::fs;
 use std::str::FromStr;
+use stats::ReportFormat;
+use stats::aggregate::Summary;
+use network::{BUFFER, GROUP_SIZE, QUORUM};
+use network::prefix::Prefix;
+use network::traffic::MessageSizes;
+use random::{random_range, ratio, Probability, ONE};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Strategy {
     Always,
     Complete,
@@ -17,24 +24,735 @@ impl FromStr for Strategy {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// How the chance of a node being the one to drop depends on its age. Only the ratios between
+/// the ages matter, as the churn decides how many nodes drop.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum DropDist {
+    /// the chance halves with every year of age
     Exponential,
+    /// the chance is inversely proportional to the age
     RevProp,
+    /// the chance is multiplied by the given factor, the inverse of a base of at least 1, with
+    /// every year of age
+    ExponentialBase(Probability),
+    /// every node has the same chance, whatever its age
+    Uniform,
+    /// the chances in percent of the ages starting from the given ones, in the order of the
+    /// ages; younger ones have the chance of the first
+    Table(Vec<(u8, f64)>),
 }
 
-impl FromStr for DropDist {
+impl DropDist {
+    /// Parses a drop model: `exponential[:BASE]`, `reverse-proportional`, `uniform` or
+    /// `table:FILE`, where the file has an `AGE,PERCENT` pair on every line that isn't blank or
+    /// a `#` comment
+    pub fn parse(spec: &str) -> Result<DropDist, String> {
+        let (model, arg) = match spec.find(':') {
+            Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+            None => (spec, None),
+        };
+        match (model, arg) {
+            ("exp", None) | ("exponential", None) => Ok(DropDist::Exponential),
+            ("exp", Some(base)) | ("exponential", Some(base)) => match base.parse() {
+                // the base is rounded to millionths, so the factor is the same everywhere
+                Ok(base) if base >= 1.0 && f64::is_finite(base) => Ok(if base == 2.0 {
+                    DropDist::Exponential
+                } else {
+                    DropDist::ExponentialBase(ratio(1_000_000, (base * 1e6).round() as u64).max(1))
+                }),
+                _ => Err(format!("The base of {} must be a number of at least 1!", spec)),
+            },
+            ("rev", None) | ("reverse-proportional", None) => Ok(DropDist::RevProp),
+            ("uniform", None) => Ok(DropDist::Uniform),
+            ("table", Some(file)) => {
+                let text = fs::read_to_string(file)
+                    .map_err(|err| format!("Can't read {}: {}", file, err))?;
+                let mut table: Vec<(u8, f64)> = vec![];
+                for (i, line) in text.lines().enumerate() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let mut fields = line.split(',').map(str::trim);
+                    let entry = match (fields.next(), fields.next(), fields.next()) {
+                        (Some(age), Some(percent), None) => {
+                            age.parse().ok().zip(percent.parse().ok())
+                        }
+                        _ => None,
+                    };
+                    match entry {
+                        Some((age, percent))
+                            if (0.0..=100.0).contains(&percent)
+                                && table.last().map_or(true, |&(last, _)| last < age) =>
+                        {
+                            table.push((age, percent))
+                        }
+                        _ => {
+                            return Err(format!(
+                                "{}, line {}: entries must be AGE,PERCENT with increasing ages \
+                                 and chances between 0 and 100!",
+                                file,
+                                i + 1
+                            ))
+                        }
+                    }
+                }
+                if table.is_empty() {
+                    return Err(format!("{} has no entries!", file));
+                }
+                Ok(DropDist::Table(table))
+            }
+            _ => Err(format!(
+                "Drop model must be exponential[:BASE], reverse-proportional, uniform or \
+                 table:FILE, not {}!",
+                spec
+            )),
+        }
+    }
+}
+
+/// When a section needs a new section key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyRotation {
+    /// whenever its set of elders changes
+    Any,
+    /// when the elders that hold the current key no longer make up a quorum of the elders
+    Quorum,
+}
+
+impl FromStr for KeyRotation {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "any" => Ok(KeyRotation::Any),
+            "quorum" => Ok(KeyRotation::Quorum),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What happens to the ages of the members of a section when it splits or merges
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgeRule {
+    /// the members retain their ages
+    Keep,
+    /// the members gain a year of age
+    Increment,
+}
+
+impl FromStr for AgeRule {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "keep" => Ok(AgeRule::Keep),
+            "increment" => Ok(AgeRule::Increment),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How much of the statistics is printed while the simulation runs, independently of the
+/// progress messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsVerbosity {
+    /// only the results at the end of the run
+    Final,
+    /// a row of a table of statistics every `stats_interval` iterations
+    Compact,
+    /// the row and the full distributions every `stats_interval` iterations
+    Full,
+}
+
+impl FromStr for StatsVerbosity {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "final" => Ok(StatsVerbosity::Final),
+            "compact" => Ok(StatsVerbosity::Compact),
+            "full" => Ok(StatsVerbosity::Full),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Which elders a section keeps when too few of its oldest nodes are old enough to be elders
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElderFallback {
+    /// the section only has the elders that are old enough, however few
+    Shrink,
+    /// the oldest adults make up the numbers up to a quorum
+    Quorum,
+}
+
+impl FromStr for ElderFallback {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "shrink" => Ok(ElderFallback::Shrink),
+            "quorum" => Ok(ElderFallback::Quorum),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What happens when a check of the network's invariants fails
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckMode {
+    /// the run stops with the diagnostic as its failure
+    Abort,
+    /// the diagnostic is printed and the run goes on
+    Warn,
+}
+
+impl FromStr for CheckMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "abort" => Ok(CheckMode::Abort),
+            "warn" => Ok(CheckMode::Warn),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A packaged experiment, sweeping a parameter over many runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Experiment {
+    /// the time until an attacker coalition first holds a quorum of elders, by the rate at which
+    /// the attacker's nodes join
+    QuorumRisk,
+}
+
+impl Experiment {
+    /// Returns the name of the experiment, as given on the command line
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Experiment::QuorumRisk => "quorum-risk",
+        }
+    }
+}
+
+impl FromStr for Experiment {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "quorum-risk" => Ok(Experiment::QuorumRisk),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How the rest of the network behaves towards a sub-tree simulated in isolation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stub {
+    /// nodes are never relocated out of the sub-tree
+    Closed,
+    /// nodes relocated out of the sub-tree leave it for good
+    Absorb,
+    /// every node relocated out of the sub-tree is replaced by one relocated into it
+    Exchange,
+}
+
+impl FromStr for Stub {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, ()> {
         match s {
-            "exp" | "exponential" => Ok(DropDist::Exponential),
-            "rev" | "reverse-proportional" => Ok(DropDist::RevProp),
+            "closed" => Ok(Stub::Closed),
+            "absorb" => Ok(Stub::Absorb),
+            "exchange" => Ok(Stub::Exchange),
             _ => Err(()),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// What happens to the relocations a section decides on while it is splitting or merging
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReorgRelocations {
+    /// the relocated nodes are sent on straight away
+    Forward,
+    /// the relocations wait until the section that decided on them has finished splitting or
+    /// merging, then go to the neighbours of the section that took over the relocated names
+    Defer,
+}
+
+impl FromStr for ReorgRelocations {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "forward" => Ok(ReorgRelocations::Forward),
+            "defer" => Ok(ReorgRelocations::Defer),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What the elders of an attacker coalition do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttackStrategy {
+    /// sign conflicting blocks
+    Equivocate,
+    /// only occupy elder slots, waiting to reach a quorum
+    Passive,
+}
+
+impl FromStr for AttackStrategy {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "equivocate" => Ok(AttackStrategy::Equivocate),
+            "passive" => Ok(AttackStrategy::Passive),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A group of attackers acting independently of any other
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Coalition {
+    /// the percentage of joining nodes belonging to the coalition
+    pub fraction: u8,
+    pub strategy: AttackStrategy,
+    /// the part of the network the coalition's nodes join, if it concentrates on one
+    pub target: Option<Prefix>,
+    /// whether the coalition's nodes reach the target by rejoining until their random names
+    /// fall in it, rather than by choosing their names
+    #[serde(default)]
+    pub rejoins: bool,
+    /// whether the coalition's nodes never leave the network on their own
+    #[serde(default)]
+    pub stays: bool,
+}
+
+impl FromStr for Coalition {
+    type Err = ();
+    /// Parses a coalition in the form `PERCENT[:STRATEGY[:PREFIX]]`
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let parts: Vec<_> = s.split(':').collect();
+        if parts.len() > 3 {
+            return Err(());
+        }
+        let fraction = parts[0].parse().map_err(|_| ())?;
+        let strategy = match parts.get(1) {
+            Some(strategy) => strategy.parse()?,
+            None => AttackStrategy::Equivocate,
+        };
+        let target = match parts.get(2) {
+            Some(prefix) => Some(Prefix::from_str(prefix).ok_or(())?),
+            None => None,
+        };
+        Ok(Coalition {
+            fraction,
+            strategy,
+            target,
+            rejoins: false,
+            stays: false,
+        })
+    }
+}
+
+/// The built-in node behaviours, see `network::behaviour`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BehaviourKind {
+    Honest,
+    Flaky,
+    Malicious,
+}
+
+impl FromStr for BehaviourKind {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "honest" => Ok(BehaviourKind::Honest),
+            "flaky" => Ok(BehaviourKind::Flaky),
+            "malicious" => Ok(BehaviourKind::Malicious),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A part of the joining nodes following a behaviour
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BehaviourSpec {
+    pub kind: BehaviourKind,
+    /// the percentage of joining nodes following the behaviour
+    pub fraction: u8,
+    /// the chance (in percent) of the node acting in an iteration
+    pub rate: f64,
+}
+
+impl FromStr for BehaviourSpec {
+    type Err = ();
+    /// Parses a behaviour in the form `KIND:PERCENT[:RATE]`
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let parts: Vec<_> = s.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(());
+        }
+        let kind = parts[0].parse()?;
+        let fraction = parts[1].parse().map_err(|_| ())?;
+        let rate = match parts.get(2) {
+            Some(rate) => rate.parse().map_err(|_| ())?,
+            None => 1.0,
+        };
+        if !(0.0..=100.0).contains(&rate) {
+            return Err(());
+        }
+        Ok(BehaviourSpec {
+            kind,
+            fraction,
+            rate,
+        })
+    }
+}
+
+/// How joining nodes choose the section to join
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JoinPolicy {
+    /// nodes get a uniformly random name
+    Random,
+    /// nodes are directed to the section with the fewest nodes
+    Smallest,
+    /// nodes are directed to the section with the lowest mean age
+    Youngest,
+}
+
+impl FromStr for JoinPolicy {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "random" => Ok(JoinPolicy::Random),
+            "smallest" => Ok(JoinPolicy::Smallest),
+            "youngest" => Ok(JoinPolicy::Youngest),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Which of the nodes that left the network rejoin, and how they are treated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RejoinPolicy {
+    /// a random node rejoins and loses age
+    Random,
+    /// the node with the most uptime credit rejoins first and loses age
+    Priority,
+    /// a random node rejoins, and keeps its age if it has enough uptime credit
+    Lenient,
+}
+
+impl FromStr for RejoinPolicy {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "random" => Ok(RejoinPolicy::Random),
+            "priority" => Ok(RejoinPolicy::Priority),
+            "lenient" => Ok(RejoinPolicy::Lenient),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A parameter that can be perturbed between runs, swept, or changed by a scenario mid-run. The
+/// age at which nodes become adults is fixed, so it isn't one of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FuzzedParam {
+    InitAge,
+    AddProbability,
+    DropProbability,
+    ElderHandover,
+    Equivocation,
+    Permanent,
+    StormStart,
+    StormEnd,
+    StormRate,
+    DiurnalAmplitude,
+    JoinTargeting,
+    ElderMinAge,
+    GroupSize,
+    Quorum,
+    Buffer,
+}
+
+impl FuzzedParam {
+    /// Returns the name of the parameter, as given on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            FuzzedParam::InitAge => "init_age",
+            FuzzedParam::AddProbability => "p_add",
+            FuzzedParam::DropProbability => "p_drop",
+            FuzzedParam::ElderHandover => "elder_handover",
+            FuzzedParam::Equivocation => "equivocation",
+            FuzzedParam::Permanent => "permanent",
+            FuzzedParam::StormStart => "storm_start",
+            FuzzedParam::StormEnd => "storm_end",
+            FuzzedParam::StormRate => "storm_rate",
+            FuzzedParam::DiurnalAmplitude => "diurnal_amplitude",
+            FuzzedParam::JoinTargeting => "join_targeting",
+            FuzzedParam::ElderMinAge => "elder_min_age",
+            FuzzedParam::GroupSize => "group_size",
+            FuzzedParam::Quorum => "quorum",
+            FuzzedParam::Buffer => "buffer",
+        }
+    }
+
+    /// Returns whether the parameter is a percentage
+    pub fn is_percentage(self) -> bool {
+        !matches!(
+            self,
+            FuzzedParam::InitAge
+                | FuzzedParam::ElderHandover
+                | FuzzedParam::StormRate
+                | FuzzedParam::ElderMinAge
+                | FuzzedParam::GroupSize
+                | FuzzedParam::Quorum
+                | FuzzedParam::Buffer
+        )
+    }
+
+    /// Returns whether the parameter only takes whole values
+    pub fn is_integer(self) -> bool {
+        !matches!(
+            self,
+            FuzzedParam::StormStart | FuzzedParam::StormEnd | FuzzedParam::DiurnalAmplitude
+        )
+    }
+
+    /// Returns whether the parameter belongs to the churn generator rather than the network, so
+    /// it can't change mid-run
+    pub fn drives_churn(self) -> bool {
+        matches!(
+            self,
+            FuzzedParam::AddProbability
+                | FuzzedParam::DropProbability
+                | FuzzedParam::StormStart
+                | FuzzedParam::StormEnd
+                | FuzzedParam::StormRate
+                | FuzzedParam::DiurnalAmplitude
+        )
+    }
+
+    /// Returns whether only the sections follow the parameter, so it can differ between them
+    pub fn is_per_section(self) -> bool {
+        matches!(
+            self,
+            FuzzedParam::Equivocation
+                | FuzzedParam::ElderMinAge
+                | FuzzedParam::GroupSize
+                | FuzzedParam::Quorum
+                | FuzzedParam::Buffer
+        )
+    }
+
+    /// Returns the largest value the parameter can be set to: 100 for percentages, the largest
+    /// age for ages, and 2^32 - 1 for numbers of iterations and nodes
+    pub fn max_value(self) -> f64 {
+        match self {
+            _ if self.is_percentage() => 100.0,
+            FuzzedParam::InitAge | FuzzedParam::ElderMinAge => f64::from(u8::MAX),
+            _ if self.is_integer() => f64::from(u32::MAX),
+            _ => f64::MAX,
+        }
+    }
+
+    /// Sets the parameter to the given value. Like the builder, setting the group size makes the
+    /// quorum more than half of it.
+    pub fn set(self, params: &mut Params, value: f64) {
+        match self {
+            FuzzedParam::InitAge => params.init_age = value as u8,
+            FuzzedParam::AddProbability => params.growth.0 = value as u8,
+            FuzzedParam::DropProbability => params.growth.1 = value as u8,
+            FuzzedParam::ElderHandover => params.elder_handover = value as u64,
+            FuzzedParam::Equivocation => params.equivocation = value as u8,
+            FuzzedParam::Permanent => params.permanent = value as u8,
+            FuzzedParam::StormStart => params.storm_start = value,
+            FuzzedParam::StormEnd => params.storm_end = value,
+            FuzzedParam::StormRate => params.storm_rate = value as usize,
+            FuzzedParam::DiurnalAmplitude => params.diurnal_amplitude = value,
+            FuzzedParam::JoinTargeting => params.join_targeting = value as u8,
+            FuzzedParam::ElderMinAge => params.elder_min_age = Some(value as u8),
+            FuzzedParam::GroupSize => {
+                params.group_size = value as usize;
+                params.quorum = params.group_size / 2 + 1;
+            }
+            FuzzedParam::Quorum => params.quorum = value as usize,
+            FuzzedParam::Buffer => params.buffer = value as usize,
+        }
+    }
+}
+
+impl FromStr for FuzzedParam {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "init_age" => Ok(FuzzedParam::InitAge),
+            "p_add" => Ok(FuzzedParam::AddProbability),
+            "p_drop" => Ok(FuzzedParam::DropProbability),
+            "elder_handover" => Ok(FuzzedParam::ElderHandover),
+            "equivocation" => Ok(FuzzedParam::Equivocation),
+            "permanent" => Ok(FuzzedParam::Permanent),
+            "storm_start" => Ok(FuzzedParam::StormStart),
+            "storm_end" => Ok(FuzzedParam::StormEnd),
+            "storm_rate" => Ok(FuzzedParam::StormRate),
+            "diurnal_amplitude" => Ok(FuzzedParam::DiurnalAmplitude),
+            "join_targeting" => Ok(FuzzedParam::JoinTargeting),
+            "elder_min_age" => Ok(FuzzedParam::ElderMinAge),
+            "group_size" => Ok(FuzzedParam::GroupSize),
+            "quorum" => Ok(FuzzedParam::Quorum),
+            "buffer" => Ok(FuzzedParam::Buffer),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A range within which a parameter is perturbed between runs, given as `NAME=MIN..MAX`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Jitter {
+    pub param: FuzzedParam,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl FromStr for Jitter {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut parts = s.splitn(2, '=');
+        let param = parts.next().ok_or(())?.trim().parse()?;
+        let mut range = parts.next().ok_or(())?.splitn(2, "..");
+        let min: f64 = range.next().ok_or(())?.trim().parse().map_err(|_| ())?;
+        let max: f64 = range.next().ok_or(())?.trim().parse().map_err(|_| ())?;
+        if min < 0.0 || min > max {
+            return Err(());
+        }
+        Ok(Jitter { param, min, max })
+    }
+}
+
+/// The values a parameter takes in a sweep, given as `NAME=MIN..MAX` or `NAME=MIN..MAX:STEP`;
+/// the step is 1 if not given
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Sweep {
+    pub param: FuzzedParam,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+}
+
+impl Sweep {
+    /// Returns the values of the parameter, from the minimum up to the maximum
+    pub fn values(&self) -> Vec<f64> {
+        let steps = ((self.max - self.min) / self.step + 1e-9).floor() as usize;
+        (0..steps + 1)
+            .map(|i| self.min + self.step * i as f64)
+            .collect()
+    }
+}
+
+impl FromStr for Sweep {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut parts = s.splitn(2, ':');
+        let range: Jitter = parts.next().ok_or(())?.parse()?;
+        let step: f64 = match parts.next() {
+            Some(step) => step.trim().parse().map_err(|_| ())?,
+            None => 1.0,
+        };
+        if step <= 0.0 || (range.param.is_integer() && step.fract() != 0.0) {
+            return Err(());
+        }
+        Ok(Sweep {
+            param: range.param,
+            min: range.min,
+            max: range.max,
+            step,
+        })
+    }
+}
+
+/// How many churn events happen in an iteration
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ChurnModel {
+    /// one event per iteration
+    Fixed,
+    /// a number of events drawn from the Poisson distribution with the given mean
+    Poisson(f64),
+    /// one event per iteration, and every `period` iterations, `joins` nodes joining and `drops`
+    /// nodes dropping at once: flash crowds and mass exits
+    Burst { period: u64, joins: u64, drops: u64 },
+    /// the numbers of joins, drops and rejoins in every iteration, read from a file
+    Trace(String),
+}
+
+impl FromStr for ChurnModel {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        if let Some(file) = s.strip_prefix("trace:") {
+            return Ok(ChurnModel::Trace(file.to_owned()));
+        }
+        let parts: Vec<_> = s.split(':').collect();
+        match (parts[0], parts.len()) {
+            ("fixed", 1) => Ok(ChurnModel::Fixed),
+            ("poisson", 2) => match parts[1].parse() {
+                Ok(mean) if mean > 0.0 && mean <= MAX_POISSON_MEAN => Ok(ChurnModel::Poisson(mean)),
+                _ => Err(()),
+            },
+            ("burst", 4) => match (parts[1].parse(), parts[2].parse(), parts[3].parse()) {
+                (Ok(period), Ok(joins), Ok(drops)) if period > 0 => Ok(ChurnModel::Burst {
+                    period,
+                    joins,
+                    drops,
+                }),
+                _ => Err(()),
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+/// The largest mean of Poisson churn, which keeps the table of its distribution small; more events
+/// per iteration than that would swamp the sections anyway
+pub const MAX_POISSON_MEAN: f64 = 100.0;
+
+/// The largest mean delay of the messages between sections, in iterations; a run doesn't last
+/// long enough for longer delays to make a difference
+pub const MAX_MSG_DELAY_MEAN: f64 = 100_000.0;
+
+/// The distribution of the number of events a section can handle per iteration
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum RateDist {
+    /// sections handle all their events immediately
+    Unlimited,
+    /// all sections handle the same number of events
+    Constant(usize),
+    /// uniform between the two values, inclusive
+    Uniform(usize, usize),
+    /// exponential with the given mean, rounded up
+    Exponential(f64),
+}
+
+impl RateDist {
+    /// Returns whether the sections' processing rates are limited
+    pub fn is_limited(&self) -> bool {
+        !matches!(*self, RateDist::Unlimited)
+    }
+}
+
+impl FromStr for RateDist {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let parts: Vec<_> = s.split(':').collect();
+        match (parts[0], parts.len()) {
+            ("unlimited", 1) => Ok(RateDist::Unlimited),
+            ("const", 2) => parts[1].parse().map(RateDist::Constant).map_err(|_| ()),
+            ("uniform", 3) => match (parts[1].parse(), parts[2].parse()) {
+                (Ok(min), Ok(max)) if min <= max => Ok(RateDist::Uniform(min, max)),
+                _ => Err(()),
+            },
+            ("exp", 2) => parts[1].parse().map(RateDist::Exponential).map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
     pub init_age: u8,
     pub split_strategy: Strategy,
@@ -42,5 +760,508 @@ pub struct Params {
     pub growth: (u8, u8),
     pub structure_output_file: Option<String>,
     pub drop_dist: DropDist,
-    pub inc_age: bool,
+    /// the iterations (start inclusive, end exclusive) in which progress messages are printed;
+    /// if `None`, they are printed throughout the run
+    pub debug_window: Option<(u64, u64)>,
+    /// the prefix progress messages about sections and nodes are restricted to, if any
+    pub debug_prefix: Option<Prefix>,
+    /// whether node names are shown as short pseudonyms in the progress messages and exports
+    pub pseudonyms: bool,
+    /// how much of the statistics is printed while the simulation runs
+    pub stats_verbosity: StatsVerbosity,
+    /// whether a dashboard shows the network while the simulation runs, in place of the
+    /// statistics and progress messages
+    pub tui: bool,
+    /// the number of iterations between the statistics printed while the simulation runs
+    pub stats_interval: u64,
+    /// the largest number of infants the whole network admits; new nodes trying to join when
+    /// there are as many are turned away
+    pub infant_quota: Option<usize>,
+    /// how often (in iterations) joining nodes refresh their cached list of sections to
+    /// bootstrap off; if `None`, they contact the right section directly
+    pub bootstrap_cache: Option<u64>,
+    /// the percentage of joining nodes that send their request to a random section, which
+    /// redirects it one hop per iteration towards the section they belong to
+    pub misdirected_joins: u8,
+    /// when sections rotate their keys, if key rotations are tracked
+    pub key_rotation: Option<KeyRotation>,
+    /// the number of elders of a complete section
+    pub group_size: usize,
+    /// the number of elders needed for a section to reach a decision
+    pub quorum: usize,
+    /// the number of adults both halves of a section need on top of `group_size` for it to split,
+    /// so that it doesn't merge again as soon as a node leaves
+    pub buffer: usize,
+    /// the age nodes must exceed to be elders, on top of being among the oldest `group_size`;
+    /// if `None`, every adult among them is an elder
+    pub elder_min_age: Option<u8>,
+    /// which elders a section keeps when too few of its oldest nodes exceed `elder_min_age`
+    pub elder_fallback: ElderFallback,
+    /// what happens to the members' ages when their section splits
+    pub age_on_split: AgeRule,
+    /// what happens to the members' ages when their section merges
+    pub age_on_merge: AgeRule,
+    /// the number of iterations for which a section stops processing events after one of its
+    /// elders gets relocated
+    pub elder_handover: u64,
+    /// the number of iterations for which a merged section stops processing events for every
+    /// elder the merge promoted or demoted
+    pub merge_stabilisation: u64,
+    pub scenario_file: Option<String>,
+    /// the built-in example whose scenario is checked if there is no scenario file
+    pub example: Option<String>,
+    pub merge_negotiation: bool,
+    pub batch_cascades: bool,
+    /// the time of creating a single signature, in microseconds
+    pub sign_cost: f64,
+    /// the time of verifying a single signature, in microseconds
+    pub verify_cost: f64,
+    /// the sizes of the messages carrying the events, if their traffic is added up
+    pub message_sizes: Option<MessageSizes>,
+    /// the duration of an iteration in seconds, for estimating the bandwidth, if given
+    pub iteration_seconds: Option<f64>,
+    /// the maximum length of the sections' prefixes
+    pub max_prefix_len: u8,
+    /// whether to measure the time spent processing events of every kind
+    pub time_events: bool,
+    /// whether to report sections whose node names are distributed suspiciously unevenly
+    pub name_uniformity: bool,
+    /// whether to report how likely nodes of every age were to be picked for relocation
+    pub relocation_fairness: bool,
+    /// whether relocations are decided by the elders: based on the hash of the block they signed
+    /// for an event rather than the hash of the event, which also picks the section the node is
+    /// relocated to instead of the smallest neighbour
+    pub elder_relocation: bool,
+    /// the number of relocations of a node within a number of iterations above which the node
+    /// is flagged as going back and forth, if relocation ping-pong is tracked
+    pub ping_pong: Option<(usize, u64)>,
+    /// the number of iterations before an elder can notice that a node died, and the chance (in
+    /// percent) that it notices in every iteration after that, if deaths are only processed once
+    /// a quorum of the elders noticed them
+    pub dead_detection: Option<(u64, f64)>,
+    /// whether to report which sections would merge if churn stopped at the end of the run
+    pub whatif_merge: bool,
+    /// the number of events the sections can handle in a single iteration before the run is
+    /// considered to be stuck in a loop
+    pub event_budget: Option<u64>,
+    /// the number of times an event can be passed between sections before it is reported as
+    /// going round in circles
+    pub max_hops: usize,
+    /// the number of iterations a relocated node spends in transit, not being a member of any
+    /// section
+    pub relocation_delay: u64,
+    /// what happens to the relocations sections decide on while splitting or merging
+    pub reorg_relocations: ReorgRelocations,
+    /// the chance (in percent) that a relocated node is lost in every iteration in transit
+    pub transit_loss: f64,
+    /// the mean number of iterations a message between sections, such as the request of a
+    /// joining or relocated node or the events of a merge, takes to reach its section
+    pub msg_delay_mean: f64,
+    /// the chance (in percent) that the request of a joining or relocated node is lost on its
+    /// way to its section
+    pub msg_loss_prob: f64,
+    /// the amount of data held by every node, transferred when it is relocated, in MB
+    pub node_data: f64,
+    /// the amount of data that can be transferred to a section per iteration, in MB (unlimited
+    /// if 0)
+    pub transfer_bandwidth: f64,
+    /// the attacker coalitions; nodes of the first one belong to coalition 1 etc.
+    pub coalitions: Vec<Coalition>,
+    /// the behaviours of the joining nodes other than the default one; nodes following the
+    /// first one have behaviour 1 etc.
+    pub behaviours: Vec<BehaviourSpec>,
+    /// the percentage of joining nodes that never drop
+    pub permanent: u8,
+    /// the number of observer nodes, which never drop or get relocated and log what they see
+    pub observers: usize,
+    /// the percentage chance that a malicious elder equivocates on a block
+    pub equivocation: u8,
+    /// the number of simulation runs to aggregate
+    pub runs: usize,
+    /// the number of runs simulated at the same time, on threads of their own
+    pub jobs: usize,
+    /// the parameters perturbed in every run
+    pub fuzz: Vec<Jitter>,
+    /// the parameters swept over in a sweep; every combination of their values is simulated
+    pub sweep: Vec<Sweep>,
+    /// the number of runs of every combination of values in a sweep
+    pub runs_per_config: usize,
+    /// the CSV file the results of a sweep are written to
+    pub sweep_file: String,
+    /// the relative width of a confidence interval (in percent) above which a warning is printed
+    pub max_ci_width: f64,
+    /// the file to write the headline metrics to, as a baseline for later runs
+    pub summary_file: Option<String>,
+    /// the headline metrics of a baseline run to compare with
+    pub baseline_file: Option<String>,
+    /// the change for the worse (in percent) from the baseline above which a metric is flagged
+    pub regression_threshold: f64,
+    /// the format of the structure output file
+    pub report_format: ReportFormat,
+    /// the file to write a record of every relocation to; in JSON if that is the report format,
+    /// in CSV otherwise
+    pub relocation_log: Option<String>,
+    /// the file to write the elders of every section to at the end of the run, with their ages
+    /// and tenure; in JSON if that is the report format, in CSV otherwise
+    pub elder_list: Option<String>,
+    /// the file to write a time series of the statistics to, with a row every `stats_interval`
+    /// iterations; in JSON if that is the report format, in CSV otherwise
+    pub metrics_file: Option<String>,
+    /// the number of latest iterations to keep records of (all of them if `None`)
+    pub history: Option<usize>,
+    /// the file to write a markdown report of the run to
+    pub report_file: Option<String>,
+    /// the file to write the final state of the network to as a GraphML graph
+    pub graph_file: Option<String>,
+    /// the file to record a trace of the events handled by the sections to
+    pub trace_file: Option<String>,
+    /// the file to record a log of the churn and the relocations, splits and merges to
+    pub record_file: Option<String>,
+    /// the event log whose churn is fed to the network instead of generating it, if a recorded
+    /// run is replayed
+    pub replay_log: Option<String>,
+    /// the Unix socket or named pipe to send the structural events of the network to
+    pub event_socket: Option<String>,
+    /// the file to save the resolved configuration of the run to
+    pub save_config: Option<String>,
+    /// the number of iterations between snapshots of the simulation, if they are taken
+    pub snapshot_every: Option<u64>,
+    /// the file snapshots are saved to
+    pub snapshot_file: String,
+    /// the snapshot the simulation resumes from, if it doesn't start afresh
+    pub resume: Option<String>,
+    /// the resident memory (in MiB) above which the simulation saves a snapshot and stops
+    pub max_memory_mb: Option<u64>,
+    /// the running time (in seconds) after which the simulation saves a snapshot and stops
+    pub max_runtime: Option<u64>,
+    /// whether to run simulations indefinitely, hunting for failures
+    pub soak: bool,
+    /// whether to run the simulation twice from the same seed and compare the final states
+    pub dual_run: bool,
+    /// the packaged experiment to run instead of a simulation, if any
+    pub experiment: Option<Experiment>,
+    /// the percentages of joining nodes belonging to the attacker swept by the quorum risk
+    /// experiment
+    pub attacker_rates: Vec<u8>,
+    /// the CSV file the results of the experiment are written to; `<experiment>.csv` if not given
+    pub experiment_file: Option<String>,
+    /// the directory for the log, checkpoint and failures of a soak test
+    pub soak_dir: String,
+    /// the number of iterations between the checks of a soak test
+    pub soak_interval: u64,
+    /// the number of iterations between the checks of the network's invariants, if they are
+    /// checked
+    pub check_frequency: Option<u64>,
+    /// what happens when an invariant doesn't hold
+    pub check_mode: CheckMode,
+    /// a trace file and the prefix of the section whose events should be replayed from it
+    pub replay: Option<(String, Prefix)>,
+    /// the prefix of the only sub-tree of the network that is simulated
+    pub restrict_prefix: Option<Prefix>,
+    /// the behaviour of the rest of the network when only a sub-tree is simulated
+    pub stub: Stub,
+    /// how many churn events happen in an iteration
+    pub churn: ChurnModel,
+    /// the chance (in percent) that a churn storm starts in a calm iteration
+    pub storm_start: f64,
+    /// the chance (in percent) that a churn storm ends in a stormy iteration
+    pub storm_end: f64,
+    /// the number of churn events per iteration during a storm
+    pub storm_rate: usize,
+    /// the length of the daily churn cycle in iterations (no cycle if 0)
+    pub diurnal_period: u64,
+    /// the amplitude (in percentage points) of the sine wave shifting the join and drop
+    /// probabilities over the daily cycle
+    pub diurnal_amplitude: f64,
+    /// join and drop probabilities for consecutive parts of the daily cycle, used instead of
+    /// the sine wave if not empty
+    pub diurnal_table: Vec<(u8, u8)>,
+    /// the distribution of the sections' processing rates
+    pub rate_dist: RateDist,
+    /// how joining nodes choose their section
+    pub join_policy: JoinPolicy,
+    /// the percentage of joining nodes directed according to the join policy; the rest get
+    /// random names
+    pub join_targeting: u8,
+    /// which nodes rejoin the network and how they are treated
+    pub rejoin_policy: RejoinPolicy,
+    /// the uptime (in iterations) that spares a rejoining node from losing age under the
+    /// lenient rejoin policy
+    pub uptime_credit: u64,
+    /// the chance (in percent) that a dropped node comes back after `rejoin_delay` iterations
+    /// with its age halved
+    pub rejoin_prob: f64,
+    /// the number of iterations after which a dropped node comes back, if it does
+    pub rejoin_delay: u64,
+}
+
+/// A fully resolved configuration of a run, which reproduces it exactly when loaded: the
+/// parameters together with the seed of the random number generator
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    pub seed: [u32; 4],
+    pub params: Params,
+}
+
+/// The parameters the command line arguments default to
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            init_age: 1,
+            split_strategy: Strategy::Complete,
+            norejectyoung: false,
+            growth: (90, 7),
+            structure_output_file: None,
+            drop_dist: DropDist::Exponential,
+            debug_window: None,
+            debug_prefix: None,
+            pseudonyms: false,
+            stats_verbosity: StatsVerbosity::Final,
+            tui: false,
+            stats_interval: 10000,
+            infant_quota: None,
+            bootstrap_cache: None,
+            misdirected_joins: 0,
+            key_rotation: None,
+            group_size: GROUP_SIZE,
+            quorum: QUORUM,
+            buffer: BUFFER,
+            elder_min_age: None,
+            elder_fallback: ElderFallback::Quorum,
+            age_on_split: AgeRule::Keep,
+            age_on_merge: AgeRule::Keep,
+            elder_handover: 0,
+            merge_stabilisation: 0,
+            scenario_file: None,
+            example: None,
+            merge_negotiation: false,
+            batch_cascades: false,
+            sign_cost: 20.0,
+            verify_cost: 50.0,
+            message_sizes: None,
+            iteration_seconds: None,
+            max_prefix_len: 64,
+            time_events: false,
+            name_uniformity: false,
+            relocation_fairness: false,
+            elder_relocation: false,
+            ping_pong: None,
+            dead_detection: None,
+            whatif_merge: false,
+            event_budget: None,
+            max_hops: 8,
+            relocation_delay: 0,
+            reorg_relocations: ReorgRelocations::Forward,
+            transit_loss: 0.0,
+            msg_delay_mean: 0.0,
+            msg_loss_prob: 0.0,
+            node_data: 0.0,
+            transfer_bandwidth: 0.0,
+            coalitions: vec![],
+            behaviours: vec![],
+            permanent: 0,
+            observers: 0,
+            equivocation: 0,
+            runs: 1,
+            jobs: 1,
+            fuzz: vec![],
+            sweep: vec![],
+            runs_per_config: 1,
+            sweep_file: "sweep.csv".to_owned(),
+            max_ci_width: 10.0,
+            summary_file: None,
+            baseline_file: None,
+            regression_threshold: 5.0,
+            report_format: ReportFormat::Text,
+            metrics_file: None,
+            relocation_log: None,
+            elder_list: None,
+            history: None,
+            report_file: None,
+            graph_file: None,
+            trace_file: None,
+            record_file: None,
+            replay_log: None,
+            event_socket: None,
+            save_config: None,
+            snapshot_every: None,
+            snapshot_file: "ageing_sim.snapshot".to_owned(),
+            resume: None,
+            max_memory_mb: None,
+            max_runtime: None,
+            soak: false,
+            dual_run: false,
+            experiment: None,
+            attacker_rates: vec![5, 10, 15, 20, 25, 30],
+            experiment_file: None,
+            soak_dir: "soak".to_owned(),
+            soak_interval: 1000,
+            check_frequency: None,
+            check_mode: CheckMode::Abort,
+            replay: None,
+            restrict_prefix: None,
+            stub: Stub::Closed,
+            churn: ChurnModel::Fixed,
+            storm_start: 0.0,
+            storm_end: 10.0,
+            storm_rate: 10,
+            diurnal_period: 0,
+            diurnal_amplitude: 20.0,
+            diurnal_table: vec![],
+            rate_dist: RateDist::Unlimited,
+            join_policy: JoinPolicy::Random,
+            join_targeting: 100,
+            rejoin_policy: RejoinPolicy::Random,
+            uptime_credit: 1000,
+            rejoin_prob: 0.0,
+            rejoin_delay: 100,
+        }
+    }
+}
+
+impl Params {
+    /// Checks the parameters the library can be given directly, without the checks of the
+    /// command line parsing, and returns what is wrong with them, if anything
+    pub fn validate(&self) -> Result<(), String> {
+        let (add, drop) = self.growth;
+        if add >= 100 {
+            return Err(format!("Add probability must be below 100, not {}", add));
+        }
+        if u16::from(add) + u16::from(drop) > 100 {
+            return Err(format!(
+                "Add and drop probabilities must add up to at most 100, not {} + {}",
+                add, drop
+            ));
+        }
+        if self.max_prefix_len > 64 {
+            return Err(format!(
+                "Maximum prefix length can't exceed the 64 bits of a name, not {}",
+                self.max_prefix_len
+            ));
+        }
+        match self.drop_dist {
+            DropDist::ExponentialBase(factor) if factor == 0 || factor > ONE => {
+                return Err(format!(
+                    "Drop model factor must be above 0 and at most the certainty {}, not {}",
+                    ONE, factor
+                ));
+            }
+            DropDist::Table(ref table) => {
+                if table.is_empty() {
+                    return Err("Drop model table must have entries".to_owned());
+                }
+                let valid = table.iter().all(|&(_, percent)| (0.0..=100.0).contains(&percent))
+                    && table.windows(2).all(|pair| pair[0].0 < pair[1].0);
+                if !valid {
+                    return Err(format!(
+                        "Drop model table must have increasing ages and chances between 0 and \
+                         100, not {:?}",
+                        table
+                    ));
+                }
+            }
+            _ => (),
+        }
+        if let Some(&(add, drop)) = self
+            .diurnal_table
+            .iter()
+            .find(|&&(add, drop)| u16::from(add) + u16::from(drop) > 100)
+        {
+            return Err(format!(
+                "Diurnal add and drop probabilities must add up to at most 100, not {} + {}",
+                add, drop
+            ));
+        }
+        if !(0.0..=MAX_MSG_DELAY_MEAN).contains(&self.msg_delay_mean) {
+            return Err(format!(
+                "Mean message delay must be between 0 and {}, not {}",
+                MAX_MSG_DELAY_MEAN, self.msg_delay_mean
+            ));
+        }
+        if self.merge_negotiation && self.batch_cascades {
+            return Err("Negotiated merges can't be batched in cascades".to_owned());
+        }
+        if self.group_size == 0 {
+            return Err("Group size must be at least 1".to_owned());
+        }
+        if self.quorum == 0 || self.quorum > self.group_size {
+            return Err(format!(
+                "Quorum must be between 1 and the group size {}, not {}",
+                self.group_size, self.quorum
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the attacker coalition with the given number (counted from 1)
+    pub fn coalition(&self, number: u8) -> Option<&Coalition> {
+        match number {
+            0 => None,
+            n => self.coalitions.get(n as usize - 1),
+        }
+    }
+
+    /// Returns whether the elders of the given coalition equivocate
+    pub fn equivocates(&self, coalition: u8) -> bool {
+        match self.coalition(coalition) {
+            Some(c) => c.strategy == AttackStrategy::Equivocate,
+            None => false,
+        }
+    }
+
+    /// Returns whether the nodes of the given coalition never leave the network on their own
+    pub fn stays(&self, coalition: u8) -> bool {
+        self.coalition(coalition).is_some_and(|c| c.stays)
+    }
+
+    /// Returns a copy of the parameters with the fuzzed ones drawn at random from their ranges,
+    /// along with the values drawn, or what is wrong with the parameters drawn
+    pub fn jittered(&self) -> Result<(Params, Summary), String> {
+        let values: Vec<_> = self
+            .fuzz
+            .iter()
+            .map(|jitter| {
+                // drawn in steps of a thousandth of the range, to keep the draw free of floats
+                let step = random_range(0, 1001);
+                let mut value = jitter.min + (jitter.max - jitter.min) * f64::from(step) / 1000.0;
+                if jitter.param.is_integer() {
+                    value = value.round();
+                }
+                (jitter.param, value)
+            })
+            .collect();
+        let params = self.with_values(&values)?;
+        let values = values
+            .into_iter()
+            .map(|(param, value)| (param.name(), value))
+            .collect();
+        Ok((params, values))
+    }
+
+    /// Returns a copy of the parameters with the given ones set to the given values, or what is
+    /// wrong with the result. The group size is set first, so that a quorum given along with it
+    /// replaces the default one.
+    pub fn with_values(&self, values: &[(FuzzedParam, f64)]) -> Result<Params, String> {
+        let mut params = self.clone();
+        let (group_sizes, others): (Vec<_>, Vec<_>) = values
+            .iter()
+            .partition(|&&(param, _)| param == FuzzedParam::GroupSize);
+        for &(param, value) in group_sizes.into_iter().chain(others) {
+            param.set(&mut params, value);
+        }
+        // the drop probability gives way if the probabilities add up to more than 100%
+        params.growth.1 = params.growth.1.min(100 - params.growth.0);
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Returns whether the messages between sections can be delayed or lost
+    pub fn delays_messages(&self) -> bool {
+        self.msg_delay_mean > 0.0 || self.msg_loss_prob > 0.0
+    }
+
+    /// Returns the length of the shortest prefix a section can have
+    pub fn min_prefix_len(&self) -> u8 {
+        self.restrict_prefix.map_or(0, |pfx| pfx.len())
+    }
 }
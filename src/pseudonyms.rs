@@ -0,0 +1,47 @@
+//! Short pseudonyms for node names, like `N1723`, that the logs and exports of a run show
+//! instead of the names' hexadecimal digits. A name gets the next number the first time it is
+//! shown, so two runs with the same seed and options number their nodes the same way.
+//!
+//! The pseudonyms of a run live on the thread simulating it; a run simulated on another thread
+//! hands them over with its results, so the exports written afterwards use the same ones.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use network::prefix::Name;
+
+/// The pseudonyms given out so far in a run
+#[derive(Default)]
+pub struct Pseudonyms {
+    numbers: BTreeMap<Name, u64>,
+}
+
+thread_local! {
+    static PSEUDONYMS: RefCell<Option<Pseudonyms>> = const { RefCell::new(None) };
+}
+
+/// Starts giving out pseudonyms on this thread, from `N1`
+pub fn start() {
+    install(Some(Pseudonyms::default()));
+}
+
+/// Takes the pseudonyms given out on this thread so far, which shows names in hexadecimal again
+pub fn take() -> Option<Pseudonyms> {
+    PSEUDONYMS.with(|pseudonyms| pseudonyms.borrow_mut().take())
+}
+
+/// Continues giving out the given pseudonyms on this thread, or shows names in hexadecimal if
+/// there are none
+pub fn install(pseudonyms: Option<Pseudonyms>) {
+    PSEUDONYMS.with(|cell| *cell.borrow_mut() = pseudonyms);
+}
+
+/// Returns the number of the pseudonym of the given name, giving it the next one if it hasn't
+/// got one yet, or `None` if names aren't shown as pseudonyms
+pub fn of(name: Name) -> Option<u64> {
+    PSEUDONYMS.with(|pseudonyms| {
+        let mut pseudonyms = pseudonyms.borrow_mut();
+        let numbers = &mut pseudonyms.as_mut()?.numbers;
+        let next = numbers.len() as u64 + 1;
+        Some(*numbers.entry(name).or_insert(next))
+    })
+}
@@ -1,17 +1,11 @@
-use rand::{self, thread_rng, Rand, Rng, SeedableRng, XorShiftRng};
+use rand::{self, thread_rng, Rand, Rng};
 use rand::distributions::range::SampleRange;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::env;
 
 thread_local! {
-    static SEED: [u32; 4] = match env::var("AGE_SEED") {
-        Ok(value) => {
-            let nums: Vec<u32> = value.split(|c| c == '[' || c == ']' || c == ' ' || c == ',')
-                                      .filter_map(|s| s.parse().ok())
-                                      .collect();
-            assert_eq!(nums.len(), 4, "AGE_SEED {} isn't in the form '[1, 2, 3, 4]'.", value);
-            [nums[0], nums[1], nums[2], nums[3]]
-        }
+    static SEED: Cell<[u32; 4]> = Cell::new(match env::var("AGE_SEED") {
+        Ok(value) => parse_seed(&value).unwrap_or_else(|msg| panic!("{}", msg)),
         Err(_) => {
             let mut rng = thread_rng();
             [rng.next_u32().wrapping_add(rng.next_u32()),
@@ -19,20 +13,151 @@ thread_local! {
              rng.next_u32().wrapping_add(rng.next_u32()),
              rng.next_u32().wrapping_add(rng.next_u32())]
         }
+    });
+
+    static WEAK_RNG: RefCell<XorShift> = RefCell::new(SEED.with(|seed| XorShift::from_seed(seed.get())));
+}
+
+/// The xorshift generator of `rand::XorShiftRng`, producing exactly the same values, but with a
+/// state that can be read, so that a simulation can be saved and resumed mid-run.
+#[derive(Clone)]
+struct XorShift {
+    state: [u32; 4],
+}
+
+impl XorShift {
+    /// Creates a generator in the given state. Panics if the state is all zeros.
+    fn from_seed(seed: [u32; 4]) -> XorShift {
+        assert!(seed.iter().any(|&x| x != 0), "The seed can't be all zeros!");
+        XorShift { state: seed }
+    }
+}
+
+impl Rng for XorShift {
+    fn next_u32(&mut self) -> u32 {
+        let [x, y, z, w] = self.state;
+        let t = x ^ (x << 11);
+        let next = w ^ (w >> 19) ^ (t ^ (t >> 8));
+        self.state = [y, z, w, next];
+        next
+    }
+}
+
+/// The formats in which a seed can be given
+pub const SEED_FORMATS: &[&str] = &[
+    "a list of four 32-bit numbers, e.g. [1, 2, 3, 4], 1,2,3,4 or \"1 2 3 4\"",
+    "32 hex digits, optionally prefixed with 0x, e.g. 0x00000001000000020000000300000004",
+    "16 bytes in base64, e.g. AAAAAQAAAAIAAAADAAAABA==",
+];
+
+/// Parses a seed given in any of the `SEED_FORMATS`. Hex digits and base64 encode the four
+/// numbers in big-endian byte order.
+pub fn parse_seed(text: &str) -> Result<[u32; 4], String> {
+    let text = text.trim();
+    let error = |reason: String| {
+        format!(
+            "Invalid seed \"{}\": {}. A seed can be given as:\n\t{}",
+            text,
+            reason,
+            SEED_FORMATS.join("\n\t")
+        )
+    };
+    if text.starts_with('[') || text.contains(|c: char| c == ',' || c.is_whitespace()) {
+        let nums = text
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<u32>()
+                    .map_err(|_| error(format!("{} isn't a 32-bit number", s)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if nums.len() != 4 {
+            return Err(error(format!("expected 4 numbers, found {}", nums.len())));
+        }
+        return Ok([nums[0], nums[1], nums[2], nums[3]]);
+    }
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    let bytes = if digits.len() == 32 {
+        // `from_str_radix` would also accept a sign in front of the digits
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error("it contains characters other than hex digits".to_owned()));
+        }
+        (0..16)
+            .map(|i| u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).unwrap())
+            .collect()
+    } else if text.len() == 22 || text.len() == 24 {
+        decode_base64(text).ok_or_else(|| error("it isn't valid base64".to_owned()))?
+    } else {
+        return Err(error("it isn't in any of the accepted formats".to_owned()));
     };
+    let mut seed = [0; 4];
+    for (num, chunk) in seed.iter_mut().zip(bytes.chunks(4)) {
+        *num = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    Ok(seed)
+}
 
-    static WEAK_RNG: RefCell<XorShiftRng> = RefCell::new(
-        SEED.with(|seed| {
-            println!("Seed: {:?}", seed);
-            XorShiftRng::from_seed(*seed)
-        })
-    );
+/// Decodes 16 bytes from base64, with or without padding, in either the standard or the URL-safe
+/// alphabet
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    if text.len() != 22 {
+        return None;
+    }
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    let mut bytes = Vec::with_capacity(16);
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | u32::from(value);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// Checks that the seed in `AGE_SEED`, if there is one, is valid
+pub fn check_env_seed() -> Result<(), String> {
+    match env::var("AGE_SEED") {
+        Ok(value) => parse_seed(&value).map(|_| ()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Seeds the thread with the given seed: the seed is returned by `seed` and the thread-local weak
+/// RNG starts afresh from it, independently of the other threads
+pub fn seed_thread(seed: [u32; 4]) {
+    SEED.with(|s| s.set(seed));
+    restore_state(seed);
 }
 
 /// Get the seed used for the random number generator.
 #[allow(unused)]
 pub fn seed() -> [u32; 4] {
-    SEED.with(|seed| *seed)
+    SEED.with(Cell::get)
+}
+
+/// Returns the current state of the thread-local weak RNG, which `restore_state` continues from
+pub fn state() -> [u32; 4] {
+    WEAK_RNG.with(|rng| rng.borrow().state)
+}
+
+/// Puts the thread-local weak RNG in a state returned by `state`
+pub fn restore_state(state: [u32; 4]) {
+    WEAK_RNG.with(|rng| rng.replace(XorShift::from_seed(state)));
 }
 
 /// Random value from the thread-local weak RNG.
@@ -67,3 +192,202 @@ where
 pub fn shuffle<T>(values: &mut [T]) {
     WEAK_RNG.with(|rng| rng.borrow_mut().shuffle(values))
 }
+
+/// Draws a seed for a new generator from the thread-local weak RNG.
+pub fn random_seed() -> [u32; 4] {
+    [random(), random(), random(), random()]
+}
+
+/// Runs `f` with the thread-local weak RNG temporarily replaced by one seeded with `seed`, so that
+/// the random values it draws are reproducible independently of everything else.
+pub fn with_seed<T, F: FnOnce() -> T>(seed: [u32; 4], f: F) -> T {
+    let rng = WEAK_RNG.with(|rng| rng.replace(XorShift::from_seed(seed)));
+    let result = f();
+    WEAK_RNG.with(|weak_rng| {
+        let _ = weak_rng.replace(rng);
+    });
+    result
+}
+
+/// A probability in fixed point, as a fraction of `ONE`. Probabilities are kept away from floating
+/// point, so that a seed produces the same run on every platform and with every compiler.
+pub type Probability = u64;
+
+/// The probability of a certain event.
+pub const ONE: Probability = 1 << 32;
+
+/// Returns `true` with the given probability.
+pub fn gen_bool_with_probability(p: Probability) -> bool {
+    random_range(0, ONE) < p
+}
+
+/// Returns the probability `num / den`.
+pub fn ratio(num: u64, den: u64) -> Probability {
+    assert!(den > 0, "Probability {}/{} has a zero denominator.", num, den);
+    (u128::from(num) * u128::from(ONE) / u128::from(den)) as Probability
+}
+
+/// Converts a percentage given on the command line to a probability. The percentage is rounded
+/// to millionths of a percent and the rest is integer arithmetic, so the result is the same
+/// everywhere and exact for percentages such as 0.1 that binary floating point can't represent.
+pub fn from_percent(percent: f64) -> Probability {
+    ratio((percent.clamp(0.0, 100.0) * 1e6).round() as u64, 100_000_000)
+}
+
+/// Returns the cumulative Poisson distribution with the given mean: the probabilities of at
+/// most 0, 1, 2... events, ending with `ONE`. The mean is rounded to millionths and the rest is
+/// integer arithmetic, so that the result is the same everywhere. The terms are computed relative
+/// to the most likely number of events, so nothing overflows however large the mean is.
+pub fn poisson_cdf(mean: f64) -> Vec<Probability> {
+    // the weights of the numbers of events, as fractions of the weight of the mode in fixed
+    // point; the ones that would be smaller than a unit of `Probability` are left out
+    const MODE: u128 = 1 << 64;
+    const NEGLIGIBLE: u128 = MODE / ONE as u128;
+    let den: u128 = 1_000_000;
+    let num = (mean.max(0.0) * 1e6).round() as u128;
+    let mode = (num / den) as usize;
+    let mut weights = vec![MODE];
+    let mut weight = MODE;
+    let mut k = mode;
+    while k > 0 {
+        weight = weight * k as u128 * den / num;
+        if weight < NEGLIGIBLE {
+            break;
+        }
+        weights.push(weight);
+        k -= 1;
+    }
+    let first = mode + 1 - weights.len();
+    weights.reverse();
+    let mut weight = MODE;
+    let mut k = mode as u128;
+    loop {
+        k += 1;
+        weight = weight * num / (den * k);
+        if weight < NEGLIGIBLE {
+            break;
+        }
+        weights.push(weight);
+    }
+    let total: u128 = weights.iter().sum();
+    let mut cdf = vec![0; first];
+    let mut cumulative = 0;
+    for weight in weights {
+        cumulative += weight;
+        cdf.push((cumulative * u128::from(ONE) / total) as Probability);
+        if cdf[cdf.len() - 1] == ONE {
+            break;
+        }
+    }
+    cdf
+}
+
+/// Draws a value from a cumulative distribution of the values 0, 1, 2... as returned by
+/// `poisson_cdf`
+pub fn random_from_cdf(cdf: &[Probability]) -> usize {
+    let x = random_range(0, ONE);
+    cdf.partition_point(|&p| p <= x).min(cdf.len() - 1)
+}
+
+/// Returns the probability of two independent events both happening.
+pub fn and(p: Probability, q: Probability) -> Probability {
+    ((u128::from(p) * u128::from(q)) >> 32) as Probability
+}
+
+/// Returns the probability of `n` independent events with probability `p` all happening.
+pub fn pow(mut p: Probability, mut n: u32) -> Probability {
+    let mut result = ONE;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = and(result, p);
+        }
+        p = and(p, p);
+        n >>= 1;
+    }
+    result
+}
+
+/// Draws how many times in a row an event with probability `p` happens before it doesn't, from
+/// the geometric distribution with mean `p / (1 - p)`. The bits of the result are independent,
+/// the `k`-th being 1 with probability `p^(2^k) / (1 + p^(2^k))`, so they are drawn one at a
+/// time, with a number of random values that only grows with the logarithm of the mean.
+pub fn geometric(p: Probability) -> u64 {
+    // a certain event would happen forever
+    let mut p = p.min(ONE - 1);
+    let mut result = 0;
+    let mut bit = 0;
+    while p > 0 && bit < 64 {
+        if gen_bool_with_probability(ratio(p, ONE + p)) {
+            result |= 1 << bit;
+        }
+        p = and(p, p);
+        bit += 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_percent_is_exact() {
+        assert_eq!(from_percent(0.0), 0);
+        assert_eq!(from_percent(50.0), ONE / 2);
+        assert_eq!(from_percent(100.0), ONE);
+        assert_eq!(from_percent(0.1), ratio(1, 1000));
+        assert_eq!(from_percent(12.5), ONE / 8);
+        assert_eq!(from_percent(-5.0), 0);
+        assert_eq!(from_percent(150.0), ONE);
+    }
+
+    fn to_f64(p: Probability) -> f64 {
+        p as f64 / ONE as f64
+    }
+
+    #[test]
+    fn geometric_has_the_expected_mean() {
+        assert_eq!(geometric(0), 0);
+        for &mean in &[0.5, 3.0, 1000.0] {
+            let p = from_percent(100.0 * mean / (1.0 + mean));
+            let draws = 20_000;
+            let sum: u64 = with_seed([1, 2, 3, 4], || (0..draws).map(|_| geometric(p)).sum());
+            let sample_mean = sum as f64 / draws as f64;
+            assert!((sample_mean / mean - 1.0).abs() < 0.05, "{} vs {}", sample_mean, mean);
+        }
+        // a certain delay is capped instead of going on forever
+        assert!(geometric(ONE) > 0);
+    }
+
+    #[test]
+    fn poisson_cdf_of_small_means() {
+        assert_eq!(poisson_cdf(0.0), vec![ONE]);
+        let cdf = poisson_cdf(0.5);
+        let mut pmf = (-0.5f64).exp();
+        let mut expected = 0.0;
+        for (k, &p) in cdf.iter().enumerate() {
+            expected += pmf;
+            pmf *= 0.5 / (k + 1) as f64;
+            assert!((to_f64(p) - expected).abs() < 1e-8, "{}: {:?}", k, cdf);
+        }
+        assert_eq!(cdf.last(), Some(&ONE));
+        assert!(cdf.len() < 20);
+    }
+
+    #[test]
+    fn poisson_cdf_of_large_means() {
+        for &mean in &[709.5, 1000.0, 10_000.0] {
+            let cdf = poisson_cdf(mean);
+            assert!(cdf.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert_eq!(cdf.last(), Some(&ONE));
+            // the median of a Poisson distribution is within a third of its mean
+            let median = cdf.iter().position(|&p| p >= ONE / 2).unwrap() as f64;
+            assert!((median - mean).abs() < 1.0, "median {} of mean {}", median, mean);
+            // the most likely number of events has a probability of about 1/sqrt(2 pi mean)
+            let mode = mean as usize;
+            let p_mode = to_f64(cdf[mode] - cdf[mode - 1]);
+            let expected = 1.0 / (2.0 * std::f64::consts::PI * mean).sqrt();
+            assert!((p_mode / expected - 1.0).abs() < 0.01, "{} vs {}", p_mode, expected);
+        }
+    }
+}
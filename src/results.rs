@@ -0,0 +1,854 @@
+//! The results printed at the end of a run
+
+use network::Network;
+use network::attack;
+use network::network::{Observation, Output, Turbulence, SIZE_BRACKET, SPLIT_DEPTH_WINDOW, SPLIT_GAP_BRACKET,
+                       STALENESS_TICKS, TURBULENCE_WINDOW};
+use network::timing::ProcessingTime;
+use network::traffic::Traffic;
+use stats;
+use stats::metrics::{sparkline, DistSummary};
+use params::{KeyRotation, Params, RejoinPolicy, ReorgRelocations};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::ops::AddAssign;
+
+pub fn print_dist<K>(mut dist: BTreeMap<K, usize>)
+where
+    K: Copy + Ord + Display + From<u8> + AddAssign,
+{
+    let mut key = K::from(1);
+    while !dist.is_empty() {
+        let num = dist.remove(&key).unwrap_or(0);
+        println!("{}\t{}", key, num);
+        key += K::from(1);
+    }
+}
+
+/// Prints only the values that occurred, for distributions with large gaps
+pub fn print_sparse_dist<K: Display>(dist: &BTreeMap<K, usize>) {
+    for (key, num) in dist {
+        println!("{}\t{}", key, num);
+    }
+}
+
+/// Prints the percentiles and variance of a distribution, with a sparkline of its shape
+pub fn print_dist_summary<K: Copy + Into<f64>>(title: &str, dist: &BTreeMap<K, usize>) {
+    println!("{}: {}", title, DistSummary::of(dist));
+    println!("  {}", sparkline(dist));
+}
+
+/// The largest number of nodes flagged for relocation ping-pong that are listed
+const PING_PONG_LISTED: usize = 20;
+
+/// Prints how often the same nodes were relocated, and the nodes relocated more than `limit`
+/// times within `window` iterations
+fn print_ping_pong(network: &Network, limit: usize, window: u64) {
+    let output = network.output();
+    println!("\nRelocation ping-pong:");
+    println!("Relocations per node (of the nodes relocated at least once):");
+    print_sparse_dist(&network.relocation_counts());
+    println!("Iterations between consecutive relocations of a node (in brackets from):");
+    print_sparse_dist(&output.relocation_intervals);
+    println!(
+        "Nodes relocated more than {} times within {} iterations: {}",
+        limit,
+        window,
+        output.ping_pongs.len()
+    );
+    if output.ping_pongs.is_empty() {
+        return;
+    }
+    println!("iteration\tnode\tage\trelocations");
+    for ping_pong in output.ping_pongs.iter().take(PING_PONG_LISTED) {
+        println!(
+            "{}\t{:?}\t{}\t{}",
+            ping_pong.iteration,
+            ping_pong.node.name(),
+            ping_pong.node.age(),
+            ping_pong.relocations
+        );
+    }
+    if output.ping_pongs.len() > PING_PONG_LISTED {
+        println!("... and {} more", output.ping_pongs.len() - PING_PONG_LISTED);
+    }
+}
+
+/// Prints the simulated traffic between the network and the sections, with the bandwidth it
+/// needs if the duration of an iteration is given
+fn print_traffic(params: &Params, traffic: &Traffic) {
+    println!(
+        "\nSimulated traffic: {} bytes ({:.0} bytes per iteration on average, {} at the peak)",
+        traffic.total,
+        traffic.mean_per_iteration(),
+        traffic.peak
+    );
+    if let Some(seconds) = params.iteration_seconds {
+        println!(
+            "Estimated bandwidth: {:.0} bytes/s on average, {:.0} bytes/s at the peak",
+            traffic.mean_per_iteration() / seconds,
+            traffic.peak as f64 / seconds
+        );
+    }
+    println!("Bytes by kind of event:");
+    for (kind, bytes) in &traffic.by_kind {
+        println!("\t{}: {}", kind, bytes);
+    }
+    let mut by_section: Vec<_> = traffic.by_section.iter().collect();
+    by_section.sort_by_key(|&(_, &bytes)| Reverse(bytes));
+    println!("Sections with the most traffic (bytes):");
+    for (prefix, bytes) in by_section.into_iter().take(10) {
+        println!("{:?}\t{}", prefix, bytes);
+    }
+}
+
+/// The number of picks an age needs for its chance of relocation to be compared with the design
+const RELOCATION_MIN_PICKS: u64 = 30;
+/// How far the ratio of the chances of relocation of consecutive ages may be from a half before
+/// it is flagged, relative to a half
+const RELOCATION_TOLERANCE: f64 = 0.25;
+
+/// Prints how likely a node of every age was to be picked by a relocation check, compared with
+/// the design, under which every year of age halves the chance. A node is picked from among all
+/// the nodes of its age, so its chance is roughly the chance of its age being picked divided by
+/// the number of nodes it ties with. Deviations that go away once the numbers of tied nodes are
+/// taken into account are attributed to the tie-breaking rule.
+fn print_relocation_fairness(output: &Output) {
+    let tally = &output.relocation_tally;
+    println!("\nRelocation fairness (per node and check; the design halves it with every year):");
+    println!("Age\tExposure\tPicks\tChance\tRatio\tMean tie\tDeviation");
+    let mut previous: Option<(f64, f64)> = None;
+    for (&age, &exposure) in &tally.exposure {
+        let picks = tally.picks.get(&age).cloned().unwrap_or(0);
+        let chance = picks as f64 / exposure as f64;
+        let tie = tally.tied.get(&age).cloned().unwrap_or(0) as f64 / picks.max(1) as f64;
+        let (ratio, deviation) = match previous {
+            Some((prev_chance, prev_tie)) if picks >= RELOCATION_MIN_PICKS && prev_chance > 0.0 => {
+                let ratio = chance / prev_chance;
+                let deviation = if (ratio / 0.5 - 1.0).abs() <= RELOCATION_TOLERANCE {
+                    ""
+                } else if (ratio * tie / prev_tie / 0.5 - 1.0).abs() <= RELOCATION_TOLERANCE {
+                    "tie-breaking"
+                } else {
+                    "not explained by ties"
+                };
+                (format!("{:.2}", ratio), deviation)
+            }
+            _ => ("-".to_owned(), ""),
+        };
+        println!(
+            "{}\t{}\t{}\t{:.2e}\t{}\t{:.1}\t{}",
+            age, exposure, picks, chance, ratio, tie, deviation
+        );
+        previous = if picks >= RELOCATION_MIN_PICKS {
+            Some((chance, tie))
+        } else {
+            None
+        };
+    }
+}
+
+/// Prints the Kolmogorov-Smirnov distances of the sections' node names from the uniform
+/// distribution, and the sections in which they are unlikely to be random (p < 0.01)
+fn print_name_uniformity(network: &Network) {
+    let sections = network.name_uniformity();
+    let outliers: Vec<_> = sections
+        .iter()
+        .filter(|&&(_, size, distance)| size > 0 && distance > 1.63 / (size as f64).sqrt())
+        .collect();
+    let mean = sections.iter().map(|&(_, _, distance)| distance).sum::<f64>()
+        / sections.len() as f64;
+    println!(
+        "\nName uniformity: mean KS distance {:.3}, {} of {} sections clustered",
+        mean,
+        outliers.len(),
+        sections.len()
+    );
+    for &&(prefix, size, distance) in &outliers {
+        println!(
+            "\t{:?}: {} nodes, distance {:.3} (critical {:.3})",
+            prefix,
+            size,
+            distance,
+            1.63 / (size as f64).sqrt()
+        );
+    }
+}
+
+/// Prints how often the sections rotated their keys: overall, and by the current sections
+/// over the lifetimes of all the sections that had their prefixes
+fn print_key_rotations(rule: KeyRotation, network: &Network) {
+    let output = network.output();
+    let rotations: u64 = output.key_rotations.values().sum();
+    let lifetimes: u64 = output.section_lifetimes.values().sum();
+    println!(
+        "\nSection key rotations ({:?} rule): {}, {:.2} per section per 1000 iterations",
+        rule,
+        rotations,
+        1000.0 * rotations as f64 / lifetimes.max(1) as f64
+    );
+    println!("section\trotations\titerations\tper 1000 iterations");
+    for section in network.sections() {
+        let prefix = section.prefix();
+        let rotations = output.key_rotations.get(&prefix).cloned().unwrap_or(0);
+        let lifetime = output.section_lifetimes.get(&prefix).cloned().unwrap_or(0);
+        println!(
+            "{:?}\t{}\t{}\t{:.2}",
+            prefix,
+            rotations,
+            lifetime,
+            1000.0 * rotations as f64 / lifetime.max(1) as f64
+        );
+    }
+}
+
+/// Prints the iterations in which notable things first happened
+fn print_milestones(output: &Output) {
+    let mut milestones = vec![
+        ("first split".to_owned(), output.first_split),
+        ("first merge".to_owned(), output.first_merge),
+        ("first relocation".to_owned(), output.first_relocation),
+        ("first rejection".to_owned(), output.first_rejection),
+        ("first complete section".to_owned(), output.first_complete),
+        ("first section below quorum".to_owned(), output.first_below_quorum),
+    ];
+    milestones.extend(output.first_split_at_depth.iter().map(|(depth, &iteration)| {
+        (format!("first section at prefix length {}", depth), Some(iteration))
+    }));
+    println!("\nMilestones (iteration):");
+    for (milestone, iteration) in milestones {
+        match iteration {
+            Some(iteration) => println!("{}\t{}", milestone, iteration),
+            None => println!("{}\tnever", milestone),
+        }
+    }
+}
+
+/// Prints the merges that would happen if churn stopped, and the resulting sections
+fn print_merge_preview(network: &Network) {
+    let preview = network.preview_merges();
+    if preview.rounds.is_empty() {
+        println!("\nIf churn stopped now, no sections would merge");
+        return;
+    }
+    println!(
+        "\nIf churn stopped now, {} sections would merge into {}:",
+        network.num_sections(),
+        preview.sections.len()
+    );
+    for (i, round) in preview.rounds.iter().enumerate() {
+        println!("\tRound {}:", i + 1);
+        for &(merged_pfx, ref prefixes) in round {
+            println!("\t\t{:?} <- {:?}", merged_pfx, prefixes);
+        }
+    }
+    println!("Resulting sections:");
+    for (prefix, size) in &preview.sections {
+        println!("\t{:?}: {} nodes", prefix, size);
+    }
+}
+
+#[cfg(feature = "crypto")]
+fn print_crypto_stats() {
+    let stats = ::network::chain::crypto_stats();
+    println!(
+        "\nSignatures: {} in {:?} ({:?} each)",
+        stats.signatures,
+        stats.sign_time,
+        stats.sign_time / stats.signatures.max(1) as u32
+    );
+    println!(
+        "Verifications: {} in {:?} ({:?} each)",
+        stats.verifications,
+        stats.verify_time,
+        stats.verify_time / stats.verifications.max(1) as u32
+    );
+}
+
+fn print_processing_time(time: &ProcessingTime) {
+    let total = time.total().as_secs_f64().max(1e-9);
+    println!("\nProcessing time: {:.3}s", total);
+    let groups = [
+        ("Events handled by sections", &time.by_sections),
+        ("Events handled by the network", &time.by_network),
+    ];
+    for &(title, times) in &groups {
+        println!("{}:", title);
+        let mut times: Vec<_> = times.iter().collect();
+        times.sort_by_key(|&(_, &(_, elapsed))| Reverse(elapsed));
+        for (kind, &(count, elapsed)) in times {
+            println!(
+                "\t{}: {} events, {:.3}s ({:.1}%), {:.1}µs each",
+                kind,
+                count,
+                elapsed.as_secs_f64(),
+                100.0 * elapsed.as_secs_f64() / total,
+                1e6 * elapsed.as_secs_f64() / count as f64
+            );
+        }
+    }
+}
+
+/// Formats the turbulence counted over the given number of iterations as rates per 100 iterations
+fn turbulence_rates(turbulence: &Turbulence, iterations: u64) -> String {
+    let rate = |count: u64| 100.0 * count as f64 / iterations.max(1) as f64;
+    format!(
+        "{:.1} relocations, {:.1} rejections, {:.1} splits, {:.1} merges ({} iterations)",
+        rate(turbulence.relocations),
+        rate(turbulence.rejections),
+        rate(turbulence.splits),
+        rate(turbulence.merges),
+        iterations
+    )
+}
+
+/// Prints the results of a single run
+pub fn print_results(params: &Params, network: &Network) {
+    println!("Network state:\n{:?}", network);
+    println!();
+
+    println!("{:?}\n", params.clone());
+    println!(
+        "Number of sections: {} (complete: {})",
+        network.num_sections(),
+        network.complete_sections()
+    );
+    let elders_ever: usize = network.output().promotion_ages.values().sum();
+    println!(
+        "Distinct nodes ever: {} members, {} adults, {} elders",
+        network.output().joined_identities,
+        network.output().adult_identities,
+        elders_ever
+    );
+    let turnover = |ever: f64, now: usize| ever / now.max(1) as f64;
+    println!(
+        "Turnover (distinct nodes ever per current one): members {:.2}, adults {:.2}, elders {:.2}",
+        turnover(network.output().joined_identities as f64, network.num_nodes()),
+        turnover(
+            network.output().adult_identities as f64,
+            network.num_nodes() - network.num_infants()
+        ),
+        turnover(elders_ever as f64, network.num_elders())
+    );
+    println!("Longest section chain: {} blocks", network.max_chain_len());
+    if params.max_prefix_len < 64 {
+        println!(
+            "Splits refused at the maximum prefix length of {}: {}",
+            params.max_prefix_len,
+            network.output().refused_splits
+        );
+    }
+    println!(
+        "Elder relocations: {} (paused iterations: {}, delayed events: {})",
+        network.output().elder_relocations,
+        network.output().paused_iterations,
+        network.output().delayed_events
+    );
+    if let Some(min_age) = params.elder_min_age {
+        println!(
+            "Elders per section, when older than {} with the {:?} fallback (elders, section-iterations):",
+            min_age, params.elder_fallback
+        );
+        print_sparse_dist(&network.output().elder_counts);
+    }
+    if let Some(quota) = params.infant_quota {
+        println!(
+            "New nodes turned away at the infant quota of {}: {} (most infants at once: {})",
+            quota,
+            network.output().quota_rejections,
+            network.output().max_infants
+        );
+        println!("Share of the quota used (%, iterations):");
+        for (usage, iterations) in &network.output().quota_usage {
+            println!("{}\t{}", 10 * usage, iterations);
+        }
+    }
+    if params.bootstrap_cache.is_some() {
+        println!(
+            "Joining nodes with a stale contact: {} of {}",
+            network.output().stale_contacts,
+            network.output().bootstrap_hops.values().sum::<usize>()
+        );
+        println!("Hops from the contacted section to the joined one:");
+        print_sparse_dist(&network.output().bootstrap_hops);
+    }
+    if params.misdirected_joins > 0 {
+        println!(
+            "Joining nodes that contacted the wrong section: {} (still being redirected: {})",
+            network.output().redirect_hops.values().sum::<usize>() + network.num_redirected(),
+            network.num_redirected()
+        );
+        println!("Redirects until they reached the section they joined:");
+        print_sparse_dist(&network.output().redirect_hops);
+        println!("Iterations the redirects added to their joins:");
+        print_sparse_dist(&network.output().redirect_delays);
+    }
+    println!("Chance that a client's cached elders are stale after (iterations):");
+    for (ticks, chance) in network.client_staleness(STALENESS_TICKS) {
+        println!("{}\t{:.1}%", ticks, 100.0 * chance);
+    }
+    if params.storm_start > 0.0 {
+        println!(
+            "Iterations in churn storms: {}",
+            network.output().storm_iterations
+        );
+    }
+    if params.rejoin_prob > 0.0 {
+        let output = network.output();
+        println!(
+            "Joins: {} fresh, {} rejoins, {} of them after an absence of {} iterations with \
+             halved age ({} dropped nodes still away)",
+            output.adds,
+            output.rejoins + output.returns,
+            output.returns,
+            params.rejoin_delay,
+            network.num_departed()
+        );
+    }
+    if params.rejoin_policy != RejoinPolicy::Random {
+        let uptimes = &network.output().rejoin_uptimes;
+        let rejoined: usize = uptimes.values().sum();
+        let total: u64 = uptimes.iter().map(|(&uptime, &n)| uptime * n as u64).sum();
+        println!(
+            "Rejoined nodes: {} (mean uptime credit: {:.1} iterations, kept their age: {})",
+            rejoined,
+            total as f64 / rejoined.max(1) as f64,
+            network.output().lenient_rejoins
+        );
+    }
+    if params.rate_dist.is_limited() {
+        println!(
+            "Events held back by slow sections: {}",
+            network.output().throttled_events
+        );
+        println!("Times events were passed on to other sections before being handled:");
+        print_sparse_dist(&network.output().forwarding_hops);
+    }
+    if !network.output().forwarding_loops.is_empty() {
+        println!(
+            "Events forwarded more than {} times without being handled:",
+            params.max_hops
+        );
+        for &(iteration, event, ref path) in &network.output().forwarding_loops {
+            println!(
+                "\tIteration {}: {:?} went round {:?}",
+                iteration, event, path
+            );
+        }
+    }
+    if params.relocation_delay > 0 {
+        println!(
+            "Relocated nodes lost in transit: {} (at most {} in transit at once)",
+            network.output().transit_losses,
+            network.output().max_in_transit
+        );
+    }
+    if params.delays_messages() {
+        println!(
+            "Requests of joining and relocated nodes lost: {}, arrived after their section split \
+             or merged: {} (messages in flight: {})",
+            network.output().lost_messages,
+            network.output().stale_messages,
+            network.num_in_flight()
+        );
+        println!("Iterations the messages took to reach their sections:");
+        print_sparse_dist(&network.output().message_delays);
+    }
+    if let Some((delay, chance)) = params.dead_detection {
+        println!(
+            "Deaths processed once a quorum of elders noticed them ({} iterations, then {}% per \
+             iteration): {} (at most {} pending at once, {} revealed by a relocation first)",
+            delay,
+            chance,
+            network.output().detection_delays.values().sum::<usize>(),
+            network.output().max_unnoticed_deaths,
+            network.output().revealed_deaths
+        );
+        println!("Iterations until a death was processed:");
+        print_sparse_dist(&network.output().detection_delays);
+    }
+    if network.output().redeliveries > 0 {
+        println!(
+            "Joining nodes matching no section, delivered again: {}",
+            network.output().redeliveries
+        );
+    }
+    if network.output().reorg_relocations > 0 || network.output().reorg_destinations > 0 {
+        println!(
+            "Relocations decided on by splitting or merging sections ({:?}): {}, sent to \
+             splitting or merging sections: {}",
+            params.reorg_relocations,
+            network.output().reorg_relocations,
+            network.output().reorg_destinations
+        );
+        if params.reorg_relocations == ReorgRelocations::Defer {
+            println!(
+                "Iterations relocations were deferred for ({} still deferred):",
+                network.num_deferred()
+            );
+            print_sparse_dist(&network.output().deferral_delays);
+        }
+    }
+    if network.output().rerouted_events > 0 {
+        println!(
+            "Events rerouted from split or merged sections: {}",
+            network.output().rerouted_events
+        );
+    }
+    if params.node_data > 0.0 {
+        println!(
+            "Data transferred with relocated nodes: {:.1} MB",
+            network.output().data_transferred
+        );
+        if params.transfer_bandwidth > 0.0 {
+            println!("Iterations relocated nodes waited for their data:");
+            print_sparse_dist(&network.output().transfer_waits);
+        }
+    }
+    if let Some(prefix) = params.restrict_prefix {
+        println!(
+            "Relocations out of sub-tree {:?}: {}",
+            prefix,
+            network.output().stub_relocations
+        );
+    }
+
+    if params.relocation_fairness {
+        print_relocation_fairness(network.output());
+    }
+    if let Some((limit, window)) = params.ping_pong {
+        print_ping_pong(network, limit, window);
+    }
+    if params.name_uniformity {
+        print_name_uniformity(network);
+    }
+    if params.whatif_merge {
+        print_merge_preview(network);
+    }
+
+    let age_dist = network.age_distribution();
+    let fit = stats::fit::geometric_fit(&age_dist);
+    println!();
+    print_dist_summary("Ages", &age_dist);
+    print_dist_summary("Section sizes", &network.size_distribution());
+    print_dist_summary("Prefix lengths", &network.prefix_len_distribution());
+    println!("\nAge distribution:");
+    print_dist(age_dist);
+    if let Some(fit) = fit {
+        println!(
+            "Fit to halving counts from age {}: chi-square {:.1} with {} degrees of freedom \
+             (critical value {:.1}, {}), KS distance {:.3}, best fitting ratio {:.3}",
+            fit.start,
+            fit.chi_square,
+            fit.degrees_of_freedom,
+            fit.critical,
+            if fit.rejected() { "rejected" } else { "not rejected" },
+            fit.ks,
+            fit.ratio
+        );
+    }
+
+    println!("\nAge at first promotion to elder:");
+    print_dist(network.output().promotion_ages.clone());
+    println!("\nMean age at first promotion by section size:");
+    for (size, dist) in &network.output().promotion_ages_by_size {
+        println!(
+            "{}-{}\t{:.2}\t({} promotions)",
+            size,
+            size + SIZE_BRACKET - 1,
+            stats::dist_mean(dist),
+            dist.values().sum::<usize>()
+        );
+    }
+
+    println!("\nRejections distribution by age:");
+    print_dist(network.output().rejected_ages.clone());
+    let mut by_section: Vec<_> = network.output().rejections_by_section.iter().collect();
+    by_section.sort_by_key(|&(_, &count)| Reverse(count));
+    println!("\nSections rejecting the most nodes:");
+    for (prefix, count) in by_section.into_iter().take(10) {
+        println!("{:?}\t{}", prefix, count);
+    }
+
+    let mut by_section: Vec<_> = network.output().below_quorum.iter().collect();
+    by_section.sort_by_key(|&(_, &count)| Reverse(count));
+    println!(
+        "\nIterations sections spent with fewer than a quorum of elders present: {}",
+        by_section.iter().map(|&(_, &count)| count).sum::<u64>()
+    );
+    println!("Sections longest below a quorum of elders:");
+    for (prefix, count) in by_section.into_iter().take(10) {
+        println!("{:?}\t{}", prefix, count);
+    }
+
+    if !network.output().transitions.is_empty() {
+        println!(
+            "\nParameter changes, with the relocations, rejections, splits and merges per 100 \
+             iterations in the {} iterations before and after:",
+            TURBULENCE_WINDOW
+        );
+        for transition in &network.output().transitions {
+            println!(
+                "Iteration {}: {}\n  before: {}\n  after:  {}",
+                transition.iteration,
+                transition.change,
+                turbulence_rates(&transition.before, transition.before_iterations),
+                turbulence_rates(&transition.after, transition.after_iterations)
+            );
+        }
+    }
+
+    println!("\nChurn by section:");
+    println!("prefix\tjoins\tdrops\treloc in\treloc out");
+    for (prefix, churn) in &network.output().section_churn {
+        println!(
+            "{:?}\t{}\t{}\t{}\t{}",
+            prefix, churn.joins, churn.drops, churn.relocations_in, churn.relocations_out
+        );
+    }
+
+    let drop_dist = &network.output().drops_dist;
+    println!("\nDrops distribution by age:");
+    print_dist(drop_dist.clone());
+
+    println!(
+        "\nSplits per {} iterations (iteration, splits, mean and max prefix length):",
+        SPLIT_DEPTH_WINDOW
+    );
+    for (iteration, depths) in &network.output().split_depths {
+        println!(
+            "{}\t{}\t{:.2}\t{}",
+            iteration,
+            depths.splits,
+            depths.mean_depth(),
+            depths.max_depth
+        );
+    }
+    println!(
+        "Difference between the adult counts of split sections, by {} (all splits, re-merged):",
+        SPLIT_GAP_BRACKET
+    );
+    let output = network.output();
+    for (gap, splits) in &output.split_adult_gaps {
+        let remerged = output.remerged_split_gaps.get(gap).cloned().unwrap_or(0);
+        println!("{}\t{}\t{}", gap, splits, remerged);
+    }
+    print_milestones(network.output());
+    if let Some(rule) = params.key_rotation {
+        print_key_rotations(rule, network);
+    }
+
+    let ((stretch, since), sections) = network.stable_stretches();
+    println!(
+        "\nLongest stretch without splits or merges: {} iterations, from iteration {}",
+        stretch, since
+    );
+    let mut sections: Vec<_> = sections.into_iter().collect();
+    sections.sort_by_key(|&(pfx, stretch)| (Reverse(stretch), pfx));
+    println!("Sections with the longest stretches without splits or merges:");
+    for (pfx, stretch) in sections.into_iter().take(10) {
+        println!("{:?}\t{}", pfx, stretch);
+    }
+
+    let signing = &network.output().signing;
+    let (sign_time, verify_time) = signing.cpu_time(params.sign_cost, params.verify_cost);
+    let iterations = signing.per_iteration.values().sum::<usize>().max(1) as f64;
+    println!("\nSection-signed messages: {}", signing.total_messages());
+    for (kind, count) in &signing.messages {
+        println!("\t{:?}: {}", kind, count);
+    }
+    println!(
+        "Signatures: {}, verifications: {}",
+        signing.signatures, signing.verifications
+    );
+    println!(
+        "Estimated CPU time: {:.1}s signing, {:.1}s verifying ({:.3}ms per iteration)",
+        sign_time,
+        verify_time,
+        1000.0 * (sign_time + verify_time) / iterations
+    );
+    println!("Signed messages per iteration:");
+    print_sparse_dist(&signing.per_iteration);
+
+    if params.message_sizes.is_some() {
+        print_traffic(params, &network.output().traffic);
+    }
+
+    if params.time_events {
+        print_processing_time(&network.output().processing_time);
+    }
+
+    println!("\nMerge cascade depths:");
+    print_dist(network.output().merge_cascades.clone());
+    println!("Elders promoted by a single merge:");
+    print_sparse_dist(&network.output().merge_promotions);
+    println!("Elders demoted by a single merge:");
+    print_sparse_dist(&network.output().merge_demotions);
+    if params.merge_stabilisation > 0 {
+        println!(
+            "Iterations merged sections were paused to stabilise: {}",
+            network.output().stabilisation_iterations
+        );
+    }
+    println!(
+        "Merge races: {} requests absorbed by pending merges, {} pending merges superseded",
+        network.output().absorbed_merges,
+        network.output().superseded_merges
+    );
+
+    if params.merge_negotiation {
+        println!(
+            "\nMerge negotiation rounds (conflicting proposals: {}):",
+            network.output().merge_conflicts
+        );
+        print_dist(network.output().merge_rounds.clone());
+    }
+
+    if params.equivocation > 0 {
+        let detected: usize = network.output().equivocation_detection.values().sum();
+        println!(
+            "\nEquivocations: {} (detected: {})",
+            network.output().equivocations,
+            detected
+        );
+        println!("Blocks until detection:");
+        print_sparse_dist(&network.output().equivocation_detection);
+    }
+
+    if !params.coalitions.is_empty() {
+        println!("\nAttacker coalitions:");
+        println!(
+            "coalition\tjoins\tjoin attempts\tequivocations\tpeak elder share\t\
+             compromised iterations\tfirst compromised"
+        );
+        for (i, (coalition, stats)) in params
+            .coalitions
+            .iter()
+            .zip(&network.output().coalitions)
+            .enumerate()
+        {
+            println!(
+                "{} ({}% {:?})\t{}\t{}\t{}\t{:.1}% in {:?} at {}\t{}\t{}",
+                i + 1,
+                coalition.fraction,
+                coalition.strategy,
+                stats.joins,
+                stats.join_attempts,
+                stats.equivocations,
+                100.0 * stats.peak_elder_share,
+                stats.peak_section,
+                stats.peak_iteration,
+                stats.compromised_iterations,
+                stats
+                    .first_compromised
+                    .map_or_else(|| "never".to_owned(), |i| i.to_string())
+            );
+        }
+        let shares = attack::elder_shares(network.sections());
+        println!("\nAttacker elders by section:");
+        if shares.is_empty() {
+            println!("none");
+        } else {
+            println!("section\telders\tattacker elders\tshare");
+        }
+        for share in shares {
+            println!(
+                "{:?}\t{}\t{}\t{:.1}%",
+                share.prefix,
+                share.elders,
+                share.attackers,
+                100.0 * share.share()
+            );
+        }
+    }
+
+    if !params.behaviours.is_empty() {
+        println!("\nNode behaviours:");
+        println!("behaviour\tjoins\tdrops\tevictions\trejoins");
+        for (i, (behaviour, stats)) in params
+            .behaviours
+            .iter()
+            .zip(&network.output().behaviours)
+            .enumerate()
+        {
+            println!(
+                "{} ({}% {:?}, {}%/iteration)\t{}\t{}\t{}\t{}",
+                i + 1,
+                behaviour.fraction,
+                behaviour.kind,
+                behaviour.rate,
+                stats.joins,
+                stats.drops,
+                stats.evictions,
+                stats.rejoins
+            );
+        }
+    }
+
+    if let Some((share, prefix, iteration)) = network.output().worst_malicious_elders {
+        println!(
+            "\nElder timeline of {:?}, which had the most malicious elders ({:.1}% in iteration \
+             {}):",
+            prefix,
+            100.0 * share,
+            iteration
+        );
+        println!("(malicious elders are marked with * followed by their coalition)");
+        for &(iteration, ref elders) in &network.output().elder_timelines[&prefix] {
+            let elders: Vec<_> = elders
+                .iter()
+                .map(|elder| {
+                    if elder.is_malicious() {
+                        format!("{:?}*{}", elder, elder.coalition())
+                    } else {
+                        format!("{:?}", elder)
+                    }
+                })
+                .collect();
+            println!("{}\t{}", iteration, elders.join(" "));
+        }
+    }
+
+    for log in &network.output().observer_logs {
+        println!("\nLog of observer {:?}:", log.name);
+        for &(iteration, ref observation) in &log.entries {
+            match *observation {
+                Observation::Section(prefix) => println!("{}\tin section {:?}", iteration, prefix),
+                Observation::Event(event, ref response) => {
+                    println!("{}\t{:?} -> {:?}", iteration, event, response)
+                }
+                Observation::Elders(ref elders) => println!("{}\telders: {:?}", iteration, elders),
+            }
+        }
+    }
+
+    if let Some(ref drain) = network.output().drain {
+        println!(
+            "\nDrain from iteration {}, with {} sections ({} complete):",
+            drain.start, drain.sections, drain.complete_sections
+        );
+        match drain.no_complete_sections {
+            Some(it) => println!("Complete sections lasted {} iterations", it + 1 - drain.start),
+            None => println!("Complete sections remained"),
+        }
+        match drain.single_section {
+            Some(it) => println!("Back to a single section after {} iterations", it + 1 - drain.start),
+            None => println!("Never back to a single section"),
+        }
+        println!("Merges in order:");
+        for &(iteration, prefix) in &drain.merges {
+            println!("{}\t{:?}", iteration, prefix);
+        }
+    }
+
+    if params.permanent > 0 {
+        println!("\nShare of elder slots occupied by permanent nodes:");
+        for &(iteration, share) in &network.output().permanent_elders {
+            println!("{}\t{:.1}%", iteration, share);
+        }
+    }
+
+    #[cfg(feature = "crypto")]
+    print_crypto_stats();
+}
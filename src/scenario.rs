@@ -0,0 +1,571 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+use network::Network;
+use network::prefix::Prefix;
+use network::section::Section;
+use examples;
+use params::{FuzzedParam, Params};
+
+/// A quantity describing the state of the network that a scenario can make assertions about
+#[derive(Clone, Copy, Debug)]
+pub enum Metric {
+    Nodes,
+    Sections,
+    Complete,
+    Relocations,
+    Rejections,
+    Churn,
+    MinPrefixLen,
+    MaxPrefixLen,
+    BelowQuorum,
+    MergeRaces,
+    OverlappingMerges,
+}
+
+impl FromStr for Metric {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "nodes" => Ok(Metric::Nodes),
+            "sections" => Ok(Metric::Sections),
+            "complete" => Ok(Metric::Complete),
+            "relocations" => Ok(Metric::Relocations),
+            "rejections" => Ok(Metric::Rejections),
+            "churn" => Ok(Metric::Churn),
+            "min_prefix_len" => Ok(Metric::MinPrefixLen),
+            "max_prefix_len" => Ok(Metric::MaxPrefixLen),
+            "below_quorum" => Ok(Metric::BelowQuorum),
+            "merge_races" => Ok(Metric::MergeRaces),
+            "overlapping_merges" => Ok(Metric::OverlappingMerges),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Metric {
+    /// Returns the current value of the metric in the network
+    pub fn value(&self, network: &Network) -> u64 {
+        match *self {
+            Metric::Nodes => network.num_nodes() as u64,
+            Metric::Sections => network.num_sections() as u64,
+            Metric::Complete => network.complete_sections() as u64,
+            Metric::Relocations => network.output().relocations,
+            Metric::Rejections => network.output().rejections,
+            Metric::Churn => network.output().churn,
+            Metric::MinPrefixLen => network.min_prefix_len() as u64,
+            Metric::MaxPrefixLen => network.max_prefix_len() as u64,
+            Metric::BelowQuorum => network.sections_below_quorum() as u64,
+            Metric::MergeRaces => {
+                network.output().absorbed_merges + network.output().superseded_merges
+            }
+            Metric::OverlappingMerges => network.overlapping_merges() as u64,
+        }
+    }
+}
+
+/// A comparison operator used in assertions
+#[derive(Clone, Copy, Debug)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl FromStr for Comparison {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessOrEqual),
+            ">" => Ok(Comparison::Greater),
+            ">=" => Ok(Comparison::GreaterOrEqual),
+            "==" => Ok(Comparison::Equal),
+            "!=" => Ok(Comparison::NotEqual),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Comparison {
+    /// Returns whether `lhs` compares to `rhs` as required
+    pub fn holds(&self, lhs: u64, rhs: u64) -> bool {
+        match *self {
+            Comparison::Less => lhs < rhs,
+            Comparison::LessOrEqual => lhs <= rhs,
+            Comparison::Greater => lhs > rhs,
+            Comparison::GreaterOrEqual => lhs >= rhs,
+            Comparison::Equal => lhs == rhs,
+            Comparison::NotEqual => lhs != rhs,
+        }
+    }
+}
+
+/// A condition a single section can meet, for assertions about how long sections go on
+/// meeting it
+#[derive(Clone, Copy, Debug)]
+pub enum SectionCondition {
+    /// the section has fewer than a quorum of elders
+    BelowQuorum,
+    /// the number of the section's nodes compares to a value as given
+    Nodes(Comparison, u64),
+    /// the number of the section's elders compares to a value as given
+    Elders(Comparison, u64),
+}
+
+impl SectionCondition {
+    /// Parses a condition: `below quorum elders`, or `nodes <op> <value>` or
+    /// `elders <op> <value>`
+    fn parse(line: usize, words: &[&str]) -> Result<SectionCondition, String> {
+        let count = |comparison: &str, value: &str| -> Result<(Comparison, u64), String> {
+            let comparison = comparison
+                .parse()
+                .map_err(|_| format!("line {}: unknown comparison \"{}\"", line, comparison))?;
+            let value = value
+                .parse()
+                .map_err(|_| format!("line {}: \"{}\" is not a number", line, value))?;
+            Ok((comparison, value))
+        };
+        match words {
+            ["below", "quorum", "elders"] | ["below_quorum"] => Ok(SectionCondition::BelowQuorum),
+            ["nodes", comparison, value] => {
+                let (comparison, value) = count(comparison, value)?;
+                Ok(SectionCondition::Nodes(comparison, value))
+            }
+            ["elders", comparison, value] => {
+                let (comparison, value) = count(comparison, value)?;
+                Ok(SectionCondition::Elders(comparison, value))
+            }
+            _ => Err(format!(
+                "line {}: expected a section condition: \"below quorum elders\", \
+                 \"nodes <op> <value>\" or \"elders <op> <value>\"",
+                line
+            )),
+        }
+    }
+
+    /// Returns whether the section meets the condition
+    fn holds(&self, section: &Section, params: &Params) -> bool {
+        match *self {
+            SectionCondition::BelowQuorum => section.elder_count() < params.quorum,
+            SectionCondition::Nodes(comparison, value) => {
+                comparison.holds(section.len() as u64, value)
+            }
+            SectionCondition::Elders(comparison, value) => {
+                comparison.holds(section.elder_count() as u64, value)
+            }
+        }
+    }
+}
+
+/// What an assertion requires of the network
+#[derive(Clone, Copy, Debug)]
+enum Requirement {
+    /// the metric compares to the value as given
+    Metric(Metric, Comparison, u64),
+    /// no section meets the condition for more than the given number of iterations in a row
+    NoSectionFor(SectionCondition, u64),
+}
+
+/// A single assertion from a scenario file.
+/// If `iteration` is set, it is only checked after that iteration, otherwise after every one.
+/// The iterations in a row for which sections meet the condition of a `for more than`
+/// assertion are counted after every iteration, though. A section that splits or merges starts
+/// a new count under its new prefix.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    iteration: Option<u64>,
+    requirement: Requirement,
+    /// the iteration from which every section has been meeting the condition of a `for more
+    /// than` assertion
+    streaks: BTreeMap<Prefix, u64>,
+    /// the line the assertion was parsed from, for error messages
+    line: usize,
+    text: String,
+}
+
+impl Assertion {
+    /// Parses an assertion of the form `[at <iteration>] assert <metric> <op> <value>` or
+    /// `[at <iteration>] assert no section <condition> for more than <count> iterations`
+    fn parse(line: usize, text: &str) -> Result<Assertion, String> {
+        let words: Vec<_> = text.split_whitespace().collect();
+        let (iteration, words) = if words.first() == Some(&"at") {
+            let iteration = words
+                .get(1)
+                .and_then(|w| w.parse().ok())
+                .ok_or_else(|| format!("line {}: expected an iteration after \"at\"", line))?;
+            (Some(iteration), &words[2..])
+        } else {
+            (None, &words[..])
+        };
+        let requirement = if words.get(1..3) == Some(&["no", "section"]) {
+            Self::parse_duration(line, &words[3..])?
+        } else {
+            Self::parse_metric(line, words)?
+        };
+        Ok(Assertion {
+            iteration,
+            requirement,
+            streaks: BTreeMap::new(),
+            line,
+            text: text.to_owned(),
+        })
+    }
+
+    /// Parses the words of an assertion about a metric, from `assert` on
+    fn parse_metric(line: usize, words: &[&str]) -> Result<Requirement, String> {
+        if words.len() != 4 || words[0] != "assert" {
+            return Err(format!(
+                "line {}: expected \"[at <iteration>] assert <metric> <op> <value>\"",
+                line
+            ));
+        }
+        let metric = words[1]
+            .parse()
+            .map_err(|_| format!("line {}: unknown metric \"{}\"", line, words[1]))?;
+        let comparison = words[2]
+            .parse()
+            .map_err(|_| format!("line {}: unknown comparison \"{}\"", line, words[2]))?;
+        let value = words[3]
+            .parse()
+            .map_err(|_| format!("line {}: \"{}\" is not a number", line, words[3]))?;
+        Ok(Requirement::Metric(metric, comparison, value))
+    }
+
+    /// Parses the words of an assertion about how long sections meet a condition, following
+    /// `assert no section`: `<condition> for more than <count> iterations`
+    fn parse_duration(line: usize, words: &[&str]) -> Result<Requirement, String> {
+        let expected = || {
+            format!(
+                "line {}: expected \"[at <iteration>] assert no section <condition> \
+                 for more than <count> iterations\"",
+                line
+            )
+        };
+        let split = words
+            .iter()
+            .position(|&word| word == "for")
+            .ok_or_else(expected)?;
+        let count = match words[split..] {
+            ["for", "more", "than", count, unit] if unit == "iterations" || unit == "ticks" => count
+                .parse()
+                .map_err(|_| format!("line {}: \"{}\" is not a number", line, count))?,
+            _ => return Err(expected()),
+        };
+        let condition = SectionCondition::parse(line, &words[..split])?;
+        Ok(Requirement::NoSectionFor(condition, count))
+    }
+
+    /// Checks the assertion against the network after the given iteration
+    fn check(&mut self, network: &Network, iteration: u64) -> Result<(), String> {
+        if let Requirement::NoSectionFor(condition, _) = self.requirement {
+            let meeting: BTreeSet<_> = network
+                .sections()
+                .filter(|section| condition.holds(section, network.params_of(&section.prefix())))
+                .map(Section::prefix)
+                .collect();
+            self.streaks.retain(|prefix, _| meeting.contains(prefix));
+            for prefix in meeting {
+                let _ = self.streaks.entry(prefix).or_insert(iteration);
+            }
+        }
+        match self.iteration {
+            Some(it) if it != iteration => return Ok(()),
+            _ => (),
+        }
+        let failure = match self.requirement {
+            Requirement::Metric(metric, comparison, value) => {
+                let actual = metric.value(network);
+                if comparison.holds(actual, value) {
+                    return Ok(());
+                }
+                format!("{:?} is {}", metric, actual)
+            }
+            Requirement::NoSectionFor(condition, count) => {
+                let longest = self.streaks
+                    .iter()
+                    .map(|(prefix, &since)| (iteration + 1 - since, *prefix))
+                    .max();
+                match longest {
+                    Some((length, prefix)) if length > count => format!(
+                        "{:?} has met {:?} for {} iterations",
+                        prefix, condition, length
+                    ),
+                    _ => return Ok(()),
+                }
+            }
+        };
+        Err(format!(
+            "Assertion failed after iteration {} (line {}: \"{}\"): {}",
+            iteration, self.line, self.text, failure
+        ))
+    }
+}
+
+/// A scripted change to the network, made at the start of an iteration alongside the random
+/// churn
+#[derive(Clone, Copy, Debug)]
+pub enum Action {
+    /// the given number of nodes join
+    Add(u64),
+    /// the given percentage of the nodes whose names match the prefix leave
+    Drop(u8, Prefix),
+}
+
+impl Action {
+    /// Parses the words of an action following `at <iteration>`: `add <count>` or
+    /// `drop <percent>% [in <prefix>]`
+    fn parse(line: usize, words: &[&str]) -> Result<Action, String> {
+        let number = |word: &str| {
+            word.trim_end_matches('%')
+                .parse()
+                .map_err(|_| format!("line {}: \"{}\" is not a number", line, word))
+        };
+        match words {
+            ["add", count] => Ok(Action::Add(number(count)?)),
+            ["drop", percent] | ["drop", percent, "in", _] => {
+                let percent = number(percent)?;
+                if percent > 100 {
+                    return Err(format!("line {}: can't drop more than 100% of nodes", line));
+                }
+                let prefix = match words.get(3) {
+                    Some(prefix) => Prefix::from_str(prefix)
+                        .ok_or_else(|| format!("line {}: invalid prefix \"{}\"", line, prefix))?,
+                    None => Prefix::empty(),
+                };
+                Ok(Action::Drop(percent as u8, prefix))
+            }
+            _ => Err(format!(
+                "line {}: expected \"at <iteration> add <count>\" or \
+                 \"at <iteration> drop <percent>% [in <prefix>]\"",
+                line
+            )),
+        }
+    }
+
+    /// Makes the change to the network
+    fn apply(&self, network: &mut Network) {
+        match *self {
+            Action::Add(count) => {
+                for _ in 0..count {
+                    network.add_random_node();
+                }
+            }
+            Action::Drop(percent, prefix) => network.drop_share(percent, prefix),
+        }
+    }
+}
+
+/// A scripted change to a parameter, rolled out to the sections matching the prefix
+#[derive(Clone, Copy, Debug)]
+pub struct ParamChange {
+    param: FuzzedParam,
+    value: f64,
+    prefix: Prefix,
+}
+
+impl ParamChange {
+    /// Parses the words of a change following `at <iteration>`: `set <name>=<value>
+    /// [in <prefix>]`
+    fn parse(line: usize, words: &[&str]) -> Result<ParamChange, String> {
+        let (setting, prefix) = match words {
+            ["set", setting] => (setting, None),
+            ["set", setting, "in", prefix] => (setting, Some(prefix)),
+            _ => {
+                return Err(format!(
+                    "line {}: expected \"at <iteration> set <name>=<value> [in <prefix>]\"",
+                    line
+                ))
+            }
+        };
+        let mut parts = setting.splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        let param: FuzzedParam = name
+            .parse()
+            .map_err(|_| format!("line {}: unknown parameter \"{}\"", line, name))?;
+        if param.drives_churn() {
+            return Err(format!(
+                "line {}: {} belongs to the churn generator and can't change mid-run",
+                line, name
+            ));
+        }
+        let value = parts.next().unwrap_or("");
+        let value: f64 = value
+            .parse()
+            .map_err(|_| format!("line {}: \"{}\" is not a number", line, value))?;
+        if value < 0.0 || (param.is_integer() && value.fract() != 0.0) {
+            return Err(format!("line {}: invalid value {} for {}", line, value, name));
+        }
+        if param.is_percentage() && value > 100.0 {
+            return Err(format!("line {}: percentage must be between 0 and 100", line));
+        }
+        if value > param.max_value() {
+            return Err(format!(
+                "line {}: {} can't exceed {}, not {}",
+                line,
+                name,
+                param.max_value(),
+                value
+            ));
+        }
+        let prefix = match prefix {
+            Some(prefix) if !param.is_per_section() => {
+                return Err(format!(
+                    "line {}: {} can only change for the whole network, not in {}",
+                    line, name, prefix
+                ))
+            }
+            Some(prefix) => Prefix::from_str(prefix)
+                .ok_or_else(|| format!("line {}: invalid prefix \"{}\"", line, prefix))?,
+            None => Prefix::empty(),
+        };
+        Ok(ParamChange {
+            param,
+            value,
+            prefix,
+        })
+    }
+}
+
+/// A scenario read from a file: a list of statements, one per line.
+/// Empty lines and lines starting with `#` are ignored. Besides assertions, a scenario can
+/// contain:
+/// - `at <iteration> drain`, after which no more nodes join the network,
+/// - `at <iteration> add <count>` and `at <iteration> drop <percent>% [in <prefix>]`, which add
+///   nodes and drop a share of the nodes (matching the prefix) in that iteration,
+/// - `between <start> <end> no joins`, which stops the random joins from iteration `start` until
+///   before `end`,
+/// - `at <iteration> set <name>=<value> [in <prefix>]`, which changes one of the parameters of
+///   `--fuzz-params` from that iteration on, modelling an upgrade. The parameters of the churn
+///   generator can't change; `equivocation`, `elder_min_age`, `group_size`, `quorum` and
+///   `buffer` can be rolled out to the sections matching a prefix only. A section merged from
+///   ones that follow different parameters follows the ones of its own prefix. A change that
+///   would leave the parameters invalid, such as a quorum above the group size, fails the run.
+#[derive(Clone, Debug, Default)]
+pub struct Scenario {
+    assertions: Vec<Assertion>,
+    /// the iteration after which the network drains
+    drain: Option<u64>,
+    /// the scripted changes, with the iterations in which they are made
+    actions: Vec<(u64, Action)>,
+    /// the ranges of iterations (start inclusive, end exclusive) without random joins
+    no_joins: Vec<(u64, u64)>,
+    /// the scripted parameter changes, with the iterations in which they are made
+    param_changes: Vec<(u64, ParamChange)>,
+}
+
+impl Scenario {
+    /// Reads and parses a scenario file
+    pub fn from_file(path: &str) -> Scenario {
+        let mut text = String::new();
+        let _ = File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .unwrap_or_else(|_| panic!("Couldn't read scenario file {}!", path));
+        match Self::parse(&text) {
+            Ok(scenario) => scenario,
+            Err(msg) => panic!("Invalid scenario file {}: {}", path, msg),
+        }
+    }
+
+    /// Loads the scenario file given in the parameters, or the scenario of the chosen example
+    pub fn from_params(params: &Params) -> Scenario {
+        if let Some(ref file) = params.scenario_file {
+            return Scenario::from_file(file);
+        }
+        match params.example {
+            Some(ref name) => match Self::parse(examples::find(name).scenario) {
+                Ok(scenario) => scenario,
+                Err(msg) => panic!("Invalid scenario of example {}: {}", name, msg),
+            },
+            None => Default::default(),
+        }
+    }
+
+    /// Parses the contents of a scenario file
+    pub fn parse(text: &str) -> Result<Scenario, String> {
+        let mut scenario = Scenario::default();
+        for (i, line) in text.lines().enumerate() {
+            let line_text = line.trim();
+            if line_text.is_empty() || line_text.starts_with('#') {
+                continue;
+            }
+            let words: Vec<_> = line_text.split_whitespace().collect();
+            if words.len() == 3 && words[0] == "at" && words[2] == "drain" {
+                let iteration = words[1]
+                    .parse()
+                    .map_err(|_| format!("line {}: \"{}\" is not a number", i + 1, words[1]))?;
+                scenario.drain = Some(iteration);
+                continue;
+            }
+            let number = |word: &str| {
+                word.parse::<u64>()
+                    .map_err(|_| format!("line {}: \"{}\" is not a number", i + 1, word))
+            };
+            if words[0] == "between" {
+                if words.len() != 5 || words[3..] != ["no", "joins"] {
+                    return Err(format!(
+                        "line {}: expected \"between <start> <end> no joins\"",
+                        i + 1
+                    ));
+                }
+                scenario.no_joins.push((number(words[1])?, number(words[2])?));
+                continue;
+            }
+            if words.len() > 2 && words[0] == "at" && words[2] == "set" {
+                let change = ParamChange::parse(i + 1, &words[2..])?;
+                scenario.param_changes.push((number(words[1])?, change));
+                continue;
+            }
+            if words.len() > 2 && words[0] == "at" && words[2] != "assert" {
+                let action = Action::parse(i + 1, &words[2..])?;
+                scenario.actions.push((number(words[1])?, action));
+                continue;
+            }
+            scenario
+                .assertions
+                .push(Assertion::parse(i + 1, line_text)?);
+        }
+        Ok(scenario)
+    }
+
+    /// Makes the scripted changes to the network due in the given iteration
+    pub fn apply(&self, network: &mut Network, iteration: u64) {
+        for (_, action) in self.actions.iter().filter(|&&(it, _)| it == iteration) {
+            debug!("Scenario: {:?}", action);
+            action.apply(network);
+        }
+    }
+
+    /// Makes the parameter changes due in the given iteration. Unlike the scripted churn, they
+    /// aren't part of an event log, so they are made in replays, too.
+    pub fn change_params(&self, network: &mut Network, iteration: u64) {
+        for &(_, change) in self.param_changes.iter().filter(|&&(it, _)| it == iteration) {
+            network.change_param(change.param, change.value, change.prefix);
+        }
+    }
+
+    /// Returns whether the random joins are stopped in the given iteration
+    pub fn joins_stopped(&self, iteration: u64) -> bool {
+        self.no_joins
+            .iter()
+            .any(|&(start, end)| start <= iteration && iteration < end)
+    }
+
+    /// Returns whether the network starts draining after the given iteration
+    pub fn drains_after(&self, iteration: u64) -> bool {
+        self.drain == Some(iteration)
+    }
+
+    /// Checks all the assertions applicable after the given iteration and returns a
+    /// description of the first one that fails
+    pub fn check(&mut self, network: &Network, iteration: u64) -> Result<(), String> {
+        for assertion in &mut self.assertions {
+            assertion.check(network, iteration)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+//! The signals that request a diagnostics dump or stop the simulation
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the signal handler, cleared when the request is taken
+static DUMP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Set by the interrupt handler, if it is installed
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_signal(_: libc::c_int) {
+    DUMP_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Makes SIGUSR1 request a diagnostics dump instead of killing the process
+#[cfg(unix)]
+pub fn install() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let previous = unsafe { libc::signal(libc::SIGUSR1, handler) };
+    if previous == libc::SIG_ERR {
+        panic!("Couldn't install the SIGUSR1 handler!");
+    }
+}
+
+/// Signals aren't available, so dumps can't be requested
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// Makes SIGINT (Ctrl+C) stop the simulation at the end of the current iteration, so that its
+/// state can be saved, instead of killing the process
+#[cfg(unix)]
+pub fn install_interrupt() {
+    let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+    if previous == libc::SIG_ERR {
+        panic!("Couldn't install the SIGINT handler!");
+    }
+}
+
+/// Signals aren't available, so interrupts kill the process
+#[cfg(not(unix))]
+pub fn install_interrupt() {}
+
+/// Returns whether the simulation was interrupted
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Returns whether a diagnostics dump was requested since the last call
+pub fn dump_requested() -> bool {
+    DUMP_REQUESTED.swap(false, Ordering::SeqCst)
+}
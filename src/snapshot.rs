@@ -0,0 +1,85 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use bincode;
+use network::Network;
+use params::Params;
+use random;
+use workload::Workload;
+
+/// The beginning of a snapshot file, which can be read without loading the whole network
+#[derive(Serialize, Deserialize)]
+struct Header {
+    /// the parameters of the run
+    params: Params,
+    /// the number of iterations run before the snapshot was taken
+    iterations: u64,
+    /// the state of the random number generator
+    rng: [u32; 4],
+}
+
+/// A simulation restored from a snapshot
+pub struct Snapshot {
+    /// the parameters of the run
+    pub params: Params,
+    /// the number of iterations run before the snapshot was taken
+    pub iterations: u64,
+    pub network: Network,
+    pub workload: Workload,
+}
+
+/// Saves the state of a simulation after the given number of iterations, along with the state
+/// of the random number generator, so that resuming from it continues exactly like the run it
+/// was taken from. The file is replaced only once the snapshot is complete, so a crash while
+/// saving leaves the previous snapshot intact.
+pub fn save(path: &str, params: &Params, iterations: u64, network: &Network, workload: &Workload) {
+    let partial = format!("{}.partial", path);
+    let file = File::create(&partial).unwrap_or_else(|_| panic!("Couldn't create file {}!", partial));
+    let mut writer = BufWriter::new(file);
+    let header = Header {
+        params: params.clone(),
+        iterations,
+        rng: random::state(),
+    };
+    bincode::serialize_into(&mut writer, &header)
+        .and_then(|()| bincode::serialize_into(&mut writer, network))
+        .and_then(|()| bincode::serialize_into(&mut writer, workload))
+        .expect("Couldn't serialise the snapshot!");
+    writer
+        .flush()
+        .unwrap_or_else(|_| panic!("Couldn't write file {}!", partial));
+    fs::rename(&partial, path).unwrap_or_else(|_| panic!("Couldn't write file {}!", path));
+}
+
+/// Reads the header of a snapshot
+fn read_header(reader: &mut BufReader<File>, path: &str) -> Header {
+    bincode::deserialize_from(reader)
+        .unwrap_or_else(|err| panic!("Invalid snapshot file {}: {}", path, err))
+}
+
+fn open(path: &str) -> BufReader<File> {
+    let file = File::open(path).unwrap_or_else(|err| panic!("Can't read {}: {}", path, err));
+    BufReader::new(file)
+}
+
+/// Returns the parameters of the run the snapshot was taken from
+pub fn read_params(path: &str) -> Params {
+    read_header(&mut open(path), path).params
+}
+
+/// Loads a snapshot and puts the random number generator back in the state it was saved in
+pub fn load(path: &str) -> Snapshot {
+    let mut reader = open(path);
+    let header = read_header(&mut reader, path);
+    let mut network: Network = bincode::deserialize_from(&mut reader)
+        .unwrap_or_else(|err| panic!("Invalid snapshot file {}: {}", path, err));
+    let workload = bincode::deserialize_from(&mut reader)
+        .unwrap_or_else(|err| panic!("Invalid snapshot file {}: {}", path, err));
+    network.restore();
+    random::restore_state(header.rng);
+    Snapshot {
+        params: header.params,
+        iterations: header.iterations,
+        network,
+        workload,
+    }
+}
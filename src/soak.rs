@@ -0,0 +1,157 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use checks;
+use serde_json;
+use log;
+use network::Network;
+use params::Params;
+use random::{random_seed, seed, with_seed};
+use scenario::Scenario;
+use signal::dump_requested;
+use workload::Workload;
+
+/// The size at which the soak log is rotated
+const MAX_LOG_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The state of a soak run after its last successful check
+#[derive(Clone, Debug, Default, Serialize)]
+struct Checkpoint {
+    run: u64,
+    seed: [u32; 4],
+    iteration: u64,
+    nodes: usize,
+    sections: usize,
+    relocations: u64,
+    rejections: u64,
+}
+
+/// A failure found by a soak run, along with what is needed to reproduce it
+#[derive(Serialize)]
+struct Failure<'a> {
+    run: u64,
+    seed: [u32; 4],
+    message: String,
+    last_checkpoint: &'a Checkpoint,
+}
+
+/// A log file that is moved aside once it grows too large, keeping one old log
+struct Log {
+    path: PathBuf,
+    file: File,
+}
+
+impl Log {
+    fn open(path: PathBuf) -> Log {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .unwrap_or_else(|_| panic!("Couldn't open log {:?}!", path));
+        Log { path, file }
+    }
+
+    fn write(&mut self, line: &str) {
+        let _ = writeln!(self.file, "{}", line);
+        let size = self.file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        if size > MAX_LOG_SIZE {
+            let _ = fs::rename(&self.path, self.path.with_extension("log.1"));
+            *self = Log::open(self.path.clone());
+        }
+    }
+}
+
+/// Runs simulations one after another until the process is killed, hunting for rare failures.
+/// Every run goes on indefinitely, checking the invariants of the network and the scenario
+/// every `soak_interval` iterations and overwriting the checkpoint file when they hold. A run
+/// that fails a check or panics is recorded in the failures file together with its seed and
+/// last checkpoint, and a fresh run is started. A failure can be reproduced by soaking with
+/// `AGE_SEED` set to the recorded seed.
+pub fn soak(params: &Params) -> ! {
+    let dir = Path::new(&params.soak_dir);
+    fs::create_dir_all(dir).unwrap_or_else(|_| panic!("Couldn't create directory {:?}!", dir));
+    let mut log = Log::open(dir.join("soak.log"));
+    let mut failures = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("failures.jsonl"))
+        .expect("Couldn't open the failures file!");
+
+    for run in 1.. {
+        let seed = if run == 1 { seed() } else { random_seed() };
+        log.write(&format!("Run {} starts with seed {:?}", run, seed));
+        let mut checkpoint = Checkpoint {
+            run,
+            seed,
+            ..Default::default()
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            with_seed(seed, || soak_run(params, dir, &mut log, &mut checkpoint))
+        }));
+        let message = match result {
+            Ok(msg) => msg,
+            Err(payload) => match payload.downcast_ref::<&str>() {
+                Some(msg) => format!("Panic: {}", msg),
+                None => match payload.downcast_ref::<String>() {
+                    Some(msg) => format!("Panic: {}", msg),
+                    None => "Panic".to_owned(),
+                },
+            },
+        };
+        log.write(&format!("Run {} failed: {}", run, message));
+        let failure = Failure {
+            run,
+            seed,
+            message,
+            last_checkpoint: &checkpoint,
+        };
+        let line = serde_json::to_string(&failure).expect("Couldn't serialise a failure!");
+        let _ = writeln!(failures, "{}", line);
+    }
+    unreachable!()
+}
+
+/// Runs a single simulation until it fails, returning the failure
+fn soak_run(params: &Params, dir: &Path, log: &mut Log, checkpoint: &mut Checkpoint) -> String {
+    let mut network = Network::new(params.clone());
+    let mut workload = Workload::new();
+    let mut scenario = Scenario::from_params(params);
+
+    for i in 0.. {
+        log::enter_iteration(params, i);
+        debug!("Iteration {}...", i);
+        workload.generate(&mut network, params);
+        network.process_events();
+        if dump_requested() {
+            eprintln!("{}", network.diagnostics());
+        }
+        if let Some(msg) = network.failure() {
+            return msg.to_owned();
+        }
+        if let Err(msg) = scenario.check(&network, i) {
+            return msg;
+        }
+        if i % params.soak_interval != 0 {
+            continue;
+        }
+        if let Err(msg) = checks::check_network(&network) {
+            return format!("Invariant violated after iteration {}: {}", i, msg);
+        }
+        *checkpoint = Checkpoint {
+            iteration: i,
+            nodes: network.num_nodes(),
+            sections: network.num_sections(),
+            relocations: network.output().relocations,
+            rejections: network.output().rejections,
+            ..*checkpoint
+        };
+        let json = serde_json::to_string(checkpoint).expect("Couldn't serialise a checkpoint!");
+        let _ = fs::write(dir.join("checkpoint.json"), json);
+        log.write(&format!(
+            "Run {}, iteration {}: checks passed, {} nodes in {} sections",
+            checkpoint.run, i, checkpoint.nodes, checkpoint.sections
+        ));
+    }
+    unreachable!()
+}
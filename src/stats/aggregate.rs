@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use network::Network;
+
+/// Two-sided 95% critical values of Student's t-distribution for 1 to 30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Returns the critical value of the t-distribution for the 95% confidence interval
+fn t_95(degrees_of_freedom: usize) -> f64 {
+    if degrees_of_freedom == 0 {
+        f64::INFINITY
+    } else if degrees_of_freedom <= T_95.len() {
+        T_95[degrees_of_freedom - 1]
+    } else {
+        1.96
+    }
+}
+
+/// Returns the mean value of a distribution, or 0 if it is empty
+pub fn dist_mean<K: Copy + Into<f64>>(dist: &BTreeMap<K, usize>) -> f64 {
+    let count: usize = dist.values().sum();
+    let sum: f64 = dist.iter().map(|(&k, &n)| k.into() * n as f64).sum();
+    sum / count.max(1) as f64
+}
+
+/// The headline metrics of a single run, in a fixed order
+pub type Summary = Vec<(&'static str, f64)>;
+
+/// Collects the headline metrics of a finished run
+pub fn summary(network: &Network) -> Summary {
+    let output = network.output();
+    vec![
+        ("nodes", network.num_nodes() as f64),
+        ("sections", network.num_sections() as f64),
+        ("complete sections", network.complete_sections() as f64),
+        ("relocations", output.relocations as f64),
+        ("rejections", output.rejections as f64),
+        ("churn", output.churn as f64),
+        ("elder relocations", output.elder_relocations as f64),
+        ("refused splits", output.refused_splits as f64),
+        (
+            "mean age at first promotion",
+            dist_mean(&output.promotion_ages),
+        ),
+    ]
+}
+
+/// The mean of a metric over several runs, with the half-width of its 95% confidence interval
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    pub mean: f64,
+    pub half_width: f64,
+}
+
+impl Estimate {
+    /// Estimates the mean of the given samples
+    pub fn from_samples(samples: &[f64]) -> Estimate {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = if samples.len() > 1 {
+            samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        Estimate {
+            mean,
+            half_width: t_95(samples.len() - 1) * (variance / n).sqrt(),
+        }
+    }
+
+    /// Returns the width of the confidence interval relative to the mean, in percent
+    pub fn relative_width(&self) -> f64 {
+        if self.mean == 0.0 {
+            0.0
+        } else {
+            200.0 * self.half_width / self.mean.abs()
+        }
+    }
+}
+
+/// Prints the mean and the 95% confidence interval of every headline metric over all the runs.
+/// Metrics with an interval wider than `max_width` percent of the mean are flagged.
+pub fn print_aggregate(summaries: &[Summary], max_width: f64) {
+    println!("\nAggregate over {} runs (95% confidence):", summaries.len());
+    let metrics = summaries.first().map_or(0, |s| s.len());
+    for i in 0..metrics {
+        let name = summaries[0][i].0;
+        let samples: Vec<_> = summaries.iter().map(|s| s[i].1).collect();
+        let estimate = Estimate::from_samples(&samples);
+        println!("{}: {:.2} ± {:.2}", name, estimate.mean, estimate.half_width);
+        if estimate.relative_width() > max_width {
+            println!(
+                "\tWARNING: the interval is wider than {}% of the mean, more runs are needed",
+                max_width
+            );
+        }
+    }
+}
+
+/// Returns the correlation coefficient of two series of samples, or 0 if either of them is
+/// constant
+fn correlation(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let (mean_x, mean_y) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+    let cov: f64 = xs.iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let var_x: f64 = xs.iter().map(|x| (x - mean_x) * (x - mean_x)).sum();
+    let var_y: f64 = ys.iter().map(|y| (y - mean_y) * (y - mean_y)).sum();
+    if var_x == 0.0 || var_y == 0.0 {
+        0.0
+    } else {
+        cov / (var_x * var_y).sqrt()
+    }
+}
+
+/// Prints the correlation of every headline metric with every jittered parameter over the runs,
+/// showing which conclusions are sensitive to the exact parameter values
+pub fn print_sensitivity(jitters: &[Summary], summaries: &[Summary]) {
+    println!("\nCorrelation of the metrics with the jittered parameters:");
+    let params = jitters.first().map_or(0, |j| j.len());
+    let header: Vec<_> = (0..params).map(|j| jitters[0][j].0).collect();
+    println!("metric\t{}", header.join("\t"));
+    let metrics = summaries.first().map_or(0, |s| s.len());
+    for i in 0..metrics {
+        let samples: Vec<_> = summaries.iter().map(|s| s[i].1).collect();
+        let row: Vec<_> = (0..params)
+            .map(|j| {
+                let values: Vec<_> = jitters.iter().map(|jitter| jitter[j].1).collect();
+                format!("{:+.2}", correlation(&values, &samples))
+            })
+            .collect();
+        println!("{}\t{}", summaries[0][i].0, row.join("\t"));
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::fs;
+use serde_json;
+use super::aggregate::Summary;
+
+/// Returns whether an increase of the metric is an improvement, or `None` if the metric is
+/// neither good nor bad in itself
+fn higher_is_better(metric: &str) -> Option<bool> {
+    match metric {
+        "complete sections" | "mean age at first promotion" => Some(true),
+        "rejections" | "elder relocations" | "refused splits" => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns the mean of every headline metric over the runs
+pub fn mean_summary(summaries: &[Summary]) -> Summary {
+    let metrics = summaries.first().map_or(0, |s| s.len());
+    (0..metrics)
+        .map(|i| {
+            let sum: f64 = summaries.iter().map(|s| s[i].1).sum();
+            (summaries[0][i].0, sum / summaries.len() as f64)
+        })
+        .collect()
+}
+
+/// Writes the headline metrics to a JSON file, to be used as a baseline by later runs
+pub fn write_summary(file: &str, summary: &Summary) {
+    let map: BTreeMap<_, _> = summary.iter().cloned().collect();
+    let json = serde_json::to_string_pretty(&map).expect("Couldn't serialise the summary!");
+    fs::write(file, json).unwrap_or_else(|_| panic!("Couldn't write the summary to {}!", file));
+}
+
+/// Reads the headline metrics of a baseline run written by `write_summary`
+pub fn read_baseline(file: &str) -> Result<BTreeMap<String, f64>, String> {
+    let text = fs::read_to_string(file).map_err(|err| format!("Can't read {}: {}", file, err))?;
+    serde_json::from_str(&text).map_err(|err| format!("Invalid baseline {}: {}", file, err))
+}
+
+/// Prints every headline metric with its change from the baseline. Changes for the worse by
+/// more than `threshold` percent are flagged as regressions.
+pub fn print_comparison(summary: &Summary, baseline: &BTreeMap<String, f64>, threshold: f64) {
+    println!("\nComparison with the baseline:");
+    let mut regressions = 0;
+    for &(name, value) in summary {
+        let base = match baseline.get(name) {
+            Some(&base) => base,
+            None => {
+                println!("{}: {:.2} (not in the baseline)", name, value);
+                continue;
+            }
+        };
+        let change = if base == 0.0 {
+            if value == 0.0 {
+                0.0
+            } else {
+                f64::INFINITY.copysign(value)
+            }
+        } else {
+            100.0 * (value - base) / base.abs()
+        };
+        let regression = match higher_is_better(name) {
+            Some(true) => change < -threshold,
+            Some(false) => change > threshold,
+            None => false,
+        };
+        println!(
+            "{}: {:.2} -> {:.2} ({:+.1}%){}",
+            name,
+            base,
+            value,
+            change,
+            if regression { "  REGRESSION" } else { "" }
+        );
+        if regression {
+            regressions += 1;
+        }
+    }
+    if regressions > 0 {
+        println!("{} metrics got worse by more than {}%", regressions, threshold);
+    }
+}
@@ -0,0 +1,50 @@
+use std::cmp::Reverse;
+use std::io::{self, Write};
+use serde_json;
+use network::Network;
+use stats::ReportFormat;
+
+/// An elder as written to the list, with its name in full hexadecimal or as its pseudonym
+#[derive(Serialize)]
+struct Row {
+    section: String,
+    name: String,
+    age: u8,
+    /// the number of iterations the node has been an elder for, through splits and merges
+    tenure: u64,
+}
+
+/// Writes the elders of every section, in the order of the sections' prefixes and then of the
+/// elders' ages, oldest first: a JSON array of objects if the format is JSON, CSV otherwise.
+/// Prefixes are written as strings of bits, the empty one as an empty string.
+pub fn write_elder_list(
+    format: ReportFormat,
+    network: &Network,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let mut rows = vec![];
+    for section in network.sections() {
+        let mut elders: Vec<_> = section.elders().into_iter().collect();
+        elders.sort_by_key(|node| (Reverse(node.age()), node.name()));
+        rows.extend(elders.into_iter().map(|node| Row {
+            section: section.prefix().to_string(),
+            name: node.name().to_string(),
+            age: node.age(),
+            tenure: network.elder_tenure(node.name()).unwrap_or(0),
+        }));
+    }
+    if let ReportFormat::Json = format {
+        writeln!(out, "[")?;
+        for (index, row) in rows.iter().enumerate() {
+            let separator = if index == 0 { "" } else { ",\n" };
+            let row = serde_json::to_string(row).expect("Couldn't serialise an elder!");
+            write!(out, "{}  {}", separator, row)?;
+        }
+        return writeln!(out, "\n]");
+    }
+    writeln!(out, "section,name,age,tenure")?;
+    for row in rows {
+        writeln!(out, "{},{},{},{}", row.section, row.name, row.age, row.tenure)?;
+    }
+    Ok(())
+}
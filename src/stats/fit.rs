@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+/// The smallest expected count of a bin in the chi-square test; the tail bins expected to hold
+/// fewer nodes are pooled
+const MIN_EXPECTED: f64 = 5.0;
+/// The 95% quantile of the standard normal distribution
+const Z_95: f64 = 1.645;
+
+/// How well an age distribution matches the geometric distribution predicted by the relocation
+/// rule: every age above the most common one holds half as many nodes as the previous one
+#[derive(Clone, Copy, Debug)]
+pub struct GeometricFit {
+    /// the most common age, where the fitted distribution starts
+    pub start: u8,
+    /// the chi-square statistic of the observed counts against the expected ones
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+    /// the critical value of the chi-square statistic at the 5% significance level
+    pub critical: f64,
+    /// the Kolmogorov-Smirnov distance between the observed and expected distributions
+    pub ks: f64,
+    /// the ratio of the numbers of nodes of consecutive ages that fits the observed
+    /// distribution best
+    pub ratio: f64,
+}
+
+impl GeometricFit {
+    /// Returns whether the observed distribution is significantly different from the expected one
+    pub fn rejected(&self) -> bool {
+        self.chi_square > self.critical
+    }
+}
+
+/// Returns the approximate critical value of the chi-square distribution at the 5%
+/// significance level (Wilson-Hilferty)
+fn chi_square_95(degrees_of_freedom: usize) -> f64 {
+    let d = degrees_of_freedom as f64;
+    let a = 2.0 / (9.0 * d);
+    d * (1.0 - a + Z_95 * a.sqrt()).powi(3)
+}
+
+/// Tests the ages from the most common one upwards against the geometric distribution halving
+/// at every age. Returns `None` if there are too few nodes to tell.
+pub fn geometric_fit(dist: &BTreeMap<u8, usize>) -> Option<GeometricFit> {
+    let (&start, _) = dist.iter().max_by_key(|&(&age, &count)| (count, -i32::from(age)))?;
+    let tail: Vec<_> = dist.range(start..).map(|(&age, &count)| (age, count)).collect();
+    let total: usize = tail.iter().map(|&(_, count)| count).sum();
+    let total = total as f64;
+    let last = tail.last()?.0;
+
+    let mut bins = vec![];
+    let (mut observed, mut expected) = (0.0, 0.0);
+    let (mut cum_observed, mut cum_expected, mut ks) = (0.0, 0.0, 0.0f64);
+    for age in start..=last {
+        let count = dist.get(&age).cloned().unwrap_or(0) as f64;
+        let k = i32::from(age - start);
+        let share = if age == last {
+            // the last bin takes the whole remaining tail
+            0.5f64.powi(k)
+        } else {
+            0.5f64.powi(k + 1)
+        };
+        observed += count;
+        expected += total * share;
+        cum_observed += count / total;
+        cum_expected += share;
+        ks = ks.max((cum_observed - cum_expected).abs());
+        if expected >= MIN_EXPECTED || age == last {
+            bins.push((observed, expected));
+            observed = 0.0;
+            expected = 0.0;
+        }
+    }
+    // a small remainder is pooled with the previous bin
+    if bins.len() > 1 && bins[bins.len() - 1].1 < MIN_EXPECTED {
+        let (o, e) = bins.pop().unwrap();
+        let prev = bins.last_mut().unwrap();
+        prev.0 += o;
+        prev.1 += e;
+    }
+    if bins.len() < 2 {
+        return None;
+    }
+    let chi_square = bins.iter().map(|&(o, e)| (o - e) * (o - e) / e).sum();
+    let degrees_of_freedom = bins.len() - 1;
+
+    // the maximum likelihood estimate for a geometric distribution starting at 0
+    let mean = tail.iter()
+        .map(|&(age, count)| f64::from(age - start) * count as f64)
+        .sum::<f64>() / total;
+    Some(GeometricFit {
+        start,
+        chi_square,
+        degrees_of_freedom,
+        critical: chi_square_95(degrees_of_freedom),
+        ks,
+        ratio: mean / (1.0 + mean),
+    })
+}
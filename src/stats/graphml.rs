@@ -0,0 +1,97 @@
+use std::io::{self, Write};
+use network::Network;
+use network::section::Section;
+use params::Params;
+
+/// The attributes of the graph's vertices: their ids, names and types
+const KEYS: &[(&str, &str, &str)] = &[
+    ("kind", "kind", "string"),
+    ("prefix", "prefix", "string"),
+    ("size", "size", "int"),
+    ("elders", "elders", "int"),
+    ("complete", "complete", "boolean"),
+    ("chain", "chain_length", "long"),
+    ("name", "name", "string"),
+    ("age", "age", "int"),
+    ("elder", "elder", "boolean"),
+];
+
+/// Writes the current state of the network as a GraphML graph, for analysis in Gephi or
+/// NetworkX. Every section and every node is a vertex; nodes are connected to their sections,
+/// and neighbouring sections to each other.
+pub fn write_graph(out: &mut dyn Write, network: &Network) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        out,
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+    )?;
+    for &(id, name, kind) in KEYS {
+        writeln!(
+            out,
+            "  <key id=\"{}\" for=\"node\" attr.name=\"{}\" attr.type=\"{}\"/>",
+            id, name, kind
+        )?;
+    }
+    writeln!(out, "  <graph id=\"network\" edgedefault=\"undirected\">")?;
+    let sections: Vec<_> = network.sections().collect();
+    for section in &sections {
+        write_section(out, section, network.params())?;
+    }
+    for (i, section) in sections.iter().enumerate() {
+        for other in &sections[i + 1..] {
+            if section.prefix().is_neighbour(&other.prefix()) {
+                write_edge(out, &section_id(section), &section_id(other))?;
+            }
+        }
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")
+}
+
+fn section_id(section: &Section) -> String {
+    format!("s{}", section.prefix().to_string())
+}
+
+/// Writes the vertex of the section, the vertices of its nodes and their membership edges
+fn write_section(out: &mut dyn Write, section: &Section, params: &Params) -> io::Result<()> {
+    let id = section_id(section);
+    let data = [
+        ("kind", "section".to_owned()),
+        ("prefix", section.prefix().to_string()),
+        ("size", section.len().to_string()),
+        ("elders", section.elder_count().to_string()),
+        ("complete", section.is_complete(params).to_string()),
+        ("chain", section.chain().blocks().to_string()),
+    ];
+    write_vertex(out, &id, &data)?;
+    let elders = section.elders();
+    for node in section.iter_nodes() {
+        let node_id = format!("n{:016x}", node.name().0);
+        let data = [
+            ("kind", "node".to_owned()),
+            ("prefix", section.prefix().to_string()),
+            ("name", node.name().to_string()),
+            ("age", node.age().to_string()),
+            ("elder", elders.contains(&node).to_string()),
+        ];
+        write_vertex(out, &node_id, &data)?;
+        write_edge(out, &node_id, &id)?;
+    }
+    Ok(())
+}
+
+fn write_vertex(out: &mut dyn Write, id: &str, data: &[(&str, String)]) -> io::Result<()> {
+    writeln!(out, "    <node id=\"{}\">", id)?;
+    for &(key, ref value) in data {
+        writeln!(out, "      <data key=\"{}\">{}</data>", key, value)?;
+    }
+    writeln!(out, "    </node>")
+}
+
+fn write_edge(out: &mut dyn Write, source: &str, target: &str) -> io::Result<()> {
+    writeln!(
+        out,
+        "    <edge source=\"{}\" target=\"{}\"/>",
+        source, target
+    )
+}
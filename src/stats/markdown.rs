@@ -0,0 +1,161 @@
+use std::io::{self, Write};
+use network::Network;
+use network::network::STALENESS_TICKS;
+use params::Params;
+use stats::summary;
+
+/// The width of the ASCII charts, in characters
+const CHART_WIDTH: usize = 60;
+/// The height of the ASCII charts, in lines
+const CHART_HEIGHT: usize = 12;
+
+/// Writes a self-contained markdown report of a run, suitable for pasting into forum posts.
+/// `failure` is the description of the failed scenario assertion that ended the run, if any.
+pub fn write_report(
+    out: &mut dyn Write,
+    params: &Params,
+    network: &Network,
+    failure: Option<&str>,
+) -> io::Result<()> {
+    writeln!(out, "# Simulation report\n")?;
+    write_params(out, params)?;
+    write_summary(out, network)?;
+    write_charts(out, network)?;
+    write_events(out, network, failure)
+}
+
+fn write_params(out: &mut dyn Write, params: &Params) -> io::Result<()> {
+    writeln!(out, "## Parameters\n")?;
+    writeln!(out, "| Parameter | Value |")?;
+    writeln!(out, "|-----------|-------|")?;
+    let rows: Vec<(&str, String)> = vec![
+        ("Initial age", params.init_age.to_string()),
+        ("Split strategy", format!("{:?}", params.split_strategy)),
+        ("Reject young nodes", (!params.norejectyoung).to_string()),
+        ("Add probability (%)", params.growth.0.to_string()),
+        ("Drop probability (%)", params.growth.1.to_string()),
+        ("Drop distribution", format!("{:?}", params.drop_dist)),
+        ("Age on split", format!("{:?}", params.age_on_split)),
+        ("Age on merge", format!("{:?}", params.age_on_merge)),
+        ("Elder handover (iterations)", params.elder_handover.to_string()),
+        ("Merge negotiation", params.merge_negotiation.to_string()),
+        ("Batch cascades", params.batch_cascades.to_string()),
+        (
+            "Malicious nodes (%)",
+            params
+                .coalitions
+                .iter()
+                .map(|c| c.fraction.to_string())
+                .collect::<Vec<_>>()
+                .join(" + "),
+        ),
+        ("Equivocation chance (%)", params.equivocation.to_string()),
+    ];
+    for (name, value) in rows {
+        writeln!(out, "| {} | {} |", name, value)?;
+    }
+    writeln!(out)
+}
+
+fn write_summary(out: &mut dyn Write, network: &Network) -> io::Result<()> {
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "|--------|------:|")?;
+    for (name, value) in summary(network) {
+        writeln!(out, "| {} | {} |", name, value)?;
+    }
+    writeln!(out, "| longest section chain | {} |", network.max_chain_len())?;
+    writeln!(out)?;
+    writeln!(out, "### Client-perceived elder churn\n")?;
+    writeln!(out, "| Cached for (iterations) | Chance of stale elders |")?;
+    writeln!(out, "|------------------------:|-----------------------:|")?;
+    for (ticks, chance) in network.client_staleness(STALENESS_TICKS) {
+        writeln!(out, "| {} | {:.1}% |", ticks, 100.0 * chance)?;
+    }
+    writeln!(out)
+}
+
+fn write_charts(out: &mut dyn Write, network: &Network) -> io::Result<()> {
+    let history = &network.output().history;
+    let records: Vec<_> = history.iter().collect();
+    let (first, last) = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return Ok(()),
+    };
+    writeln!(out, "## Trajectories\n")?;
+    writeln!(out, "Iterations {} to {}.\n", first, last)?;
+    let size: Vec<_> = records.iter().map(|(_, data)| data.size).collect();
+    let sections: Vec<_> = records.iter().map(|(_, data)| data.sections).collect();
+    let complete: Vec<_> = records.iter().map(|(_, data)| data.complete).collect();
+    for &(title, ref values) in &[
+        ("Nodes", size),
+        ("Sections", sections),
+        ("Complete sections", complete),
+    ] {
+        writeln!(out, "### {}\n", title)?;
+        writeln!(out, "```")?;
+        for line in ascii_chart(values) {
+            writeln!(out, "{}", line)?;
+        }
+        writeln!(out, "```\n")?;
+    }
+    Ok(())
+}
+
+/// Draws the values as a column chart, averaging them into `CHART_WIDTH` buckets
+fn ascii_chart(values: &[usize]) -> Vec<String> {
+    let buckets = CHART_WIDTH.min(values.len());
+    let columns: Vec<f64> = (0..buckets)
+        .map(|i| {
+            let start = i * values.len() / buckets;
+            let end = (i + 1) * values.len() / buckets;
+            values[start..end].iter().sum::<usize>() as f64 / (end - start) as f64
+        })
+        .collect();
+    let max = columns.iter().cloned().fold(0.0, f64::max);
+    let label_width = format!("{:.0}", max).len();
+    let mut lines = vec![];
+    for row in (0..CHART_HEIGHT).rev() {
+        let threshold = max * (row as f64 + 0.5) / CHART_HEIGHT as f64;
+        let label = if row == CHART_HEIGHT - 1 {
+            format!("{:>w$.0}", max, w = label_width)
+        } else if row == 0 {
+            format!("{:>w$}", 0, w = label_width)
+        } else {
+            " ".repeat(label_width)
+        };
+        let bars: String = columns
+            .iter()
+            .map(|&value| if value > 0.0 && value >= threshold { '#' } else { ' ' })
+            .collect();
+        lines.push(format!("{} |{}", label, bars));
+    }
+    lines.push(format!("{} +{}", " ".repeat(label_width), "-".repeat(buckets)));
+    lines
+}
+
+fn write_events(out: &mut dyn Write, network: &Network, failure: Option<&str>) -> io::Result<()> {
+    let output = network.output();
+    writeln!(out, "## Notable events\n")?;
+    match output.first_split {
+        Some(iteration) => writeln!(out, "- First split in iteration {}", iteration)?,
+        None => writeln!(out, "- The network never split")?,
+    }
+    let (size, iteration) = output.largest_section;
+    writeln!(
+        out,
+        "- Largest section: {} nodes, in iteration {}",
+        size, iteration
+    )?;
+    if let Some(iteration) = output.first_below_quorum {
+        writeln!(
+            out,
+            "- A section first fell below quorum in iteration {}",
+            iteration
+        )?;
+    }
+    if let Some(msg) = failure {
+        writeln!(out, "- **Failure:** {}", msg)?;
+    }
+    Ok(())
+}
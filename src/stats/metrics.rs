@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use serde_json;
+use network::Network;
+use stats::{dist_mean, ReportFormat};
+
+/// The bars of a sparkline, from the lowest to the highest
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The most buckets a sparkline has, one character each
+pub const SPARKLINE_WIDTH: usize = 40;
+
+/// The smallest, largest and mean value of a distribution, its variance and its percentiles
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct DistSummary {
+    pub min: f64,
+    pub p10: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl DistSummary {
+    /// Summarises a distribution given as the number of times every value occurred; all the
+    /// statistics of an empty distribution are 0
+    pub fn of<K: Copy + Into<f64>>(dist: &BTreeMap<K, usize>) -> DistSummary {
+        let count: usize = dist.values().sum();
+        if count == 0 {
+            return Default::default();
+        }
+        let mean = dist_mean(dist);
+        let variance = dist.iter()
+            .map(|(&k, &n)| n as f64 * (k.into() - mean).powi(2))
+            .sum::<f64>() / count as f64;
+        DistSummary {
+            min: dist.keys().next().map_or(0.0, |&k| k.into()),
+            p10: percentile(dist, 0.1),
+            p25: percentile(dist, 0.25),
+            median: percentile(dist, 0.5),
+            p75: percentile(dist, 0.75),
+            p90: percentile(dist, 0.9),
+            p99: percentile(dist, 0.99),
+            max: dist.keys().next_back().map_or(0.0, |&k| k.into()),
+            mean,
+            variance,
+        }
+    }
+}
+
+impl fmt::Display for DistSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "min {}, p10 {}, p25 {}, median {}, p75 {}, p90 {}, p99 {}, max {}; mean {:.2}, \
+             variance {:.2}",
+            self.min,
+            self.p10,
+            self.p25,
+            self.median,
+            self.p75,
+            self.p90,
+            self.p99,
+            self.max,
+            self.mean,
+            self.variance
+        )
+    }
+}
+
+/// Returns the smallest value that more than the given share of the distribution is at or below
+fn percentile<K: Copy + Into<f64>>(dist: &BTreeMap<K, usize>, share: f64) -> f64 {
+    let count: usize = dist.values().sum();
+    let mut seen = 0;
+    dist.iter()
+        .find(|&(_, &n)| {
+            seen += n;
+            seen as f64 > share * count as f64
+        })
+        .map_or(0.0, |(&k, _)| k.into())
+}
+
+/// Renders a distribution of whole values as a line of bars: a bar per value from the smallest
+/// to the largest, or per bucket of consecutive values if there are more than
+/// `SPARKLINE_WIDTH` of them, as high as the number of times the values occurred relative to
+/// the most common one. Buckets of values that never occurred are blank, so the tails stand out
+/// however thin they are.
+pub fn sparkline<K: Copy + Into<f64>>(dist: &BTreeMap<K, usize>) -> String {
+    let (min, max) = match (dist.keys().next(), dist.keys().next_back()) {
+        (Some(&min), Some(&max)) => (min.into(), max.into()),
+        _ => return String::new(),
+    };
+    let values = (max - min) as usize + 1;
+    let buckets = values.min(SPARKLINE_WIDTH);
+    let mut counts = vec![0; buckets];
+    for (&value, &n) in dist {
+        let bucket = (value.into() - min) as usize * buckets / values;
+        counts[bucket] += n;
+    }
+    let highest = counts.iter().cloned().max().unwrap_or(0).max(1);
+    counts
+        .into_iter()
+        .map(|n| {
+            if n == 0 {
+                ' '
+            } else {
+                SPARKS[(n * SPARKS.len() - 1) / highest]
+            }
+        })
+        .collect()
+}
+
+/// The statistics of the network at one tick of the metrics time series
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Metrics {
+    pub iteration: u64,
+    pub nodes: usize,
+    pub sections: usize,
+    pub complete: usize,
+    pub merges: u64,
+    pub splits: u64,
+    pub relocations: u64,
+    pub rejections: u64,
+    /// the ages of the nodes
+    pub ages: DistSummary,
+    /// the numbers of nodes in the sections
+    pub section_sizes: DistSummary,
+    /// the lengths of the sections' prefixes
+    pub prefix_lens: DistSummary,
+}
+
+impl Metrics {
+    /// Collects the statistics of the network after the given iteration
+    pub fn collect(network: &Network, iteration: u64) -> Metrics {
+        let output = network.output();
+        Metrics {
+            iteration,
+            nodes: network.num_nodes(),
+            sections: network.num_sections(),
+            complete: network.complete_sections(),
+            merges: output.merges,
+            splits: output.splits,
+            relocations: output.relocations,
+            rejections: output.rejections,
+            ages: DistSummary::of(&network.age_distribution()),
+            section_sizes: DistSummary::of(&network.size_distribution()),
+            prefix_lens: DistSummary::of(&network.prefix_len_distribution()),
+        }
+    }
+}
+
+/// The columns of the CSV time series
+const CSV_HEADER: &str = "iteration,nodes,sections,complete,merges,splits,relocations,rejections,\
+                          age_min,age_p10,age_p25,age_median,age_p75,age_p90,age_p99,age_max,\
+                          age_mean,age_variance,\
+                          size_min,size_p10,size_p25,size_median,size_p75,size_p90,size_p99,\
+                          size_max,size_mean,size_variance,\
+                          prefix_len_min,prefix_len_p10,prefix_len_p25,prefix_len_median,\
+                          prefix_len_p75,prefix_len_p90,prefix_len_p99,prefix_len_max,\
+                          prefix_len_mean,prefix_len_variance";
+
+/// Writes the metrics time series to a file as it is collected: a JSON array of objects if the
+/// format is JSON, CSV otherwise. The JSON array is closed when the writer is dropped.
+pub struct MetricsWriter {
+    out: BufWriter<File>,
+    json: bool,
+    rows: usize,
+}
+
+impl MetricsWriter {
+    /// Creates the file and writes the header of the time series
+    pub fn create(path: &str, format: ReportFormat) -> io::Result<MetricsWriter> {
+        let json = matches!(format, ReportFormat::Json);
+        let mut out = BufWriter::new(File::create(path)?);
+        if json {
+            writeln!(out, "[")?;
+        } else {
+            writeln!(out, "{}", CSV_HEADER)?;
+        }
+        Ok(MetricsWriter { out, json, rows: 0 })
+    }
+
+    /// Writes the metrics of a tick as a row of the time series
+    pub fn write(&mut self, metrics: &Metrics) -> io::Result<()> {
+        if self.json {
+            let separator = if self.rows == 0 { "" } else { ",\n" };
+            let row = serde_json::to_string(metrics).expect("Couldn't serialise the metrics!");
+            write!(self.out, "{}  {}", separator, row)?;
+        } else {
+            write!(
+                self.out,
+                "{},{},{},{},{},{},{},{}",
+                metrics.iteration,
+                metrics.nodes,
+                metrics.sections,
+                metrics.complete,
+                metrics.merges,
+                metrics.splits,
+                metrics.relocations,
+                metrics.rejections
+            )?;
+            for dist in &[metrics.ages, metrics.section_sizes, metrics.prefix_lens] {
+                write!(
+                    self.out,
+                    ",{},{},{},{},{},{},{},{},{:.3},{:.3}",
+                    dist.min,
+                    dist.p10,
+                    dist.p25,
+                    dist.median,
+                    dist.p75,
+                    dist.p90,
+                    dist.p99,
+                    dist.max,
+                    dist.mean,
+                    dist.variance
+                )?;
+            }
+            writeln!(self.out)?;
+        }
+        self.rows += 1;
+        Ok(())
+    }
+}
+
+impl Drop for MetricsWriter {
+    fn drop(&mut self) {
+        if self.json {
+            let _ = writeln!(self.out, "\n]");
+        }
+        let _ = self.out.flush();
+    }
+}
@@ -0,0 +1,14 @@
+pub mod aggregate;
+pub mod baseline;
+pub mod elders;
+pub mod fit;
+pub mod graphml;
+pub mod markdown;
+pub mod metrics;
+pub mod recorder;
+pub mod relocations;
+pub mod report;
+
+pub use self::aggregate::{dist_mean, print_aggregate, print_sensitivity, summary};
+pub use self::recorder::Recorder;
+pub use self::report::ReportFormat;
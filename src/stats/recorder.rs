@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+use network::NetworkStructure;
+
+/// A columnar store of the network structure recorded after every iteration.
+/// If a capacity is set, only the latest `capacity` records are kept, so that very long runs
+/// don't keep growing in memory.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Recorder {
+    capacity: Option<usize>,
+    /// the iteration of the oldest record kept
+    first: u64,
+    size: VecDeque<usize>,
+    sections: VecDeque<usize>,
+    complete: VecDeque<usize>,
+}
+
+impl Recorder {
+    /// Creates an empty recorder keeping at most `capacity` records (all of them if `None`)
+    pub fn new(capacity: Option<usize>) -> Recorder {
+        Recorder {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Appends the record for the next iteration, dropping the oldest one if full
+    pub fn record(&mut self, structure: NetworkStructure) {
+        if self.capacity == Some(self.size.len()) {
+            if self.size.is_empty() {
+                return;
+            }
+            let _ = self.size.pop_front();
+            let _ = self.sections.pop_front();
+            let _ = self.complete.pop_front();
+            self.first += 1;
+        }
+        self.size.push_back(structure.size);
+        self.sections.push_back(structure.sections);
+        self.complete.push_back(structure.complete);
+    }
+
+    /// Returns the number of records kept
+    fn len(&self) -> usize {
+        self.size.len()
+    }
+
+    /// Iterates over the records kept, together with the iterations they were recorded after
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (u64, NetworkStructure)> + 'a {
+        (0..self.len()).map(move |i| {
+            let structure = NetworkStructure {
+                size: self.size[i],
+                sections: self.sections[i],
+                complete: self.complete[i],
+            };
+            (self.first + i as u64, structure)
+        })
+    }
+}
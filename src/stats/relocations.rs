@@ -0,0 +1,75 @@
+use std::io::{self, Write};
+use serde_json;
+use network::network::RelocationRecord;
+use stats::ReportFormat;
+
+/// A relocation as written to the log, with the names in full hexadecimal or as their pseudonyms
+#[derive(Serialize)]
+struct Row {
+    iteration: u64,
+    src: String,
+    /// `None` if the node never arrived in the simulated network
+    dst: Option<String>,
+    old_name: String,
+    new_name: String,
+    old_age: u8,
+    new_age: u8,
+    transit: u64,
+    retries: u64,
+}
+
+impl Row {
+    fn new(record: &RelocationRecord) -> Row {
+        Row {
+            iteration: record.iteration,
+            src: record.src.to_string(),
+            dst: record.dst.map(|dst| dst.to_string()),
+            old_name: record.old.name().to_string(),
+            new_name: record.new.name().to_string(),
+            old_age: record.old.age(),
+            new_age: record.new.age(),
+            transit: record.transit,
+            retries: record.retries,
+        }
+    }
+}
+
+/// Writes the log of relocations: a JSON array of objects if the format is JSON, CSV otherwise.
+/// Prefixes are written as strings of bits, the empty one as an empty string. A node that never
+/// arrived in the simulated network has no destination: `null` in JSON, `-` in CSV.
+pub fn write_relocation_log(
+    format: ReportFormat,
+    records: &[RelocationRecord],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let rows = records.iter().map(Row::new);
+    if let ReportFormat::Json = format {
+        writeln!(out, "[")?;
+        for (index, row) in rows.enumerate() {
+            let separator = if index == 0 { "" } else { ",\n" };
+            let row = serde_json::to_string(&row).expect("Couldn't serialise a relocation!");
+            write!(out, "{}  {}", separator, row)?;
+        }
+        return writeln!(out, "\n]");
+    }
+    writeln!(
+        out,
+        "iteration,src,dst,old_name,new_name,old_age,new_age,transit,retries"
+    )?;
+    for row in rows {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            row.iteration,
+            row.src,
+            row.dst.as_ref().map_or("-", |dst| &dst[..]),
+            row.old_name,
+            row.new_name,
+            row.old_age,
+            row.new_age,
+            row.transit,
+            row.retries
+        )?;
+    }
+    Ok(())
+}
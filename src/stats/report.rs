@@ -0,0 +1,146 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+use serde_json;
+use network::NetworkStructure;
+use stats::Recorder;
+
+/// The formats in which the recorded network structure can be written
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ReportFormat {
+    /// space-separated columns, as expected by `plot.gp`
+    Text,
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "text" | "txt" => Ok(ReportFormat::Text),
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ReportFormat {
+    /// Returns the formatter producing reports in this format
+    pub fn formatter(&self) -> Box<dyn Formatter> {
+        match *self {
+            ReportFormat::Text => Box::new(TextFormatter),
+            ReportFormat::Csv => Box::new(CsvFormatter),
+            ReportFormat::Json => Box::new(JsonFormatter),
+            ReportFormat::Markdown => Box::new(MarkdownFormatter),
+        }
+    }
+}
+
+/// Turns records into a report. A report consists of a header, a row for every record and
+/// a footer.
+pub trait Formatter {
+    fn header(&self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn row(&self, out: &mut dyn Write, index: usize, iteration: u64, data: &NetworkStructure)
+        -> io::Result<()>;
+
+    fn footer(&self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct TextFormatter;
+
+impl Formatter for TextFormatter {
+    fn row(&self, out: &mut dyn Write, _: usize, iteration: u64, data: &NetworkStructure)
+        -> io::Result<()> {
+        writeln!(
+            out,
+            "{} {} {} {}",
+            iteration, data.size, data.sections, data.complete
+        )
+    }
+}
+
+struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn header(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "iteration,size,sections,complete")
+    }
+
+    fn row(&self, out: &mut dyn Write, _: usize, iteration: u64, data: &NetworkStructure)
+        -> io::Result<()> {
+        writeln!(
+            out,
+            "{},{},{},{}",
+            iteration, data.size, data.sections, data.complete
+        )
+    }
+}
+
+struct JsonFormatter;
+
+#[derive(Serialize)]
+struct JsonRow {
+    iteration: u64,
+    size: usize,
+    sections: usize,
+    complete: usize,
+}
+
+impl Formatter for JsonFormatter {
+    fn header(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "[")
+    }
+
+    fn row(&self, out: &mut dyn Write, index: usize, iteration: u64, data: &NetworkStructure)
+        -> io::Result<()> {
+        let separator = if index == 0 { "" } else { ",\n" };
+        let row = JsonRow {
+            iteration,
+            size: data.size,
+            sections: data.sections,
+            complete: data.complete,
+        };
+        let row = serde_json::to_string(&row).expect("Couldn't serialise a record!");
+        write!(out, "{}  {}", separator, row)
+    }
+
+    fn footer(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\n]")
+    }
+}
+
+struct MarkdownFormatter;
+
+impl Formatter for MarkdownFormatter {
+    fn header(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "| Iteration | Size | Sections | Complete |")?;
+        writeln!(out, "|----------:|-----:|---------:|---------:|")
+    }
+
+    fn row(&self, out: &mut dyn Write, _: usize, iteration: u64, data: &NetworkStructure)
+        -> io::Result<()> {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            iteration, data.size, data.sections, data.complete
+        )
+    }
+}
+
+/// Writes a report of all the records in the given format
+pub fn write_report(format: ReportFormat, records: &Recorder, out: &mut dyn Write) -> io::Result<()> {
+    let formatter = format.formatter();
+    formatter.header(out)?;
+    for (index, (iteration, data)) in records.iter().enumerate() {
+        formatter.row(out, index, iteration, &data)?;
+    }
+    formatter.footer(out)
+}
@@ -0,0 +1,188 @@
+use std::fs::File;
+use std::io::Write;
+use std::process;
+use network::Network;
+use params::{FuzzedParam, Params};
+use random::{random_seed, with_seed};
+use stats::{self, aggregate::Summary};
+
+/// The metrics shown in the table printed at the end of a sweep; all the metrics are written to
+/// the CSV file
+const TABLE_METRICS: [&str; 3] = ["splits", "relocations", "max section size"];
+
+/// The results of the runs of one combination of parameter values
+struct Config {
+    values: Vec<(FuzzedParam, f64)>,
+    summaries: Vec<Summary>,
+    failures: usize,
+}
+
+/// Runs the simulation for every combination of the values of the swept parameters,
+/// `runs_per_config` times each, and exits. Every run has a seed of its own, drawn from the main
+/// one, so that a run can be reproduced alone by setting `AGE_SEED` to it along with the
+/// parameter values. The mean and the standard deviation of every headline metric are printed
+/// for every combination and written to the sweep file.
+pub fn run<F>(params: &Params, simulate: F) -> !
+where
+    F: Fn(&Params) -> (Network, Option<String>),
+{
+    // the sweep makes many runs, so their progress messages are off unless asked for
+    let params = Params {
+        debug_window: params.debug_window.or(Some((0, 0))),
+        ..params.clone()
+    };
+    let mut configs = vec![];
+    for values in combinations(&params) {
+        let description = describe(&values);
+        let mut config = Config {
+            values,
+            summaries: vec![],
+            failures: 0,
+        };
+        for run in 0..params.runs_per_config {
+            let seed = random_seed();
+            let result: Result<_, String> = with_seed(seed, || {
+                let (run_params, _) = params.with_values(&config.values)?.jittered()?;
+                Ok(simulate(&run_params))
+            });
+            let (network, failure) = match result {
+                Ok(run) => run,
+                Err(msg) => {
+                    println!("{}, run {}: invalid parameters: {}", description, run + 1, msg);
+                    config.failures += 1;
+                    continue;
+                }
+            };
+            if let Some(msg) = failure {
+                println!("{}, run {} (seed {:?}) failed: {}", description, run + 1, seed, msg);
+                config.failures += 1;
+                continue;
+            }
+            let output = network.output();
+            let mut summary = stats::summary(&network);
+            summary.push(("splits", output.splits as f64));
+            summary.push(("max section size", output.largest_section.0 as f64));
+            println!("{}, run {} (seed {:?}): done", description, run + 1, seed);
+            config.summaries.push(summary);
+        }
+        configs.push(config);
+    }
+    print_table(&configs);
+    write_csv(&params.sweep_file, &configs);
+    process::exit(0)
+}
+
+/// Returns every combination of the values of the swept parameters
+fn combinations(params: &Params) -> Vec<Vec<(FuzzedParam, f64)>> {
+    params.sweep.iter().fold(vec![vec![]], |combinations, sweep| {
+        combinations
+            .iter()
+            .flat_map(|combination| {
+                sweep.values().into_iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push((sweep.param, value));
+                    combination
+                })
+            })
+            .collect()
+    })
+}
+
+/// Describes a combination of values as `name=value, ...`
+fn describe(values: &[(FuzzedParam, f64)]) -> String {
+    values
+        .iter()
+        .map(|&(param, value)| format!("{}={}", param.name(), value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the mean and the standard deviation of the given metric over the runs of a
+/// combination, or `None` if all of them failed
+fn mean_and_std_dev(summaries: &[Summary], metric: usize) -> Option<(f64, f64)> {
+    if summaries.is_empty() {
+        return None;
+    }
+    let n = summaries.len() as f64;
+    let mean = summaries.iter().map(|s| s[metric].1).sum::<f64>() / n;
+    let variance = if summaries.len() > 1 {
+        summaries
+            .iter()
+            .map(|s| (s[metric].1 - mean) * (s[metric].1 - mean))
+            .sum::<f64>() / (n - 1.0)
+    } else {
+        0.0
+    };
+    Some((mean, variance.sqrt()))
+}
+
+/// Returns the names of the metrics collected in every run
+fn metric_names(configs: &[Config]) -> Vec<&'static str> {
+    configs
+        .iter()
+        .flat_map(|config| config.summaries.first())
+        .next()
+        .map_or_else(Vec::new, |summary| summary.iter().map(|&(name, _)| name).collect())
+}
+
+/// Prints the mean ± the standard deviation of the main metrics for every combination of values
+fn print_table(configs: &[Config]) {
+    let names = metric_names(configs);
+    println!("\nSweep results (mean ± standard deviation):");
+    for config in configs {
+        let metrics: Vec<_> = TABLE_METRICS
+            .iter()
+            .map(|metric| {
+                let result = names
+                    .iter()
+                    .position(|name| name == metric)
+                    .and_then(|i| mean_and_std_dev(&config.summaries, i));
+                match result {
+                    Some((mean, std_dev)) => format!("{} {:.2} ± {:.2}", metric, mean, std_dev),
+                    None => format!("{} -", metric),
+                }
+            })
+            .collect();
+        let failures = if config.failures > 0 {
+            format!(" ({} runs failed)", config.failures)
+        } else {
+            String::new()
+        };
+        println!("{}: {}{}", describe(&config.values), metrics.join(", "), failures);
+    }
+}
+
+/// Writes the mean and the standard deviation of every metric for every combination of values
+fn write_csv(file: &str, configs: &[Config]) {
+    let names = metric_names(configs);
+    let mut csv = File::create(file).unwrap_or_else(|_| panic!("Couldn't create file {}!", file));
+    let mut header: Vec<String> = configs
+        .first()
+        .map_or_else(Vec::new, |config| {
+            config.values.iter().map(|&(param, _)| param.name().to_owned()).collect()
+        });
+    header.push("runs".to_owned());
+    header.push("failed_runs".to_owned());
+    for name in &names {
+        let name = name.replace(' ', "_");
+        header.push(format!("{}_mean", name));
+        header.push(format!("{}_std_dev", name));
+    }
+    let _ = writeln!(csv, "{}", header.join(","));
+    for config in configs {
+        let mut row: Vec<String> = config.values.iter().map(|&(_, value)| value.to_string()).collect();
+        row.push((config.summaries.len() + config.failures).to_string());
+        row.push(config.failures.to_string());
+        for i in 0..names.len() {
+            match mean_and_std_dev(&config.summaries, i) {
+                Some((mean, std_dev)) => {
+                    row.push(mean.to_string());
+                    row.push(std_dev.to_string());
+                }
+                None => row.extend(vec![String::new(), String::new()]),
+            }
+        }
+        let _ = writeln!(csv, "{}", row.join(","));
+    }
+    println!("\nResults written to {}", file);
+}
@@ -0,0 +1,188 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use serde_json;
+use network::churn::{NetworkEvent, SectionEvent};
+use network::prefix::{Name, Prefix};
+use network::section::Section;
+use params::Params;
+use random::{random_seed, with_seed};
+
+/// A single line of a trace file
+#[derive(Serialize, Deserialize)]
+pub enum Record {
+    /// The full state of a section. Recorded whenever the network creates or modifies a section
+    /// other than by passing it an event.
+    Snapshot {
+        iteration: u64,
+        section: Box<Section>,
+        /// the flags of the nodes, as they aren't serialised with the nodes
+        flags: Vec<(Name, u16)>,
+    },
+    /// An event handled by a section, along with the seed of the random generator the section
+    /// used while handling it and the events it responded with
+    Event {
+        iteration: u64,
+        prefix: Prefix,
+        seed: [u32; 4],
+        event: NetworkEvent,
+        /// the flags of the node passed in the event
+        flags: u16,
+        response: Vec<SectionEvent>,
+    },
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<BufWriter<File>>> = RefCell::new(Default::default());
+}
+
+/// Starts recording a trace to the given file
+pub fn start(path: &str) {
+    let file = File::create(path).unwrap_or_else(|_| panic!("Couldn't create file {}!", path));
+    TRACE.with(|trace| *trace.borrow_mut() = Some(BufWriter::new(file)));
+}
+
+/// Stops recording the trace, flushing what is left to the file
+pub fn finish() {
+    TRACE.with(|trace| {
+        if let Some(mut file) = trace.borrow_mut().take() {
+            let _ = file.flush();
+        }
+    })
+}
+
+/// Returns whether a trace is being recorded
+pub fn is_enabled() -> bool {
+    TRACE.with(|trace| trace.borrow().is_some())
+}
+
+/// Appends a record to the trace, if one is being recorded
+pub fn record(record: &Record) {
+    TRACE.with(|trace| {
+        if let Some(ref mut file) = *trace.borrow_mut() {
+            let line = serde_json::to_string(record).expect("Couldn't serialise a trace record!");
+            let _ = writeln!(file, "{}", line);
+        }
+    })
+}
+
+/// Records a snapshot of the section, if a trace is being recorded
+pub fn snapshot(iteration: u64, section: &Section) {
+    if !is_enabled() {
+        return;
+    }
+    let flags = section
+        .nodes()
+        .into_iter()
+        .filter(|node| node.flags() != 0)
+        .map(|node| (node.name(), node.flags()))
+        .collect();
+    record(&Record::Snapshot {
+        iteration,
+        section: Box::new(section.clone()),
+        flags,
+    });
+}
+
+/// Returns the event with the flags of the node passed in it restored
+fn restore_flags(event: NetworkEvent, flags: u16) -> NetworkEvent {
+    match event {
+        NetworkEvent::Live(node) => NetworkEvent::Live(node.with_flags(flags)),
+        NetworkEvent::Gone(node) => NetworkEvent::Gone(node.with_flags(flags)),
+        NetworkEvent::Relocated(node) => NetworkEvent::Relocated(node.with_flags(flags)),
+        event => event,
+    }
+}
+
+/// Replays the events recorded in the trace for the section with the given prefix against that
+/// section alone, starting from its latest snapshot before each event. Returns the number of
+/// events replayed, or a description of the first event for which the section responded
+/// differently than in the recorded run.
+pub fn replay_section(path: &str, prefix: Prefix, params: &Params) -> Result<usize, String> {
+    let file = File::open(path).map_err(|_| format!("Couldn't open trace file {}!", path))?;
+    let mut section: Option<Section> = None;
+    let mut replayed = 0;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|_| format!("Couldn't read trace file {}!", path))?;
+        let record = serde_json::from_str(&line)
+            .map_err(|err| format!("line {}: invalid record: {}", i + 1, err))?;
+        match record {
+            Record::Snapshot {
+                iteration,
+                section: snapshot,
+                flags,
+            } => {
+                if snapshot.prefix() == prefix {
+                    let mut snapshot = snapshot;
+                    snapshot.restore_flags(&flags);
+                    println!("Iteration {}: restored {:?}", iteration, snapshot);
+                    section = Some(*snapshot);
+                }
+            }
+            Record::Event {
+                iteration,
+                prefix: pfx,
+                seed,
+                event,
+                flags,
+                response,
+            } => {
+                if pfx != prefix {
+                    continue;
+                }
+                let event = restore_flags(event, flags);
+                let section = section.as_mut().ok_or_else(|| {
+                    format!("line {}: event for {:?} before its snapshot", i + 1, prefix)
+                })?;
+                let replayed_response = with_seed(seed, || section.handle_event(event, params));
+                println!(
+                    "Iteration {}: {:?} -> {:?}",
+                    iteration, event, replayed_response
+                );
+                // the flags of the recorded nodes are lost, so compare the serialised
+                // forms
+                if serde_json::to_string(&replayed_response).ok()
+                    != serde_json::to_string(&response).ok()
+                {
+                    return Err(format!(
+                        "Iteration {} (line {}): {:?} responded to {:?} with {:?}, but the trace \
+                         recorded {:?}",
+                        iteration,
+                        i + 1,
+                        prefix,
+                        event,
+                        replayed_response,
+                        response
+                    ));
+                }
+                replayed += 1;
+            }
+        }
+    }
+    Ok(replayed)
+}
+
+/// Passes the event to the section, recording it in the trace if one is being recorded. The
+/// section handles it with a random generator seeded for the event whether or not a trace is
+/// being recorded, so that recording one doesn't change the run.
+pub fn handle_event(
+    iteration: u64,
+    section: &mut Section,
+    event: NetworkEvent,
+    params: &Params,
+) -> Vec<SectionEvent> {
+    let seed = random_seed();
+    let response = with_seed(seed, || section.handle_event(event, params));
+    if !is_enabled() {
+        return response;
+    }
+    record(&Record::Event {
+        iteration,
+        prefix: section.prefix(),
+        seed,
+        event,
+        flags: event.get_node().map_or(0, |node| node.flags()),
+        response: response.clone(),
+    });
+    response
+}
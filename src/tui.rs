@@ -0,0 +1,295 @@
+//! A dashboard showing the network while the simulation runs, in place of the statistics and
+//! progress messages scrolling by: the numbers of sections and nodes, the relocation rate, the
+//! distributions of the ages and the section sizes, and the last progress messages. It is
+//! redrawn at most every `FRAME_INTERVAL`, and it reads single keys: `p` pauses and resumes the
+//! simulation, `s` or space steps one iteration while it is paused and `q` stops it, after
+//! which the results are printed as usual.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use ageing_sim::log;
+use ageing_sim::network::Network;
+use signal;
+
+/// The least time between two frames while the simulation runs
+const FRAME_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the keys are read while the simulation is paused
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The number of lines of progress messages kept for the log panel
+const LOG_LINES: usize = 1000;
+
+/// The most rows a histogram takes; values are grouped into brackets to fit
+const HISTOGRAM_ROWS: usize = 12;
+
+/// The size of a terminal that doesn't tell its own
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+/// The dashboard of a single run. The terminal is restored when it is dropped.
+pub struct Dashboard {
+    terminal: Terminal,
+    paused: bool,
+    /// when the last frame was drawn
+    last_frame: Option<Instant>,
+    /// the number of relocations when the relocation rate was last measured, and when
+    last_relocations: (u64, Instant),
+    /// the relocations per second, as last measured
+    relocation_rate: f64,
+}
+
+impl Dashboard {
+    /// Switches the terminal to the dashboard and starts capturing the progress messages
+    pub fn start() -> Dashboard {
+        log::capture(LOG_LINES);
+        Dashboard {
+            terminal: Terminal::enter(),
+            paused: false,
+            last_frame: None,
+            last_relocations: (0, Instant::now()),
+            relocation_rate: 0.0,
+        }
+    }
+
+    /// Shows the network after the given iteration and handles the keys pressed since the last
+    /// one. While the simulation is paused, waits until it is resumed, stepped or stopped.
+    /// Returns whether the simulation goes on.
+    pub fn after_iteration(&mut self, network: &Network, iteration: u64) -> bool {
+        let mut changed = true;
+        loop {
+            let mut step = false;
+            for key in self.terminal.read_keys() {
+                match key {
+                    b'q' | b'Q' => return false,
+                    b'p' | b'P' => {
+                        self.paused = !self.paused;
+                        changed = true;
+                    }
+                    b's' | b'S' | b' ' if self.paused => step = true,
+                    _ => (),
+                }
+            }
+            let due = self
+                .last_frame
+                .map_or(true, |last| last.elapsed() >= FRAME_INTERVAL);
+            if changed && (self.paused || due) {
+                self.draw(network, iteration);
+            }
+            if !self.paused || step || signal::interrupted() {
+                return true;
+            }
+            changed = false;
+            thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+    }
+
+    /// Draws a frame of the dashboard
+    fn draw(&mut self, network: &Network, iteration: u64) {
+        let now = Instant::now();
+        let relocations = network.output().relocations;
+        let (last_relocations, last_time) = self.last_relocations;
+        let elapsed = now.duration_since(last_time).as_secs_f64();
+        if !self.paused && elapsed >= 1.0 {
+            self.relocation_rate = (relocations - last_relocations) as f64 / elapsed;
+            self.last_relocations = (relocations, now);
+        }
+        self.last_frame = Some(now);
+
+        let (width, height) = self.terminal.size();
+        let state = if self.paused { "paused" } else { "running" };
+        let mut lines = vec![
+            format!(
+                "Iteration {} [{}]    p: pause/resume  s: step  q: stop",
+                iteration, state
+            ),
+            String::new(),
+            format!(
+                "Sections: {} ({} complete)   Nodes: {}   Relocations: {} ({:.1}/s)",
+                network.num_sections(),
+                network.complete_sections(),
+                network.num_nodes(),
+                relocations,
+                self.relocation_rate
+            ),
+            format!(
+                "Splits: {}   Merges: {}   Rejections: {}",
+                network.output().splits,
+                network.output().merges,
+                network.output().rejections
+            ),
+            String::new(),
+        ];
+
+        let ages: BTreeMap<_, _> = network
+            .age_distribution()
+            .into_iter()
+            .map(|(age, count)| (age as usize, count))
+            .collect();
+        let mut sizes = BTreeMap::new();
+        for section in network.sections() {
+            *sizes.entry(section.len()).or_insert(0) += 1;
+        }
+        let column = width / 2;
+        let left = histogram("Ages", &ages, column);
+        let right = histogram("Section sizes", &sizes, width - column);
+        for row in 0..left.len().max(right.len()) {
+            let left = left.get(row).map_or("", |line| &line[..]);
+            let right = right.get(row).map_or("", |line| &line[..]);
+            lines.push(format!("{:<width$}{}", left, right, width = column));
+        }
+
+        lines.push(String::new());
+        lines.push("Log".to_owned());
+        let room = height.saturating_sub(lines.len());
+        lines.extend(log::tail(room));
+
+        // every line is cut to the width and clears the rest of its row, and there is no line
+        // break after the last one, which would scroll a full screen
+        let rows: Vec<String> = lines
+            .iter()
+            .take(height)
+            .map(|line| line.chars().take(width).chain("\x1b[K".chars()).collect())
+            .collect();
+        let frame = format!("\x1b[H{}\x1b[J", rows.join("\n"));
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        let _ = stdout.write_all(frame.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        log::release();
+    }
+}
+
+/// Returns the lines of a histogram of the distribution with a title, fitting in the given
+/// width. The values are grouped into brackets of equal width so that there are at most
+/// `HISTOGRAM_ROWS` rows.
+fn histogram(title: &str, dist: &BTreeMap<usize, usize>, width: usize) -> Vec<String> {
+    let mut lines = vec![title.to_owned()];
+    let (min, max) = match (dist.keys().next(), dist.keys().next_back()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return lines,
+    };
+    let bracket = (max - min) / HISTOGRAM_ROWS + 1;
+    let mut rows = BTreeMap::new();
+    for (&value, &count) in dist {
+        *rows.entry((value - min) / bracket).or_insert(0) += count;
+    }
+    let highest = rows.values().cloned().max().unwrap_or(0).max(1);
+    // the label, the count and the spaces around them
+    let bar_width = width.saturating_sub(20).max(1);
+    for (row, count) in rows {
+        let start = min + row * bracket;
+        let label = if bracket == 1 {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, start + bracket - 1)
+        };
+        let bar = "#".repeat(count * bar_width / highest);
+        lines.push(format!("{:>7} {} {}", label, bar, count));
+    }
+    lines
+}
+
+/// The terminal switched to its alternate screen, reading keys as they are pressed rather than
+/// by the line. It is switched back when dropped.
+struct Terminal {
+    /// the settings of the terminal to restore, if it is one
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+impl Terminal {
+    #[cfg(unix)]
+    fn enter() -> Terminal {
+        let saved = unsafe {
+            let mut saved: libc::termios = ::std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut saved) == 0 {
+                let mut raw = saved;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+                raw.c_cc[libc::VMIN] = 0;
+                raw.c_cc[libc::VTIME] = 0;
+                let _ = libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw);
+                Some(saved)
+            } else {
+                None
+            }
+        };
+        Self::switch_screen(true);
+        Terminal { saved }
+    }
+
+    #[cfg(not(unix))]
+    fn enter() -> Terminal {
+        Self::switch_screen(true);
+        Terminal {}
+    }
+
+    /// Switches to the alternate screen with the cursor hidden, or back
+    fn switch_screen(alternate: bool) {
+        let codes = if alternate {
+            "\x1b[?1049h\x1b[?25l"
+        } else {
+            "\x1b[?25h\x1b[?1049l"
+        };
+        print!("{}", codes);
+        let _ = io::stdout().flush();
+    }
+
+    /// Returns the keys pressed since the last call, without waiting for any
+    #[cfg(unix)]
+    fn read_keys(&self) -> Vec<u8> {
+        if self.saved.is_none() {
+            return vec![];
+        }
+        let mut keys = [0u8; 16];
+        let read = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                keys.as_mut_ptr() as *mut libc::c_void,
+                keys.len(),
+            )
+        };
+        keys[..read.max(0) as usize].to_vec()
+    }
+
+    /// Keys can't be read without waiting for a whole line
+    #[cfg(not(unix))]
+    fn read_keys(&self) -> Vec<u8> {
+        vec![]
+    }
+
+    /// Returns the width and height of the terminal
+    #[cfg(unix)]
+    fn size(&self) -> (usize, usize) {
+        let mut size: libc::winsize = unsafe { ::std::mem::zeroed() };
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result == 0 && size.ws_col > 0 && size.ws_row > 0 {
+            (size.ws_col as usize, size.ws_row as usize)
+        } else {
+            DEFAULT_SIZE
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn size(&self) -> (usize, usize) {
+        DEFAULT_SIZE
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        Self::switch_screen(false);
+        #[cfg(unix)]
+        {
+            if let Some(ref saved) = self.saved {
+                let _ = unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved) };
+            }
+        }
+    }
+}
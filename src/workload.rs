@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::fs;
+use event_log;
+use network::Network;
+use params::{ChurnModel, Params};
+use random::{from_percent, poisson_cdf, random_from_cdf, random_range, Probability, ONE};
+
+/// The numbers of joins, drops and rejoins in the iterations of a churn trace
+pub type ChurnTrace = BTreeMap<u64, (u64, u64, u64)>;
+
+/// Generates the random churn events driving the simulation.
+/// The churn model decides how many events happen in an iteration: one, a Poisson-distributed
+/// number, or one with periodic bursts of joins and drops. Churn can optionally be bursty: the
+/// generator then switches between a calm state and a storm state, with `storm_rate` times as
+/// many events. The join and drop probabilities can also follow a daily cycle. Once the network
+/// is drained, nodes only leave it. A churn trace replaces all of this with the events it lists.
+/// Nodes following a behaviour also act on their own every iteration.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Workload {
+    /// whether a churn storm is in progress
+    storm: bool,
+    /// the number of iterations generated so far
+    iteration: u64,
+    /// whether joins have stopped and only drops continue
+    drain: bool,
+    /// whether the joins are stopped for now, while the other churn continues
+    joins_stopped: bool,
+    /// the cumulative distribution of the number of events in an iteration, for Poisson churn;
+    /// computed on first use
+    #[serde(skip)]
+    poisson: Vec<Probability>,
+    /// the churn trace being replayed, read on first use
+    #[serde(skip)]
+    trace: Option<ChurnTrace>,
+}
+
+impl Workload {
+    pub fn new() -> Workload {
+        Default::default()
+    }
+
+    /// Stops the joins, so that the network shrinks
+    pub fn drain(&mut self) {
+        self.drain = true;
+    }
+
+    /// Stops or resumes the joins without affecting the other churn
+    pub fn stop_joins(&mut self, stopped: bool) {
+        self.joins_stopped = stopped;
+    }
+
+    /// Generates the churn events for one iteration
+    pub fn generate(&mut self, network: &mut Network, params: &Params) {
+        if event_log::is_replaying() {
+            network.replay_churn();
+            self.iteration += 1;
+            return;
+        }
+        if let ChurnModel::Trace(ref file) = params.churn {
+            self.replay_trace(network, file);
+        } else {
+            self.random_churn(network, params);
+        }
+        if !params.behaviours.is_empty() {
+            network.apply_behaviours();
+        }
+        self.iteration += 1;
+    }
+
+    /// Generates the random churn events of the churn model
+    fn random_churn(&mut self, network: &mut Network, params: &Params) {
+        if params.storm_start > 0.0 {
+            self.update_storm(params);
+        }
+        let mut events = match params.churn {
+            ChurnModel::Poisson(mean) => {
+                if self.poisson.is_empty() {
+                    self.poisson = poisson_cdf(mean);
+                }
+                random_from_cdf(&self.poisson)
+            }
+            _ => 1,
+        };
+        if self.storm {
+            network.record_storm_iteration();
+            events *= params.storm_rate;
+        }
+        let probs = if self.drain {
+            (0, 100)
+        } else {
+            self.probabilities(params)
+        };
+        for _ in 0..events {
+            random_event(network, probs, !self.joins_stopped);
+        }
+        if let ChurnModel::Burst {
+            period,
+            joins,
+            drops,
+        } = params.churn
+        {
+            if (self.iteration + 1) % period == 0 {
+                debug!("Churn burst: {} joins, {} drops", joins, drops);
+                self.burst(network, joins, drops, 0);
+            }
+        }
+    }
+
+    /// Generates the events the churn trace lists for the current iteration
+    fn replay_trace(&mut self, network: &mut Network, file: &str) {
+        if self.trace.is_none() {
+            self.trace = Some(read_churn_trace(file).unwrap_or_else(|msg| panic!("{}", msg)));
+        }
+        let events = self.trace
+            .as_ref()
+            .and_then(|trace| trace.get(&self.iteration).cloned());
+        if let Some((joins, drops, rejoins)) = events {
+            self.burst(network, joins, drops, rejoins);
+        }
+    }
+
+    /// Makes the given numbers of nodes join, drop and rejoin the network at once; there are no
+    /// joins once the network is drained or while they are stopped
+    fn burst(&self, network: &mut Network, joins: u64, drops: u64, rejoins: u64) {
+        if !self.drain && !self.joins_stopped {
+            for _ in 0..joins {
+                network.add_random_node();
+            }
+        }
+        for _ in 0..drops {
+            network.drop_random_node();
+        }
+        for _ in 0..rejoins {
+            network.rejoin_random_node();
+        }
+    }
+
+    /// Returns the join and drop probabilities for the current iteration. With a diurnal
+    /// pattern, they are either taken from the table, which is spread evenly over the period, or
+    /// shifted by a sine wave: joins peak at a quarter of the period and drops three quarters.
+    fn probabilities(&self, params: &Params) -> (u8, u8) {
+        if params.diurnal_period == 0 {
+            return params.growth;
+        }
+        let phase = self.iteration % params.diurnal_period;
+        if !params.diurnal_table.is_empty() {
+            let len = params.diurnal_table.len() as u64;
+            return params.diurnal_table[(phase * len / params.diurnal_period) as usize];
+        }
+        let sine = sine(phase, params.diurnal_period) as f64 / ONE as f64;
+        let shift = params.diurnal_amplitude * sine;
+        let p_add = (f64::from(params.growth.0) + shift).clamp(0.0, 100.0).round();
+        let p_drop = (f64::from(params.growth.1) - shift).clamp(0.0, 100.0 - p_add).round();
+        (p_add as u8, p_drop as u8)
+    }
+
+    /// Moves between the calm and storm states
+    fn update_storm(&mut self, params: &Params) {
+        let x = random_range(0, ONE);
+        if self.storm && x < from_percent(params.storm_end) {
+            debug!("Churn storm ends");
+            self.storm = false;
+        } else if !self.storm && x < from_percent(params.storm_start) {
+            debug!("Churn storm starts");
+            self.storm = true;
+        }
+    }
+}
+
+/// Returns the sine of the angle `2π * phase / period` in fixed point, as a fraction of `ONE`.
+/// Uses Bhaskara's approximation, accurate to 0.2%, to avoid the platform's floating point sine.
+fn sine(phase: u64, period: u64) -> i64 {
+    // the position in the positive or negative half of the wave
+    let (x, sign) = if 2 * phase < period {
+        (2 * phase, 1)
+    } else {
+        (2 * phase - period, -1)
+    };
+    let (x, period) = (u128::from(x), u128::from(period));
+    let num = 16 * x * (period - x);
+    let den = 5 * period * period - 4 * x * (period - x);
+    sign * (u128::from(ONE) * num / den) as i64
+}
+
+/// Generates a random churn event in the network. There are three possible kinds:
+/// node joining, node leaving and node rejoining. A join doesn't happen unless `joins` is set.
+fn random_event(network: &mut Network, probs: (u8, u8), joins: bool) {
+    let x = random_range(0, 100);
+    if x < probs.0 {
+        if joins {
+            network.add_random_node();
+        }
+    } else if x >= probs.0 && x < probs.0 + probs.1 {
+        network.drop_random_node();
+    } else {
+        network.rejoin_random_node();
+    }
+}
+
+/// Reads a churn trace: lines of an iteration and the numbers of joins, drops and optionally
+/// rejoins in it, separated by spaces or commas. Empty lines and lines starting with `#` are
+/// skipped.
+pub fn read_churn_trace(path: &str) -> Result<ChurnTrace, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+    let mut trace = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let numbers: Vec<u64> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid churn trace {}, line {}: not a number", path, i + 1))?;
+        let events = match numbers[..] {
+            [iteration, joins, drops] => (iteration, (joins, drops, 0)),
+            [iteration, joins, drops, rejoins] => (iteration, (joins, drops, rejoins)),
+            _ => {
+                return Err(format!(
+                    "Invalid churn trace {}, line {}: expected ITERATION JOINS DROPS [REJOINS]",
+                    path,
+                    i + 1
+                ))
+            }
+        };
+        if trace.insert(events.0, events.1).is_some() {
+            return Err(format!(
+                "Invalid churn trace {}, line {}: iteration {} is listed twice",
+                path,
+                i + 1,
+                events.0
+            ));
+        }
+    }
+    Ok(trace)
+}
@@ -0,0 +1,101 @@
+extern crate ageing_sim;
+
+use ageing_sim::{Network, Params};
+use ageing_sim::scenario::Scenario;
+use ageing_sim::params::{DropDist, FuzzedParam};
+
+/// Two runs with the same parameters and seed end in the same state
+#[test]
+fn seeded_runs_are_reproducible() {
+    let run = || {
+        Network::builder()
+            .growth(90, 7)
+            .seed([1, 2, 3, 4])
+            .run(3000)
+            .unwrap()
+    };
+    let (first, second) = (run(), run());
+    assert!(first.failure().is_none());
+    assert_eq!(first.num_nodes(), second.num_nodes());
+    assert_eq!(first.state_hash(), second.state_hash());
+}
+
+/// The group size set through the builder bounds the number of elders of every section
+#[test]
+fn group_size_bounds_the_elders() {
+    let network = Network::builder()
+        .growth(90, 0)
+        .group_size(5)
+        .buffer(1)
+        .seed([1, 2, 3, 4])
+        .run(3000)
+        .unwrap();
+    assert!(network.failure().is_none());
+    assert!(network.num_sections() > 1);
+    assert!(network.sections().all(|section| section.elder_count() <= 5));
+    assert!(network.sections().any(|section| section.elder_count() == 5));
+}
+
+/// Invalid parameters are reported before the simulation starts
+#[test]
+fn invalid_parameters_are_rejected() {
+    assert!(Network::builder().growth(100, 0).build().is_err());
+    assert!(Network::builder().growth(60, 50).run(10).is_err());
+    assert!(Network::builder().max_prefix_len(65).build().is_err());
+    assert!(Network::builder().group_size(0).build().is_err());
+    assert!(Network::builder().quorum(9).build().is_err());
+    assert!(Network::builder().drop_dist(DropDist::ExponentialBase(0)).build().is_err());
+    assert!(Network::builder().drop_dist(DropDist::Table(vec![])).build().is_err());
+    let diurnal = Params {
+        diurnal_table: vec![(90, 5), (200, 100)],
+        ..Params::default()
+    };
+    assert!(Network::builder().params(diurnal).build().is_err());
+    let negotiated_cascades = Params {
+        merge_negotiation: true,
+        batch_cascades: true,
+        ..Params::default()
+    };
+    assert!(Network::builder().params(negotiated_cascades).build().is_err());
+    assert!(Network::builder().group_size(4).build().is_ok());
+}
+
+/// Sweeping the group size makes the quorum a majority of it, unless the quorum is swept too, and
+/// combinations that don't go together are refused
+#[test]
+fn swept_group_sizes_set_the_quorum() {
+    let params = Params::default();
+    let swept = params.with_values(&[(FuzzedParam::GroupSize, 12.0)]).unwrap();
+    assert_eq!((swept.group_size, swept.quorum), (12, 7));
+    let values = [(FuzzedParam::Quorum, 9.0), (FuzzedParam::GroupSize, 10.0)];
+    let swept = params.with_values(&values).unwrap();
+    assert_eq!((swept.group_size, swept.quorum), (10, 9));
+    let values = [(FuzzedParam::GroupSize, 6.0), (FuzzedParam::Quorum, 7.0)];
+    assert!(params.with_values(&values).is_err());
+}
+
+/// A scenario can shrink the group size of the sections under a prefix, and changes that don't fit
+/// the parameters are refused
+#[test]
+fn scripted_group_size_changes() {
+    let mut scenario = Scenario::parse("at 2000 set group_size=5 in 0").unwrap();
+    let network = Network::builder()
+        .growth(90, 0)
+        .seed([1, 2, 3, 4])
+        .run_scenario(&mut scenario, 3000)
+        .unwrap();
+    let (shrunk, rest): (Vec<_>, Vec<_>) = network
+        .sections()
+        .partition(|section| section.prefix().to_string().starts_with('0'));
+    assert!(!shrunk.is_empty() && !rest.is_empty());
+    assert!(shrunk.iter().all(|section| section.elder_count() <= 5));
+    assert!(rest.iter().any(|section| section.elder_count() > 5));
+
+    assert!(Scenario::parse("at 10 set init_age=300").is_err());
+    assert!(Scenario::parse("at 10 set elder_min_age=1000").is_err());
+    let mut scenario = Scenario::parse("at 10 set quorum=9").unwrap();
+    let result = Network::builder()
+        .seed([1, 2, 3, 4])
+        .run_scenario(&mut scenario, 20);
+    assert!(result.is_err());
+}
@@ -0,0 +1,19 @@
+extern crate ageing_sim;
+
+use ageing_sim::Network;
+use ageing_sim::scenario::Scenario;
+
+/// Runs the merge race regression scenario up to its last assertion with a few seeds
+#[test]
+fn merge_races_never_overlap() {
+    let text = include_str!("../scenarios/merge_races.txt");
+    for &seed in &[[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]] {
+        let mut scenario = Scenario::parse(text).unwrap();
+        let result = Network::builder()
+            .seed(seed)
+            .run_scenario(&mut scenario, 751);
+        if let Err(msg) = result {
+            panic!("Seed {:?}: {}", seed, msg);
+        }
+    }
+}